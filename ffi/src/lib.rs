@@ -0,0 +1,4 @@
+//! C API of p3d as a shared and a static library. The functions are p3d's own (`ffi` feature);
+//! this crate only links them into the C artifacts, so p3d itself stays an rlib.
+
+pub use p3d::{p3d_error_name, p3d_free_string_list, p3d_last_error_message, p3d_process_c, P3DStringList};
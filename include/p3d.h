@@ -0,0 +1,83 @@
+#ifndef P3D_H
+#define P3D_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ Hashes returned by `p3d_process_c`, released with `p3d_free_string_list`
+ */
+typedef struct P3DStringList {
+  char **strings;
+  size_t len;
+} P3DStringList;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ `p3d_process_n` for C callers. Returns 0 and fills `out` on success, otherwise a negative
+ `error_code` and leaves `out` untouched. Free a filled `out` with `p3d_free_string_list`.
+
+ - `file_type`: 0 OBJ, 1 glTF, 2 GLB, 3 STL, 4 PLY, 5 XYZ, 6 3MF, 7 OFF, 8 detected from the
+   content; anything else fails with `UnsupportedFileType` (-4).
+ - `algo`: 0 `Grid2d`, 1 `Grid2dV2`, 2 `Grid2dV3`, 3 `Grid2dV3a`, 4 `Spectr`, 5 `Skeleton`,
+   6 `CloudGrid`, 7 `Voxel`, 8 `FixedGrid`, 9 `CellStats`, 10 `GridNormals`; anything else,
+   or a null `input_ptr`/`out`, fails with `InvalidParams` (-13).
+ - `trans_ptr`: null for no rotation, otherwise 4 bytes (the `trans` of `p3d_process`).
+
+ Errors are the `P3DError::code` of the variant, negated; `p3d_error_name` names them and
+ `p3d_last_error_message` describes the failure. A panic inside the library is caught and
+ fails with `Panic` (-28) instead of unwinding into the caller.
+
+ # Safety
+ `input_ptr` must point to `input_len` readable bytes, `trans_ptr` be null or point to 4
+ readable bytes, and `out` point to a writable `P3DStringList`.
+ */
+int32_t p3d_process_c(const uint8_t *input_ptr,
+                      size_t input_len,
+                      uint8_t file_type,
+                      uint8_t algo,
+                      size_t depth,
+                      int16_t par1,
+                      int16_t par2,
+                      const uint8_t *trans_ptr,
+                      struct P3DStringList *out);
+
+/*
+ Releases the strings of a list filled by `p3d_process_c` and empties it.
+
+ # Safety
+ `list` must be null or point to a list filled by `p3d_process_c` (or already freed).
+ */
+void p3d_free_string_list(struct P3DStringList *list);
+
+/*
+ Name of the `P3DError` variant an error code of `p3d_process_c` stands for (`"Ok"` for 0), as
+ a static NUL terminated string, or null for a code no variant has. The codes of a
+ `ParseError` are named by format, as `"PlyError"` or `"GltfError"`.
+ */
+const char *p3d_error_name(int32_t code);
+
+/*
+ Copies the message of the last failed `p3d_process_c` call on the calling thread into `buf`,
+ truncated to `len - 1` bytes and NUL terminated, like `snprintf`. Returns the length of the
+ whole message, 0 when the last call succeeded or none was made; `buf` may be null when
+ `len` is 0, to size the buffer first.
+
+ # Safety
+ `buf` must point to `len` writable bytes.
+ */
+size_t p3d_last_error_message(char *buf, size_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* P3D_H */
@@ -1,8 +1,8 @@
-use alloc::collections::VecDeque;
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use core::iter::repeat;
-use core::ops::SubAssign;
+use core::ops::{Range, SubAssign};
 
 use base16ct;
 use cgmath::MetricSpace;
@@ -12,11 +12,18 @@ use cgmath::Point2;
 use ndarray::{Array1, Array2, Array3, ArrayBase, ArrayView1, ArrayView2, Axis};
 use ndarray::arr1;
 use peroxide::fuga::*;
+use blake2::Blake2b;
+use blake2::digest::consts::U32;
 use sha2::{Digest, Sha256};
+use sha3::{Keccak256, Sha3_256};
 use tri_mesh::mesh::Mesh;
+use tri_mesh::prelude::{FaceID, HalfEdgeID, Vector3, VertexID};
 
-use crate::contour::Rect;
-use crate::polyline::GenPolyLines;
+use crate::cancel::Cancel;
+use crate::contour::{chain_order, Rect};
+use crate::polyline::{Candidates, GenPolyLines};
+use crate::sum::{compensated_sum, CompensatedSum};
+use crate::{HashDetail, SectionDetail};
 
 type VectorTriangles = Array3<f64>;
 //type Triangle = Array3<f64>;
@@ -25,14 +32,65 @@ type VectorTriangles = Array3<f64>;
 // [[x0, y0, z0], [x1, y1, z1], [x3, y3, z3]]
 type Vec2 = Point2<f64>;
 
+/// A 256-bit digest, the bytes behind every hex hash of `p3d_process`
+pub type RawHash = [u8; 32];
+
+/// Digest the output hashes are computed with. The contour data they cover is the same
+/// whichever is chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum HashAlgo {
+    /// What `p3d_process` has always used
+    Sha256,
+    Blake2b256,
+    /// Original Keccak padding, as in Ethereum
+    Keccak256,
+    Sha3_256,
+}
+
+fn digest_with<'a, D: Digest, I: IntoIterator<Item = &'a [u8]>>(parts: I) -> RawHash {
+    let mut hasher = D::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hasher.finalize()[..32]);
+    hash
+}
 
-pub(crate) fn find_top_std(centers: &Vec<Vec<Vec2>>, depth: usize, grid_size: i16, rect: Rect) -> Vec<String> {
+// Digest of the concatenation of `parts`
+pub(crate) fn digest<'a, I: IntoIterator<Item = &'a [u8]>>(algo: HashAlgo, parts: I) -> RawHash {
+    match algo {
+        HashAlgo::Sha256 => digest_with::<Sha256, I>(parts),
+        HashAlgo::Blake2b256 => digest_with::<Blake2b<U32>, I>(parts),
+        HashAlgo::Keccak256 => digest_with::<Keccak256, I>(parts),
+        HashAlgo::Sha3_256 => digest_with::<Sha3_256, I>(parts),
+    }
+}
+
+// Lowercase hex of a digest, as `p3d_process` returns it
+pub(crate) fn to_hex(hash: &RawHash) -> String {
+    let mut buf = [0u8; 64];
+    base16ct::lower::encode_str(hash, &mut buf).unwrap().to_string()
+}
+
+// Digest of a hex hash in either case, `None` unless it is exactly 64 hex digits
+pub(crate) fn from_hex(hex: &str) -> Option<RawHash> {
+    let mut hash = [0u8; 32];
+    match base16ct::mixed::decode(hex, &mut hash) {
+        Ok(bytes) if bytes.len() == 32 => Some(hash),
+        _ => None,
+    }
+}
+
+
+pub(crate) fn find_top_std(centers: &Vec<Vec<Vec2>>, depth: usize, grid_size: i16, rect: Rect, hash_algo: HashAlgo, cancel: Option<&Cancel>) -> Vec<RawHash> {
     let mut hashes = vec![];
     if centers.len() == 0 {
         return hashes;
     }
 
-    let ss = GenPolyLines::select_top(centers, depth, grid_size, rect);
+    let ss = GenPolyLines::select_top(centers, depth, grid_size, rect, cancel);
 
     for a in ss.iter() {
         let data: Vec<u8> = a.1.nodes.as_slice().iter()
@@ -40,27 +98,20 @@ pub(crate) fn find_top_std(centers: &Vec<Vec<Vec2>>, depth: usize, grid_size: i1
             .flatten()
             .collect();
 
-        let mut hasher = Sha256::new();
-        hasher.update(data.as_slice());
-
-        let mut buf = [0u8; 64];
-        let hash = hasher.finalize();
-        let hex_hash = base16ct::lower::encode_str(&hash, &mut buf).unwrap();
-
-        hashes.push(hex_hash.to_string());
+        hashes.push(digest(hash_algo, [data.as_slice()]));
     }
     hashes.dedup();
     hashes
 }
 
-pub(crate) fn find_top_std_2(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect) -> Vec<String> {
+pub(crate) fn find_top_std_2(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect, hash_algo: HashAlgo, cancel: Option<&Cancel>) -> Vec<RawHash> {
     let mut hashes = vec![];
     if centers.len() == 0 {
         return hashes;
     }
 
     const N: usize = 2;
-    let ss = GenPolyLines::select_top_all(centers, depth, grid_size, rect);
+    let ss = GenPolyLines::select_top_all(centers, depth, grid_size, rect, cancel);
     if ss.len() < n_sect {
         return hashes;
     }
@@ -85,16 +136,19 @@ pub(crate) fn find_top_std_2(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usi
     let mut stack: Vec<usize> = repeat(0).take(n_sect).collect();
 
     loop {
-        let mut sco = 0.;
+        if matches!(cancel, Some(cancel) if cancel.poll()) {
+            break;
+        }
+        let mut sco = CompensatedSum::default();
         let mut h: Vec<u8> = Vec::new();
         for l in 0..n_sect {
             let k = stack[l];
             if k < ss[l].len() {
-                sco += ss[l][k].0;
+                sco.add(ss[l][k].0);
                 h.extend(ss[l][k].1.clone());
             }
         }
-        ff(sco, h);
+        ff(sco.value(), h);
 
         let mut j = 0;
         while j < n_sect {
@@ -111,35 +165,37 @@ pub(crate) fn find_top_std_2(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usi
     }
 
     for hash in best_totals.iter() {
-        let mut hasher = Sha256::new();
-        hasher.update(hash.1.as_slice());
-
-        let mut a: Vec<u8> = repeat(0).take(32 * n_sect).collect();
-        let mut buf = a.as_mut();
-        let hash = hasher.finalize();
-        let hex_hash = base16ct::lower::encode_str(&hash, &mut buf).unwrap();
-
-        hashes.push(hex_hash.to_string());
+        hashes.push(digest(hash_algo, [hash.1.as_slice()]));
     }
     hashes.dedup();
     hashes
 }
 
-pub(crate) fn find_top_std_3(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect) -> Vec<String> {
+pub(crate) fn find_top_std_3(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect, hash_algo: HashAlgo, cancel: Option<&Cancel>) -> Vec<RawHash> {
+    find_top_std_3_scored(centers, depth, n_sect, grid_size, rect, None, hash_algo, cancel).into_iter().map(|(hash, _)| hash).collect()
+}
+
+// `find_top_std_3` with the `HashDetail` of every hash, see `find_top_std_4_scored`
+pub(crate) fn find_top_std_3_scored(
+    centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect, fixed_scale: Option<f64>,
+    hash_algo: HashAlgo, cancel: Option<&Cancel>,
+) -> Vec<(RawHash, HashDetail)> {
     let mut hashes = vec![];
+    let to_fixed = fixed_scale.map(|k| move |d: f64| fixed_point(d, k) as f64);
     if centers.len() == 0 {
         return hashes;
     }
 
     const N: usize = 2;
-    let ss = GenPolyLines::select_top_all_3(centers, depth, grid_size, rect);
+    let ss = GenPolyLines::select_top_all_3(centers, depth, grid_size, rect, cancel);
     if ss.len() < n_sect {
         return hashes;
     }
 
-    let mut best_totals: Vec<(f64, Vec<u8>)> = Vec::with_capacity(depth);
+    let mut best_totals: Vec<(f64, Vec<u8>, Vec<usize>)> = Vec::with_capacity(depth);
 
-    let mut ff = |d: f64, hash: Vec<u8>| {
+    let mut ff = |d: f64, hash: Vec<u8>, choice: &[usize]| {
+        let d = to_fixed.map_or(d, |f| f(d));
         if let Some(_) = best_totals.iter().find(|a| a.0 == d) {
             return;
         } else {
@@ -152,10 +208,10 @@ pub(crate) fn find_top_std_3(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usi
                     .map(|(index, _)| index);
 
                 if let Some(i) = m {
-                    best_totals[i] = (d, hash);
+                    best_totals[i] = (d, hash, choice.to_vec());
                 }
             } else {
-                best_totals.push((d, hash));
+                best_totals.push((d, hash, choice.to_vec()));
             }
         }
     };
@@ -163,16 +219,19 @@ pub(crate) fn find_top_std_3(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usi
     let mut stack: Vec<usize> = repeat(0).take(n_sect).collect();
 
     loop {
-        let mut sco = 0.;
+        if matches!(cancel, Some(cancel) if cancel.poll()) {
+            break;
+        }
+        let mut sco = CompensatedSum::default();
         let mut h: Vec<u8> = Vec::new();
         for l in 0..n_sect {
             let k = stack[l];
             if k < ss[l].len() {
-                sco += ss[l][k].0;
+                sco.add(ss[l][k].0);
                 h.extend(ss[l][k].1.clone());
             }
         }
-        ff(sco, h);
+        ff(sco.value(), h, &stack);
 
         let mut j = 0;
         while j < n_sect {
@@ -190,37 +249,70 @@ pub(crate) fn find_top_std_3(centers: &Vec<Vec<Vec2>>, depth: usize, n_sect: usi
 
     best_totals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
     for hash in best_totals.iter() {
-        let mut hasher = Sha256::new();
-        hasher.update(hash.1.as_slice());
-
-        let mut a: Vec<u8> = repeat(0).take(32 * n_sect).collect();
-        let mut buf = a.as_mut();
-        let hash = hasher.finalize();
-        let hex_hash = base16ct::lower::encode_str(&hash, &mut buf).unwrap();
-
-        hashes.push(hex_hash.to_string());
+        hashes.push((digest(hash_algo, [hash.1.as_slice()]), hash_detail(&ss, &hash.2)));
+    }
+    hashes.dedup_by(|a, b| a.0 == b.0);
+    for (rank, (_, detail)) in hashes.iter_mut().enumerate() {
+        detail.rank = rank;
     }
-    hashes.dedup();
     hashes
 }
 
+// The `HashDetail` of the candidate taking `choice[l]` of the polylines of section `l`; sections
+// without polylines are skipped, as in the total
+fn hash_detail(ss: &Candidates, choice: &[usize]) -> HashDetail {
+    let sections: Vec<SectionDetail> = choice.iter().enumerate()
+        .filter_map(|(l, &k)| ss[l].get(k).map(|(score, _, nodes)| SectionDetail {
+            section: l,
+            score: *score,
+            cells: nodes.iter().map(|p| [p.x, p.y]).collect(),
+        }))
+        .collect();
+    let mut score = CompensatedSum::default();
+    for section in sections.iter() {
+        score.add(section.score);
+    }
+    HashDetail { rank: 0, score: score.value(), sections }
+}
+
 pub(crate) fn find_top_std_4(
-    cntrs: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect,
-) -> Vec<String> {
-    let mut hashes = vec![];
+    cntrs: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect, hash_algo: HashAlgo, cancel: Option<&Cancel>,
+) -> Vec<RawHash> {
+    find_top_std_4_scored(cntrs, depth, n_sect, grid_size, rect, None, hash_algo, cancel).into_iter().map(|(hash, _)| hash).collect()
+}
+
+// `find_top_std_4` with the `HashDetail` of every hash. With `fixed_scale` the totals are
+// converted by `fixed_point` before they are compared, so the kept candidates and their order
+// don't depend on the last bits of the float sums.
+pub(crate) fn find_top_std_4_scored(
+    cntrs: &Vec<Vec<Vec2>>, depth: usize, n_sect: usize, grid_size: usize, rect: Rect, fixed_scale: Option<f64>,
+    hash_algo: HashAlgo, cancel: Option<&Cancel>,
+) -> Vec<(RawHash, HashDetail)> {
     if cntrs.len() == 0 {
-        return hashes;
+        return vec![];
     }
+    let ss = GenPolyLines::select_top_all_4(cntrs, N_4, grid_size, rect, cancel);
+    rank_candidates_4(&ss, depth, n_sect, fixed_scale, hash_algo, cancel)
+}
 
-    const N: usize = 2;
-    let ss = GenPolyLines::select_top_all_4(cntrs, N, grid_size, rect);
+// Polylines `find_top_std_4` keeps of every section
+const N_4: usize = 2;
+
+// The ranking of `find_top_std_4_scored` over the polylines of its sections: of the candidates
+// taking one of the `N_4` polylines of every section (or none, past the end of its list), the
+// `depth` with the lowest distinct totals, each the first with its total in counter order
+fn rank_candidates_4(ss: &Candidates, depth: usize, n_sect: usize, fixed_scale: Option<f64>, hash_algo: HashAlgo, cancel: Option<&Cancel>) -> Vec<(RawHash, HashDetail)> {
+    let mut hashes = vec![];
+    let to_fixed = fixed_scale.map(|k| move |d: f64| fixed_point(d, k) as f64);
+    const N: usize = N_4;
     if ss.len() < n_sect {
         return hashes;
     }
 
-    let mut best_totals: Vec<(f64, Vec<u8>)> = Vec::with_capacity(depth);
+    let mut best_totals: Vec<(f64, Vec<u8>, Vec<usize>)> = Vec::with_capacity(depth);
 
-    let mut ff = |d: f64, hash: Vec<u8>| {
+    let mut ff = |d: f64, hash: Vec<u8>, choice: &[usize]| {
+        let d = to_fixed.map_or(d, |f| f(d));
         if let Some(_) = best_totals.iter().find(|a| a.0 == d) {
             return
         }
@@ -234,11 +326,11 @@ pub(crate) fn find_top_std_4(
 
                 if let Some((i, r)) = m {
                     if r.0 > d {
-                        best_totals[i] = (d, hash);
+                        best_totals[i] = (d, hash, choice.to_vec());
                     }
                 }
             } else {
-                best_totals.push((d, hash));
+                best_totals.push((d, hash, choice.to_vec()));
             }
         }
     };
@@ -246,16 +338,19 @@ pub(crate) fn find_top_std_4(
     let mut stack: Vec<usize> = repeat(0).take(n_sect).collect();
 
     loop {
-        let mut sco = 0.;
+        if matches!(cancel, Some(cancel) if cancel.poll()) {
+            break;
+        }
+        let mut sco = CompensatedSum::default();
         let mut h: Vec<u8> = Vec::new();
         for l in 0..n_sect {
             let k = stack[l];
             if k < ss[l].len() {
-                sco += ss[l][k].0;
+                sco.add(ss[l][k].0);
                 h.extend(ss[l][k].1.clone());
             }
         }
-        ff(sco, h);
+        ff(sco.value(), h, &stack);
 
         let mut j = 0;
         while j < n_sect {
@@ -277,20 +372,210 @@ pub(crate) fn find_top_std_4(
 
     best_totals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
     for hash in best_totals.iter() {
-        let mut hasher = Sha256::new();
-        hasher.update(hash.1.as_slice());
+        hashes.push((digest(hash_algo, [hash.1.as_slice()]), hash_detail(ss, &hash.2)));
+    }
+    hashes.dedup_by(|a, b| a.0 == b.0);
+    for (rank, (_, detail)) in hashes.iter_mut().enumerate() {
+        detail.rank = rank;
+    }
+    hashes
+}
 
-        let mut a: Vec<u8> = repeat(0).take(32 * n_sect).collect();
-        let mut buf= a.as_mut();
-        let hash = hasher.finalize();
-        let hex_hash = base16ct::lower::encode_str(&hash, &mut buf).unwrap();
+// The polylines of every section `find_top_std_4` takes its candidates from
+pub(crate) fn candidates_4(cntrs: &Vec<Vec<Vec2>>, grid_size: usize, rect: Rect, cancel: Option<&Cancel>) -> Candidates {
+    if cntrs.is_empty() {
+        return Vec::new();
+    }
+    GenPolyLines::select_top_all_4(cntrs, N_4, grid_size, rect, cancel)
+}
+
+// Whether every hash of `claimed` is among those `find_top_std_4` ranks from the polylines
+// `ss`, and how many candidates were totalled to tell. Instead of totalling all of them, the
+// candidates are enumerated up to a bound on their total above the lowest one, raised until
+// the hashes ranking in the `depth` lowest totals either include every claimed one or are all
+// known. The bound has a margin far above the rounding of the sums, so every candidate with a
+// total under it is seen and the verdict is the one of the full ranking. Scores that aren't
+// all finite, or rankings that keep every candidate anyway, fall back to it.
+pub(crate) fn verify_candidates_4(ss: &Candidates, depth: usize, n_sect: usize, hash_algo: HashAlgo, claimed: &[RawHash], cancel: Option<&Cancel>) -> (bool, usize) {
+    if ss.is_empty() || ss.len() < n_sect {
+        return (claimed.is_empty(), 0);
+    }
+    // The two choices of every section with polylines, a missing second one adding nothing
+    let free: Vec<usize> = (0..n_sect).filter(|&l| !ss[l].is_empty()).collect();
+    let options = |l: usize| (ss[l][0].0, ss[l].get(1).map_or(0.0, |c| c.0));
+    let candidates = 1usize.checked_shl(free.len() as u32).unwrap_or(usize::MAX);
+    if candidates <= depth || free.iter().any(|&l| !(options(l).0.is_finite() && options(l).1.is_finite())) {
+        let hashes = rank_candidates_4(ss, depth, n_sect, None, hash_algo, cancel);
+        return (claimed.iter().all(|h| hashes.iter().any(|(hash, _)| hash == h)), candidates);
+    }
+
+    // Every candidate is the lowest one with some sections flipped to their other choice, which
+    // adds the difference of the two to the total; flips are tried from the smallest difference
+    let mut lowest: Vec<usize> = vec![0; n_sect];
+    let mut flips: Vec<(f64, usize)> = Vec::with_capacity(free.len());
+    let mut magnitude = 1.0;
+    for &l in free.iter() {
+        let (first, second) = options(l);
+        lowest[l] = if second < first { 1 } else { 0 };
+        flips.push(((second - first).abs(), l));
+        magnitude += first.abs() + second.abs();
+    }
+    flips.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    let margin = 1e-9 * magnitude;
+    let span: f64 = flips.iter().map(|f| f.0).sum();
+    let total = |choice: &[usize]| {
+        let mut sco = CompensatedSum::default();
+        for (l, &k) in choice.iter().enumerate() {
+            if k < ss[l].len() {
+                sco.add(ss[l][k].0);
+            }
+        }
+        sco.value()
+    };
+    let lowest_total = total(&lowest);
+
+    let mut totalled = 0;
+    let mut bound = 0.0;
+    loop {
+        // Candidates whose flips add up to at most the bound (and the margin), depth first
+        let mut seen: Vec<(f64, Vec<usize>)> = Vec::new();
+        let mut stack: Vec<(usize, f64, Vec<usize>)> = vec![(0, 0.0, lowest.clone())];
+        while let Some((next, added, choice)) = stack.pop() {
+            if matches!(cancel, Some(cancel) if cancel.poll()) {
+                return (false, totalled);
+            }
+            totalled += 1;
+            seen.push((total(&choice), choice.clone()));
+            for (i, &(difference, l)) in flips.iter().enumerate().skip(next) {
+                if added + difference > bound + margin {
+                    break;
+                }
+                let mut flipped = choice.clone();
+                flipped[l] = 1 - flipped[l];
+                stack.push((i + 1, added + difference, flipped));
+            }
+        }
+
+        // Totals up to the bound have all their candidates seen, so their ranks and first
+        // candidates (in counter order, the last section changing slowest) are settled
+        let settled = lowest_total + bound;
+        seen.retain(|(d, _)| *d <= settled || bound >= span);
+        seen.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then_with(|| a.1.iter().rev().cmp(b.1.iter().rev())));
+        seen.dedup_by(|a, b| a.0 == b.0);
+        seen.truncate(depth);
+        let ranked: Vec<RawHash> = seen.iter()
+            .map(|(_, choice)| {
+                let h: Vec<u8> = choice.iter().enumerate()
+                    .filter_map(|(l, &k)| ss[l].get(k))
+                    .flat_map(|c| c.1.iter().cloned())
+                    .collect();
+                digest(hash_algo, [h.as_slice()])
+            })
+            .collect();
+        let holds = claimed.iter().all(|h| ranked.contains(h));
+        if holds || seen.len() == depth || bound >= span {
+            return (holds, totalled);
+        }
+        bound = (2.0 * bound).max(flips.iter().map(|f| f.0).find(|&d| d > bound).unwrap_or(span));
+    }
+}
+
+// Quantization step of the normalized harmonic magnitudes in `find_spectr`
+const SPECTR_STEP: f64 = 1.0 / 16.0;
+
+// Radial distance from the centroid of `cntr` as a function of the angle, the farthest point in
+// each of `bins` equal sectors. Sectors without points repeat the previous one (circularly), all
+// zeros when the contour is empty.
+fn radial_profile(cntr: &[Vec2], bins: usize) -> Vec<f64> {
+    let mut profile: Vec<Option<f64>> = repeat(None).take(bins).collect();
+    if cntr.is_empty() {
+        return repeat(0.0).take(bins).collect();
+    }
+    let n = cntr.len() as f64;
+    let cx = cntr.iter().map(|p| p.x).sum::<f64>() / n;
+    let cy = cntr.iter().map(|p| p.y).sum::<f64>() / n;
+    for p in cntr.iter() {
+        let (dx, dy) = (p.x - cx, p.y - cy);
+        let angle = libm::atan2(dy, dx) + core::f64::consts::PI;
+        let bin = ((angle / (2.0 * core::f64::consts::PI) * bins as f64) as usize).min(bins - 1);
+        let r = (dx * dx + dy * dy).sqrt();
+        profile[bin] = Some(profile[bin].map_or(r, |m: f64| m.max(r)));
+    }
+    let last = profile.iter().rev().find_map(|r| *r).unwrap_or(0.0);
+    let mut prev = last;
+    profile.into_iter()
+        .map(|r| {
+            prev = r.unwrap_or(prev);
+            prev
+        })
+        .collect()
+}
+
+// DFT magnitudes of `profile` for harmonics 1..=bins/2, divided by the DC term. Zeros when the
+// DC term is zero (empty or single-point contour).
+fn harmonics(profile: &[f64]) -> Vec<f64> {
+    let bins = profile.len();
+    let dc = profile.iter().sum::<f64>();
+    (1..=bins / 2)
+        .map(|k| {
+            if dc <= 0.0 {
+                return 0.0;
+            }
+            let (mut re, mut im) = (0.0, 0.0);
+            for (j, r) in profile.iter().enumerate() {
+                let phi = 2.0 * core::f64::consts::PI * (k * j) as f64 / bins as f64;
+                re += r * libm::cos(phi);
+                im -= r * libm::sin(phi);
+            }
+            (re * re + im * im).sqrt() / dc
+        })
+        .collect()
+}
+
+// Spectral signature of the sections: the radial distance function of every contour, sampled in
+// `grid_size` angular bins, is reduced to the magnitudes of its DFT. These don't change when the
+// contour is rotated (up to the binning), and dropping the DC term and dividing by it makes them
+// insensitive to translation and scale. Magnitudes are quantized to steps of `SPECTR_STEP`; the
+// i-th hash covers the first i + 1 harmonics of every section, so earlier hashes are coarser and
+// more tolerant. Sections missing from `centers` (nothing cut there) count as empty ones, with
+// zero harmonics after those of the sections cut.
+pub(crate) fn find_spectr(centers: &Vec<Vec<Vec2>>, depth: usize, n_sections: usize, grid_size: i16, _rect: Rect, hash_algo: HashAlgo) -> Vec<RawHash> {
+    let mut hashes = vec![];
+    if grid_size < 2 {
+        return hashes;
+    }
+    let mut spectra: Vec<Vec<u8>> = centers.iter()
+        .map(|cntr| {
+            harmonics(&radial_profile(cntr, grid_size as usize)).iter()
+                .map(|m| libm::round(m / SPECTR_STEP).min(255.0) as u8)
+                .collect()
+        })
+        .collect();
+    let n_harmonics = grid_size as usize / 2;
+    spectra.resize(spectra.len().max(n_sections), vec![0; n_harmonics]);
 
-        hashes.push(hex_hash.to_string());
+    for i in 0..depth.min(n_harmonics) {
+        hashes.push(digest(hash_algo, spectra.iter().map(|spectrum| &spectrum[..=i])));
     }
     hashes.dedup();
     hashes
 }
 
+/// Resolution of fixed-point scores: units per grid cell area
+pub const SCORE_SCALE: f64 = 65536.0;
+
+// Fixed-point value of a total score: `round(score * scale)`, saturating at `u32::MAX`
+pub(crate) fn fixed_point(score: f64, scale: f64) -> u32 {
+    let v = libm::round(score * scale);
+    if !(v >= 0.0) {
+        0
+    } else if v >= u32::MAX as f64 {
+        u32::MAX
+    } else {
+        v as u32
+    }
+}
+
 fn cross(triangles: &VectorTriangles) -> Array2<f64> {
     let dims = triangles.dim();
     let mut d = Array3::zeros((dims.0, 2, dims.1));
@@ -367,7 +652,8 @@ pub fn mass_properties(triangles: VectorTriangles) -> (Array1<f64>, Array2<f64>)
 
 
     let coefficients: Array1<f64> = arr1(&[1. / 6., 1. / 24., 1. / 24., 1. / 24., 1. / 60., 1. / 60., 1. / 60., 1. / 120., 1. / 120., 1. / 120.]);
-    let integrated: Array1<f64> = integral.sum_axis(Axis(1)) * coefficients;
+    let sums: Array1<f64> = integral.outer_iter().map(|row| compensated_sum(row.iter().cloned())).collect();
+    let integrated: Array1<f64> = sums * coefficients;
     let volume = integrated[0];
     let center_mass: Array1<f64> = if volume.abs() < 1e-10 {
         arr1(&[0., 0., 0.])
@@ -423,6 +709,14 @@ fn principal_axis(inertia: Array2<f64>) -> (Array1<f64>, Array2<f64>) {
     (components, vectors.reversed_axes())
 }
 
+// Principal moments of inertia, smallest first
+pub fn principal_moments(inertia: Array2<f64>) -> [f64; 3] {
+    let (components, _vectors) = principal_axis(inertia);
+    let mut m = [components[0].abs(), components[1].abs(), components[2].abs()];
+    m.sort_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+    m
+}
+
 #[allow(dead_code)]
 fn transform_around(matrix: Array2<f64>, point: &Array1<f64>) -> Array2<f64> {
     let mut translate: Array2<f64> = Array2::eye(4);
@@ -435,8 +729,97 @@ fn transform_around(matrix: Array2<f64>, point: &Array1<f64>) -> Array2<f64> {
     return result;
 }
 
+// Center and inertia tensor (same layout as `mass_properties`) from the total mass,
+// the first moments and the raw second moments (integral of x * x^T) about the origin
+fn central_inertia(mass: f64, first: Array1<f64>, second: Array2<f64>) -> (Array1<f64>, Array2<f64>) {
+    let center_mass: Array1<f64> = if mass.abs() < 1e-10 {
+        arr1(&[0., 0., 0.])
+    } else {
+        first / mass
+    };
+
+    let mut inertia: Array2<f64> = Array2::zeros((3, 3));
+    for i in 0..3 {
+        for j in 0..3 {
+            inertia[[i, j]] = if i == j {
+                let (a, b) = ((i + 1) % 3, (i + 2) % 3);
+                second[[a, a]] + second[[b, b]] -
+                    mass * (center_mass[a].powi(2) + center_mass[b].powi(2))
+            } else {
+                second[[i, j]] - mass * center_mass[i] * center_mass[j]
+            };
+        }
+    }
+    (center_mass, inertia)
+}
+
+// Inertia of the surface as a thin shell of unit density, every triangle weighted by its area
+pub fn surface_mass_properties(triangles: &VectorTriangles) -> (Array1<f64>, Array2<f64>) {
+    let mut mass = CompensatedSum::default();
+    let mut first = [CompensatedSum::default(); 3];
+    let mut second = [[CompensatedSum::default(); 3]; 3];
+
+    for t in triangles.outer_iter() {
+        let (p0, p1, p2) = (t.row(0), t.row(1), t.row(2));
+        let e1 = &p1 - &p0;
+        let e2 = &p2 - &p0;
+        let n = arr1(&[
+            e1[1] * e2[2] - e1[2] * e2[1],
+            e1[2] * e2[0] - e1[0] * e2[2],
+            e1[0] * e2[1] - e1[1] * e2[0],
+        ]);
+        let area = n.dot(&n).sqrt() / 2.0;
+        let sum = &p0 + &p1 + &p2;
+
+        mass.add(area);
+        for i in 0..3 {
+            first[i].add(sum[i] * (area / 3.0));
+        }
+        // Exact second moment of a triangle: A / 12 * (sum(v v^T) + s s^T)
+        for i in 0..3 {
+            for j in 0..3 {
+                second[i][j].add(area / 12.0 *
+                    (p0[i] * p0[j] + p1[i] * p1[j] + p2[i] * p2[j] + sum[i] * sum[j]));
+            }
+        }
+    }
+    central_inertia(mass.value(), moments_first(&first), moments_second(&second))
+}
+
+// Inertia of the vertices as a point cloud, every vertex with unit mass
+pub fn point_mass_properties(points: &Array2<f64>) -> (Array1<f64>, Array2<f64>) {
+    let mass = points.nrows() as f64;
+    let mut first = [CompensatedSum::default(); 3];
+    let mut second = [[CompensatedSum::default(); 3]; 3];
+    for p in points.outer_iter() {
+        for i in 0..3 {
+            first[i].add(p[i]);
+            for j in 0..3 {
+                second[i][j].add(p[i] * p[j]);
+            }
+        }
+    }
+    central_inertia(mass, moments_first(&first), moments_second(&second))
+}
+
+fn moments_first(first: &[CompensatedSum; 3]) -> Array1<f64> {
+    first.iter().map(|s| s.value()).collect()
+}
+
+fn moments_second(second: &[[CompensatedSum; 3]; 3]) -> Array2<f64> {
+    Array2::from_shape_fn((3, 3), |(i, j)| second[i][j].value())
+}
+
+/// Principal inertia transform of a closed triangle mesh (`[n, 3, 3]`: triangles, corners,
+/// coordinates), treated as a solid of uniform density. The upper 3 x 3 block of the 4 x 4
+/// result holds the principal axes, column 3 the translation moving the center of mass to the
+/// origin. `align_mesh` applies it to a `Mesh`.
 pub fn principal_inertia_transform(triangles: VectorTriangles) -> Array2<f64> {
     let (center_mass, inertia) = mass_properties(triangles);
+    inertia_transform(center_mass, inertia)
+}
+
+pub fn inertia_transform(center_mass: Array1<f64>, inertia: Array2<f64>) -> Array2<f64> {
     let (_components, vectors) = principal_axis(inertia);
 
     // TODO: Reorder vectors by components
@@ -451,70 +834,216 @@ pub fn principal_inertia_transform(triangles: VectorTriangles) -> Array2<f64> {
     transform
 }
 
-pub fn intersect(mesh: &Mesh, z_sect: f64) -> Vec::<Vec2> {
-    let mut sect = Vec::<Vec2>::new();
+// Material tag of contour points whose face has no material
+pub const NO_MATERIAL: u32 = u32::MAX;
 
-    for vertex_id in mesh.vertex_iter() {
-        let p = mesh.vertex_position(vertex_id);
-        if (p.z - z_sect).abs() < 0.15 {
-            sect.push(Vec2{x: p.x, y: p.y});
+// Faces get their ids in construction order, so the n-th face of `face_iter` is the n-th triangle
+fn face_material_map(mesh: &Mesh, face_materials: &[u32]) -> BTreeMap<FaceID, u32> {
+    mesh.face_iter().zip(face_materials.iter().cloned()).collect()
+}
+
+fn face_material(materials: &BTreeMap<FaceID, u32>, face: Option<FaceID>) -> u32 {
+    face.and_then(|f| materials.get(&f).cloned())
+        .unwrap_or(NO_MATERIAL)
+}
+
+// Where the edge from `p1` to `p2` crosses the plane at `z_sect`, snapped to an end within `delta`
+pub(crate) fn intersect_edge(p1: Vector3<f64>, p2: Vector3<f64>, z_sect: f64, delta: f64) -> Option<Vec2> {
+    if !(p2.z >= z_sect && p1.z <= z_sect || p2.z <= z_sect && p1.z >= z_sect) {
+        return None;
+    }
+    let (x, y);
+    let z1 = z_sect - p1.z;
+    let z2 = p2.z - z_sect;
+    if z1.abs() < delta {
+        (x, y) = (p1.x, p1.y);
+    }
+    else if z2.abs() < delta {
+        (x, y) = (p2.x, p2.y);
+    }
+    else {
+        let k = z2 / z1;
+        x = (p2.x + k * p1.x) / (k + 1.0);
+        y = (p2.y + k * p1.y) / (k + 1.0);
+    }
+    Some(Vec2{x, y})
+}
+
+// Material of a face around the vertex
+fn vertex_material(materials: &BTreeMap<FaceID, u32>, mesh: &Mesh, vertex_id: VertexID) -> u32 {
+    face_material(materials, mesh.walker_from_vertex(vertex_id).face_id())
+}
+
+// Material of the face the crossed edge belongs to
+fn edge_material(materials: &BTreeMap<FaceID, u32>, mesh: &Mesh, edge_id: HalfEdgeID) -> u32 {
+    let mut walker = mesh.walker_from_halfedge(edge_id);
+    let face = walker.face_id().or_else(|| walker.as_twin().face_id());
+    face_material(materials, face)
+}
+
+// Vertices closer than this to a section plane are its points in the vertex based algorithms
+pub(crate) const NEAR_PLANE: f64 = 0.15;
+
+// Sections (planes at `z0 + (n + 1) * step`) whose z is in [lo, hi], widened by one on each
+// side for rounding
+pub(crate) fn plane_range(lo: f64, hi: f64, z0: f64, step: f64, n_sections: usize) -> Range<usize> {
+    let first = libm::floor((lo - z0) / step) - 2.0;
+    let last = libm::floor((hi - z0) / step) + 1.0;
+    if step <= 0.0 || !first.is_finite() || !last.is_finite() {
+        return 0..n_sections;
+    }
+    let clamp = |n: f64| if n < 0.0 { 0 } else if n > n_sections as f64 { n_sections } else { n as usize };
+    clamp(first)..clamp(last + 1.0)
+}
+
+// The z extent of every edge (or of every vertex, widened by `NEAR_PLANE`) of a mesh, in the
+// order the mesh iterates them: what `SectionIndex` needs of the mesh, kept to index several
+// slicings of the same mesh without walking it again
+#[derive(Clone)]
+pub(crate) struct ZRanges {
+    of_edges: bool,
+    edges: Vec<(HalfEdgeID, f64, f64)>,
+    vertices: Vec<(VertexID, f64, f64)>,
+}
+
+impl ZRanges {
+    pub(crate) fn new(mesh: &Mesh, edges: bool) -> Self {
+        if edges {
+            let edges = mesh.edge_iter()
+                .map(|edge_id| {
+                    let (p1, p2) = mesh.edge_positions(edge_id);
+                    (edge_id, p1.z.min(p2.z), p1.z.max(p2.z))
+                })
+                .collect();
+            ZRanges { of_edges: true, edges, vertices: Vec::new() }
+        } else {
+            let vertices = mesh.vertex_iter()
+                .map(|vertex_id| {
+                    let z = mesh.vertex_position(vertex_id).z;
+                    (vertex_id, z - NEAR_PLANE, z + NEAR_PLANE)
+                })
+                .collect();
+            ZRanges { of_edges: false, edges: Vec::new(), vertices }
         }
     }
-    sect
+
+    // Whether these are the ranges of the edges
+    pub(crate) fn edges(&self) -> bool {
+        self.of_edges
+    }
 }
 
-pub fn intersect_2(mesh: &Mesh, z_sect: f64, delta: f64) -> Vec::<Vec2> {
-    let mut sect = Vec::<Vec2>::new();
+// The vertices and edges that can meet each of the planes of a slicing (`z0 + (n + 1) * step`,
+// or given heights), found in one pass over the mesh. A section then visits only its
+// candidates instead of the whole mesh, which makes slicing about O(faces + output) instead of O(faces * sections).
+// Candidates keep the mesh's iteration order and still go through the exact tests of
+// `intersect` and `intersect_2`, so the sections are the same as a full scan.
+// The positions and materials of the candidates are copied out of the mesh (which can't be
+// shared between threads), so the sections can be cut in parallel from one index.
+pub(crate) struct SectionIndex {
+    // Candidates of each section, as indices into `points` or `ends`
+    vertices: Vec<Vec<usize>>,
+    edges: Vec<Vec<usize>>,
+    points: Vec<(Vector3<f64>, u32)>,
+    ends: Vec<(Vector3<f64>, Vector3<f64>, u32)>,
+}
+
+impl SectionIndex {
+    // Only what the algorithm slices with is indexed: the edges when `ranges` are those of the
+    // edges (`intersect_2`), the vertices otherwise. `face_materials` may be empty when the
+    // points are not tagged.
+    pub(crate) fn new(mesh: &Mesh, ranges: &ZRanges, z0: f64, step: f64, n_sections: usize, face_materials: &[u32]) -> Self {
+        Self::build(mesh, ranges, n_sections, face_materials, |lo, hi| plane_range(lo, hi, z0, step, n_sections))
+    }
 
-    for edge_id in mesh.edge_iter() {
-        let (p1, p2) = mesh.edge_positions(edge_id);
-        if p2.z >= z_sect && p1.z <= z_sect || p2.z <= z_sect && p1.z >= z_sect {
-            let (x, y);
-            let z1 = z_sect - p1.z;
-            let z2 = p2.z - z_sect;
-            if z1.abs() < delta {
-                (x, y) = (p1.x, p1.y);
+    // Same as `new` for planes at arbitrary ascending `heights`, section `n` at `heights[n]`
+    pub(crate) fn with_heights(mesh: &Mesh, ranges: &ZRanges, heights: &[f64], face_materials: &[u32]) -> Self {
+        let n_sections = heights.len();
+        let planes = |lo: f64, hi: f64| {
+            if !lo.is_finite() || !hi.is_finite() {
+                return 0..n_sections;
             }
-            else if z2.abs() < delta {
-                (x, y) = (p2.x, p2.y);
+            let first = heights.partition_point(|&z| z < lo).saturating_sub(1);
+            let last = heights.partition_point(|&z| z <= hi);
+            first..(last + 1).min(n_sections)
+        };
+        Self::build(mesh, ranges, n_sections, face_materials, planes)
+    }
+
+    fn build<P: Fn(f64, f64) -> Range<usize>>(mesh: &Mesh, ranges: &ZRanges, n_sections: usize, face_materials: &[u32], planes: P) -> Self {
+        let materials = face_material_map(mesh, face_materials);
+        let mut index = SectionIndex {
+            vertices: vec![Vec::new(); n_sections],
+            edges: vec![Vec::new(); n_sections],
+            points: Vec::with_capacity(ranges.vertices.len()),
+            ends: Vec::with_capacity(ranges.edges.len()),
+        };
+        for &(edge_id, lo, hi) in ranges.edges.iter() {
+            let (p1, p2) = mesh.edge_positions(edge_id);
+            for n in planes(lo, hi) {
+                index.edges[n].push(index.ends.len());
             }
-            else {
-                let k = z2 / z1;
-                x = (p2.x + k * p1.x) / (k + 1.0);
-                y = (p2.y + k * p1.y) / (k + 1.0);
+            index.ends.push((p1, p2, edge_material(&materials, mesh, edge_id)));
+        }
+        for &(vertex_id, lo, hi) in ranges.vertices.iter() {
+            for n in planes(lo, hi) {
+                index.vertices[n].push(index.points.len());
             }
-            sect.push(Vec2{x, y});
+            index.points.push((mesh.vertex_position(vertex_id), vertex_material(&materials, mesh, vertex_id)));
         }
+        index
     }
-    sect
-}
 
-pub fn get_contour(sect: Vec<Vec2>) -> Vec<Point2<f64>> {
-    let len = sect.len();
-    if len == 0 {
-        return Vec::new();
+    // Vertices of section `n` within 0.15 of the plane
+    pub(crate) fn intersect(&self, n: usize, z_sect: f64) -> Vec::<Vec2> {
+        self.intersect_tagged(n, z_sect).into_iter().map(|(p, _)| p).collect()
     }
 
-    let mut mt: Vec<Vec<f32>> = Vec::with_capacity(len);
-    let mut v: Vec<f32> = Vec::with_capacity(len);
-    v.resize(len, 0f32);
-    mt.resize(len, v);
+    // Edges of section `n` crossing the plane, snapped to an end within `delta`
+    pub(crate) fn intersect_2(&self, n: usize, z_sect: f64, delta: f64) -> Vec::<Vec2> {
+        self.intersect_2_tagged(n, z_sect, delta).into_iter().map(|(p, _)| p).collect()
+    }
 
-    for (i, p) in sect.iter().enumerate() {
-        //let v: Vec<f32> = Vec::with_capacity(len);
-        // v.resize(len, 0f32);
-        //mt.push(v);
-        for (j, q) in sect.iter().enumerate() {
-            mt[i][j] = p.distance2(*q) as f32;
-        }
+    // Same as `intersect`, each point tagged with the material of a face around the vertex
+    pub(crate) fn intersect_tagged(&self, n: usize, z_sect: f64) -> Vec::<(Vec2, u32)> {
+        self.vertices[n].iter()
+            .map(|&i| self.points[i])
+            .filter(|(p, _)| (p.z - z_sect).abs() < NEAR_PLANE)
+            .map(|(p, tag)| (Vec2{x: p.x, y: p.y}, tag))
+            .collect()
     }
 
-    let mut ii: Vec<usize> = (0..len).collect();
-    for i in 0..len - 1 {
-        let v = &mt[ii[i]];
-        let j = (i + 1..len).min_by_key(|&k| (v[ii[k]] * 10000.0) as u32).unwrap();
-        ii.swap(i + 1, j);
+    // Same as `intersect_2`, each point tagged with the material of the face the crossed edge belongs to
+    pub(crate) fn intersect_2_tagged(&self, n: usize, z_sect: f64, delta: f64) -> Vec::<(Vec2, u32)> {
+        self.edges[n].iter()
+            .filter_map(|&i| {
+                let (p1, p2, tag) = self.ends[i];
+                intersect_edge(p1, p2, z_sect, delta).map(|p| (p, tag))
+            })
+            .collect()
+    }
+}
+
+pub fn get_contour(sect: Vec<Vec2>) -> Vec<Point2<f64>> {
+    contour_with_sources(sect).0
+}
+
+// Same as `get_contour`, carrying the material tag of every source point along
+pub fn get_contour_tagged(sect: Vec<(Vec2, u32)>) -> (Vec<Point2<f64>>, Vec<u32>) {
+    let (points, tags): (Vec<Vec2>, Vec<u32>) = sect.into_iter().unzip();
+    let (cntr, sources) = contour_with_sources(points);
+    let cntr_tags = sources.iter().map(|&i| tags[i]).collect();
+    (cntr, cntr_tags)
+}
+
+// Orders the section points into a contour, returning for each contour point
+// the index of the section point it comes from
+fn contour_with_sources(sect: Vec<Vec2>) -> (Vec<Point2<f64>>, Vec<usize>) {
+    let len = sect.len();
+    if len == 0 {
+        return (Vec::new(), Vec::new());
     }
+    let ii = chain_order(&sect);
 
     let mut cntr: Vec<Point2<f64>> = sect
         .iter().enumerate()
@@ -522,6 +1051,8 @@ pub fn get_contour(sect: Vec<Vec2>) -> Vec<Point2<f64>> {
         .collect();
 
     cntr.push(cntr.as_slice()[0]);
+    let mut sources: Vec<usize> = ii.clone();
+    sources.push(ii[0]);
 
     // println!("contour len: {}", sect.len());
     // println!("contour: {:?}", cntr);
@@ -537,7 +1068,8 @@ pub fn get_contour(sect: Vec<Vec2>) -> Vec<Point2<f64>> {
 
         let p = Point2 { x: p0.x + (n as f64) * d2, y: p0.y + (n as f64) * d2 * k };
         cntr.push(p);
+        sources.push(ii[0]);
     }
 
-    cntr
+    (cntr, sources)
 }
@@ -0,0 +1,67 @@
+use alloc::sync::Arc;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::P3DError;
+
+#[derive(Default)]
+struct Inner {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// Accounting of the large buffers of `p3d_process_*` calls, see `ProcessOptions::allocations`:
+/// the loaded geometry, the mesh built from it, the triangle array of the alignment and the
+/// section contours. Every buffer is counted from when it is made until the stage using it
+/// ends, and a buffer that would take the bytes held at once over `Limits::max_allocation`
+/// fails the call with `P3DError::LimitExceeded` before it is made. Clones share the counts,
+/// so one kept by the caller reads the peak after the call (which `P3DResult::peak_allocation`
+/// reports too). Sizes are estimates of the heap bytes, not an allocator hook.
+#[derive(Clone, Default)]
+pub struct Allocations(Arc<Inner>);
+
+impl Allocations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bytes held now, 0 between calls
+    pub fn current(&self) -> usize {
+        self.0.current.load(Ordering::Relaxed)
+    }
+
+    /// Most bytes held at once since this was made
+    pub fn peak(&self) -> usize {
+        self.0.peak.load(Ordering::Relaxed)
+    }
+
+    // Counts `bytes` of `what` until the returned charge is dropped, unless that takes the
+    // total over `limit`
+    pub(crate) fn charge(&self, what: &'static str, bytes: usize, limit: usize) -> Result<Charge, P3DError> {
+        let held = self.0.current.fetch_add(bytes, Ordering::Relaxed).saturating_add(bytes);
+        if held > limit {
+            self.0.current.fetch_sub(bytes, Ordering::Relaxed);
+            return Err(P3DError::LimitExceeded { what, value: held, limit });
+        }
+        self.0.peak.fetch_max(held, Ordering::Relaxed);
+        Ok(Charge { allocations: self.clone(), bytes })
+    }
+}
+
+impl fmt::Debug for Allocations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Allocations").field("current", &self.current()).field("peak", &self.peak()).finish()
+    }
+}
+
+// Bytes counted by an `Allocations` while the buffer they stand for lives
+pub(crate) struct Charge {
+    allocations: Allocations,
+    bytes: usize,
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.allocations.0.current.fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,137 @@
+// Command line front end of the crate (`cli` feature): hashes model files with `p3d_process_cfg`.
+
+use std::path::Path;
+use std::process::exit;
+
+use p3d::{p3d_process_cfg, AlgoType, InputFileType, P3DParams, TransEncoding};
+
+const USAGE: &str = "\
+Usage: p3d [OPTIONS] FILE...
+
+Prints the hashes of every FILE, one per line, prefixed with the file name when there are several.
+
+Options:
+  --algo NAME       grid2d, grid2d_v2, grid2d_v3, grid2d_v3a (default), spectr, skeleton,
+                    cloud_grid, voxel, fixed_grid, cell_stats or grid_normals
+  --grid N          cells along each side of the grid (default 8)
+  --sections N      number of sections (default 10)
+  --depth N         maximum number of hashes (default 10)
+  --rot A,B,C,D     rotation, 4 bytes, or 8 for the finer v2 encoding
+  --type EXT        file type (obj, gltf, glb, stl, ply, xyz, 3mf, off) instead of the extension
+                    or, for other extensions, the content
+  --json            print a JSON object per file: {\"file\", \"algo\", \"hashes\"} or {\"file\", \"error\"}
+  -h, --help        print this help";
+
+struct Args {
+    params: P3DParams,
+    file_type: Option<InputFileType>,
+    json: bool,
+    files: Vec<String>,
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("p3d: {}\n\n{}", message, USAGE);
+    exit(2);
+}
+
+fn number<T: std::str::FromStr>(flag: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| usage_error(&format!("{} expects a number, got '{}'", flag, value)))
+}
+
+fn parse_args() -> Args {
+    let mut args = Args { params: P3DParams::default(), file_type: None, json: false, files: Vec::new() };
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        let mut value = |flag: &str| argv.next().unwrap_or_else(|| usage_error(&format!("{} expects a value", flag)));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            }
+            "--algo" => {
+                let name = value("--algo");
+                args.params.algo = name.parse::<AlgoType>().unwrap_or_else(|e| usage_error(&e.to_string()));
+            }
+            "--grid" => args.params.grid_size = number("--grid", &value("--grid")),
+            "--sections" => args.params.n_sections = number("--sections", &value("--sections")),
+            "--depth" => args.params.depth = number("--depth", &value("--depth")),
+            "--rot" => {
+                let rot: Vec<u8> = value("--rot").split(',').map(|b| number("--rot", b.trim())).collect();
+                let encoding = if rot.len() == 8 { TransEncoding::V2 } else { TransEncoding::V1 };
+                let transform = encoding.decode(&rot).unwrap_or_else(|_| usage_error("--rot expects 4 or 8 comma separated bytes"));
+                args.params = args.params.transform(transform);
+            }
+            "--type" => {
+                let ext = value("--type");
+                args.file_type = Some(InputFileType::from_extension(&ext).unwrap_or_else(|| usage_error(&format!("unknown file type '{}'", ext))));
+            }
+            "--json" => args.json = true,
+            flag if flag.starts_with('-') && flag != "-" => usage_error(&format!("unknown option '{}'", flag)),
+            _ => args.files.push(arg),
+        }
+    }
+    if args.files.is_empty() {
+        usage_error("no input file");
+    }
+    args
+}
+
+// The type of a file by its extension, otherwise detected from the content (gzipped files too)
+fn file_type(path: &Path) -> InputFileType {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(InputFileType::from_extension)
+        .unwrap_or(InputFileType::Auto)
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn main() {
+    let args = parse_args();
+    let mut failed = false;
+    for file in args.files.iter() {
+        let hashes = std::fs::read(file)
+            .map_err(|e| e.to_string())
+            .and_then(|input| {
+                let file_type = args.file_type.unwrap_or_else(|| file_type(Path::new(file)));
+                p3d_process_cfg(&input, file_type, &args.params).map_err(|e| e.to_string())
+            });
+        match (hashes, args.json) {
+            (Ok(hashes), false) => {
+                for hash in hashes.iter() {
+                    if args.files.len() > 1 {
+                        println!("{}: {}", file, hash);
+                    } else {
+                        println!("{}", hash);
+                    }
+                }
+            }
+            (Ok(hashes), true) => {
+                let hashes: Vec<String> = hashes.iter().map(|h| json_string(h)).collect();
+                println!("{{\"file\":{},\"algo\":\"{}\",\"hashes\":[{}]}}", json_string(file), args.params.algo, hashes.join(","));
+            }
+            (Err(message), json) => {
+                failed = true;
+                eprintln!("p3d: {}: {}", file, message);
+                if json {
+                    println!("{{\"file\":{},\"error\":{}}}", json_string(file), json_string(&message));
+                }
+            }
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
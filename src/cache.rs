@@ -0,0 +1,74 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{build_mesh_opts, load_geometry, process_mesh, AlgoType, Geometry, InputFileType, P3DError, ProcessOptions};
+
+fn file_type(path: &Path) -> Result<InputFileType, P3DError> {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if ext.eq_ignore_ascii_case("gz") {
+        // `.off.gz` and the like, decompressed when loaded (`gzip` feature)
+        return file_type(Path::new(path.file_stem().unwrap_or_default()));
+    }
+    InputFileType::from_extension(ext).ok_or(P3DError::UnsupportedFileType)
+}
+
+/// Geometry of model files as loaded from disk (before alignment), keyed by path. An entry is
+/// reloaded when the file's modification time changes. The file type comes from the extension
+/// (`.obj`, `.gltf`, `.glb`, `.stl`, `.ply`, `.xyz`, `.3mf`, `.off`, any of them followed by
+/// `.gz`) and files are loaded with the default `ProcessOptions`.
+#[derive(Default)]
+pub struct MeshCache {
+    entries: HashMap<PathBuf, (SystemTime, Geometry)>,
+    hits: usize,
+    misses: usize,
+}
+
+impl MeshCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lookups served from the cache so far
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Lookups that had to load the file so far
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn geometry(&mut self, path: &Path) -> Result<Geometry, P3DError> {
+        let mtime = std::fs::metadata(path).and_then(|m| m.modified()).map_err(|e| P3DError::Io(e))?;
+        if let Some((cached_mtime, geometry)) = self.entries.get(path) {
+            if *cached_mtime == mtime {
+                self.hits += 1;
+                return Ok(geometry.clone());
+            }
+        }
+        self.misses += 1;
+        let input = std::fs::read(path).map_err(|e| P3DError::Io(e))?;
+        let geometry = load_geometry(&input, file_type(path)?, &ProcessOptions::default())?;
+        self.entries.insert(path.to_path_buf(), (mtime, geometry.clone()));
+        Ok(geometry)
+    }
+
+    /// Same as `p3d_process` on the contents of `path`, loading the file only if needed.
+    pub fn process(&mut self, path: &Path, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError> {
+        let opts = ProcessOptions::default();
+        let (mesh, face_materials) = build_mesh_opts(self.geometry(path)?, &opts)?;
+        process_mesh(mesh, &face_materials, algo, 10, par1, par2, trans, &opts).map(|res| res.hashes)
+    }
+}
+
+/// Processes several files with the same parameters through `cache`, one result per path.
+pub fn p3d_process_paths(paths: &[&Path], cache: &mut MeshCache, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Vec<Result<Vec<String>, P3DError>> {
+    paths.iter().map(|path| cache.process(path, algo, par1, par2, trans)).collect()
+}
@@ -0,0 +1,21 @@
+use gltf::Document;
+use tri_mesh::prelude::*;
+
+use crate::error::gltf_error;
+use crate::{skinning, P3DError};
+
+// Rotation taking the view of camera `camera` (by index) to the model axes: the camera forward
+// direction becomes +Z and its up direction stays +Y. Scale and translation of the camera node
+// are ignored.
+pub(crate) fn camera_view(document: &Document, camera: usize) -> Result<Matrix4<f64>, P3DError> {
+    let node = document.nodes().find(|n| n.camera().map(|c| c.index()) == Some(camera))
+        .ok_or_else(|| gltf_error("no node uses the view camera"))?;
+    let global = skinning::node_transforms(document)[node.index()];
+
+    // glTF cameras look down their local -Z with +Y up
+    let right = global.x.truncate().normalize();
+    let up = global.y.truncate().normalize();
+    let back = global.z.truncate().normalize();
+    let rows = Matrix3::from_cols(-right, up, -back);
+    Ok(Matrix4::from(rows.transpose()))
+}
@@ -0,0 +1,64 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// Calls of `poll` between two calls of the check
+const POLL_INTERVAL: usize = 256;
+
+struct Inner {
+    check: Box<dyn Fn() -> bool + Send + Sync>,
+    cancelled: AtomicBool,
+    polls: AtomicUsize,
+}
+
+/// Stops a running `p3d_process_*` call, see `ProcessOptions::cancel`. Either a token
+/// cancelled by calling `cancel` on a clone of it (from another thread, a signal handler...),
+/// or a check called periodically while slicing and ranking, e.g. comparing the time with a
+/// deadline. Once cancelled it stays cancelled, whatever the check returns afterwards.
+#[derive(Clone)]
+pub struct Cancel(Arc<Inner>);
+
+impl Cancel {
+    /// A token only cancelled by `cancel`
+    pub fn token() -> Self {
+        Self::new(|| false)
+    }
+
+    /// Cancelled as soon as `check` returns true
+    pub fn new<F: Fn() -> bool + Send + Sync + 'static>(check: F) -> Self {
+        Cancel(Arc::new(Inner { check: Box::new(check), cancelled: AtomicBool::new(false), polls: AtomicUsize::new(0) }))
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token was cancelled or the check returns true now
+    pub fn is_cancelled(&self) -> bool {
+        if self.0.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        if (self.0.check)() {
+            self.cancel();
+            return true;
+        }
+        false
+    }
+
+    // `is_cancelled` for inner loops: the token every time, the check only every
+    // `POLL_INTERVAL` calls
+    pub(crate) fn poll(&self) -> bool {
+        if self.0.polls.fetch_add(1, Ordering::Relaxed) % POLL_INTERVAL == 0 {
+            self.is_cancelled()
+        } else {
+            self.0.cancelled.load(Ordering::Relaxed)
+        }
+    }
+}
+
+impl fmt::Debug for Cancel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cancel").field("cancelled", &self.0.cancelled.load(Ordering::Relaxed)).finish()
+    }
+}
@@ -0,0 +1,94 @@
+//! The principal inertia alignment `p3d_process` poses meshes with, on raw position and index
+//! buffers, for canonicalizing meshes outside the hashing (before storage or rendering).
+//! Positions are the x, y, z of every vertex and indices the vertex indices of every triangle,
+//! as for `p3d_process_mesh`, and are checked the same way.
+
+use alloc::vec::Vec;
+
+use ndarray::{Array1, Array2};
+use cgmath::Transform as _;
+use tri_mesh::prelude::{Matrix4, Point3, Vector3};
+
+use crate::algo_grid::{inertia_transform, principal_moments};
+use crate::{
+    checked_geometry, pit_matrices, triangle_array, weighted_inertia, Geometry, InertiaWeighting, P3DError, ProcessOptions,
+};
+
+/// Center of mass and inertia tensor of a mesh, see `inertia_tensor`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Inertia {
+    pub center: [f64; 3],
+    /// Inertia tensor about `center`, rows first: the moments about the axes on the diagonal,
+    /// the negated products of inertia off it
+    pub tensor: [[f64; 3]; 3],
+}
+
+impl Inertia {
+    /// Moments about the principal axes, smallest first
+    pub fn principal_moments(&self) -> [f64; 3] {
+        principal_moments(self.layout())
+    }
+
+    // The tensor as `algo_grid` keeps it, with the products of inertia off the diagonal
+    fn layout(&self) -> Array2<f64> {
+        Array2::from_shape_fn((3, 3), |(i, j)| if i == j { self.tensor[i][j] } else { -self.tensor[i][j] })
+    }
+}
+
+// Center and tensor in the layout of `algo_grid::mass_properties`
+fn mass_properties(positions: &[f64], indices: &[u32], weighting: InertiaWeighting) -> Result<(Array1<f64>, Array2<f64>), P3DError> {
+    let geometry = Geometry { positions: positions.to_vec(), indices: indices.to_vec(), materials: Vec::new() };
+    let geometry = checked_geometry(geometry, &ProcessOptions::default())?;
+    let corner = |i: u32| {
+        let k = i as usize * 3;
+        [geometry.positions[k], geometry.positions[k + 1], geometry.positions[k + 2]]
+    };
+    // The volume integrals depend on the corner a triangle starts at, this is where
+    // `Mesh::face_vertices` (and `Soup`) start them
+    let corners: Vec<[[f64; 3]; 3]> = geometry.indices.chunks(3).map(|t| [corner(t[1]), corner(t[2]), corner(t[0])]).collect();
+    let vertices = || geometry.positions.chunks(3).map(|p| Vector3::new(p[0], p[1], p[2])).collect();
+    weighted_inertia(triangle_array(&corners), vertices, weighting)
+}
+
+/// Center of mass and inertia tensor of the mesh, with the mass distributed as `weighting`
+/// says. `InertiaWeighting::Volume`, what `p3d_process` uses, gives the tensor the hashes have
+/// always been computed from, which depends on the corners the triangles start at and only
+/// approximates that of the solid; the other weightings are exact. Flat and degenerate meshes
+/// fail like in `p3d_process`.
+pub fn inertia_tensor(positions: &[f64], indices: &[u32], weighting: InertiaWeighting) -> Result<Inertia, P3DError> {
+    let (center, inertia) = mass_properties(positions, indices, weighting)?;
+    let mut tensor = [[0.0; 3]; 3];
+    for (i, row) in tensor.iter_mut().enumerate() {
+        for (j, t) in row.iter_mut().enumerate() {
+            *t = if i == j { inertia[[i, j]] } else { -inertia[[i, j]] };
+        }
+    }
+    Ok(Inertia { center: [center[0], center[1], center[2]], tensor })
+}
+
+/// The transform `p3d_process` aligns the mesh with before slicing: the center of mass to the
+/// origin, then the principal axes onto the coordinate axes. The same mesh in any position and
+/// orientation gets the same pose, up to the signs of the axes (see
+/// `ProcessOptions::orient_axes`). Pass it as `ProcessOptions::alignment` to hash other
+/// meshes in the pose of this one.
+pub fn alignment_transform(positions: &[f64], indices: &[u32], weighting: InertiaWeighting) -> Result<Matrix4<f64>, P3DError> {
+    let (center, inertia) = mass_properties(positions, indices, weighting)?;
+    let (shift, rotation) = pit_matrices(&inertia_transform(center, inertia))?;
+    Ok(rotation * Matrix4::from_translation(shift))
+}
+
+/// Applies `transform` to the x, y, z of every vertex in `positions`
+pub fn apply(positions: &mut [f64], transform: &Matrix4<f64>) {
+    for p in positions.chunks_exact_mut(3) {
+        let q = transform.transform_point(Point3::new(p[0], p[1], p[2]));
+        p.copy_from_slice(&[q.x, q.y, q.z]);
+    }
+}
+
+/// Moves the mesh to its `alignment_transform` in place and returns the transform, whose
+/// inverse takes it back.
+pub fn canonicalize(positions: &mut [f64], indices: &[u32], weighting: InertiaWeighting) -> Result<Matrix4<f64>, P3DError> {
+    let transform = alignment_transform(positions, indices, weighting)?;
+    apply(positions, &transform);
+    Ok(transform)
+}
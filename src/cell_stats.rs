@@ -0,0 +1,123 @@
+use alloc::vec::Vec;
+
+use crate::algo_grid::{digest, HashAlgo, RawHash};
+use crate::contour::Rect;
+use crate::{CellStatistic, Vec2};
+
+// Sub-cells along each side of a cell for `CellStatistic::Entropy`
+const ENTROPY_BINS: usize = 4;
+
+// Skewness mapped to [0, 1] by `(s + SKEW_RANGE) / (2 * SKEW_RANGE)`, clamped
+const SKEW_RANGE: f64 = 4.0;
+
+// Moments of the distances of the contour points of a cell from its center, as a fraction of
+// half its diagonal, and where in the cell they fall
+#[derive(Clone, Default)]
+struct Cell {
+    count: u32,
+    sums: [f64; 3],
+    bins: [u32; ENTROPY_BINS * ENTROPY_BINS],
+}
+
+impl Cell {
+    fn moments(&self) -> (f64, f64, f64) {
+        let n = self.count as f64;
+        let mean = self.sums[0] / n;
+        let variance = (self.sums[1] / n - mean * mean).max(0.0);
+        let third = self.sums[2] / n - 3.0 * mean * variance - mean * mean * mean;
+        (mean, variance, third)
+    }
+
+    // The statistic of an occupied cell, in [0, 1] but for `Count`
+    fn value(&self, statistic: CellStatistic, mean_count: f64) -> f64 {
+        let (mean, variance, third) = self.moments();
+        match statistic {
+            CellStatistic::Count => self.count as f64 / mean_count,
+            CellStatistic::Mean => mean,
+            CellStatistic::StdDev => libm::sqrt(variance),
+            CellStatistic::Skewness => {
+                let sigma = libm::sqrt(variance);
+                let skew = if sigma > 0.0 { third / (sigma * sigma * sigma) } else { 0.0 };
+                ((skew + SKEW_RANGE) / (2.0 * SKEW_RANGE)).clamp(0.0, 1.0)
+            }
+            CellStatistic::Entropy => {
+                let n = self.count as f64;
+                let bits: f64 = self.bins.iter()
+                    .filter(|&&b| b > 0)
+                    .map(|&b| {
+                        let p = b as f64 / n;
+                        -p * libm::log2(p)
+                    })
+                    .sum();
+                bits / libm::log2((ENTROPY_BINS * ENTROPY_BINS) as f64)
+            }
+        }
+    }
+}
+
+// The cells of the `grid_size` x `grid_size` grid over `rect` with the points of `contour`,
+// rows (y) first. Points outside the rect count in the nearest cell.
+fn section_cells(contour: &[Vec2], grid_size: usize, rect: &Rect) -> Vec<Cell> {
+    let n = grid_size as i32;
+    let (w, h) = (rect.width() / grid_size as f64, rect.height() / grid_size as f64);
+    let half_diagonal = libm::sqrt(w * w + h * h) / 2.0;
+    let [min_x, _, min_y, _] = rect.bounds();
+    let mut cells = vec![Cell::default(); grid_size * grid_size];
+    for p in contour.iter() {
+        let p = rect.clamp(p);
+        let (i, j) = rect.cell(&p, n);
+        let (i, j) = (i.max(0).min(n - 1), j.max(0).min(n - 1));
+        // Position within the cell, in [0, 1]
+        let u = ((p.x - min_x) / w - i as f64).clamp(0.0, 1.0);
+        let v = ((p.y - min_y) / h - j as f64).clamp(0.0, 1.0);
+        let (dx, dy) = ((u - 0.5) * w, (v - 0.5) * h);
+        let d = if half_diagonal > 0.0 { libm::sqrt(dx * dx + dy * dy) / half_diagonal } else { 0.0 };
+        let cell = &mut cells[(j * n + i) as usize];
+        cell.count += 1;
+        cell.sums[0] += d;
+        cell.sums[1] += d * d;
+        cell.sums[2] += d * d * d;
+        let bin = |t: f64| ((t * ENTROPY_BINS as f64) as usize).min(ENTROPY_BINS - 1);
+        cell.bins[bin(v) * ENTROPY_BINS + bin(u)] += 1;
+    }
+    cells
+}
+
+// `AlgoType::CellStats`: the `statistic` of every grid cell of every section, hashed by
+// `quantized_hashes`
+pub(crate) fn find_top_cell_stats(
+    centers: &[Vec<Vec2>], depth: usize, grid_size: i16, rect: &Rect, statistic: CellStatistic, hash_algo: HashAlgo,
+) -> Vec<RawHash> {
+    let grid_size = grid_size.max(1) as usize;
+    let sections: Vec<Vec<Cell>> = centers.iter().map(|contour| section_cells(contour, grid_size, rect)).collect();
+    let values: Vec<Vec<Option<f64>>> = sections.iter()
+        .map(|cells| {
+            let occupied = cells.iter().filter(|c| c.count > 0).count();
+            let total: u32 = cells.iter().map(|c| c.count).sum();
+            let mean_count = total as f64 / occupied.max(1) as f64;
+            cells.iter().map(|c| if c.count > 0 { Some(c.value(statistic, mean_count)) } else { None }).collect()
+        })
+        .collect();
+    quantized_hashes(&values, depth, hash_algo)
+}
+
+// Per-cell values of every section (None for an empty cell), hashed at increasing precision.
+// Hash `d` digests the values rounded down to steps of `2^-d`, one byte per cell (0 for an
+// empty cell, then 1 + the steps, at most 255), so the first hashes are the coarsest and most
+// robust to noise. Repeated hashes are dropped.
+pub(crate) fn quantized_hashes(values: &[Vec<Option<f64>>], depth: usize, hash_algo: HashAlgo) -> Vec<RawHash> {
+    let mut hashes: Vec<RawHash> = Vec::with_capacity(depth);
+    for level in 0..depth {
+        let scale = (1u64 << level.min(32)) as f64;
+        let quantized: Vec<Vec<u8>> = values.iter()
+            .map(|cells| cells.iter()
+                .map(|v| v.map_or(0, |v| 1 + libm::floor(v * scale).min(254.0) as u8))
+                .collect())
+            .collect();
+        let hash = digest(hash_algo, quantized.iter().map(|s| s.as_slice()));
+        if !hashes.contains(&hash) {
+            hashes.push(hash);
+        }
+    }
+    hashes
+}
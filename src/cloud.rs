@@ -0,0 +1,127 @@
+use alloc::vec::Vec;
+
+use cgmath::Point2;
+use ndarray::Array2;
+use tri_mesh::prelude::{Matrix4, SquareMatrix, Vector3};
+
+use crate::algo_grid::{self, digest, to_hex};
+use crate::contour::Rect;
+use crate::degenerate::{check_inertia, check_span};
+use crate::{
+    charge, check_flat, check_limit, check_params, load_geometry, pit_matrices, plain_input, transform_matrix, xyz, InputFileType, P3DError,
+    Allocations, P3DParams, P3DResult, RawHash,
+};
+
+// Points of any input: every vertex of a mesh, faces or not
+fn load_points(input: &[u8], file_type: InputFileType, params: &P3DParams) -> Result<Vec<f64>, P3DError> {
+    let opts = &params.options;
+    let input = &*plain_input(input, opts)?;
+    let positions = match file_type.resolve(input)? {
+        // The OBJ loaders keep only the vertices faces use
+        InputFileType::Obj => xyz::obj_points(input)?,
+        file_type => load_geometry(input, file_type, opts)?.positions,
+    };
+    check_limit("vertices", positions.len() / 3, opts.limits.max_vertices)?;
+    Ok(positions)
+}
+
+// Point counts of the cells of one slab, quantized for the hash at `level`:
+// the density of every cell relative to the mean density of the slab, in steps of 2^-level
+fn quantize(cells: &[u32], level: usize) -> Vec<u8> {
+    let total: u32 = cells.iter().sum();
+    if total == 0 {
+        return vec![0; cells.len()];
+    }
+    let scale = cells.len() as f64 * (1u64 << level.min(32)) as f64 / total as f64;
+    cells.iter().map(|&n| libm::floor(n as f64 * scale).min(255.0) as u8).collect()
+}
+
+// `AlgoType::CloudGrid`: aligns the points by their own principal axes (every point with unit
+// mass), splits them into `n_sections` slabs of equal height along z and counts the points of
+// every slab in the cells of a grid over the XY bounds of all points. Hash `d` digests the
+// densities of all slabs quantized to `2^-d` of the mean, so the first hashes are the coarsest
+// and most robust to noise. Repeated hashes are dropped.
+pub(crate) fn process_cloud(input: &[u8], file_type: InputFileType, params: &P3DParams) -> Result<P3DResult, P3DError> {
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    process_points(load_points(input, file_type, params)?, params)
+}
+
+// `process_cloud` of the coordinates of every point, three by three
+pub(crate) fn process_points(positions: Vec<f64>, params: &P3DParams) -> Result<P3DResult, P3DError> {
+    let opts = &params.options;
+    let mut points: Vec<Vector3<f64>> = positions.chunks(3).map(|p| Vector3::new(p[0], p[1], p[2])).collect();
+    check_span(&points)?;
+    let _points = charge(opts, "point bytes", points.len().saturating_mul(48))?;
+
+    let (shift, mut transform) = if let Some(alignment) = opts.alignment {
+        (Vector3::new(0.0, 0.0, 0.0), alignment)
+    } else if opts.align {
+        let mut matrix: Array2<f64> = Array2::zeros((points.len(), 3));
+        for (i, p) in points.iter().enumerate() {
+            matrix[[i, 0]] = p.x;
+            matrix[[i, 1]] = p.y;
+            matrix[[i, 2]] = p.z;
+        }
+        let (center, inertia) = algo_grid::point_mass_properties(&matrix);
+        check_inertia(&inertia)?;
+        pit_matrices(&algo_grid::inertia_transform(center, inertia))?
+    } else {
+        (Vector3::new(0.0, 0.0, 0.0), Matrix4::identity())
+    };
+    if let Some(rot) = transform_matrix(params.transform)? {
+        transform = rot * transform;
+    }
+    for p in points.iter_mut() {
+        *p = (transform * (*p + shift).extend(1.0)).truncate();
+    }
+
+    let (mut v_min, mut v_max) = (points[0], points[0]);
+    for p in points.iter() {
+        v_min = Vector3::new(v_min.x.min(p.x), v_min.y.min(p.y), v_min.z.min(p.z));
+        v_max = Vector3::new(v_max.x.max(p.x), v_max.y.max(p.y), v_max.z.max(p.z));
+    }
+    check_flat(v_min, v_max)?;
+
+    let n_slabs = params.n_sections as usize;
+    check_limit("sections", n_slabs, opts.limits.max_sections)?;
+    let n = params.grid_size as i32;
+    let rect = Rect::new(v_min.x, v_max.x, v_min.y, v_max.y);
+    let height = (v_max.z - v_min.z) / n_slabs as f64;
+    let mut occupancy = vec![vec![0u32; (n * n) as usize]; n_slabs];
+    for p in points.iter() {
+        let k = (((p.z - v_min.z) / height) as usize).min(n_slabs - 1);
+        let (i, j) = rect.cell(&Point2::new(p.x, p.y), n);
+        occupancy[k][(j.max(0).min(n - 1) * n + i.max(0).min(n - 1)) as usize] += 1;
+    }
+
+    let mut hashes: Vec<RawHash> = Vec::with_capacity(params.depth);
+    for level in 0..params.depth {
+        let slabs: Vec<Vec<u8>> = occupancy.iter().map(|cells| quantize(cells, level)).collect();
+        let hash = digest(opts.hash_algo, slabs.iter().map(|s| s.as_slice()));
+        if !hashes.contains(&hash) {
+            hashes.push(hash);
+        }
+    }
+    let populated = occupancy.iter().filter(|cells| cells.iter().any(|&c| c > 0)).count();
+
+    Ok(P3DResult {
+        hashes: hashes.iter().map(to_hex).collect(),
+        centers: Vec::new(),
+        section_heights: (0..n_slabs).map(|k| v_min.z + (k as f64 + 0.5) * height).collect(),
+        rect: rect.bounds(),
+        materials: Vec::new(),
+        section_loops: Vec::new(),
+        shift,
+        transform,
+        v_min,
+        v_max,
+        out_of_bounds: 0,
+        robustness: (populated as f64 / n_slabs as f64) as f32,
+        scores: Vec::new(),
+        grid_size: params.grid_size,
+        vertices: if opts.debug_geometry { points.iter().map(|p| [p.x, p.y, p.z]).collect() } else { Vec::new() },
+        occupancy: if opts.debug_geometry { occupancy } else { Vec::new() },
+        hash_details: Vec::new(),
+        peak_allocation: opts.allocations.as_ref().map_or(0, Allocations::peak),
+    })
+}
@@ -0,0 +1,66 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{p3d_process_cfg_detailed, InputFileType, P3DError, P3DParams};
+
+/// How alike two objects are, see `p3d_compare`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Similarity {
+    /// Mean of `grid_overlap` and of the `hash_overlap` values, in [0, 1]
+    pub score: f64,
+    /// Jaccard index of the best `d` hashes of each object, for every `d` from 1 to `depth`.
+    /// Exact collisions show at depth 1, near duplicates further down.
+    pub hash_overlap: Vec<f64>,
+    /// Weighted Jaccard index of the share of contour points in every grid cell, section by
+    /// section, in [0, 1]. Unlike the hashes it degrades gradually as the shapes drift apart.
+    pub grid_overlap: f64,
+}
+
+fn jaccard(a: &[String], b: &[String]) -> f64 {
+    let common = a.iter().filter(|h| b.contains(h)).count();
+    let union = a.len() + b.len() - common;
+    if union == 0 { 0.0 } else { common as f64 / union as f64 }
+}
+
+// Share of the points of every section in every cell
+fn cell_shares(occupancy: &[Vec<u32>]) -> Vec<Vec<f64>> {
+    occupancy.iter()
+        .map(|cells| {
+            let total: u32 = cells.iter().sum();
+            cells.iter().map(|&n| if total > 0 { n as f64 / total as f64 } else { 0.0 }).collect()
+        })
+        .collect()
+}
+
+fn grid_overlap(a: &[Vec<u32>], b: &[Vec<u32>]) -> f64 {
+    let (a, b) = (cell_shares(a), cell_shares(b));
+    let (mut min, mut max) = (0.0, 0.0);
+    for k in 0..a.len().max(b.len()) {
+        let (sa, sb) = (a.get(k).map(|s| s.as_slice()).unwrap_or(&[]), b.get(k).map(|s| s.as_slice()).unwrap_or(&[]));
+        for c in 0..sa.len().max(sb.len()) {
+            let (x, y) = (sa.get(c).cloned().unwrap_or(0.0), sb.get(c).cloned().unwrap_or(0.0));
+            min += x.min(y);
+            max += x.max(y);
+        }
+    }
+    if max > 0.0 { min / max } else { 0.0 }
+}
+
+/// Processes two objects with the same `params` and scores how alike they are, for catching
+/// near duplicates that don't share a hash. Sections are matched bottom to top, so the
+/// objects should be processed under the same alignment (the default PIT alignment, or the
+/// same `ProcessOptions::alignment`). With `GridSize::Auto` the two may get different grids
+/// and `grid_overlap` is then meaningless.
+pub fn p3d_compare(a: &[u8], a_type: InputFileType, b: &[u8], b_type: InputFileType, params: &P3DParams) -> Result<Similarity, P3DError> {
+    let mut params = params.clone();
+    params.options.debug_geometry = true;
+    let ra = p3d_process_cfg_detailed(a, a_type, &params)?;
+    let rb = p3d_process_cfg_detailed(b, b_type, &params)?;
+
+    let hash_overlap: Vec<f64> = (1..=params.depth)
+        .map(|d| jaccard(&ra.hashes[..d.min(ra.hashes.len())], &rb.hashes[..d.min(rb.hashes.len())]))
+        .collect();
+    let grid_overlap = grid_overlap(&ra.occupancy, &rb.occupancy);
+    let mean_hash = hash_overlap.iter().sum::<f64>() / hash_overlap.len() as f64;
+    Ok(Similarity { score: (grid_overlap + mean_hash) / 2.0, hash_overlap, grid_overlap })
+}
@@ -0,0 +1,96 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::validate::find;
+use crate::weld::prune_unused;
+use crate::{Geometry, P3DError};
+
+/// Hashes of one connected part of an object, see `p3d_process_components`.
+#[derive(Debug)]
+pub struct ComponentHashes {
+    /// Triangles of the part
+    pub faces: usize,
+    /// Surface area of the part
+    pub area: f64,
+    /// The hashes of the part on its own, or why there are none (small debris is often flat)
+    pub hashes: Result<Vec<String>, P3DError>,
+}
+
+// Connected part of every triangle, triangles sharing a vertex being connected, numbered in
+// the order of their first triangles
+pub(crate) fn face_components(n_vertices: usize, indices: &[u32]) -> (Vec<usize>, usize) {
+    let mut parent: Vec<usize> = (0..n_vertices).collect();
+    for t in indices.chunks(3) {
+        let roots = [find(&mut parent, t[0] as usize), find(&mut parent, t[1] as usize), find(&mut parent, t[2] as usize)];
+        let root = roots[0].min(roots[1]).min(roots[2]);
+        for r in roots {
+            parent[r] = root;
+        }
+    }
+    let mut label = vec![usize::MAX; n_vertices];
+    let mut count = 0;
+    let components = indices.chunks(3)
+        .map(|t| {
+            let root = find(&mut parent, t[0] as usize);
+            if label[root] == usize::MAX {
+                label[root] = count;
+                count += 1;
+            }
+            label[root]
+        })
+        .collect();
+    (components, count)
+}
+
+fn triangle_area(positions: &[f64], t: &[u32]) -> f64 {
+    let p = |i: u32| &positions[i as usize * 3..i as usize * 3 + 3];
+    let (a, b, c) = (p(t[0]), p(t[1]), p(t[2]));
+    let (u, v) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+    let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+    libm::sqrt(n[0] * n[0] + n[1] * n[1] + n[2] * n[2]) / 2.0
+}
+
+// Surface area of every part
+fn component_areas(positions: &[f64], indices: &[u32], components: &[usize], count: usize) -> Vec<f64> {
+    let mut areas = vec![0.0; count];
+    for (t, &c) in indices.chunks(3).zip(components.iter()) {
+        areas[c] += triangle_area(positions, t);
+    }
+    areas
+}
+
+// Parts by decreasing surface area, the first of equal ones first
+fn by_area(areas: &[f64]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..areas.len()).collect();
+    order.sort_by(|&a, &b| areas[b].partial_cmp(&areas[a]).unwrap_or(core::cmp::Ordering::Equal).then(a.cmp(&b)));
+    order
+}
+
+// Keeps the triangles of the part with the largest surface area (`Repair::largest_component`),
+// the returned mask tells which were kept
+pub(crate) fn largest_component(positions: &[f64], indices: &[u32]) -> (Vec<u32>, Vec<bool>) {
+    let (components, count) = face_components(positions.len() / 3, indices);
+    if count < 2 {
+        return (indices.to_vec(), vec![true; indices.len() / 3]);
+    }
+    let largest = by_area(&component_areas(positions, indices, &components, count))[0];
+    let kept: Vec<bool> = components.iter().map(|&c| c == largest).collect();
+    let indices = indices.chunks(3).zip(kept.iter()).filter(|(_, &keep)| keep).flat_map(|(t, _)| t.iter().cloned()).collect();
+    (indices, kept)
+}
+
+// Geometry of every part on its own with its surface area, largest first. Every part keeps
+// only its own vertices.
+pub(crate) fn split_components(geometry: Geometry) -> Vec<(f64, Geometry)> {
+    let (components, count) = face_components(geometry.positions.len() / 3, &geometry.indices);
+    let areas = component_areas(&geometry.positions, &geometry.indices, &components, count);
+    by_area(&areas).into_iter()
+        .map(|part| {
+            let faces = components.iter().enumerate().filter(|(_, &c)| c == part).map(|(f, _)| f);
+            let indices = faces.clone().flat_map(|f| geometry.indices[3 * f..3 * f + 3].iter().cloned()).collect();
+            let materials = if geometry.materials.is_empty() { Vec::new() } else { faces.map(|f| geometry.materials[f]).collect() };
+            let (positions, indices) = prune_unused(geometry.positions.clone(), indices);
+            (areas[part], Geometry { positions, indices, materials })
+        })
+        .collect()
+}
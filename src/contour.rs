@@ -1,9 +1,10 @@
+use alloc::collections::BTreeMap;
 use alloc::collections::BTreeSet as HashSet;
 use alloc::vec::Vec;
 
 #[allow(unused_imports)]
 use cgmath::num_traits::real::Real;
-use cgmath::Point2;
+use cgmath::{MetricSpace, Point2};
 
 pub(crate) type CellSet = HashSet<(i32, i32)>;
 
@@ -24,8 +25,38 @@ impl Rect {
             max_y,
         }
     }
+    pub(crate) fn bounds(&self) -> [f64; 4] { [self.min_x, self.max_x, self.min_y, self.max_y] }
     pub(crate) fn width(&self) -> f64 { self.max_x - self.min_x }
     pub(crate) fn height(&self) -> f64 { self.max_y - self.min_y }
+
+    pub(crate) fn contains(&self, p: &Point2<f64>) -> bool {
+        p.x >= self.min_x && p.x <= self.max_x && p.y >= self.min_y && p.y <= self.max_y
+    }
+
+    pub(crate) fn clamp(&self, p: &Point2<f64>) -> Point2<f64> {
+        Point2::new(p.x.max(self.min_x).min(self.max_x), p.y.max(self.min_y).min(self.max_y))
+    }
+
+    // Rounds `p` to the nearest node of a grid of the given spacing anchored at the min corner
+    pub(crate) fn snap(&self, p: &Point2<f64>, step: f64) -> Point2<f64> {
+        Point2::new(
+            self.min_x + libm::round((p.x - self.min_x) / step) * step,
+            self.min_y + libm::round((p.y - self.min_y) / step) * step,
+        )
+    }
+
+    // Cell of an `n` x `n` grid over the rect holding `p`, the max edges belong to the last cell
+    pub(crate) fn cell(&self, p: &Point2<f64>, n: i32) -> (i32, i32) {
+        let mut i = ((p.x - self.min_x) / (self.width() / n as f64)) as i32;
+        let mut j = ((p.y - self.min_y) / (self.height() / n as f64)) as i32;
+        if i == n {
+            i = n - 1;
+        }
+        if j == n {
+            j = n - 1;
+        }
+        (i, j)
+    }
 }
 
 pub(crate) struct Cntr {
@@ -126,3 +157,167 @@ impl Cntr {
         z.clone()
     }
 }
+
+fn segment_distance2(p: Point2<f64>, a: Point2<f64>, b: Point2<f64>) -> f64 {
+    let (abx, aby) = (b.x - a.x, b.y - a.y);
+    let (apx, apy) = (p.x - a.x, p.y - a.y);
+    let len2 = abx * abx + aby * aby;
+    let t = if len2 > 0.0 { ((apx * abx + apy * aby) / len2).max(0.0).min(1.0) } else { 0.0 };
+    let (dx, dy) = (apx - t * abx, apy - t * aby);
+    dx * dx + dy * dy
+}
+
+// Point strictly between `a` and `b` farthest from the segment a-b, with its squared distance
+fn farthest(points: &[Point2<f64>], a: usize, b: usize) -> (usize, f64) {
+    let mut best = (a, -1.0);
+    for k in a + 1..b {
+        let d2 = segment_distance2(points[k], points[a], points[b]);
+        if d2 > best.1 {
+            best = (k, d2);
+        }
+    }
+    best
+}
+
+// Douglas-Peucker simplification: indices of the points to keep so that every dropped point
+// lies within `tolerance` of the simplified polyline. A closed loop (first point repeated at
+// the end) is first split at the point farthest from its start so it can't collapse.
+pub(crate) fn simplify_indices(points: &[Point2<f64>], tolerance: f64) -> Vec<usize> {
+    let len = points.len();
+    if len < 3 {
+        return (0..len).collect();
+    }
+
+    let mut keep = vec![false; len];
+    keep[0] = true;
+    keep[len - 1] = true;
+    let tol2 = tolerance * tolerance;
+
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    if points[0] == points[len - 1] {
+        let (k, _) = farthest(points, 0, len - 1);
+        keep[k] = true;
+        stack.push((0, k));
+        stack.push((k, len - 1));
+    } else {
+        stack.push((0, len - 1));
+    }
+
+    while let Some((a, b)) = stack.pop() {
+        if b <= a + 1 {
+            continue;
+        }
+        let (k, d2) = farthest(points, a, b);
+        if d2 > tol2 {
+            keep[k] = true;
+            stack.push((a, k));
+            stack.push((k, b));
+        }
+    }
+
+    (0..len).filter(|&i| keep[i]).collect()
+}
+
+// What `chain_order` compares candidates by: their squared distance to the current point as
+// f32, scaled and truncated. Keys saturate, so points far enough apart all tie.
+fn chain_key(p: Point2<f64>, q: Point2<f64>) -> u32 {
+    ((p.distance2(q) as f32) * 10000.0) as u32
+}
+
+// Order in which the section points are chained into a contour: from the first point, each
+// time the remaining point with the smallest `chain_key`, ties going to the earliest in a
+// selection that swaps every pick into place. Candidates are looked up in a sparse grid of
+// cells about as wide as the spacing of points along the contour, ring by ring around the
+// current point, so a step costs about the points nearby rather than all the remaining ones.
+pub(crate) fn chain_order(points: &[Point2<f64>]) -> Vec<usize> {
+    let len = points.len();
+    let mut order: Vec<usize> = (0..len).collect();
+    if len < 2 {
+        return order;
+    }
+    // Position in `order` of every point
+    let mut pos: Vec<usize> = (0..len).collect();
+
+    let finite = points.iter().all(|p| p.x.is_finite() && p.y.is_finite());
+    let (mut x0, mut y0, mut x1, mut y1) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for p in points.iter() {
+        x0 = x0.min(p.x);
+        y0 = y0.min(p.y);
+        x1 = x1.max(p.x);
+        y1 = y1.max(p.y);
+    }
+    let size = 2.0 * ((x1 - x0) + (y1 - y0)) / len as f64;
+    let cell = |p: Point2<f64>| (libm::floor((p.x - x0) / size) as i64, libm::floor((p.y - y0) / size) as i64);
+    let mut cells: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+    let indexed = finite && size > 0.0;
+    if indexed {
+        for (k, p) in points.iter().enumerate().skip(1) {
+            cells.entry(cell(*p)).or_default().push(k);
+        }
+    }
+
+    for i in 0..len - 1 {
+        let p = points[order[i]];
+        let next = if indexed {
+            let mut best: Option<(u32, usize)> = None;
+            let consider = |best: &mut Option<(u32, usize)>, k: usize| {
+                let candidate = (chain_key(p, points[k]), pos[k]);
+                if best.is_none() || Some(candidate) < *best {
+                    *best = Some(candidate);
+                }
+            };
+            let (cx, cy) = cell(p);
+            let mut r: i64 = 0;
+            loop {
+                if ((2 * r + 1) * (2 * r + 1)) as usize > cells.len() {
+                    cells.values().flatten().for_each(|&k| consider(&mut best, k));
+                    break;
+                }
+                let mut ring = Vec::new();
+                if r == 0 {
+                    ring.push((cx, cy));
+                }
+                for d in -r..=r {
+                    if r > 0 {
+                        ring.extend([(cx + d, cy - r), (cx + d, cy + r)]);
+                    }
+                    if d.abs() < r {
+                        ring.extend([(cx - r, cy + d), (cx + r, cy + d)]);
+                    }
+                }
+                for c in ring {
+                    if let Some(ks) = cells.get(&c) {
+                        ks.iter().for_each(|&k| consider(&mut best, k));
+                    }
+                }
+                // Cells past ring `r` are at least `r` cells away, less a margin for rounding
+                let reach = (r as f64 - 0.01) * size;
+                if let Some((key, _)) = best {
+                    if reach > 0.0 && chain_key(Point2::new(0.0, 0.0), Point2::new(reach, 0.0)) > key {
+                        break;
+                    }
+                }
+                r += 1;
+            }
+            best.map(|(_, at)| at).unwrap_or(i + 1)
+        } else {
+            (i + 1..len).min_by_key(|&k| chain_key(p, points[order[k]])).unwrap_or(i + 1)
+        };
+
+        let picked = order[next];
+        let moved = order[i + 1];
+        order.swap(i + 1, next);
+        pos[moved] = next;
+        pos[picked] = i + 1;
+        if indexed {
+            let c = cell(points[picked]);
+            if let Some(ks) = cells.get_mut(&c) {
+                ks.retain(|&k| k != picked);
+                if ks.is_empty() {
+                    cells.remove(&c);
+                }
+            }
+        }
+    }
+    order
+}
@@ -0,0 +1,52 @@
+use alloc::vec::Vec;
+
+use crate::error::gltf_error;
+use crate::P3DError;
+
+fn sextet(c: u8) -> Option<u32> {
+    match c {
+        b'A'..=b'Z' => Some((c - b'A') as u32),
+        b'a'..=b'z' => Some((c - b'a') as u32 + 26),
+        b'0'..=b'9' => Some((c - b'0') as u32 + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    }
+}
+
+// Standard or URL-safe base64, padding optional, whitespace ignored
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = text.bytes().filter(|c| !c.is_ascii_whitespace()).collect();
+    let digits = match digits.iter().position(|&c| c == b'=') {
+        Some(end) if digits[end..].iter().all(|&c| c == b'=') && digits.len() % 4 == 0 => &digits[..end],
+        Some(_) => return None,
+        None => &digits[..],
+    };
+    if digits.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let mut bits = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            bits |= sextet(c)? << (18 - 6 * i);
+        }
+        let bytes = [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8];
+        out.extend_from_slice(&bytes[..chunk.len() - 1]);
+    }
+    Some(out)
+}
+
+// Bytes of a `data:` URI, `None` for any other URI. Only base64 payloads are accepted, as
+// written by glTF exporters (`data:application/octet-stream;base64,...`).
+pub(crate) fn decode(uri: &str) -> Option<Result<Vec<u8>, P3DError>> {
+    let rest = uri.strip_prefix("data:")?;
+    let (header, payload) = match rest.find(',') {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => return Some(Err(gltf_error("data URI without ','"))),
+    };
+    if !header.ends_with(";base64") {
+        return Some(Err(gltf_error("data URI is not base64")));
+    }
+    Some(decode_base64(payload).ok_or_else(|| gltf_error("invalid base64 in data URI")))
+}
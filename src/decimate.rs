@@ -0,0 +1,267 @@
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::vec3::{cross, dot, norm, sub, Point};
+use crate::Decimation;
+
+// Error quadric of the planes of the faces around a vertex, each weighted by its area:
+// a², ab, ac, ad, b², bc, bd, c², cd, d² of the planes ax + by + cz + d = 0 and the area
+type Quadric = [f64; 11];
+
+fn normal(corners: [Point; 3]) -> Point {
+    cross(sub(corners[1], corners[0]), sub(corners[2], corners[0]))
+}
+
+fn plane_quadric(corners: [Point; 3]) -> Quadric {
+    let n = normal(corners);
+    let length = norm(n);
+    if length == 0.0 {
+        return [0.0; 11];
+    }
+    let (a, b, c) = (n[0] / length, n[1] / length, n[2] / length);
+    let d = -(a * corners[0][0] + b * corners[0][1] + c * corners[0][2]);
+    let w = length / 2.0;
+    [w * a * a, w * a * b, w * a * c, w * a * d, w * b * b, w * b * c, w * b * d, w * c * c, w * c * d, w * d * d, w]
+}
+
+fn add(q: &mut Quadric, r: &Quadric) {
+    for (x, y) in q.iter_mut().zip(r.iter()) {
+        *x += y;
+    }
+}
+
+// Mean squared distance of `p` from the planes of `q`, weighted by their areas
+fn cost(q: &Quadric, p: Point) -> f64 {
+    let [x, y, z] = p;
+    let error = q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+        + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+        + q[7] * z * z + 2.0 * q[8] * z + q[9];
+    if q[10] > 0.0 { error.max(0.0) / q[10] } else { 0.0 }
+}
+
+// Collapse of the edge from `a` to `b` into `a` at `position`, valid while neither end changed
+// since (`stamps`)
+struct Collapse {
+    cost: f64,
+    a: u32,
+    b: u32,
+    stamps: (u32, u32),
+    position: Point,
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Cheapest first out of the (max-)heap, ties by vertex indices so the order is the same everywhere
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+            .then_with(|| (other.a, other.b).cmp(&(self.a, self.b)))
+    }
+}
+
+struct Decimator {
+    points: Vec<Point>,
+    faces: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    // Faces around every vertex, dead ones included until the vertex is touched; face ids are
+    // `usize` as a mesh may have more faces than a `u32` counts
+    vertex_faces: Vec<Vec<usize>>,
+    quadrics: Vec<Quadric>,
+    stamps: Vec<u32>,
+    // Vertices on a border or a non-manifold edge stay where they are
+    locked: Vec<bool>,
+    heap: BinaryHeap<Collapse>,
+}
+
+impl Decimator {
+    fn corners(&self, f: usize) -> [Point; 3] {
+        self.faces[f].map(|v| self.points[v as usize])
+    }
+
+    fn live_faces(&self, v: u32) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_faces[v as usize].iter().cloned().filter(move |&f| self.alive[f])
+    }
+
+    fn neighbors(&self, v: u32) -> Vec<u32> {
+        let mut neighbors: Vec<u32> = self.live_faces(v)
+            .flat_map(|f| self.faces[f])
+            .filter(|&u| u != v)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    // The cheapest of the ends and the midpoint of the edge, in that order on ties
+    fn push(&mut self, a: u32, b: u32) {
+        if self.locked[a as usize] || self.locked[b as usize] {
+            return;
+        }
+        let mut q = self.quadrics[a as usize];
+        add(&mut q, &self.quadrics[b as usize]);
+        let (pa, pb) = (self.points[a as usize], self.points[b as usize]);
+        let mid = [(pa[0] + pb[0]) / 2.0, (pa[1] + pb[1]) / 2.0, (pa[2] + pb[2]) / 2.0];
+        let (position, cost) = [pa, pb, mid].iter()
+            .map(|&p| (p, cost(&q, p)))
+            .fold((pa, f64::INFINITY), |best, c| if c.1 < best.1 { c } else { best });
+        let (a, b) = (a.min(b), a.max(b));
+        let stamps = (self.stamps[a as usize], self.stamps[b as usize]);
+        self.heap.push(Collapse { cost, a, b, stamps, position });
+    }
+
+    // Whether merging `b` into `a` at `p` keeps the surface manifold and no face turns over
+    fn can_collapse(&self, a: u32, b: u32, p: Point) -> bool {
+        let shared = self.live_faces(a).filter(|&f| self.faces[f].contains(&b)).count();
+        let (na, nb) = (self.neighbors(a), self.neighbors(b));
+        if na.iter().filter(|v| nb.binary_search(v).is_ok()).count() != shared {
+            return false;
+        }
+        self.live_faces(a).chain(self.live_faces(b))
+            .filter(|&f| !(self.faces[f].contains(&a) && self.faces[f].contains(&b)))
+            .all(|f| {
+                let before = self.corners(f);
+                let mut after = before;
+                for (k, &v) in self.faces[f].iter().enumerate() {
+                    if v == a || v == b {
+                        after[k] = p;
+                    }
+                }
+                let (n0, n1) = (normal(before), normal(after));
+                dot(n0, n1) > 0.0
+            })
+    }
+
+    // Merges `b` into `a` at `p`, returns the number of faces removed
+    fn collapse(&mut self, a: u32, b: u32, p: Point) -> usize {
+        let mut removed = 0;
+        let faces_b = core::mem::take(&mut self.vertex_faces[b as usize]);
+        for f in faces_b {
+            if !self.alive[f] {
+                continue;
+            }
+            let face = &mut self.faces[f];
+            if face.contains(&a) {
+                self.alive[f] = false;
+                removed += 1;
+            } else {
+                for v in face.iter_mut().filter(|v| **v == b) {
+                    *v = a;
+                }
+                self.vertex_faces[a as usize].push(f);
+            }
+        }
+        let alive = &self.alive;
+        self.vertex_faces[a as usize].retain(|&f| alive[f]);
+        self.points[a as usize] = p;
+        let qb = self.quadrics[b as usize];
+        add(&mut self.quadrics[a as usize], &qb);
+        self.stamps[a as usize] += 1;
+        self.stamps[b as usize] += 1;
+        for n in self.neighbors(a) {
+            self.push(a, n);
+        }
+        removed
+    }
+}
+
+// Edge-collapse decimation of a triangle mesh to `target`, deterministic to the bit: plain f64
+// arithmetic in a fixed order, ties broken by vertex indices. Collapses run cheapest first by
+// the quadric error (mean squared distance from the planes of the merged faces) and never touch
+// border or non-manifold edges, break the manifold or turn a face over. Returns the positions
+// of the vertices left, in their order, the faces left, in theirs, and which faces were kept.
+pub(crate) fn decimate(positions: &[f64], indices: &[u32], target: Decimation) -> (Vec<f64>, Vec<u32>, Vec<bool>) {
+    let points: Vec<Point> = positions.chunks(3).map(|p| [p[0], p[1], p[2]]).collect();
+    let faces: Vec<[u32; 3]> = indices.chunks(3).map(|t| [t[0], t[1], t[2]]).collect();
+    let mut vertex_faces = vec![Vec::new(); points.len()];
+    let mut quadrics = vec![[0.0; 11]; points.len()];
+    let mut edge_faces: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+    for (f, face) in faces.iter().enumerate() {
+        let q = plane_quadric(face.map(|v| points[v as usize]));
+        for k in 0..3 {
+            let (u, v) = (face[k], face[(k + 1) % 3]);
+            vertex_faces[u as usize].push(f);
+            add(&mut quadrics[u as usize], &q);
+            *edge_faces.entry((u.min(v), u.max(v))).or_insert(0) += 1;
+        }
+    }
+    let mut locked = vec![false; points.len()];
+    for (&(u, v), &n) in edge_faces.iter() {
+        if n != 2 {
+            locked[u as usize] = true;
+            locked[v as usize] = true;
+        }
+    }
+    let (v_min, v_max) = points.iter().fold(([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]), |(lo, hi), p| {
+        ([lo[0].min(p[0]), lo[1].min(p[1]), lo[2].min(p[2])], [hi[0].max(p[0]), hi[1].max(p[1]), hi[2].max(p[2])])
+    });
+    let diagonal = sub(v_max, v_min);
+    let (target_faces, max_cost) = match target {
+        Decimation::Faces(n) => (n, f64::INFINITY),
+        Decimation::MaxError(e) => (0, e * e * dot(diagonal, diagonal)),
+    };
+
+    let n_faces = faces.len();
+    let mut d = Decimator {
+        stamps: vec![0; points.len()],
+        alive: vec![true; n_faces],
+        points,
+        faces,
+        vertex_faces,
+        quadrics,
+        locked,
+        heap: BinaryHeap::new(),
+    };
+    for &(u, v) in edge_faces.keys() {
+        if u != v {
+            d.push(u, v);
+        }
+    }
+    let mut live = n_faces;
+    while live > target_faces {
+        let c = match d.heap.pop() {
+            Some(c) => c,
+            None => break,
+        };
+        if c.cost > max_cost {
+            break;
+        }
+        if (d.stamps[c.a as usize], d.stamps[c.b as usize]) != c.stamps || !d.can_collapse(c.a, c.b, c.position) {
+            continue;
+        }
+        live -= d.collapse(c.a, c.b, c.position);
+    }
+
+    let mut remap = vec![u32::MAX; d.points.len()];
+    let mut kept_positions = Vec::new();
+    let mut kept_indices = Vec::with_capacity(live * 3);
+    for (face, _) in d.faces.iter().zip(d.alive.iter()).filter(|(_, &alive)| alive) {
+        for &v in face.iter() {
+            if remap[v as usize] == u32::MAX {
+                remap[v as usize] = 0;
+            }
+        }
+    }
+    for (v, p) in d.points.iter().enumerate() {
+        if remap[v] != u32::MAX {
+            remap[v] = (kept_positions.len() / 3) as u32;
+            kept_positions.extend_from_slice(p);
+        }
+    }
+    for (face, _) in d.faces.iter().zip(d.alive.iter()).filter(|(_, &alive)| alive) {
+        kept_indices.extend(face.iter().map(|&v| remap[v as usize]));
+    }
+    (kept_positions, kept_indices, d.alive)
+}
@@ -0,0 +1,91 @@
+use alloc::vec::Vec;
+
+use ndarray::Array2;
+use tri_mesh::prelude::{InnerSpace, Vector3};
+
+use crate::algo_grid::{point_mass_properties, principal_moments};
+use crate::{P3DError, FLAT_EPSILON};
+
+// Smallest principal moment of inertia, as a fraction of the largest, of an alignment that can
+// be trusted. Moments go with the square of the sizes, and the eigen solver is only good to
+// about 1e-16 of the largest, so this is a thickness of about 1e-6 of the length.
+const RANK_EPSILON: f64 = 1e-12;
+
+fn farthest<F: Fn(&Vector3<f64>) -> f64>(points: &[Vector3<f64>], distance: F) -> (Vector3<f64>, f64) {
+    points.iter().fold((points[0], 0.0), |(best, d), p| {
+        let dp = distance(p);
+        if dp > d { (*p, dp) } else { (best, d) }
+    })
+}
+
+// Principal moments of inertia of the points, every one with unit mass, smallest first
+fn point_moments(points: &[Vector3<f64>]) -> [f64; 3] {
+    let mut matrix: Array2<f64> = Array2::zeros((points.len(), 3));
+    for (i, p) in points.iter().enumerate() {
+        matrix[[i, 0]] = p.x;
+        matrix[[i, 1]] = p.y;
+        matrix[[i, 2]] = p.z;
+    }
+    principal_moments(point_mass_properties(&matrix).1)
+}
+
+// Rejects points that don't span a volume: all in one point or on one line
+// (`DegenerateGeometry`), or within `FLAT_EPSILON` of the bounding box diagonal of a plane
+// (`FlatObject`). The span is found from the point farthest from the first one, then the point
+// farthest from the line through both, so it doesn't depend on the axes like `check_flat` does.
+pub(crate) fn check_span(points: &[Vector3<f64>]) -> Result<(), P3DError> {
+    if points.is_empty() {
+        return Err(P3DError::EmptyMesh);
+    }
+    let (mut v_min, mut v_max) = (points[0], points[0]);
+    for p in points.iter() {
+        v_min = Vector3::new(v_min.x.min(p.x), v_min.y.min(p.y), v_min.z.min(p.z));
+        v_max = Vector3::new(v_max.x.max(p.x), v_max.y.max(p.y), v_max.z.max(p.z));
+    }
+    let extent = (v_max - v_min).magnitude();
+    let tolerance = FLAT_EPSILON * extent;
+    let degenerate = |rank: u8| P3DError::DegenerateGeometry { rank, moments: point_moments(points) };
+
+    let a = points[0];
+    let (b, length) = farthest(points, |p| (p - a).magnitude());
+    if length <= tolerance {
+        return Err(degenerate(0));
+    }
+    let axis = (b - a) / length;
+    let (c, width) = farthest(points, |p| (p - a).cross(axis).magnitude());
+    if width <= tolerance {
+        return Err(degenerate(1));
+    }
+    let normal = (b - a).cross(c - a).normalize();
+    let (_, thickness) = farthest(points, |p| (p - a).dot(normal).abs());
+    if thickness <= tolerance {
+        return Err(P3DError::FlatObject { thickness, extent });
+    }
+    Ok(())
+}
+
+// `check_span` of the vertices faces use
+pub(crate) fn check_geometry_span(positions: &[f64], indices: &[u32]) -> Result<(), P3DError> {
+    let mut used = vec![false; positions.len() / 3];
+    for &i in indices.iter() {
+        if let Some(u) = used.get_mut(i as usize) {
+            *u = true;
+        }
+    }
+    let points: Vec<Vector3<f64>> = positions.chunks(3).zip(used)
+        .filter(|(_, used)| *used)
+        .map(|(p, _)| Vector3::new(p[0], p[1], p[2]))
+        .collect();
+    check_span(&points)
+}
+
+// Rejects an inertia tensor the principal axes can't be taken from: the mass on a line leaves
+// a principal moment near zero (`rank` 1), no mass at all all of them (`rank` 0)
+pub(crate) fn check_inertia(inertia: &Array2<f64>) -> Result<(), P3DError> {
+    let moments = principal_moments(inertia.clone());
+    if moments[0] <= RANK_EPSILON * moments[2] {
+        let rank = if moments[2] > 0.0 { 1 } else { 0 };
+        return Err(P3DError::DegenerateGeometry { rank, moments });
+    }
+    Ok(())
+}
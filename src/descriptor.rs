@@ -0,0 +1,70 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Merges several ranked descriptors (as returned by `p3d_process`) into one scene descriptor.
+///
+/// Aggregation is a Borda count: in a descriptor of length `n` the label at rank `r`
+/// (0 is the best) scores `n - r`, and scores of the same label are summed over all
+/// descriptors. Labels are re-ranked by total score (ties broken by the label itself so
+/// the result is deterministic) and the top `K` are returned, where `K` is the length
+/// of the longest input descriptor.
+pub fn combine_descriptors(descriptors: &[Vec<String>]) -> Vec<String> {
+    let k = descriptors.iter().map(|d| d.len()).max().unwrap_or(0);
+    let mut scores: BTreeMap<&str, usize> = BTreeMap::new();
+
+    for d in descriptors.iter() {
+        let n = d.len();
+        for (r, label) in d.iter().enumerate() {
+            // A repeated label keeps the score of its best rank within one descriptor
+            if d[..r].contains(label) {
+                continue;
+            }
+            *scores.entry(label.as_str()).or_insert(0) += n - r;
+        }
+    }
+
+    let mut ranked: Vec<(&str, usize)> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked.into_iter().take(k).map(|(label, _)| String::from(label)).collect()
+}
+
+/// Labels of two descriptors split into those only in `a`, only in `b` and in both.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DescriptorDiff {
+    /// Labels of `a` missing from `b`, in the order of `a`
+    pub only_a: Vec<String>,
+    /// Labels of `b` missing from `a`, in the order of `b`
+    pub only_b: Vec<String>,
+    /// Labels of both with their rank in `a` and in `b`, in the order of `a`
+    pub common: Vec<(String, usize, usize)>,
+}
+
+impl DescriptorDiff {
+    /// Common labels that moved to another rank
+    pub fn rank_changes(&self) -> impl Iterator<Item = &(String, usize, usize)> {
+        self.common.iter().filter(|(_, ra, rb)| ra != rb)
+    }
+}
+
+/// Compares two descriptors label by label. A repeated label is ranked by its first occurrence.
+pub fn descriptor_diff(a: &[String], b: &[String]) -> DescriptorDiff {
+    let rank = |d: &[String], label: &String| d.iter().position(|l| l == label);
+    let mut diff = DescriptorDiff::default();
+
+    for (ra, label) in a.iter().enumerate() {
+        if a[..ra].contains(label) {
+            continue;
+        }
+        match rank(b, label) {
+            Some(rb) => diff.common.push((label.clone(), ra, rb)),
+            None => diff.only_a.push(label.clone()),
+        }
+    }
+    for (rb, label) in b.iter().enumerate() {
+        if !b[..rb].contains(label) && rank(a, label).is_none() {
+            diff.only_b.push(label.clone());
+        }
+    }
+    diff
+}
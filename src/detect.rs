@@ -0,0 +1,66 @@
+use crate::InputFileType;
+
+// Bytes of a text file looked at, enough for any header and a few lines after comments
+const SNIFF_BYTES: usize = 4096;
+
+// First keywords of an OBJ statement
+const OBJ_KEYWORDS: [&str; 12] = ["v", "vt", "vn", "vp", "f", "l", "p", "o", "g", "s", "mtllib", "usemtl"];
+
+// Binary STL: the file size is exactly the header, the count and 50 bytes per triangle
+fn is_binary_stl(input: &[u8]) -> bool {
+    input.len() >= 84 && {
+        let count = u32::from_le_bytes([input[80], input[81], input[82], input[83]]) as usize;
+        count.checked_mul(50).and_then(|n| n.checked_add(84)) == Some(input.len())
+    }
+}
+
+fn first_word(text: &str) -> &str {
+    text.split_whitespace().next().unwrap_or("")
+}
+
+// Start of the input as text, `None` when it isn't UTF-8 (a character cut at the end is fine)
+fn text_head(input: &[u8]) -> Option<&str> {
+    let head = &input[..input.len().min(SNIFF_BYTES)];
+    match core::str::from_utf8(head) {
+        Ok(text) => Some(text),
+        Err(e) if e.error_len().is_none() => core::str::from_utf8(&head[..e.valid_up_to()]).ok(),
+        Err(_) => None,
+    }
+}
+
+// The type of a decompressed input by its content: the GLB and ZIP (3MF) magics, the size of a
+// binary STL (checked before text, as binary headers often start with `solid` too), then the
+// start of the text: `{` for glTF, the `ply`, `solid` and `OFF` headers, and the first statement
+// past blank and `#` lines, an OBJ keyword or a number (XYZ)
+pub(crate) fn detect(input: &[u8]) -> Option<InputFileType> {
+    if input.starts_with(b"glTF") {
+        return Some(InputFileType::Glb);
+    }
+    if input.starts_with(b"PK\x03\x04") {
+        return Some(InputFileType::ThreeMf);
+    }
+    if is_binary_stl(input) {
+        return Some(InputFileType::Stl);
+    }
+    let text = text_head(input)?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text).trim_start();
+    if text.starts_with('{') {
+        return Some(InputFileType::Gltf);
+    }
+    match first_word(text) {
+        "ply" => return Some(InputFileType::Ply),
+        "solid" => return Some(InputFileType::Stl),
+        _ => {}
+    }
+    let line = text.lines()
+        .map(|raw| raw.split('#').next().unwrap_or("").trim())
+        .find(|content| !content.is_empty())?;
+    let word = first_word(line);
+    match word.find("OFF") {
+        // The counts may follow the keyword without a space (ModelNet)
+        Some(i) if word[..i].chars().all(|c| matches!(c, 'S' | 'T' | 'C' | 'N')) => Some(InputFileType::Off),
+        _ if OBJ_KEYWORDS.contains(&word) => Some(InputFileType::Obj),
+        _ if word.split(',').next().unwrap_or("").parse::<f64>().is_ok() => Some(InputFileType::Xyz),
+        _ => None,
+    }
+}
@@ -0,0 +1,96 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use gltf::json::Index;
+use gltf::{Gltf, Primitive, Semantic};
+
+use crate::error::gltf_error;
+use crate::{P3DError, ProcessOptions, DRACO_EXTENSION};
+
+// Positions and triangle list of a primitive
+type Decoded = (Vec<[f32; 3]>, Vec<u32>);
+
+type Decode = dyn Fn(&[u8], u32) -> Option<Decoded> + Send + Sync;
+
+/// Decoder of `KHR_draco_mesh_compression` primitives, see `ProcessOptions::draco`. Gets the
+/// bytes of the compressed buffer view and the Draco id of the POSITION attribute, and returns
+/// the decoded positions and triangle list, or `None` when the data can't be decoded. Wraps
+/// any Draco implementation, e.g. bindings to the reference decoder.
+#[derive(Clone)]
+pub struct DracoDecoder(Arc<Decode>);
+
+impl DracoDecoder {
+    pub fn new<F: Fn(&[u8], u32) -> Option<Decoded> + Send + Sync + 'static>(decode: F) -> Self {
+        DracoDecoder(Arc::new(decode))
+    }
+}
+
+impl fmt::Debug for DracoDecoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DracoDecoder")
+    }
+}
+
+// Positions and triangles of a Draco compressed primitive, `None` for an uncompressed one.
+// The accessors of a compressed primitive only describe the decoded data, they have no buffer
+// view to read.
+pub(crate) fn decode_primitive(gltf_data: &Gltf, primitive: &Primitive, buffers: &[Option<Vec<u8>>], opts: &ProcessOptions) -> Result<Option<Decoded>, P3DError> {
+    let extension = match primitive.extension_value(DRACO_EXTENSION) {
+        Some(extension) => extension,
+        None => return Ok(None),
+    };
+    let decoder = opts.draco.as_ref()
+        .ok_or_else(|| gltf_error("Draco compressed primitive, set ProcessOptions::draco to decode it"))?;
+    // Skinning reads the joints and weights, which are compressed too
+    if opts.pose.is_some() && primitive.get(&Semantic::Joints(0)).is_some() {
+        return Err(gltf_error("skinned Draco compressed primitives can't be posed"));
+    }
+    let view = extension.get("bufferView").and_then(|v| v.as_u64())
+        .and_then(|v| gltf_data.views().nth(v as usize))
+        .ok_or_else(|| gltf_error("Draco primitive with a missing or invalid bufferView"))?;
+    let position = extension.get("attributes").and_then(|a| a.get("POSITION")).and_then(|v| v.as_u64())
+        .ok_or_else(|| gltf_error("Draco primitive without a POSITION attribute"))?;
+    let data = buffers[view.buffer().index()].as_deref()
+        .and_then(|buffer| {
+            let end = view.offset().checked_add(view.length())?;
+            buffer.get(view.offset()..end)
+        })
+        .ok_or_else(|| gltf_error("Draco primitive bufferView out of its buffer"))?;
+
+    let (positions, indices) = decoder.0(data, position as u32)
+        .ok_or_else(|| gltf_error("Draco primitive decoding failed"))?;
+    if indices.len() % 3 != 0 {
+        return Err(gltf_error("Draco primitive indices don't make triangles"));
+    }
+    if indices.iter().any(|&i| i as usize >= positions.len()) {
+        return Err(gltf_error("Draco primitive index out of range"));
+    }
+    Ok(Some((positions, indices)))
+}
+
+// Parses a glTF document, accepting the Draco extension where `gltf` validation would reject it:
+// as a required extension, and the accessors of compressed primitives having no buffer view
+pub(crate) fn parse(input: &[u8]) -> gltf::Result<Gltf> {
+    let Gltf { document, blob } = Gltf::from_slice_without_validation(input)?;
+    let mut json = document.into_json();
+    json.extensions_required.retain(|e: &String| e != DRACO_EXTENSION);
+    // Validated as if those accessors were stored in the compressed buffer view
+    let mut checked = json.clone();
+    for primitive in checked.meshes.iter().flat_map(|mesh| mesh.primitives.iter()) {
+        let view = primitive.extensions.as_ref()
+            .and_then(|extensions| extensions.others.get(DRACO_EXTENSION))
+            .and_then(|extension| extension.get("bufferView"))
+            .and_then(|view| view.as_u64());
+        if let Some(view) = view {
+            for accessor in primitive.attributes.values().chain(primitive.indices.iter()) {
+                if let Some(accessor) = checked.accessors.get_mut(accessor.value()) {
+                    accessor.buffer_view.get_or_insert(Index::new(view as u32));
+                }
+            }
+        }
+    }
+    gltf::Document::from_json(checked)?;
+    Ok(Gltf { document: gltf::Document::from_json_without_validation(json), blob })
+}
@@ -0,0 +1,161 @@
+use core::fmt;
+
+use crate::P3DError;
+
+/// Input format of a `P3DError::ParseError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Obj,
+    /// glTF and GLB, with their buffers, extensions, animations and cameras
+    Gltf,
+    Stl,
+    Ply,
+    Xyz,
+    ThreeMf,
+    Off,
+    /// The gzip wrapper (`gzip` feature) of any of the others
+    Gzip,
+}
+
+/// Where a `P3DError::ParseError` was found in the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offset {
+    /// Bytes from the start of the input; the decompressed data of a gzipped one, unless the
+    /// gzip wrapper itself is the problem
+    Byte(usize),
+    /// 1-based line of a text format
+    Line(usize),
+    /// Not at one place: data that is missing, or inconsistent across the input
+    Unknown,
+}
+
+// `ParseError` of a glTF or GLB file that doesn't hold what it refers to or what is asked of it
+pub(crate) fn gltf_error(reason: &'static str) -> P3DError {
+    P3DError::ParseError { format: Format::Gltf, offset: Offset::Unknown, reason }
+}
+
+// `ParseError` on `line` of a text format
+pub(crate) fn line_error(format: Format, line: usize, reason: &'static str) -> P3DError {
+    P3DError::ParseError { format, offset: Offset::Line(line), reason }
+}
+
+// `ParseError` for input of `format` that isn't UTF-8, at the first byte that isn't
+pub(crate) fn utf8_error(format: Format, e: core::str::Utf8Error) -> P3DError {
+    P3DError::ParseError { format, offset: Offset::Byte(e.valid_up_to()), reason: "not valid UTF-8" }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Obj => "OBJ",
+            Format::Gltf => "glTF",
+            Format::Stl => "STL",
+            Format::Ply => "PLY",
+            Format::Xyz => "XYZ",
+            Format::ThreeMf => "3MF",
+            Format::Off => "OFF",
+            Format::Gzip => "gzip",
+        })
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Offset::Byte(at) => write!(f, " at byte {}", at),
+            Offset::Line(line) => write!(f, " on line {}", line),
+            Offset::Unknown => Ok(()),
+        }
+    }
+}
+
+impl P3DError {
+    /// Stable number of the variant, for callers that can't match on it (FFI, WASM, logs);
+    /// `p3d_process_c` returns it negated. Never reused once assigned:
+    /// 1 `InvalidObject`, 2 `MeshError`, 3 `MathError`, 4 `UnsupportedFileType`,
+    /// 6 `DegenerateMesh`, 7 `EmptyMesh`, 8 `MalformedIndices`, 9 `InvalidMesh`,
+    /// 12 `SelfIntersecting`, 13 `InvalidParams`, 14 `Io`, 15 `Zip`, 16 `MeshTooLarge`,
+    /// 18 `LimitExceeded`, 19 `Cancelled`, 24 `NonFiniteCoordinate`, 25 `FlatObject`,
+    /// 26 `DegenerateGeometry`, 27 `TooManyVertices`. A `ParseError` keeps the number of the
+    /// variant its format had before: 5 glTF, 10 OBJ, 11 STL, 17 PLY, 20 XYZ, 21 3MF, 22 OFF,
+    /// 23 gzip. 28 is no variant: `p3d_process_c` returns it, negated, for a panic it caught.
+    pub fn code(&self) -> u16 {
+        match self {
+            P3DError::InvalidObject(_) => 1,
+            P3DError::MeshError(_) => 2,
+            P3DError::MathError => 3,
+            P3DError::UnsupportedFileType => 4,
+            P3DError::DegenerateMesh => 6,
+            P3DError::EmptyMesh => 7,
+            P3DError::MalformedIndices { .. } => 8,
+            P3DError::InvalidMesh { .. } => 9,
+            P3DError::ParseError { format, .. } => match format {
+                Format::Gltf => 5,
+                Format::Obj => 10,
+                Format::Stl => 11,
+                Format::Ply => 17,
+                Format::Xyz => 20,
+                Format::ThreeMf => 21,
+                Format::Off => 22,
+                Format::Gzip => 23,
+            },
+            P3DError::SelfIntersecting => 12,
+            P3DError::InvalidParams { .. } => 13,
+            P3DError::MeshTooLarge { .. } => 16,
+            P3DError::LimitExceeded { .. } => 18,
+            P3DError::Cancelled => 19,
+            P3DError::NonFiniteCoordinate { .. } => 24,
+            P3DError::FlatObject { .. } => 25,
+            P3DError::DegenerateGeometry { .. } => 26,
+            P3DError::TooManyVertices { .. } => 27,
+            #[cfg(feature = "std")]
+            P3DError::Io(_) => 14,
+            #[cfg(feature = "zip")]
+            P3DError::Zip(_) => 15,
+        }
+    }
+}
+
+impl fmt::Display for P3DError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            P3DError::InvalidObject(e) => write!(f, "invalid OBJ: {:?}", e),
+            P3DError::MeshError(e) => write!(f, "mesh error: {:?}", e),
+            P3DError::MathError => f.write_str("numerical failure"),
+            P3DError::UnsupportedFileType => f.write_str("unsupported file type"),
+            P3DError::DegenerateMesh => f.write_str("degenerate mesh: flat along some axis"),
+            P3DError::EmptyMesh => f.write_str("the input has no faces"),
+            P3DError::MalformedIndices { indices, vertices } => write!(f, "malformed indices: {} indices, {} vertices", indices, vertices),
+            P3DError::InvalidMesh { reason, indices, vertices } => write!(f, "invalid mesh: {} ({} indices, {} vertices)", reason, indices, vertices),
+            P3DError::ParseError { format, offset, reason } => write!(f, "{}: {}{}", format, reason, offset),
+            P3DError::NonFiniteCoordinate { vertex } => write!(f, "vertex {} has a non-finite coordinate", vertex),
+            P3DError::FlatObject { thickness, extent } => write!(f, "flat object: {:e} thick, {:e} across", thickness, extent),
+            P3DError::DegenerateGeometry { rank, moments } => {
+                let span = if *rank == 0 { "a point" } else { "a line" };
+                write!(f, "degenerate geometry: spans {}, principal moments {:e}, {:e}, {:e}", span, moments[0], moments[1], moments[2])
+            }
+            P3DError::TooManyVertices { vertices, limit } => write!(f, "{} vertices, more than the {} a vertex index addresses", vertices, limit),
+            P3DError::SelfIntersecting => f.write_str("the mesh intersects itself"),
+            P3DError::InvalidParams { param, reason } => write!(f, "invalid parameters: {} {}", param, reason),
+            P3DError::MeshTooLarge { faces, limit } => write!(f, "{} faces, over the limit of {}", faces, limit),
+            P3DError::LimitExceeded { what, value, limit } => write!(f, "{} {}, over the limit of {}", value, what, limit),
+            P3DError::Cancelled => f.write_str("cancelled"),
+            #[cfg(feature = "std")]
+            P3DError::Io(e) => write!(f, "I/O: {}", e),
+            #[cfg(feature = "zip")]
+            P3DError::Zip(e) => write!(f, "ZIP: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for P3DError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            P3DError::Io(e) => Some(e),
+            #[cfg(feature = "zip")]
+            P3DError::Zip(e) => Some(e),
+            _ => None,
+        }
+    }
+}
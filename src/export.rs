@@ -0,0 +1,114 @@
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::{P3DResult, Vec2};
+
+// Side of the square panel of one section in the SVG, and the margin around panels
+const PANEL: f64 = 200.0;
+const MARGIN: f64 = 10.0;
+// Height of the caption under a panel
+const CAPTION: f64 = 16.0;
+
+// JSON has no infinities or NaN, an empty result has an infinite `rect`
+fn write_number(out: &mut String, x: f64) {
+    if x.is_finite() {
+        let _ = write!(out, "{}", x);
+    } else {
+        out.push_str("null");
+    }
+}
+
+fn write_point(out: &mut String, p: &Vec2) {
+    write_list(out, &[p.x, p.y], |out, &x| write_number(out, x));
+}
+
+fn write_list<T>(out: &mut String, items: &[T], mut write_item: impl FnMut(&mut String, &T)) {
+    out.push('[');
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_item(out, item);
+    }
+    out.push(']');
+}
+
+impl P3DResult {
+    /// The sections as SVG, for showing what the algorithm ranked: a panel per section in
+    /// `centers`, bottom to top from left to right, with the section `rect` scaled to the panel
+    /// (y up), the `grid_size` grid, the cells shaded by their `occupancy` (when
+    /// `ProcessOptions::debug_geometry` was set), the contour and its height as caption.
+    pub fn sections_svg(&self) -> String {
+        let [min_x, max_x, min_y, max_y] = self.rect;
+        // The longer side of the `rect` spans the panel, the grid cells keep its aspect
+        let scale = PANEL / (max_x - min_x).max(max_y - min_y).max(f64::MIN_POSITIVE);
+        let (w, h) = ((max_x - min_x) * scale, (max_y - min_y) * scale);
+        let n = self.grid_size.max(1) as usize;
+        let (cell_w, cell_h) = (w / n as f64, h / n as f64);
+        let width = MARGIN + self.centers.len() as f64 * (PANEL + MARGIN);
+        let height = 2.0 * MARGIN + PANEL + CAPTION;
+
+        let mut svg = String::new();
+        let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#, width, height, width, height);
+        for (k, cntr) in self.centers.iter().enumerate() {
+            let left = MARGIN + k as f64 * (PANEL + MARGIN);
+            let _ = writeln!(svg, r#"<g transform="translate({} {})">"#, left, MARGIN + PANEL - h);
+            if let Some(cells) = self.occupancy.get(k) {
+                let most = cells.iter().copied().max().unwrap_or(0).max(1) as f64;
+                for (c, &count) in cells.iter().enumerate().take(n * n).filter(|(_, &count)| count > 0) {
+                    // Rows go up in y, the SVG down
+                    let (i, j) = (c % n, c / n);
+                    let _ = writeln!(svg, r#"<rect x="{:.3}" y="{:.3}" width="{:.3}" height="{:.3}" fill="steelblue" fill-opacity="{:.3}"><title>{}</title></rect>"#,
+                        i as f64 * cell_w, (n - 1 - j) as f64 * cell_h, cell_w, cell_h, count as f64 / most, count);
+                }
+            }
+            svg.push_str(r#"<path fill="none" stroke="lightgray" stroke-width="0.5" d=""#);
+            for i in 0..=n {
+                let _ = write!(svg, "M{:.3} 0V{:.3}M0 {:.3}H{:.3}", i as f64 * cell_w, h, i as f64 * cell_h, w);
+            }
+            svg.push_str("\"/>\n");
+            svg.push_str(r#"<polyline fill="none" stroke="black" stroke-width="1" points=""#);
+            for (i, p) in cntr.iter().enumerate() {
+                let sep = if i > 0 { " " } else { "" };
+                let _ = write!(svg, "{}{:.3},{:.3}", sep, (p.x - min_x) * scale, h - (p.y - min_y) * scale);
+            }
+            svg.push_str("\"/>\n");
+            let z = self.section_heights.get(k).copied().unwrap_or(f64::NAN);
+            let _ = writeln!(svg, r#"<text x="{}" y="{:.3}" font-family="sans-serif" font-size="12" text-anchor="middle">z = {:.4}</text>"#,
+                PANEL / 2.0, h + CAPTION, z);
+            svg.push_str("</g>\n");
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The sections as compact JSON, for frontends drawing them their own way:
+    /// `{"grid_size":8,"rect":[min_x,max_x,min_y,max_y],"sections":[{"z":..,"contour":[[x,y],..],"cells":[..]},..]}`,
+    /// a section per contour in `centers`, bottom to top. `cells` (the `occupancy` of the
+    /// section, rows first) is only there when `ProcessOptions::debug_geometry` was set.
+    /// Coordinates are written in full, so they read back exactly.
+    pub fn sections_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(json, r#"{{"grid_size":{},"rect":"#, self.grid_size);
+        write_list(&mut json, &self.rect, |out, &x| write_number(out, x));
+        json.push_str(r#","sections":["#);
+        for (k, cntr) in self.centers.iter().enumerate() {
+            if k > 0 {
+                json.push(',');
+            }
+            json.push_str(r#"{"z":"#);
+            write_number(&mut json, self.section_heights.get(k).copied().unwrap_or(f64::NAN));
+            json.push_str(r#","contour":"#);
+            write_list(&mut json, cntr, write_point);
+            if let Some(cells) = self.occupancy.get(k) {
+                json.push_str(r#","cells":"#);
+                write_list(&mut json, cells, |out, count| {
+                    let _ = write!(out, "{}", count);
+                });
+            }
+            json.push('}');
+        }
+        json.push_str("]}");
+        json
+    }
+}
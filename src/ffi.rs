@@ -0,0 +1,203 @@
+use alloc::boxed::Box;
+use alloc::ffi::CString;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::ffi::c_char;
+use std::panic::AssertUnwindSafe;
+
+use crate::{p3d_process_n, AlgoType, InputFileType, P3DError};
+
+/// Hashes returned by `p3d_process_c`, released with `p3d_free_string_list`
+#[repr(C)]
+pub struct P3DStringList {
+    pub strings: *mut *mut c_char,
+    pub len: usize,
+}
+
+// Names `p3d_error_name` returns, NUL terminated, by `P3DError::code`. A `ParseError` goes by
+// the name of the variant its format had before it.
+const ERROR_NAMES: [&[u8]; 29] = [
+    b"Ok\0", b"InvalidObject\0", b"MeshError\0", b"MathError\0", b"UnsupportedFileType\0", b"GltfError\0",
+    b"DegenerateMesh\0", b"EmptyMesh\0", b"MalformedIndices\0", b"InvalidMesh\0", b"ObjSyntax\0", b"StlError\0",
+    b"SelfIntersecting\0", b"InvalidParams\0", b"Io\0", b"Zip\0", b"MeshTooLarge\0", b"PlyError\0",
+    b"LimitExceeded\0", b"Cancelled\0", b"XyzError\0", b"ThreeMfError\0", b"OffError\0", b"GzipError\0",
+    b"NonFiniteCoordinate\0", b"FlatObject\0", b"DegenerateGeometry\0",
+    b"TooManyVertices\0", b"Panic\0",
+];
+
+// Code `p3d_process_c` returns for a panic it caught, past those of `P3DError`
+pub(crate) const PANIC_CODE: i32 = -28;
+
+std::thread_local! {
+    // Message of the last failed call on this thread, empty after a successful one
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+// Negative code returned by `p3d_process_c` for every `P3DError` variant
+pub(crate) fn error_code(err: &P3DError) -> i32 {
+    -(err.code() as i32)
+}
+
+// `PANIC_CODE`, with the message of the panic kept for `p3d_last_error_message`
+pub(crate) fn panicked(payload: &(dyn Any + Send)) -> i32 {
+    let message = payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+        .unwrap_or("no message");
+    LAST_ERROR.with(|last| *last.borrow_mut() = format!("panic: {}", message));
+    PANIC_CODE
+}
+
+// `error_code` of `err`, kept for `p3d_last_error_message`
+fn fail(err: &P3DError) -> i32 {
+    LAST_ERROR.with(|last| *last.borrow_mut() = err.to_string());
+    error_code(err)
+}
+
+fn file_type(code: u8) -> Option<InputFileType> {
+    match code {
+        0 => Some(InputFileType::Obj),
+        1 => Some(InputFileType::Gltf),
+        2 => Some(InputFileType::Glb),
+        3 => Some(InputFileType::Stl),
+        4 => Some(InputFileType::Ply),
+        5 => Some(InputFileType::Xyz),
+        6 => Some(InputFileType::ThreeMf),
+        7 => Some(InputFileType::Off),
+        8 => Some(InputFileType::Auto),
+        _ => None,
+    }
+}
+
+fn algo_type(code: u8) -> Option<AlgoType> {
+    match code {
+        0 => Some(AlgoType::Grid2d),
+        1 => Some(AlgoType::Grid2dV2),
+        2 => Some(AlgoType::Grid2dV3),
+        3 => Some(AlgoType::Grid2dV3a),
+        4 => Some(AlgoType::Spectr),
+        5 => Some(AlgoType::Skeleton),
+        6 => Some(AlgoType::CloudGrid),
+        7 => Some(AlgoType::Voxel),
+        8 => Some(AlgoType::FixedGrid),
+        9 => Some(AlgoType::CellStats),
+        10 => Some(AlgoType::GridNormals),
+        _ => None,
+    }
+}
+
+/// `p3d_process_n` for C callers. Returns 0 and fills `out` on success, otherwise a negative
+/// `error_code` and leaves `out` untouched. Free a filled `out` with `p3d_free_string_list`.
+///
+/// - `file_type`: 0 OBJ, 1 glTF, 2 GLB, 3 STL, 4 PLY, 5 XYZ, 6 3MF, 7 OFF, 8 detected from the
+///   content; anything else fails with `UnsupportedFileType` (-4).
+/// - `algo`: 0 `Grid2d`, 1 `Grid2dV2`, 2 `Grid2dV3`, 3 `Grid2dV3a`, 4 `Spectr`, 5 `Skeleton`,
+///   6 `CloudGrid`, 7 `Voxel`, 8 `FixedGrid`, 9 `CellStats`, 10 `GridNormals`; anything else,
+///   or a null `input_ptr`/`out`, fails with `InvalidParams` (-13).
+/// - `trans_ptr`: null for no rotation, otherwise 4 bytes (the `trans` of `p3d_process`).
+///
+/// Errors are the `P3DError::code` of the variant, negated; `p3d_error_name` names them and
+/// `p3d_last_error_message` describes the failure. A panic inside the library is caught and
+/// fails with `Panic` (-28) instead of unwinding into the caller.
+///
+/// # Safety
+/// `input_ptr` must point to `input_len` readable bytes, `trans_ptr` be null or point to 4
+/// readable bytes, and `out` point to a writable `P3DStringList`.
+#[no_mangle]
+pub unsafe extern "C" fn p3d_process_c(
+    input_ptr: *const u8,
+    input_len: usize,
+    file_type: u8,
+    algo: u8,
+    depth: usize,
+    par1: i16,
+    par2: i16,
+    trans_ptr: *const u8,
+    out: *mut P3DStringList,
+) -> i32 {
+    // A panic must not unwind into the C caller
+    std::panic::catch_unwind(AssertUnwindSafe(|| {
+        let invalid = |param, reason| fail(&P3DError::InvalidParams { param, reason });
+        if input_ptr.is_null() || out.is_null() {
+            return invalid(if input_ptr.is_null() { "input_ptr" } else { "out" }, "is a null pointer");
+        }
+        let file_type = match self::file_type(file_type) {
+            Some(t) => t,
+            None => return fail(&P3DError::UnsupportedFileType),
+        };
+        let algo = match algo_type(algo) {
+            Some(a) => a,
+            None => return invalid("algo", "unknown algorithm"),
+        };
+        let input = core::slice::from_raw_parts(input_ptr, input_len);
+        let trans = if trans_ptr.is_null() {
+            None
+        } else {
+            let t = core::slice::from_raw_parts(trans_ptr, 4);
+            Some([t[0], t[1], t[2], t[3]])
+        };
+
+        let hashes = match p3d_process_n(input, file_type, algo, depth, par1, par2, trans) {
+            Ok(hashes) => hashes,
+            Err(err) => return fail(&err),
+        };
+        LAST_ERROR.with(|last| last.borrow_mut().clear());
+        // Hex hashes never contain NUL
+        let strings: Box<[*mut c_char]> = hashes.into_iter()
+            .map(|h| CString::new(h).unwrap_or_default().into_raw())
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+        let len = strings.len();
+        *out = P3DStringList { strings: Box::into_raw(strings) as *mut *mut c_char, len };
+        0
+    })).unwrap_or_else(|payload| panicked(&*payload))
+}
+
+/// Releases the strings of a list filled by `p3d_process_c` and empties it.
+///
+/// # Safety
+/// `list` must be null or point to a list filled by `p3d_process_c` (or already freed).
+#[no_mangle]
+pub unsafe extern "C" fn p3d_free_string_list(list: *mut P3DStringList) {
+    if list.is_null() || (*list).strings.is_null() {
+        return;
+    }
+    let strings = Box::from_raw(core::ptr::slice_from_raw_parts_mut((*list).strings, (*list).len));
+    for &s in strings.iter() {
+        drop(CString::from_raw(s));
+    }
+    (*list).strings = core::ptr::null_mut();
+    (*list).len = 0;
+}
+
+/// Name of the `P3DError` variant an error code of `p3d_process_c` stands for (`"Ok"` for 0), as
+/// a static NUL terminated string, or null for a code no variant has. The codes of a
+/// `ParseError` are named by format, as `"PlyError"` or `"GltfError"`.
+#[no_mangle]
+pub extern "C" fn p3d_error_name(code: i32) -> *const c_char {
+    match ERROR_NAMES.get(code.unsigned_abs() as usize) {
+        Some(name) if code <= 0 => name.as_ptr() as *const c_char,
+        _ => core::ptr::null(),
+    }
+}
+
+/// Copies the message of the last failed `p3d_process_c` call on the calling thread into `buf`,
+/// truncated to `len - 1` bytes and NUL terminated, like `snprintf`. Returns the length of the
+/// whole message, 0 when the last call succeeded or none was made; `buf` may be null when
+/// `len` is 0, to size the buffer first.
+///
+/// # Safety
+/// `buf` must point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn p3d_last_error_message(buf: *mut c_char, len: usize) -> usize {
+    LAST_ERROR.with(|last| {
+        let message = last.borrow();
+        if !buf.is_null() && len > 0 {
+            let n = message.len().min(len - 1);
+            core::ptr::copy_nonoverlapping(message.as_ptr(), buf as *mut u8, n);
+            *buf.add(n) = 0;
+        }
+        message.len()
+    })
+}
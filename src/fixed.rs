@@ -0,0 +1,377 @@
+use alloc::vec::Vec;
+
+use tri_mesh::prelude::{Matrix4, Vector3};
+
+use crate::algo_grid::{digest, to_hex};
+use crate::{check_limit, transform_matrix, Allocations, Geometry, P3DError, P3DParams, P3DResult, RawHash};
+
+// The vertices are rounded to a lattice of 2^LATTICE_BITS units per half extent of the
+// bounding box, so the moments below fit in an i128
+const LATTICE_BITS: i32 = 15;
+
+// Fraction bits of the coordinates after alignment, in lattice units
+const COORD_BITS: u32 = 16;
+
+// Fraction bits of rotation matrices and of the Jacobi iteration
+const ROT_BITS: u32 = 30;
+
+const JACOBI_SWEEPS: usize = 16;
+
+// Rounding policy of the whole pipeline: products and quotients round to the nearest integer,
+// ties away from zero; square roots and grid cells round down.
+
+// `n / d` rounded to nearest, ties away from zero, for `d > 0`
+fn div_round(n: i128, d: i128) -> i128 {
+    let q = (n.abs() + d / 2) / d;
+    if n < 0 { -q } else { q }
+}
+
+// Product of two fixed-point values with `ROT_BITS` fraction bits
+fn mul(a: i64, b: i64) -> i64 {
+    div_round(a as i128 * b as i128, 1 << ROT_BITS) as i64
+}
+
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    // Newton's iteration from above
+    let mut x = 1u128 << ((129 - n.leading_zeros()) / 2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+// Eigenvalues and eigenvectors (columns) of the symmetric matrix `a`, by cyclic Jacobi rotations
+fn jacobi(mut a: [[i64; 3]; 3]) -> ([i64; 3], [[i64; 3]; 3]) {
+    let one = 1i128 << ROT_BITS;
+    let mut v = [[0i64; 3]; 3];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = one as i64;
+    }
+    for _ in 0..JACOBI_SWEEPS {
+        for &(p, q) in [(0, 1), (0, 2), (1, 2)].iter() {
+            let apq = a[p][q];
+            if apq == 0 {
+                continue;
+            }
+            // t = tan of the rotation angle, the smaller root of t^2 + t (a_qq - a_pp) / a_pq - 1
+            let d = a[q][q] - a[p][p];
+            let root = isqrt((d as i128 * d as i128 + 4 * apq as i128 * apq as i128) as u128) as i128;
+            let t = div_round((2 * apq.abs() as i128) << ROT_BITS, d.abs() as i128 + root) as i64;
+            let t = if (apq < 0) != (d < 0) { -t } else { t };
+            let c = div_round(one * one, isqrt((one * one + t as i128 * t as i128) as u128) as i128) as i64;
+            let s = mul(t, c);
+
+            let shift = mul(t, apq);
+            a[p][p] -= shift;
+            a[q][q] += shift;
+            a[p][q] = 0;
+            a[q][p] = 0;
+            let r = 3 - p - q;
+            let (arp, arq) = (a[r][p], a[r][q]);
+            a[r][p] = mul(c, arp) - mul(s, arq);
+            a[p][r] = a[r][p];
+            a[r][q] = mul(s, arp) + mul(c, arq);
+            a[q][r] = a[r][q];
+            for row in v.iter_mut() {
+                let (vp, vq) = (row[p], row[q]);
+                row[p] = mul(c, vp) - mul(s, vq);
+                row[q] = mul(s, vp) + mul(c, vq);
+            }
+        }
+    }
+    ([a[0][0], a[1][1], a[2][2]], v)
+}
+
+fn mat_mul(a: &[[i64; 3]; 3], b: &[[i64; 3]; 3]) -> [[i64; 3]; 3] {
+    let mut m = [[0i64; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            let sum: i128 = (0..3).map(|k| a[i][k] as i128 * b[k][j] as i128).sum();
+            m[i][j] = div_round(sum, 1 << ROT_BITS) as i64;
+        }
+    }
+    m
+}
+
+fn cross(a: &[i64; 3], b: &[i64; 3]) -> [i64; 3] {
+    [
+        mul(a[1], b[2]) - mul(a[2], b[1]),
+        mul(a[2], b[0]) - mul(a[0], b[2]),
+        mul(a[0], b[1]) - mul(a[1], b[0]),
+    ]
+}
+
+// Exact moments of the lattice geometry: the mass and the first and second moments about the
+// origin, each scaled by an integer (`first_div`, `second_div`) relative to the mass
+struct Moments {
+    mass: i128,
+    first: [i128; 3],
+    second: [[i128; 3]; 3],
+    first_div: i128,
+    second_div: i128,
+}
+
+// Moments of the solid the faces enclose, as signed tetrahedra to the origin: 6 V = sum(det),
+// 24 V c = sum(det s) and 120 M2 = sum(det (sum(v v^T) + s s^T)), with s the sum of the corners
+fn solid_moments(lattice: &[[i64; 3]], indices: &[u32]) -> Moments {
+    let mut moments = Moments { mass: 0, first: [0; 3], second: [[0; 3]; 3], first_div: 4, second_div: 20 };
+    for t in indices.chunks_exact(3) {
+        let [a, b, c] = [lattice[t[0] as usize], lattice[t[1] as usize], lattice[t[2] as usize]].map(|p| p.map(|x| x as i128));
+        let det = a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0]) + a[2] * (b[0] * c[1] - b[1] * c[0]);
+        let s = [a[0] + b[0] + c[0], a[1] + b[1] + c[1], a[2] + b[2] + c[2]];
+        moments.mass += det;
+        for i in 0..3 {
+            moments.first[i] += det * s[i];
+            for j in 0..3 {
+                moments.second[i][j] += det * (a[i] * a[j] + b[i] * b[j] + c[i] * c[j] + s[i] * s[j]);
+            }
+        }
+    }
+    if moments.mass < 0 {
+        // Faces wound inwards
+        moments.mass = -moments.mass;
+        moments.first.iter_mut().for_each(|m| *m = -*m);
+        moments.second.iter_mut().flatten().for_each(|m| *m = -*m);
+    }
+    moments
+}
+
+// Moments of the vertices as points of unit mass, for surfaces enclosing no volume
+fn point_moments(lattice: &[[i64; 3]], used: &[bool]) -> Moments {
+    let mut moments = Moments { mass: 0, first: [0; 3], second: [[0; 3]; 3], first_div: 1, second_div: 1 };
+    for p in lattice.iter().zip(used.iter()).filter(|(_, &u)| u).map(|(p, _)| p.map(|x| x as i128)) {
+        moments.mass += 1;
+        for i in 0..3 {
+            moments.first[i] += p[i];
+            for j in 0..3 {
+                moments.second[i][j] += p[i] * p[j];
+            }
+        }
+    }
+    moments
+}
+
+// `n / d` with `bits` fraction bits, split so `n` may use the whole i128
+fn div_fixed(n: i128, d: i128, bits: u32) -> i128 {
+    ((n / d) << bits) + div_round((n % d) << bits, d)
+}
+
+// Center of mass (`COORD_BITS` fraction bits) and rotation (`ROT_BITS`) whose rows are the
+// principal axes by increasing variance, each pointing to the side with the larger third
+// moment of the vertices, z = x ^ y
+fn principal_axes(lattice: &[[i64; 3]], used: &[bool], moments: &Moments) -> Result<([i64; 3], [[i64; 3]; 3]), P3DError> {
+    let mass = moments.mass;
+    let center = moments.first.map(|f| div_fixed(f, mass * moments.first_div, COORD_BITS));
+    let mut covariance = [[0i128; 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            covariance[i][j] = div_fixed(moments.second[i][j], mass * moments.second_div, 2 * COORD_BITS) - center[i] * center[j];
+        }
+    }
+    let max = covariance.iter().flatten().map(|c| c.abs()).max().unwrap_or(0);
+    if max == 0 {
+        return Err(P3DError::DegenerateMesh);
+    }
+    let scaled = covariance.map(|row| row.map(|c| div_round(c << ROT_BITS, max) as i64));
+    let (values, vectors) = jacobi(scaled);
+
+    let mut order = [0, 1, 2];
+    order.sort_by_key(|&k| values[k]);
+    let mut axes = order.map(|k| [vectors[0][k], vectors[1][k], vectors[2][k]]);
+    let center = center.map(|c| c as i64);
+    for axis in axes.iter_mut().take(2) {
+        let skew: i128 = lattice.iter().zip(used.iter()).filter(|(_, &u)| u)
+            .map(|(p, _)| {
+                let along: i128 = (0..3).map(|i| axis[i] as i128 * ((p[i] << COORD_BITS) - center[i]) as i128).sum();
+                let along = div_round(along, 1 << (ROT_BITS + COORD_BITS));
+                along * along * along
+            })
+            .sum();
+        if skew < 0 {
+            *axis = axis.map(|c| -c);
+        }
+    }
+    axes[2] = cross(&axes[0], &axes[1]);
+    Ok((center, axes))
+}
+
+// Lattice coordinates of every vertex, the input point at the lattice origin and the exponent
+// of the lattice unit: `2^-exponent` in input units
+type Lattice = (Vec<[i64; 3]>, [f64; 3], i32);
+
+fn lattice(positions: &[f64], used: &[bool]) -> Result<Lattice, P3DError> {
+    let (mut lo, mut hi) = ([f64::INFINITY; 3], [f64::NEG_INFINITY; 3]);
+    for p in positions.chunks_exact(3).zip(used.iter()).filter(|(_, &u)| u).map(|(p, _)| p) {
+        for i in 0..3 {
+            lo[i] = lo[i].min(p[i]);
+            hi[i] = hi[i].max(p[i]);
+        }
+    }
+    let center = [0, 1, 2].map(|i| (lo[i] + hi[i]) / 2.0);
+    let half = (0..3).map(|i| (hi[i] - lo[i]) / 2.0).fold(0.0, f64::max);
+    if !(half > 0.0 && half.is_finite()) {
+        return Err(P3DError::DegenerateMesh);
+    }
+    // Exact scaling by a power of two with `half * 2^exponent` in [2^(LATTICE_BITS - 1), 2^LATTICE_BITS)
+    let exponent = LATTICE_BITS - libm::frexp(half).1;
+    // Unused vertices may be anything, and are left at the origin
+    let lattice = positions.chunks_exact(3).zip(used.iter())
+        .map(|(p, &u)| if u { [0, 1, 2].map(|i| libm::round(libm::ldexp(p[i] - center[i], exponent)) as i64) } else { [0; 3] })
+        .collect();
+    Ok((lattice, center, exponent))
+}
+
+// Shares of the total length in every cell of a section, in steps of 2^-level of the mean
+fn quantize(cells: &[u64], level: usize) -> Vec<u8> {
+    let total: u128 = cells.iter().map(|&c| c as u128).sum();
+    if total == 0 {
+        return vec![0; cells.len()];
+    }
+    let scale = (cells.len() as u128) << level.min(32);
+    cells.iter().map(|&c| (c as u128 * scale / total).min(255) as u8).collect()
+}
+
+// `AlgoType::FixedGrid`: the pipeline of the contour grids in integer arithmetic, so the
+// hashes are bit-identical on every platform and compiler. The vertices are rounded to a
+// lattice of 2^15 units per half extent of the bounding box; the exact moments of the solid
+// (of the vertices, if the faces enclose no volume) give the center and the covariance, whose
+// principal axes a fixed number of fixed-point Jacobi sweeps finds. The axes are ordered by
+// increasing variance, so z is the longest, and oriented by the third moment of the vertices.
+// Every triangle crossing one of the `n_sections` planes evenly spaced along z adds the length
+// of its segment to the grid cell of the segment's midpoint, on a `grid_size` grid over the XY
+// bounds of the aligned vertices. Hash `d` digests the length shares of all cells of all
+// sections in steps of `2^-d` of the mean, like `CloudGrid`; repeated hashes are dropped.
+pub(crate) fn process_fixed(geometry: Geometry, params: &P3DParams) -> Result<P3DResult, P3DError> {
+    let opts = &params.options;
+    let Geometry { positions, indices, .. } = geometry;
+    if indices.is_empty() {
+        return Err(P3DError::EmptyMesh);
+    }
+    let mut used = vec![false; positions.len() / 3];
+    for &i in indices.iter() {
+        used[i as usize] = true;
+    }
+    let (lattice, box_center, exponent) = lattice(&positions, &used)?;
+
+    let one = 1i64 << ROT_BITS;
+    let (center, mut rotation) = if opts.align {
+        let moments = match solid_moments(&lattice, &indices) {
+            solid if solid.mass > 0 => solid,
+            _ => point_moments(&lattice, &used),
+        };
+        principal_axes(&lattice, &used, &moments)?
+    } else {
+        ([0; 3], [[one, 0, 0], [0, one, 0], [0, 0, one]])
+    };
+    if let Some(rot) = transform_matrix(params.transform)? {
+        let rot = [0, 1, 2].map(|i| [0, 1, 2].map(|j| libm::round(libm::ldexp(rot[j][i], ROT_BITS as i32)) as i64));
+        rotation = mat_mul(&rot, &rotation);
+    }
+
+    let aligned: Vec<[i64; 3]> = lattice.iter()
+        .map(|p| {
+            let d = [0, 1, 2].map(|i| ((p[i] << COORD_BITS) - center[i]) as i128);
+            rotation.map(|row| div_round((0..3).map(|i| row[i] as i128 * d[i]).sum(), 1 << ROT_BITS) as i64)
+        })
+        .collect();
+    let (mut lo, mut hi) = ([i64::MAX; 3], [i64::MIN; 3]);
+    for p in aligned.iter().zip(used.iter()).filter(|(_, &u)| u).map(|(p, _)| p) {
+        for i in 0..3 {
+            lo[i] = lo[i].min(p[i]);
+            hi[i] = hi[i].max(p[i]);
+        }
+    }
+    // Thinner than a lattice unit
+    if (0..3).any(|i| hi[i] - lo[i] < 1 << COORD_BITS) {
+        return Err(P3DError::DegenerateMesh);
+    }
+
+    let n_sections = params.n_sections as usize;
+    check_limit("sections", n_sections, opts.limits.max_sections)?;
+    let n = params.grid_size as usize;
+    let heights: Vec<i64> = (1..=n_sections as i128)
+        .map(|k| lo[2] + div_round(k * (hi[2] - lo[2]) as i128, n_sections as i128 + 1) as i64)
+        .collect();
+    let (width, depth) = ((hi[0] - lo[0]) as i128, (hi[1] - lo[1]) as i128);
+    let cell = |sum: i64, min: i64, size: i128| (((sum - 2 * min) as i128 * n as i128 / (2 * size)) as usize).min(n - 1);
+
+    let mut lengths = vec![vec![0u64; n * n]; n_sections];
+    for t in indices.chunks_exact(3) {
+        let corners = [aligned[t[0] as usize], aligned[t[1] as usize], aligned[t[2] as usize]];
+        let z_lo = corners.iter().map(|p| p[2]).min().unwrap_or(0);
+        let z_hi = corners.iter().map(|p| p[2]).max().unwrap_or(0);
+        let planes = heights.partition_point(|&h| h <= z_lo)..heights.partition_point(|&h| h <= z_hi);
+        for k in planes {
+            let h = heights[k];
+            // Exactly two edges have one end below the plane and the other not; computed from
+            // the end below, so the triangles sharing an edge find the same point
+            let mut ends = [[0i64; 2]; 2];
+            let mut found = 0;
+            for e in 0..3 {
+                let (u, v) = (corners[e], corners[(e + 1) % 3]);
+                if (u[2] < h) == (v[2] < h) {
+                    continue;
+                }
+                let (u, v) = if u[2] < h { (u, v) } else { (v, u) };
+                let at = |i: usize| u[i] + div_round((v[i] - u[i]) as i128 * (h - u[2]) as i128, (v[2] - u[2]) as i128) as i64;
+                ends[found] = [at(0), at(1)];
+                found += 1;
+            }
+            let (dx, dy) = ((ends[1][0] - ends[0][0]) as i128, (ends[1][1] - ends[0][1]) as i128);
+            let length = isqrt((dx * dx + dy * dy) as u128) as u64;
+            let (i, j) = (cell(ends[0][0] + ends[1][0], lo[0], width), cell(ends[0][1] + ends[1][1], lo[1], depth));
+            lengths[k][j * n + i] += length;
+        }
+    }
+
+    let mut hashes: Vec<RawHash> = Vec::with_capacity(params.depth);
+    for level in 0..params.depth {
+        let sections: Vec<Vec<u8>> = lengths.iter().map(|cells| quantize(cells, level)).collect();
+        let hash = digest(opts.hash_algo, sections.iter().map(|s| s.as_slice()));
+        if !hashes.contains(&hash) {
+            hashes.push(hash);
+        }
+    }
+    let populated = lengths.iter().filter(|cells| cells.iter().any(|&c| c > 0)).count();
+
+    // Reported in input units, aligned
+    let unit = |x: i64| libm::ldexp(x as f64, -exponent - COORD_BITS as i32);
+    let vector = |p: [i64; 3]| Vector3::new(unit(p[0]), unit(p[1]), unit(p[2]));
+    let r = rotation.map(|row| row.map(|c| libm::ldexp(c as f64, -(ROT_BITS as i32))));
+    Ok(P3DResult {
+        hashes: hashes.iter().map(to_hex).collect(),
+        centers: Vec::new(),
+        section_heights: heights.iter().map(|&h| unit(h)).collect(),
+        rect: [unit(lo[0]), unit(hi[0]), unit(lo[1]), unit(hi[1])],
+        materials: Vec::new(),
+        section_loops: Vec::new(),
+        shift: -(Vector3::new(box_center[0], box_center[1], box_center[2]) + vector(center)),
+        transform: Matrix4::new(
+            r[0][0], r[1][0], r[2][0], 0.0,
+            r[0][1], r[1][1], r[2][1], 0.0,
+            r[0][2], r[1][2], r[2][2], 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ),
+        v_min: vector(lo),
+        v_max: vector(hi),
+        out_of_bounds: 0,
+        robustness: (populated as f64 / n_sections as f64) as f32,
+        scores: Vec::new(),
+        grid_size: params.grid_size,
+        vertices: if opts.debug_geometry { aligned.iter().map(|&p| vector(p).into()).collect() } else { Vec::new() },
+        occupancy: if opts.debug_geometry {
+            // Lengths in lattice units
+            lengths.iter().map(|cells| cells.iter().map(|&c| (c >> COORD_BITS).min(u32::MAX as u64) as u32).collect()).collect()
+        } else {
+            Vec::new()
+        },
+        hash_details: Vec::new(),
+        peak_allocation: opts.allocations.as_ref().map_or(0, Allocations::peak),
+    })
+}
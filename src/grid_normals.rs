@@ -0,0 +1,120 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use tri_mesh::mesh::Mesh;
+
+use crate::algo_grid::{HashAlgo, RawHash};
+use crate::cancel::Cancel;
+use crate::cell_stats::quantized_hashes;
+use crate::contour::Rect;
+use crate::vec3::{cross, dot, norm, sub, Point};
+use crate::Vec2;
+
+// `v` scaled to length 1, zero stays zero
+fn unit(v: Point) -> Point {
+    let length = norm(v);
+    if length > 0.0 { [v[0] / length, v[1] / length, v[2] / length] } else { [0.0; 3] }
+}
+
+// An edge of the mesh with what the sections read where they cross it: the normals at its
+// ends (of the faces around each, weighted by area) and how sharply the surface bends over it
+struct Edge {
+    ends: [Point; 2],
+    normals: [Point; 2],
+    // Angle between the normals of the two faces on the edge, as a fraction of a half turn;
+    // 1 on a border or a non-manifold edge
+    bend: f64,
+}
+
+fn mesh_edges(mesh: &Mesh) -> Vec<Edge> {
+    let ids: BTreeMap<_, usize> = mesh.vertex_iter().enumerate().map(|(i, v)| (v, i)).collect();
+    let positions: Vec<Point> = mesh.vertex_iter()
+        .map(|v| {
+            let p = mesh.vertex_position(v);
+            [p.x, p.y, p.z]
+        })
+        .collect();
+    let mut vertex_normals = vec![[0.0; 3]; positions.len()];
+    let mut face_normals: Vec<Point> = Vec::new();
+    let mut edge_faces: BTreeMap<(usize, usize), Vec<usize>> = BTreeMap::new();
+    for f in mesh.face_iter() {
+        let (a, b, c) = mesh.face_vertices(f);
+        let face = [ids[&a], ids[&b], ids[&c]];
+        // Twice the area long
+        let n = cross(sub(positions[face[1]], positions[face[0]]), sub(positions[face[2]], positions[face[0]]));
+        for k in 0..3 {
+            let normal = &mut vertex_normals[face[k]];
+            for (c, d) in normal.iter_mut().zip(n.iter()) {
+                *c += d;
+            }
+            let (u, v) = (face[k], face[(k + 1) % 3]);
+            edge_faces.entry((u.min(v), u.max(v))).or_default().push(face_normals.len());
+        }
+        face_normals.push(unit(n));
+    }
+    edge_faces.into_iter()
+        .map(|((u, v), faces)| {
+            let bend = match faces[..] {
+                [f, g] => libm::acos(dot(face_normals[f], face_normals[g]).clamp(-1.0, 1.0)) / core::f64::consts::PI,
+                _ => 1.0,
+            };
+            Edge { ends: [positions[u], positions[v]], normals: [unit(vertex_normals[u]), unit(vertex_normals[v])], bend }
+        })
+        .collect()
+}
+
+// Surface the crossings of a section add up in one grid cell
+#[derive(Clone, Default)]
+struct Cell {
+    count: u32,
+    // Sums of `|n.z|` of the interpolated normals and of the bends of the crossed edges
+    tilt: f64,
+    bend: f64,
+}
+
+// The cells of the `grid_size` x `grid_size` grid over `rect` (rows first) with the edges
+// crossing the plane at `z`. Edges in the plane cross it nowhere in particular and are left out.
+fn section_cells(edges: &[Edge], z: f64, grid_size: usize, rect: &Rect) -> Vec<Cell> {
+    let n = grid_size as i32;
+    let mut cells = vec![Cell::default(); grid_size * grid_size];
+    for edge in edges.iter() {
+        let ([p, q], [np, nq]) = (edge.ends, edge.normals);
+        if p[2] == q[2] || z < p[2].min(q[2]) || z > p[2].max(q[2]) {
+            continue;
+        }
+        let t = (z - p[2]) / (q[2] - p[2]);
+        let point = rect.clamp(&Vec2 { x: p[0] + t * (q[0] - p[0]), y: p[1] + t * (q[1] - p[1]) });
+        let normal = unit([0, 1, 2].map(|k| (1.0 - t) * np[k] + t * nq[k]));
+        let (i, j) = rect.cell(&point, n);
+        let (i, j) = (i.max(0).min(n - 1), j.max(0).min(n - 1));
+        let cell = &mut cells[(j * n + i) as usize];
+        cell.count += 1;
+        cell.tilt += normal[2].abs();
+        cell.bend += edge.bend;
+    }
+    cells
+}
+
+// `AlgoType::GridNormals`: for every grid cell of every section, the mean steepness of the
+// surface where the section crosses it (`|n.z|` of the vertex normals interpolated along the
+// crossed edges, 0 for a wall, 1 for a floor) and the mean bend of the crossed edges (the angle
+// between their faces, which a smooth tessellation keeps small and a faceted one concentrates
+// on few edges). Both are hashed by `quantized_hashes`, the cells of a section in two rows, so
+// an empty cell marks the silhouette as the contour grids see it.
+pub(crate) fn find_top_grid_normals(
+    mesh: &Mesh, heights: &[f64], depth: usize, grid_size: i16, rect: &Rect, hash_algo: HashAlgo, cancel: Option<&Cancel>,
+) -> Vec<RawHash> {
+    let grid_size = grid_size.max(1) as usize;
+    let edges = mesh_edges(mesh);
+    let mut values: Vec<Vec<Option<f64>>> = Vec::with_capacity(heights.len());
+    for &z in heights.iter() {
+        if matches!(cancel, Some(cancel) if cancel.is_cancelled()) {
+            return Vec::new();
+        }
+        let cells = section_cells(&edges, z, grid_size, rect);
+        let mean = |sum: fn(&Cell) -> f64| cells.iter()
+            .map(move |c| if c.count > 0 { Some(sum(c) / c.count as f64) } else { None });
+        values.push(mean(|c| c.tilt).chain(mean(|c| c.bend)).collect());
+    }
+    quantized_hashes(&values, depth, hash_algo)
+}
@@ -0,0 +1,62 @@
+use alloc::vec::Vec;
+
+use miniz_oxide::inflate::{decompress_to_vec_with_limit, TINFLStatus};
+
+use crate::{Format, Offset, P3DError};
+
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Header flags
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+fn gzip_error(at: usize, reason: &'static str) -> P3DError {
+    P3DError::ParseError { format: Format::Gzip, offset: Offset::Byte(at), reason }
+}
+
+pub(crate) fn is_gzip(input: &[u8]) -> bool {
+    input.starts_with(&MAGIC)
+}
+
+// Data of the first member of a gzip stream, at most `limit` bytes. The length recorded in
+// the trailer is checked, the CRC isn't.
+pub(crate) fn decompress(input: &[u8], limit: usize) -> Result<Vec<u8>, P3DError> {
+    if input.len() < 18 || !is_gzip(input) {
+        return Err(gzip_error(0, "truncated header"));
+    }
+    if input[2] != 8 {
+        return Err(gzip_error(2, "unknown compression method"));
+    }
+    let flags = input[3];
+    let mut at = 10;
+    if flags & FEXTRA != 0 {
+        let len = input.get(at..at + 2).ok_or_else(|| gzip_error(at, "truncated header"))?;
+        at += 2 + u16::from_le_bytes([len[0], len[1]]) as usize;
+    }
+    // Zero terminated file name and comment
+    for flag in [FNAME, FCOMMENT].iter() {
+        if flags & flag != 0 {
+            let end = input.get(at..).and_then(|rest| rest.iter().position(|&b| b == 0))
+                .ok_or_else(|| gzip_error(at, "truncated header"))?;
+            at += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        at += 2;
+    }
+    let deflated = input.get(at..).ok_or_else(|| gzip_error(input.len(), "truncated header"))?;
+
+    let data = decompress_to_vec_with_limit(deflated, limit).map_err(|e| match e.status {
+        // Stopped at the limit, the data is longer
+        TINFLStatus::HasMoreOutput => P3DError::LimitExceeded { what: "input bytes", value: limit.saturating_add(1), limit },
+        _ => gzip_error(at, "corrupt deflate stream"),
+    })?;
+    // The trailer (CRC and size) is past the deflate stream, at the end of a single member
+    let size = &input[input.len() - 4..];
+    if u32::from_le_bytes([size[0], size[1], size[2], size[3]]) != data.len() as u32 {
+        return Err(gzip_error(input.len() - 4, "size mismatch, truncated or concatenated stream"));
+    }
+    Ok(data)
+}
@@ -0,0 +1,211 @@
+#[cfg(feature = "std")]
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::obj_parser::{coordinate, resolve_index, syntax_error, triangulate_face};
+use crate::weld::merge_coincident;
+use crate::{check_limit, check_vertex_count, Geometry, P3DError, P3DMesh, ProcessOptions};
+#[cfg(feature = "std")]
+use crate::{check_params, P3DParams};
+
+/// Storage of the vertex positions while an OBJ file is streamed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    /// Coordinates at full precision. The hashes can differ from `p3d_process` on the whole
+    /// file, which reads OBJ coordinates as f32.
+    F64,
+    /// Coordinates read as f32, as `p3d_process` reads them, giving the same hashes and halving
+    /// the memory they take until the file is complete
+    F32,
+}
+
+enum Points {
+    F64(Vec<f64>),
+    F32(Vec<f32>),
+}
+
+impl Points {
+    fn len(&self) -> usize {
+        match self {
+            Points::F64(p) => p.len() / 3,
+            Points::F32(p) => p.len() / 3,
+        }
+    }
+
+    fn precision(&self) -> Precision {
+        match self {
+            Points::F64(_) => Precision::F64,
+            Points::F32(_) => Precision::F32,
+        }
+    }
+
+    fn push(&mut self, p: [f64; 3]) {
+        match self {
+            Points::F64(points) => points.extend_from_slice(&p),
+            Points::F32(points) => points.extend(p.iter().map(|&c| c as f32)),
+        }
+    }
+
+    fn get(&self, id: u32) -> [f64; 3] {
+        let i = id as usize * 3;
+        match self {
+            Points::F64(p) => [p[i], p[i + 1], p[i + 2]],
+            Points::F32(p) => [p[i] as f64, p[i + 1] as f64, p[i + 2] as f64],
+        }
+    }
+}
+
+/// OBJ reader fed the file in chunks of any size, so it never has to be in memory whole.
+/// Positions go straight into their final buffer and faces index them directly, instead of
+/// first being copied per distinct (position, normal) as the whole-file loaders do; the mesh
+/// comes out the same, vertices in the order faces first use them.
+///
+/// ```ignore
+/// let mut stream = ObjStream::new(Precision::F64, &ProcessOptions::default());
+/// for chunk in chunks {
+///     stream.feed(chunk)?;
+/// }
+/// let hashes = stream.finish()?.process(AlgoType::Grid2dV3a, 10, 8, 10, None)?;
+/// ```
+pub struct ObjStream {
+    opts: ProcessOptions,
+    points: Points,
+    normal_count: usize,
+    // Triangles, as indices into `points`
+    indices: Vec<u32>,
+    // Start of a line the last chunk ended in
+    pending: Vec<u8>,
+    line: usize,
+    bytes: usize,
+}
+
+impl ObjStream {
+    /// A reader building the mesh with `opts`, which also bound the input (`Limits`)
+    pub fn new(precision: Precision, opts: &ProcessOptions) -> Self {
+        let points = match precision {
+            Precision::F64 => Points::F64(Vec::new()),
+            Precision::F32 => Points::F32(Vec::new()),
+        };
+        Self { opts: opts.clone(), points, normal_count: 0, indices: Vec::new(), pending: Vec::new(), line: 0, bytes: 0 }
+    }
+
+    /// Parses every line the chunk completes. Fails on the first syntax error, with its line.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), P3DError> {
+        self.bytes += chunk.len();
+        check_limit("input bytes", self.bytes, self.opts.limits.max_input_bytes)?;
+        let mut rest = chunk;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            if self.pending.is_empty() {
+                self.parse_line(&rest[..end])?;
+            } else {
+                self.pending.extend_from_slice(&rest[..end]);
+                let line = core::mem::take(&mut self.pending);
+                self.parse_line(&line)?;
+            }
+            rest = &rest[end + 1..];
+        }
+        self.pending.extend_from_slice(rest);
+        Ok(())
+    }
+
+    fn parse_line(&mut self, raw: &[u8]) -> Result<(), P3DError> {
+        self.line += 1;
+        let line = self.line;
+        let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+        let text = core::str::from_utf8(raw).map_err(|_| syntax_error(line, "not valid UTF-8"))?;
+        let mut tokens = text.split('#').next().unwrap_or("").split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut p = [0.0; 3];
+                for c in p.iter_mut() {
+                    let token = tokens.next().ok_or_else(|| syntax_error(line, "vertex needs 3 coordinates"))?;
+                    *c = coordinate(token, self.points.precision(), line)?;
+                }
+                self.points.push(p);
+                check_limit("vertices", self.points.len(), self.opts.limits.max_vertices)?;
+                check_vertex_count(self.points.len())?;
+            }
+            Some("vn") => self.normal_count += 1,
+            Some("f") => {
+                let mut corners: Vec<u32> = Vec::new();
+                for corner in tokens {
+                    let mut parts = corner.split('/');
+                    corners.push(resolve_index(parts.next().unwrap_or(""), self.points.len(), line)? as u32);
+                    let _texture = parts.next();
+                    if let Some(t) = parts.next().filter(|t| !t.is_empty()) {
+                        resolve_index(t, self.normal_count, line)?;
+                    }
+                }
+                if corners.len() < 3 {
+                    return Err(syntax_error(line, "face needs at least 3 vertices"));
+                }
+                let points = &self.points;
+                for triangle in triangulate_face(&corners, |id| points.get(id)) {
+                    self.indices.extend_from_slice(&triangle);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Parses the last line, drops the positions no face uses, then builds and aligns the mesh
+    /// as `P3DMesh::load_opts` would
+    pub fn finish(mut self) -> Result<P3DMesh, P3DError> {
+        if !self.pending.is_empty() {
+            let line = core::mem::take(&mut self.pending);
+            self.parse_line(&line)?;
+        }
+        if self.opts.view_camera.is_some() {
+            return Err(P3DError::UnsupportedFileType);
+        }
+
+        // Renumber the used positions in order of first use
+        let mut remap = vec![u32::MAX; self.points.len()];
+        let mut used = 0;
+        for i in self.indices.iter() {
+            if remap[*i as usize] == u32::MAX {
+                remap[*i as usize] = used;
+                used += 1;
+            }
+        }
+        let mut positions = vec![0.0; used as usize * 3];
+        for (id, &to) in remap.iter().enumerate() {
+            if to != u32::MAX {
+                positions[to as usize * 3..to as usize * 3 + 3].copy_from_slice(&self.points.get(id as u32));
+            }
+        }
+        drop(self.points);
+        for i in self.indices.iter_mut() {
+            *i = remap[*i as usize];
+        }
+        drop(remap);
+
+        let (positions, indices) = merge_coincident(positions, self.indices);
+        P3DMesh::from_geometry(Geometry { positions, indices, materials: Vec::new() }, &self.opts)
+    }
+}
+
+// Bytes read from a `Read` at a time
+#[cfg(feature = "std")]
+const READ_CHUNK: usize = 64 << 10;
+
+/// Same as `p3d_process_cfg` on an OBJ file, read from `reader` in chunks through `ObjStream`
+/// rather than loaded whole. `AlgoType::CloudGrid` needs the whole input and is refused.
+#[cfg(feature = "std")]
+pub fn p3d_process_obj_reader<R: std::io::Read>(mut reader: R, precision: Precision, params: &P3DParams) -> Result<Vec<String>, P3DError> {
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    let mut stream = ObjStream::new(precision, &params.options);
+    let mut buffer = vec![0u8; READ_CHUNK];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => stream.feed(&buffer[..n])?,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(P3DError::Io(e)),
+        }
+    }
+    stream.finish()?
+        .process_transform(params.algo, params.depth, params.grid_size, params.n_sections, params.transform)
+        .map(|result| result.hashes)
+}
@@ -4,288 +4,6566 @@
 extern crate alloc;
 #[macro_use]
 extern crate ndarray;
+#[cfg(feature = "std")]
+extern crate std;
 
+use alloc::borrow::Cow;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::String;
 use alloc::vec::Vec;
-use gltf::{Gltf, Semantic};
+use core::cell::RefCell;
+use core::iter::repeat;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use gltf::{Gltf, Primitive, Semantic};
+use gltf::accessor::{Accessor, DataType, Dimensions, Item, Iter as AccessorIter};
+use gltf::mesh::Mode;
 
 use obj::{load_obj, Obj, Vertex, ObjError};
 use tri_mesh::prelude::*;
-use cgmath::Point2;
-use ndarray::arr2;
+use cgmath::{Matrix3, Point2, Quaternion};
+use cgmath::Transform as _;
+use ndarray::{arr1, arr2, Array1, Array2};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 use ndarray::Array3;
 use tri_mesh::mesh_builder::Error as MeshError;
-use crate::algo_grid::{get_contour, intersect, intersect_2};
-use crate::contour::Rect;
+use crate::algo_grid::{get_contour, get_contour_tagged, SectionIndex, ZRanges};
+use crate::allocations::Charge;
+pub use crate::algo_grid::{principal_inertia_transform, HashAlgo, RawHash, NO_MATERIAL};
+use crate::algo_grid::{digest, from_hex, to_hex};
+use crate::contour::{simplify_indices, Rect};
+use crate::error::gltf_error;
+use crate::soup::Soup;
+use crate::source::source_geometry;
 
+#[macro_use]
+mod trace;
 mod polyline;
 mod contour;
 mod algo_grid;
+mod allocations;
+#[cfg(feature = "cache")]
+mod cache;
+mod camera;
+mod cell_stats;
+mod cancel;
+pub mod canonicalize;
+mod cloud;
+mod compare;
+mod components;
+mod data_uri;
+mod decimate;
+mod degenerate;
+#[cfg(feature = "gzip")]
+mod gzip;
+mod descriptor;
+mod detect;
+mod error;
+mod export;
+mod fixed;
+mod grid_normals;
+#[cfg(feature = "draco")]
+mod draco;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "obj_parser")]
+mod io;
+#[cfg(feature = "obj_parser")]
+mod obj_parser;
+mod obj_export;
+mod self_intersection;
+mod self_test;
+mod shape_descriptor;
+mod loops;
+mod skeleton;
+mod triangulate;
+mod weld;
+mod validate;
+mod vec3;
+mod voxel;
+mod xyz;
+mod skinning;
+mod source;
+mod soup;
+mod off;
+mod ply;
+mod progress;
+mod stl;
+mod sum;
+#[cfg(feature = "testvec")]
+mod testvec;
+#[cfg(feature = "zip")]
+mod three_mf;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "wasm")]
+mod wasm;
+pub use allocations::Allocations;
+pub use cancel::Cancel;
+pub use compare::{p3d_compare, Similarity};
+pub use components::ComponentHashes;
+pub use descriptor::{combine_descriptors, descriptor_diff, DescriptorDiff};
+pub use error::{Format, Offset};
+#[cfg(feature = "draco")]
+pub use draco::DracoDecoder;
+pub use progress::{Progress, Stage};
+pub use self_test::self_test;
+pub use shape_descriptor::{shape_descriptor_distance, shape_descriptor_hash};
+pub use source::MeshSource;
+#[cfg(feature = "testvec")]
+pub use testvec::{test_vectors, test_vectors_json, TestShape, TestVector};
+pub use validate::{p3d_validate, MeshReport};
+#[cfg(feature = "cache")]
+pub use cache::{p3d_process_paths, MeshCache};
+#[cfg(feature = "obj_parser")]
+pub use io::{ObjStream, Precision};
+#[cfg(all(feature = "obj_parser", feature = "std"))]
+pub use io::p3d_process_obj_reader;
+#[cfg(feature = "ffi")]
+pub use ffi::{p3d_error_name, p3d_free_string_list, p3d_last_error_message, p3d_process_c, P3DStringList};
+#[cfg(feature = "wasm")]
+pub use wasm::p3d_process_js;
 use algo_grid::{
     find_top_std,
     find_top_std_2,
     find_top_std_3,
+    find_top_std_3_scored,
     find_top_std_4,
+    find_top_std_4_scored,
+    candidates_4,
+    verify_candidates_4,
+    find_spectr,
 };
 type Vec2 = Point2<f64>;
 
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum AlgoType {
     Grid2d,
     Grid2dV2,
     Grid2dV3,
     Grid2dV3a,
+    /// Rotation invariant spectrum of every section's radial distance function, see
+    /// `algo_grid::find_spectr`. Yields up to `min(depth, grid_size / 2)` hashes.
     Spectr,
+    /// Shape of the approximate medial axis: the chain of grid cells holding the centroid of
+    /// every section. Yields a single hash.
+    Skeleton,
+    /// Point density in `par2` slabs along the principal axis, on a `par1` grid, computed
+    /// from the vertices alone without building a mesh, so point clouds (`InputFileType::Xyz`,
+    /// face-less PLY or OBJ) can be hashed. Yields up to `depth` hashes, from the coarsest
+    /// density steps to the finest (see `cloud::process_cloud`). Only the `p3d_process_*`
+    /// calls taking an input accept it, not a loaded `P3DMesh`; of the `ProcessOptions` it
+    /// follows `align`, `alignment`, `limits`, `hash_algo` and `debug_geometry`, and
+    /// `P3DResult::robustness` is the share of slabs with points.
+    CloudGrid,
+    /// Solid voxelization of the aligned mesh, `par1` x `par1` voxels per layer and `par2`
+    /// layers, hashed whole for several sub-voxel shifts of the grid and ranked by how few
+    /// voxels lie close to the surface (see `voxel::find_top_voxel`). Less sensitive than the
+    /// contour grids to how a model was tessellated; needs a closed mesh. Yields up to
+    /// `min(depth, 16)` hashes and ignores `GridSize::Auto`.
+    Voxel,
+    /// Section grids computed entirely in integer and fixed-point arithmetic, from the
+    /// quantized vertices to the alignment, slicing and grid statistics, so the hashes are
+    /// bit-identical across architectures, compilers and `wasm32`, for consensus use. Every
+    /// section adds the length of its segments to the `par1` grid cells they lie in; yields up
+    /// to `depth` hashes, from the coarsest length shares to the finest (see
+    /// `fixed::process_fixed`). Only the `p3d_process_*` calls taking an input or a
+    /// `MeshSource` accept it, not a loaded `P3DMesh`; of the `ProcessOptions` it follows the
+    /// loading options, `align`, `limits`, `hash_algo` and `debug_geometry`. The hashes differ
+    /// from those of the other algorithms.
+    FixedGrid,
+    /// A statistic of the contour points in every grid cell of every section,
+    /// `ProcessOptions::cell_statistic`, instead of the standard deviation the contour grids
+    /// rank polylines by; slices like `Grid2dV3a`. Yields up to `depth` hashes, from the
+    /// coarsest quantization of the statistics to the finest (see
+    /// `cell_stats::find_top_cell_stats`), for comparing the statistics on a collection.
+    CellStats,
+    /// The sections of `Grid2dV3a` read through the surface they cut: in every grid cell, how
+    /// steep the interpolated vertex normals are where the section crosses the mesh and how
+    /// sharply the crossed edges bend, so a smooth and a faceted model with the same
+    /// silhouettes hash apart. Yields up to `depth` hashes, from the coarsest quantization to
+    /// the finest (see `grid_normals::find_top_grid_normals`). Not accepted by
+    /// `p3d_process_spacing`.
+    GridNormals,
 }
 
-#[derive(Debug)]
+impl AlgoType {
+    // Names of `Display` and `FromStr`, the same as the serde ones
+    const NAMES: [(AlgoType, &'static str); 11] = [
+        (AlgoType::Grid2d, "grid2d"),
+        (AlgoType::Grid2dV2, "grid2d_v2"),
+        (AlgoType::Grid2dV3, "grid2d_v3"),
+        (AlgoType::Grid2dV3a, "grid2d_v3a"),
+        (AlgoType::Spectr, "spectr"),
+        (AlgoType::Skeleton, "skeleton"),
+        (AlgoType::CloudGrid, "cloud_grid"),
+        (AlgoType::Voxel, "voxel"),
+        (AlgoType::FixedGrid, "fixed_grid"),
+        (AlgoType::CellStats, "cell_stats"),
+        (AlgoType::GridNormals, "grid_normals"),
+    ];
+}
+
+/// The snake case name of the algorithm, `grid2d_v3a` for `Grid2dV3a`
+impl core::fmt::Display for AlgoType {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = AlgoType::NAMES.iter().find(|(algo, _)| algo == self).map_or("", |(_, name)| name);
+        f.write_str(name)
+    }
+}
+
+/// Parses the names `Display` gives, in any ASCII case; others fail with `P3DError::InvalidParams`
+impl core::str::FromStr for AlgoType {
+    type Err = P3DError;
+
+    fn from_str(s: &str) -> Result<Self, P3DError> {
+        AlgoType::NAMES.iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(s))
+            .map(|&(algo, _)| algo)
+            .ok_or(P3DError::InvalidParams { param: "algo", reason: "unknown algorithm name" })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
 pub enum InputFileType {
     Obj,
     Gltf,
     Glb,
+    /// Binary or ASCII STL, coincident vertices are welded
+    Stl,
+    /// ASCII or binary PLY, polygons are fan triangulated. A PLY without faces is a point
+    /// cloud, which the mesh algorithms reject with `P3DError::EmptyMesh`
+    Ply,
+    /// ASCII point cloud, `x y z` per line (further columns ignored), for `AlgoType::CloudGrid`;
+    /// the mesh algorithms reject it with `P3DError::EmptyMesh`
+    Xyz,
+    /// 3MF package (`zip` feature): the mesh objects of every build item, with the item and
+    /// component transforms applied. Coordinates stay in the unit of the model; without the
+    /// feature it fails with `P3DError::UnsupportedFileType`
+    ThreeMf,
+    /// ASCII OFF (ModelNet, Princeton Shape Benchmark), polygons are fan triangulated
+    Off,
+    /// Any of the above, told by the content (see `InputFileType::detect`) after decompression.
+    /// Fails with `P3DError::UnsupportedFileType` when the content doesn't match any
+    Auto,
+}
+
+impl InputFileType {
+    /// The type of files with extension `ext` (`obj`, `gltf`, `glb`, `stl`, `ply`, `xyz`, `3mf`
+    /// or `off`, in any ASCII case, without the dot)
+    pub fn from_extension(ext: &str) -> Option<InputFileType> {
+        match ext.to_ascii_lowercase().as_str() {
+            "obj" => Some(InputFileType::Obj),
+            "gltf" => Some(InputFileType::Gltf),
+            "glb" => Some(InputFileType::Glb),
+            "stl" => Some(InputFileType::Stl),
+            "ply" => Some(InputFileType::Ply),
+            "xyz" => Some(InputFileType::Xyz),
+            "3mf" => Some(InputFileType::ThreeMf),
+            "off" => Some(InputFileType::Off),
+            _ => None,
+        }
+    }
+
+    /// The type of `input` (uncompressed) by its content: the GLB and ZIP magic bytes, the size
+    /// of a binary STL, a JSON object for glTF, the `ply`, `solid` and `OFF` headers, then the
+    /// first statement of the text, an OBJ keyword or a number for XYZ. `None` when nothing
+    /// matches; never `Some(InputFileType::Auto)`
+    pub fn detect(input: &[u8]) -> Option<InputFileType> {
+        detect::detect(input)
+    }
+
+    // The type to load `input` (decompressed) as
+    pub(crate) fn resolve(self, input: &[u8]) -> Result<InputFileType, P3DError> {
+        match self {
+            InputFileType::Auto => InputFileType::detect(input).ok_or(P3DError::UnsupportedFileType),
+            file_type => Ok(file_type),
+        }
+    }
 }
 
+/// Why processing failed. `code` gives a stable number for every variant, `Display` a message.
 #[derive(Debug)]
 pub enum P3DError {
     InvalidObject(ObjError),
     MeshError(MeshError),
     MathError,
     UnsupportedFileType,
-    GltfError(String),
+    DegenerateMesh,
+    /// The input has no faces
+    EmptyMesh,
+    /// `indices` vertex indices that don't make whole triangles, or use more than `vertices`
+    MalformedIndices { indices: usize, vertices: usize },
+    /// Geometry the mesh can't be built from, for `reason`
+    InvalidMesh { reason: &'static str, indices: usize, vertices: usize },
+    /// Input the loader of `format` rejected, for `reason`, where it found the problem
+    ParseError { format: Format, offset: Offset, reason: &'static str },
+    /// A NaN or infinite coordinate of a vertex the faces use, by its index in the input
+    NonFiniteCoordinate { vertex: usize },
+    /// The vertices lie within `thickness` of a plane, next to the `extent` (bounding box
+    /// diagonal) of the object: there is no volume to align and the sections are segments
+    FlatObject { thickness: f64, extent: f64 },
+    /// The vertices span only a point (`rank` 0) or a line (`rank` 1), or the mass the
+    /// alignment weighs does, leaving its inertia tensor singular. `moments` are the principal
+    /// moments of inertia (of the vertices, or of the alignment), smallest first.
+    DegenerateGeometry { rank: u8, moments: [f64; 3] },
+    /// More vertices than a `u32` vertex id can address (`limit`), counted across every part
+    /// of the input
+    TooManyVertices { vertices: usize, limit: usize },
+    SelfIntersecting,
+    /// Parameter `param` (`depth`, `par1` the grid size, `par2` the number of sections, or
+    /// an option) out of range or not supported, for `reason`
+    InvalidParams { param: &'static str, reason: &'static str },
+    /// More faces than `ProcessOptions::max_faces`
+    MeshTooLarge { faces: usize, limit: usize },
+    /// Input over one of the `ProcessOptions::limits`, named by `what`
+    LimitExceeded { what: &'static str, value: usize, limit: usize },
+    /// `ProcessOptions::cancel` was cancelled before the hashes were complete
+    Cancelled,
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    #[cfg(feature = "zip")]
+    Zip(zip::result::ZipError),
 }
 
+/// Pose normalization applied after the (optional) principal inertia alignment and before slicing.
+/// Scaling is uniform and brings the bounding box diagonal to 1, centering moves the bounding
+/// box center to the origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Normalization {
+    /// Coordinates are used as they are
+    None,
+    /// Invariant to uniform scaling
+    ScaleOnly,
+    /// Invariant to uniform scaling and translation, but not to rotation when `align` is off
+    ScaleAndCenter,
+    /// Scale and center, and always rotate to the principal inertia axes (ignores `align`)
+    Full,
+    /// Invariant to uniform scaling like `ScaleOnly`, but the radius of gyration of the surface
+    /// (every triangle weighted by its area, around their centroid) becomes 1 instead of the
+    /// bounding box diagonal, so a stray vertex or thin spike barely changes the scale
+    Gyration,
+}
 
-pub fn p3d_process(input: &[u8], file_type: InputFileType, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError> {
-    p3d_process_n(input, file_type, algo, 10, par1, par2, trans)
+/// Mass distribution the principal inertia axes are computed from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum InertiaWeighting {
+    /// The mesh as a solid body of uniform density. Needs a closed mesh, independent of the
+    /// tessellation. This is what `p3d_process` has always used.
+    Volume,
+    /// The surface as a thin shell, every triangle weighted by its area. Independent of the
+    /// tessellation and also defined for open meshes.
+    Surface,
+    /// Every vertex with unit mass. Densely tessellated regions pull the axes towards them.
+    Vertices,
 }
 
-#[allow(unused_variables)]
-pub fn p3d_process_n(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError>
-{
-    let grid_size: i16 = par1;
-    let n_sections: i16 = par2;
+/// What happens to contour points that fall outside the section bounding `Rect`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum OobPolicy {
+    /// Points stay as they are: they count in the contour score, but their grid cells are
+    /// never visited. This is what `p3d_process` has always done.
+    Keep,
+    /// Points are moved to the nearest edge of the `Rect`, so they land in an edge cell.
+    Clamp,
+    /// Points are removed from the contour.
+    Drop,
+}
 
-    let (model_vertices, model_indices): (Vec<f64>, Vec<u32>) = match file_type {
-        InputFileType::Obj => {
-            let model: Obj<Vertex, u32> = load_obj(input).map_err(|e| P3DError::InvalidObject(e))?;
-            let vertices = model.vertices
-                .iter()
-                .flat_map(|v| v.position.iter())
-                .map(|v| <f64 as NumCast>::from(*v).unwrap())
-                .collect();
-            (vertices, model.indices)
-        }
-        InputFileType::Gltf => {
-            // TODO: Implement glTF loading
-            let gltf_data = Gltf::from_slice(input).map_err(|e| P3DError::GltfError(format!("glTF parsing error: {:?}", e)))?;
-            let mut positions: Vec<[f32; 3]> = Vec::new();
-            let mut indices: Vec<u32> = Vec::new();
-
-            for mesh in gltf_data.meshes() {
-                for primitive in mesh.primitives() {
-                    let reader = primitive.reader(|buffer| Some(gltf_data.blob.as_deref().unwrap_or(&buffer.source()[..])));
-                    if let Some(pos_iter) = reader.read_positions() {
-                        positions.extend(pos_iter);
-                    }
-                    if let Some(indices_iter) = reader.read_indices() {
-                        indices.extend(indices_iter.into_u32());
-                    }
-                    if !positions.is_empty() && !indices.is_empty() {
-                        break;
-                    }
-                }
-                if !positions.is_empty() && !indices.is_empty() {
-                    break;
-                }
-            }
+/// Resolution of the grid the section contours are ranked on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum GridSize {
+    /// `par1` as passed to the process call
+    Fixed,
+    /// Picked from the sections (see `auto_grid_size`) and reported in `P3DResult::grid_size`,
+    /// so it can be passed as `par1` to reproduce the result
+    Auto,
+}
 
-            if positions.is_empty() || indices.is_empty() {
-                return Err(P3DError::GltfError("No valid geometry (vertices/indices) found in glTF file".to_string()));
-            }
+/// Axis the sections are taken along, after the inertia transform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum SliceAxis {
+    X,
+    Y,
+    /// The principal axis of the largest moment, as `p3d_process` has always used
+    Z,
+    /// Along all three, every hash combining the hashes of the same rank along X, Y and Z, so
+    /// objects must agree on all three to match. Gives as many hashes as the axis with the
+    /// fewest.
+    All,
+}
 
-            let model_vertices_f64: Vec<f64> = positions.into_iter().flat_map(|pos| [pos[0] as f64, pos[1] as f64, pos[2] as f64]).collect();
-            (model_vertices_f64, indices)
-        }
-        InputFileType::Glb => {
-            // TODO: Implement GLB loading
-            let gltf_data = Gltf::from_slice(input).map_err(|e| P3DError::GltfError(format!("GLB parsing error: {:?}", e)))?;
-            let mut positions: Vec<[f32; 3]> = Vec::new();
-            let mut indices: Vec<u32> = Vec::new();
+/// Where the section planes go between the bottom and the top of the mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum SectionPlacement {
+    /// Evenly spaced, as `p3d_process` has always placed them
+    Uniform,
+    /// Denser where the cross-section area changes fastest, so thin features and steps get
+    /// sections that even spacing may miss (see `adaptive_heights`). Computed from the mesh
+    /// in a fixed order, so every node places them the same.
+    Adaptive,
+}
 
-            for mesh in gltf_data.meshes() {
-                for primitive in mesh.primitives() {
-                    let reader = primitive.reader(|buffer| Some(gltf_data.blob.as_deref().unwrap_or(&buffer.source()[..])));
-                    if let Some(pos_iter) = reader.read_positions() {
-                        positions.extend(pos_iter);
-                    }
-                    if let Some(indices_iter) = reader.read_indices() {
-                        indices.extend(indices_iter.into_u32());
-                    }
-                    if !positions.is_empty() && !indices.is_empty() {
-                        break;
-                    }
-                }
-                if !positions.is_empty() && !indices.is_empty() {
-                    break;
-                }
-            }
+/// Statistic of the contour points in a grid cell `AlgoType::CellStats` hashes. The moments
+/// are of the distances of the points from the center of the cell, as a fraction of half its
+/// diagonal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum CellStatistic {
+    /// Points in the cell, relative to the mean of the occupied cells of the section
+    Count,
+    /// Mean distance
+    Mean,
+    /// Standard deviation of the distances
+    StdDev,
+    /// Skewness of the distances, mapped from [-4, 4] to [0, 1]
+    Skewness,
+    /// Shannon entropy of the points over 4 x 4 sub-cells, as a fraction of the largest
+    Entropy,
+}
 
-            if positions.is_empty() || indices.is_empty() {
-                return Err(P3DError::GltfError("No valid geometry (vertices/indices) found in GLB file".to_string()));
-            }
+/// How the principal inertia alignment treats an object and its mirror image. The principal
+/// axes only fix directions; which way each points, and so whether the alignment mirrors the
+/// object, is otherwise up to the eigen solver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Reflection {
+    /// Axis signs as the eigen solver returns them (or as `orient_axes` sets x and y), as
+    /// `p3d_process` has always done
+    Unspecified,
+    /// The alignment is always a proper rotation and x and y point to where the third moment
+    /// of the vertices along them is positive, so an asymmetric object and its mirror image
+    /// hash differently
+    Distinguish,
+    /// Each axis points to where the third moment along it is positive, mirroring where
+    /// needed, so an object and its mirror image are brought to the same pose and hash alike.
+    /// That takes an alignment that turns with the object: `InertiaWeighting::Surface` and
+    /// `Vertices` do, the products of inertia of `Volume` don't, so with it the pose of a
+    /// mirror image still depends on the input orientation.
+    Ignore,
+}
+
+/// Animation frame a skinned glTF/GLB mesh is posed in before slicing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationPose {
+    /// Index into the `animations` of the file
+    pub animation: usize,
+    /// Time in seconds, clamped to the keyframes of every channel
+    pub time: f32,
+}
+
+/// Picks what of a glTF/GLB file is hashed, by default every mesh of the scene.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum MeshSelector {
+    /// A single mesh by its position in `meshes`, every node instancing it
+    Index(usize),
+    /// A single mesh by its name, every node instancing it
+    Name(String),
+    /// The mesh of the node with this name (e.g. a Blender object), only that instance
+    Node(String),
+    /// Every mesh of the scene merged, as without a selector
+    All,
+}
+
+// Smallest distance between two sections `p3d_process` accepts by default.
+pub const DEFAULT_MIN_STEP: f64 = 1e-9;
+
+// Meshes thinner than this fraction of their bounding box diagonal count as flat
+const FLAT_EPSILON: f64 = 1e-9;
+
+// glTF extension of Draco compressed primitives
+const DRACO_EXTENSION: &str = "KHR_draco_mesh_compression";
+
+/// Bounds of the grid size `GridSize::Auto` picks
+pub const AUTO_GRID_MIN: i16 = 4;
+pub const AUTO_GRID_MAX: i16 = 64;
+
+/// Largest depth (number of hashes) accepted
+pub const MAX_DEPTH: usize = 1 << 16;
+
+/// Default `ProcessOptions::max_faces`
+pub const DEFAULT_MAX_FACES: usize = 2_000_000;
 
-            let model_vertices_f64: Vec<f64> = positions.into_iter().flat_map(|pos| [pos[0] as f64, pos[1] as f64, pos[2] as f64]).collect();
-            (model_vertices_f64, indices)
+/// Bounds on what an untrusted input may cost, checked while it is loaded and before the mesh
+/// is built or sliced, so a crafted file fails with `P3DError::LimitExceeded` instead of
+/// exhausting memory or hanging. Faces are bounded by `ProcessOptions::max_faces`. The defaults
+/// leave room for any reasonable model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct Limits {
+    /// Bytes of the input file, and of all its external glTF buffers together
+    pub max_input_bytes: usize,
+    /// Vertices of the loaded geometry, counting every glTF instance
+    pub max_vertices: usize,
+    /// Sections sliced (`par2`, or the number `p3d_process_spacing` works out)
+    pub max_sections: usize,
+    /// Estimated bytes held by the geometry and the mesh built from it, and with
+    /// `ProcessOptions::allocations` by all the buffers it counts at once
+    pub max_allocation: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_input_bytes: 256 << 20,
+            max_vertices: 4_000_000,
+            max_sections: 4096,
+            max_allocation: 1 << 30,
         }
-    };
+    }
+}
 
-    let mut mesh = MeshBuilder::new()
-        .with_indices(model_indices)
-        .with_positions(model_vertices)
-        .build()
-        .map_err(|e| P3DError::MeshError(e))?;
+/// Clean-up of the loaded geometry before the mesh is built, and so before the inertia
+/// transform, for scans and exports that trip up `MeshBuilder` or skew the alignment. Runs
+/// after welding (`ProcessOptions::weld_epsilon`), which joins duplicate vertices. Every step
+/// changes the hashes of the meshes it repairs, so off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct Repair {
+    /// Drop zero-area triangles: repeated or collinear corners, to within a 1e-12 fraction of
+    /// the squared bounding box diagonal
+    pub drop_degenerate: bool,
+    /// Keep only the connected part (triangles sharing vertices) with the largest surface
+    /// area, dropping floating debris of scans that would shift the center of mass. Its
+    /// vertices stay unless `prune_unused` is set too. See `p3d_process_components` for hashing
+    /// every part.
+    pub largest_component: bool,
+    /// Remove vertices no triangle uses, which otherwise count towards vertex weighted inertia
+    pub prune_unused: bool,
+}
 
-    let mut triangles: Array3<f64> = Array3::zeros((mesh.no_faces(), 3, 3));
+impl Repair {
+    /// Every step on
+    pub fn all() -> Self {
+        Self { drop_degenerate: true, largest_component: true, prune_unused: true }
+    }
+}
 
-    for (i, fid) in mesh.face_iter().enumerate() {
-        let vs = mesh.face_vertices(fid);
-        let v1 = mesh.vertex_position(vs.0);
-        let v2 = mesh.vertex_position(vs.1);
-        let v3 = mesh.vertex_position(vs.2);
-        triangles.slice_mut(s![i, .., ..])
-            .assign(
-                &arr2(&[
-                    [v1.x as f64, v1.y as f64, v1.z as f64],
-                    [v2.x as f64, v2.y as f64, v2.z as f64],
-                    [v3.x as f64, v3.y as f64, v3.z as f64],
-                ]
-                ));
+/// Target of the edge-collapse decimation of `ProcessOptions::decimation`, which thins out
+/// dense scans before the inertia transform. Collapses go cheapest first by quadric error (the
+/// mean squared distance of the merged vertex from the planes of its faces) and leave border
+/// and non-manifold edges alone, so open meshes keep their outline. Only plain float arithmetic
+/// in a fixed order, ties broken by vertex indices: every platform decimates to the same mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Decimation {
+    /// Collapse edges until at most this many triangles are left, or no edge can go
+    Faces(usize),
+    /// Collapse edges while the error stays within this fraction of the bounding box diagonal
+    MaxError(f64),
+}
+
+fn check_cancel(opts: &ProcessOptions) -> Result<(), P3DError> {
+    match &opts.cancel {
+        Some(cancel) if cancel.is_cancelled() => Err(P3DError::Cancelled),
+        _ => Ok(()),
     }
+}
 
-    let pit = algo_grid::principal_inertia_transform(triangles);
+fn report_progress(opts: &ProcessOptions, stage: Stage, done: usize, total: usize) {
+    if let Some(progress) = &opts.progress {
+        progress.report(stage, done, total);
+    }
+}
 
-    let a: Matrix3<f64> = Matrix3::new(
-        pit[[0, 0]], pit[[0, 1]], pit[[0, 2]],
-        pit[[1, 0]], pit[[1, 1]], pit[[1, 2]],
-        pit[[2, 0]], pit[[2, 1]], pit[[2, 2]],
-    );
+// `Stage::Slice` progress once a section is cut, `cut` counting them across threads
+fn report_section(opts: &ProcessOptions, cut: &AtomicUsize, total: usize) {
+    if let Some(progress) = &opts.progress {
+        progress.report(Stage::Slice, cut.fetch_add(1, Ordering::Relaxed) + 1, total);
+    }
+}
 
-    let b = a.invert().ok_or(P3DError::MathError)?;
+fn check_limit(what: &'static str, value: usize, limit: usize) -> Result<(), P3DError> {
+    if value > limit {
+        return Err(P3DError::LimitExceeded { what, value, limit });
+    }
+    Ok(())
+}
 
-    let tr: Matrix4<f64> = Matrix4::new(
-        b.x[0], b.x[1], b.x[2], 0.0,
-        b.y[0], b.y[1], b.y[2], 0.0,
-        b.z[0], b.z[1], b.z[2], 0.0,
-        0.0, 0.0, 0.0, 1.0,
-    );
+// Most vertices a mesh can have: vertex ids are `u32` from the loaders to `tri_mesh`, and every
+// id stays below the count, so `u32::MAX` itself is free to mark a missing vertex
+const MAX_VERTICES: usize = u32::MAX as usize;
 
-    let shift = Vector3::new(pit[[0, 3]], pit[[1, 3]], pit[[2, 3]]);
+// `TooManyVertices` when `count` vertices can't all have an id. Loaders check their running
+// count before narrowing ids, so no index wraps around silently.
+fn check_vertex_count(count: usize) -> Result<(), P3DError> {
+    if count > MAX_VERTICES {
+        return Err(P3DError::TooManyVertices { vertices: count, limit: MAX_VERTICES });
+    }
+    Ok(())
+}
 
-    mesh.translate(shift);
-    mesh.apply_transformation(tr);
+// The id of the vertex at `index`, for loaders numbering vertices as they meet them
+fn vertex_id(index: usize) -> Result<u32, P3DError> {
+    check_vertex_count(index.saturating_add(1))?;
+    Ok(index as u32)
+}
 
-    let k = 45.0 / 256.0;
-    if let Some(rot) = trans {
-        let axis_normalized = Vector3::new(
-            rot[0] as f64 * k,
-            rot[1] as f64 * k,
-            rot[2] as f64 * k,
-        ).normalize();
-        mesh.apply_transformation(
-            Mat4::from_axis_angle(
-                axis_normalized,
-                Deg(rot[3] as f64 * k * 360.0 / 256.0),
-            )
-        );
+// Rough bytes of a geometry and the half-edge mesh built from it: the loaded arrays, then
+// every vertex with its position and one half-edge, every face with three half-edges
+fn mesh_allocation(vertices: usize, faces: usize) -> usize {
+    geometry_bytes(vertices, faces).saturating_add(mesh_bytes(vertices, faces))
+}
+
+fn geometry_bytes(vertices: usize, faces: usize) -> usize {
+    vertices.saturating_mul(24).saturating_add(faces.saturating_mul(12))
+}
+
+fn mesh_bytes(vertices: usize, faces: usize) -> usize {
+    vertices.saturating_mul(48).saturating_add(faces.saturating_mul(16 + 3 * 32))
+}
+
+// Bytes of the triangle array the alignment integrates over
+fn triangle_bytes(faces: usize) -> usize {
+    faces.saturating_mul(9 * 8)
+}
+
+fn section_bytes(sections: &Sections) -> usize {
+    let points: usize = sections.centers.iter().map(Vec::len).sum();
+    let tags: usize = sections.materials.iter().map(Vec::len).sum();
+    let loops: usize = sections.loops.iter().flatten().map(Vec::len).sum();
+    (points + loops).saturating_mul(16).saturating_add(tags.saturating_mul(4)).saturating_add(sections.heights.len() * 8)
+}
+
+// Counts `bytes` of `what` with `ProcessOptions::allocations` while the charge lives
+fn charge(opts: &ProcessOptions, what: &'static str, bytes: usize) -> Result<Option<Charge>, P3DError> {
+    opts.allocations.as_ref().map(|a| a.charge(what, bytes, opts.limits.max_allocation)).transpose()
+}
+
+/// Default `ProcessOptions::intersect_tolerance`
+pub const DEFAULT_INTERSECT_TOLERANCE: f64 = 0.01;
+
+/// Suggested `ProcessOptions::weld_epsilon`
+pub const DEFAULT_WELD_EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct ProcessOptions {
+    /// Floor for the distance between neighbouring sections. When the Z extent is too small
+    /// to fit `n_sections` sections at least `min_step` apart, fewer sections are taken
+    /// (`floor(extent / min_step) - 1`), and if not even one fits `P3DError::DegenerateMesh`
+    /// is returned. Keeps `step` and the `intersect_2` tolerance away from subnormals.
+    pub min_step: f64,
+    /// Tag every contour point with the material of the face it was cut from
+    /// (`P3DResult::materials`). Only glTF inputs carry materials, other points get `NO_MATERIAL`.
+    pub tag_materials: bool,
+    /// Translate and rotate the mesh to its principal inertia axes before slicing.
+    pub align: bool,
+    /// Weighting of the inertia tensor used by `align`.
+    pub inertia: InertiaWeighting,
+    /// Axis-aligned box (min, max corners, input coordinates) restricting the faces the
+    /// alignment is computed from to those centered inside it; the whole mesh is still sliced.
+    /// With `InertiaWeighting::Volume` the selected faces should enclose a volume.
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::region"))]
+    pub inertia_region: Option<(Vector3<f64>, Vector3<f64>)>,
+    /// Scaling (and centering) applied right after the alignment, before the optional rotation:
+    /// the bounding box diagonal (or radius of gyration) becomes 1, so e.g. the same part
+    /// exported in millimeters and in meters hashes the same. A zero-size bounding box (or
+    /// surface) fails with `P3DError::DegenerateMesh`.
+    /// `Normalization::None` by default.
+    pub normalization: Normalization,
+    /// Only load the selected glTF/GLB mesh or node instead of the whole scene.
+    pub mesh_selector: Option<MeshSelector>,
+    /// Sample this animation and apply skinning (`JOINTS_0`/`WEIGHTS_0`) instead of using
+    /// the bind pose. Meshes without a skin are not affected.
+    pub pose: Option<AnimationPose>,
+    /// Douglas-Peucker tolerance applied to every section contour before ranking. Drops
+    /// nearly collinear points to save time in `find_top_std_*`; changes the hashes, so off
+    /// (`None`) by default.
+    pub simplify_tolerance: Option<f64>,
+    /// Handling of contour points outside the bounding `Rect`, `OobPolicy::Keep` by default.
+    /// Their number is reported in `P3DResult::out_of_bounds` whatever the policy.
+    pub out_of_bounds: OobPolicy,
+    /// Only keep contour points in grid cells hit by at least this many different sections.
+    /// Drops cells populated by a single anomalous slice; 1 (the default) keeps everything.
+    pub min_section_support: usize,
+    /// Fail with `P3DError::SelfIntersecting` when faces of the mesh cut through each other.
+    /// A heuristic: faces sharing a vertex and coplanar overlaps are not checked, and very
+    /// large meshes may pass unchecked.
+    pub check_self_intersection: bool,
+    /// Spacing of a grid (anchored at the section `Rect` corner) contour points are rounded to
+    /// before ranking. A spacing dividing the cell size puts points within `boundary_snap / 2`
+    /// of a cell boundary exactly on it, so tiny float differences can't flip their cell.
+    /// Off (0) by default.
+    pub boundary_snap: f64,
+    /// Index of a glTF camera to slice along: the mesh is rotated so the camera looks down +Z
+    /// and is not PIT aligned. `None` (default) slices along the principal axis.
+    pub view_camera: Option<usize>,
+    /// Rank `Grid2dV3`/`Grid2dV3a` candidates by fixed-point total scores and report them in
+    /// `P3DResult::scores`. A total score (summed mean squared distances between the contours
+    /// and their grid lines) becomes `round(score / cell_area * SCORE_SCALE)` as a `u32`,
+    /// `cell_area` being the area of one grid cell, so the output is the same on every machine
+    /// whenever the float scores agree to within a rounding step. Changes the hashes only when
+    /// two candidates fall within a step of each other.
+    pub fixed_point_scores: bool,
+    /// Report where every `Grid2dV3`/`Grid2dV3a` hash comes from in `P3DResult::hash_details`:
+    /// its rank, total score and the grid polyline it took in every section, for applying
+    /// thresholds or tie-breaking of one's own. Doesn't change the hashes; off by default.
+    /// Other algorithms, and `SliceAxis::All`, report none.
+    pub hash_details: bool,
+    /// Vertices closer than this fraction of the bounding box diagonal are merged before the
+    /// mesh is built, joining faces split at UV or normal seams (`DEFAULT_WELD_EPSILON` suits
+    /// most models). Changes the hashes of models with seams, so off (`None`) by default.
+    pub weld_epsilon: Option<f64>,
+    /// Trace every closed loop of a section separately (separate parts, holes) instead of
+    /// ordering all section points into a single contour, and report them in
+    /// `P3DResult::section_loops`. Loops around holes (cavities, handles) run clockwise, the
+    /// others counter-clockwise. The section contour, which the grid statistics are taken
+    /// from, becomes all loops one after the other, which changes the hashes, so off by default.
+    pub trace_loops: bool,
+    /// Vertex snapping distance of the `Grid2dV3a` and `Skeleton` sections, as a fraction of
+    /// the distance between sections. A crossed edge with an end closer than this to the section
+    /// plane contributes that vertex instead of the interpolated crossing point, so a larger
+    /// value favours the mesh vertices over exact crossings and catches faces nearly parallel to
+    /// the plane. `DEFAULT_INTERSECT_TOLERANCE` (0.01) by default.
+    pub intersect_tolerance: f64,
+    /// Inputs with more triangles than this are rejected with `P3DError::MeshTooLarge` before
+    /// the mesh is built, bounding the time and memory a crafted input can cost.
+    /// `DEFAULT_MAX_FACES` by default.
+    pub max_faces: usize,
+    /// Make the output independent of the vertex and face order of the input: the alignment
+    /// takes the triangles in a fixed order, section points are ordered into contours starting
+    /// from the smallest one (by x, then y) and the hashes come sorted instead of best first.
+    /// Changes the hashes and their order, so off by default.
+    pub canonical_order: bool,
+    /// Transform applied instead of the principal inertia alignment, for inputs already
+    /// oriented upstream. Takes precedence over `align` (and the alignment of
+    /// `Normalization::Full`); `None` by default.
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::option_matrix4"))]
+    pub alignment: Option<Matrix4<f64>>,
+    /// Resolve the sign ambiguity of the principal axes: after the alignment x and y are
+    /// reversed where needed so the third moment of the vertices along them is positive, and
+    /// z with them so the result stays a rotation. Axes the mesh is symmetric along stay
+    /// ambiguous. Changes the hashes whenever an axis gets reversed, so off by default.
+    pub orient_axes: bool,
+    /// Grid resolution, `GridSize::Fixed` (`par1`) by default. `par1` must still be valid
+    /// with `GridSize::Auto`.
+    pub grid_size: GridSize,
+    /// Report the vertices of the sliced mesh (`P3DResult::vertices`) and the number of contour
+    /// points in every grid cell of every section (`P3DResult::occupancy`), for visualizing
+    /// what was hashed. Doesn't change the hashes; off by default as both can be large.
+    pub debug_geometry: bool,
+    /// Resource bounds for untrusted input, `Limits::default()` by default.
+    pub limits: Limits,
+    /// Checked between sections while slicing and continually while ranking; once cancelled
+    /// the call fails with `P3DError::Cancelled` instead of running to completion.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancel: Option<Cancel>,
+    /// Told about the stages of the call as they go, for progress feedback on large inputs;
+    /// `None` by default. Doesn't change the hashes.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub progress: Option<Progress>,
+    /// Counts the large buffers of the call against `Limits::max_allocation` and keeps their
+    /// peak, reported in `P3DResult::peak_allocation`; `None` (no accounting) by default.
+    /// Doesn't change the hashes.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub allocations: Option<Allocations>,
+    /// Digest of the output hashes, `HashAlgo::Sha256` by default. Any other gives different
+    /// hashes for the same input.
+    pub hash_algo: HashAlgo,
+    /// Geometry clean-up before the mesh is built, none by default.
+    pub repair: Repair,
+    /// Decimation after the repairs, before the inertia transform, for scans denser than the
+    /// hashes need. Leaves only the vertices faces use. Changes the hashes, so off (`None`) by
+    /// default.
+    pub decimation: Option<Decimation>,
+    /// Axis of the sections, `SliceAxis::Z` by default. Along X or Y the sections, rect and
+    /// occupancy of a `P3DResult` are in the frame that axis is turned to Z in (coordinates
+    /// cycled), with `SliceAxis::All` they are those along Z. Any other axis changes the hashes.
+    pub slice_axis: SliceAxis,
+    /// Heights of the sections, `SectionPlacement::Uniform` by default. Adaptive placement
+    /// changes the hashes.
+    pub section_placement: SectionPlacement,
+    /// Handedness of the principal inertia alignment, `Reflection::Unspecified` by default.
+    /// Any other value orients every axis (superseding `orient_axes`) and changes the hashes
+    /// whenever an axis gets reversed. Axes the mesh is symmetric along stay ambiguous.
+    pub reflection: Reflection,
+    /// Statistic `AlgoType::CellStats` hashes, `CellStatistic::StdDev` by default. Ignored by
+    /// the other algorithms.
+    pub cell_statistic: CellStatistic,
+    /// Decoder of `KHR_draco_mesh_compression` glTF primitives. Without one, files whose
+    /// meshes are Draco compressed fail to load; `None` by default.
+    #[cfg(feature = "draco")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub draco: Option<DracoDecoder>,
+}
+
+impl Default for ProcessOptions {
+    fn default() -> Self {
+        Self {
+            min_step: DEFAULT_MIN_STEP,
+            tag_materials: false,
+            align: true,
+            inertia: InertiaWeighting::Volume,
+            inertia_region: None,
+            normalization: Normalization::None,
+            mesh_selector: None,
+            pose: None,
+            simplify_tolerance: None,
+            out_of_bounds: OobPolicy::Keep,
+            min_section_support: 1,
+            check_self_intersection: false,
+            boundary_snap: 0.0,
+            view_camera: None,
+            fixed_point_scores: false,
+            hash_details: false,
+            weld_epsilon: None,
+            trace_loops: false,
+            intersect_tolerance: DEFAULT_INTERSECT_TOLERANCE,
+            max_faces: DEFAULT_MAX_FACES,
+            canonical_order: false,
+            alignment: None,
+            orient_axes: false,
+            grid_size: GridSize::Fixed,
+            debug_geometry: false,
+            limits: Limits::default(),
+            cancel: None,
+            progress: None,
+            allocations: None,
+            hash_algo: HashAlgo::Sha256,
+            repair: Repair::default(),
+            decimation: None,
+            slice_axis: SliceAxis::Z,
+            section_placement: SectionPlacement::Uniform,
+            reflection: Reflection::Unspecified,
+            cell_statistic: CellStatistic::StdDev,
+            #[cfg(feature = "draco")]
+            draco: None,
+        }
     }
-    let (v_min, v_max) = mesh.extreme_coordinates();
+}
 
-    let mut centers: Vec<Vec<Vec2>> = Vec::with_capacity(depth);
-    let step = (v_max.z - v_min.z) / (1.0f64 + n_sections as f64);
-    for n in 0..n_sections {
-        let z_sect = v_min.z + (n as f64 + 1.0f64) * step;
-        let sect = if let AlgoType::Grid2dV3a = algo {
-            intersect_2(&mesh, z_sect, step * 0.01)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct P3DResult {
+    pub hashes: Vec<String>,
+    // Contour of every non-empty section and the Z height it was cut at
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::contours"))]
+    pub centers: Vec<Vec<Vec2>>,
+    pub section_heights: Vec<f64>,
+    // Bounds of the grid the contours were ranked on: min x, max x, min y, max y
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rect: [f64; 4],
+    // Material tag of every point in `centers`, empty unless `ProcessOptions::tag_materials` is set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub materials: Vec<Vec<u32>>,
+    // Translation applied to the mesh, followed by `transform` (PIT alignment and the optional rotation)
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::vector3"))]
+    pub shift: Vector3<f64>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::matrix4"))]
+    pub transform: Matrix4<f64>,
+    // Bounding box of the transformed mesh
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::vector3"))]
+    pub v_min: Vector3<f64>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_impl::vector3"))]
+    pub v_max: Vector3<f64>,
+    // Number of contour points found outside the section `Rect` (see `ProcessOptions::out_of_bounds`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub out_of_bounds: usize,
+    // Heuristic confidence in the descriptor, in [0, 1] (see `robustness`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub robustness: f32,
+    // Fixed-point total score of every hash, empty unless `ProcessOptions::fixed_point_scores` is set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub scores: Vec<u32>,
+    // Loops of every section in `centers`, largest first, holes clockwise, empty unless `ProcessOptions::trace_loops` is set
+    #[cfg_attr(feature = "serde", serde(default, with = "serde_impl::section_loops"))]
+    pub section_loops: Vec<Vec<Vec<Vec2>>>,
+    // Grid size the contours were ranked with: `par1`, or the one `GridSize::Auto` picked
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub grid_size: i16,
+    // Vertices of the mesh as sliced (aligned, normalized and rotated), empty unless
+    // `ProcessOptions::debug_geometry` is set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub vertices: Vec<[f64; 3]>,
+    // Contour points per grid cell of every section in `centers`, `grid_size` rows (y) of
+    // `grid_size` cells (x); empty unless `ProcessOptions::debug_geometry` is set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub occupancy: Vec<Vec<u32>>,
+    // Origin of every hash, in the order of `hashes`; empty unless `ProcessOptions::hash_details` is set
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hash_details: Vec<HashDetail>,
+    // Most bytes `ProcessOptions::allocations` counted at once so far, 0 without it
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub peak_allocation: usize,
+}
+
+/// Where a hash of the `Grid2dV3` family comes from, see `ProcessOptions::hash_details`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HashDetail {
+    /// Position of the hash in `P3DResult::hashes`, 0 for the best
+    pub rank: usize,
+    /// Sum of the section scores, lower is a closer fit; the hashes are ranked by it
+    pub score: f64,
+    /// The polyline taken in every section that has one, bottom to top
+    pub sections: Vec<SectionDetail>,
+}
+
+/// The grid polyline a hash took in one section
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionDetail {
+    /// Index of the section in `P3DResult::centers`
+    pub section: usize,
+    /// Mean squared distance between the contour and the polyline
+    pub score: f64,
+    /// Grid cells the polyline goes through, in order, as `[column, row]` of the
+    /// `grid_size` x `grid_size` grid over `P3DResult::rect`
+    pub cells: Vec<[i32; 2]>,
+}
+
+
+fn select_meshes<'a>(gltf_data: &'a Gltf, selector: Option<&MeshSelector>) -> Result<Vec<gltf::Mesh<'a>>, P3DError> {
+    let selector = match selector {
+        Some(MeshSelector::All) | None => return Ok(gltf_data.meshes().collect()),
+        Some(selector) => selector,
+    };
+    let node_mesh = |name: &str| gltf_data.nodes().find(|node| node.name() == Some(name)).and_then(|node| node.mesh());
+    let selected: Vec<gltf::Mesh> = gltf_data.meshes()
+        .filter(|mesh| match selector {
+            MeshSelector::Index(i) => mesh.index() == *i,
+            MeshSelector::Name(name) => mesh.name() == Some(name.as_str()),
+            MeshSelector::Node(name) => node_mesh(name).map(|m| m.index()) == Some(mesh.index()),
+            MeshSelector::All => true,
+        })
+        .take(1)
+        .collect();
+    if selected.is_empty() {
+        return Err(gltf_error(match selector {
+            MeshSelector::Node(_) => "no node with a mesh matches the selector",
+            _ => "no mesh matches the selector",
+        }));
+    }
+    Ok(selected)
+}
+
+// `mesh_instances` of the selected meshes, only the instance of the node for `MeshSelector::Node`
+fn selected_instances<'a>(gltf_data: &'a Gltf, selector: Option<&MeshSelector>) -> Result<Vec<(gltf::Mesh<'a>, Option<gltf::Node<'a>>)>, P3DError> {
+    let instances = mesh_instances(gltf_data, select_meshes(gltf_data, selector)?);
+    let name = match selector {
+        Some(MeshSelector::Node(name)) => name,
+        _ => return Ok(instances),
+    };
+    let node_instances: Vec<_> = instances.into_iter()
+        .filter(|(_, node)| node.as_ref().and_then(|node| node.name()) == Some(name.as_str()))
+        .collect();
+    if node_instances.is_empty() {
+        return Err(gltf_error("the selected node is not part of the scene"));
+    }
+    Ok(node_instances)
+}
+
+// Every selected mesh with each node of the scene (the default one, else the first) instancing
+// it, in node order. Meshes no scene node instances are taken once, untransformed (`None`).
+fn mesh_instances<'a>(gltf_data: &'a Gltf, meshes: Vec<gltf::Mesh<'a>>) -> Vec<(gltf::Mesh<'a>, Option<gltf::Node<'a>>)> {
+    let scene_nodes: Vec<gltf::Node> = match gltf_data.default_scene().or_else(|| gltf_data.scenes().next()) {
+        Some(scene) => {
+            let mut visited = vec![false; gltf_data.nodes().count()];
+            let mut stack: Vec<gltf::Node> = scene.nodes().collect();
+            let mut nodes = Vec::new();
+            while let Some(node) = stack.pop() {
+                if !core::mem::replace(&mut visited[node.index()], true) {
+                    stack.extend(node.children());
+                    nodes.push(node);
+                }
+            }
+            nodes.sort_by_key(|node| node.index());
+            nodes
+        }
+        None => gltf_data.nodes().collect(),
+    };
+
+    let mut instances = Vec::new();
+    for mesh in meshes {
+        let nodes: Vec<gltf::Node> = scene_nodes.iter()
+            .filter(|node| node.mesh().map(|m| m.index()) == Some(mesh.index()))
+            .cloned()
+            .collect();
+        if nodes.is_empty() {
+            instances.push((mesh, None));
         } else {
-            intersect(&mesh, z_sect)
-        };
-        let cntr = get_contour(sect);
-        if cntr.len() > 0 {
-            centers.push(cntr);
+            instances.extend(nodes.into_iter().map(|node| (mesh.clone(), Some(node))));
         }
     }
-    let rect = Rect::new(v_min.x, v_max.x, v_min.y, v_max.y);
+    instances
+}
 
-    let res = match algo {
-        AlgoType::Grid2dV2 => find_top_std_2(&centers, depth as usize, n_sections as usize, grid_size as usize, rect),
-        AlgoType::Grid2dV3 => find_top_std_3(&centers, depth as usize, n_sections as usize, grid_size as usize, rect),
-        AlgoType::Grid2dV3a => find_top_std_4(&centers, depth as usize, n_sections as usize, grid_size as usize, rect),
-        _ => find_top_std(&centers, depth as usize, grid_size, rect),
+// Indices of a triangle strip or fan as a list of triangles, with the winding of the first one.
+// Degenerate triangles (used to join strips) are dropped.
+fn triangle_list(mode: Mode, vertices: Vec<u32>) -> Vec<u32> {
+    let n = vertices.len();
+    let triangles: Vec<[u32; 3]> = match mode {
+        Mode::TriangleStrip => (0..n.saturating_sub(2))
+            .map(|i| if i % 2 == 0 {
+                [vertices[i], vertices[i + 1], vertices[i + 2]]
+            } else {
+                [vertices[i], vertices[i + 2], vertices[i + 1]]
+            })
+            .collect(),
+        Mode::TriangleFan => (1..n.saturating_sub(1))
+            .map(|i| [vertices[i], vertices[i + 1], vertices[0]])
+            .collect(),
+        _ => return vertices,
     };
+    triangles.into_iter()
+        .filter(|t| t[0] != t[1] && t[1] != t[2] && t[0] != t[2])
+        .flat_map(|t| t.to_vec())
+        .collect()
+}
 
-    Ok(res)
+// The POSITION of a primitive as floats, None without one or its data. Besides FLOAT, the
+// integer types of `KHR_mesh_quantization` are dequantized as the spec says, normalized or
+// not; `read_positions` would reinterpret their bytes as `[f32; 3]` and yield garbage.
+fn primitive_positions<'a, 's, F>(primitive: &Primitive<'a>, get_buffer_data: F) -> Result<Option<Vec<[f32; 3]>>, P3DError>
+where
+    F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+{
+    let accessor = match primitive.get(&Semantic::Positions) {
+        Some(accessor) => accessor,
+        None => return Ok(None),
+    };
+    if accessor.dimensions() != Dimensions::Vec3 {
+        return Err(gltf_error("POSITION accessor is not VEC3"));
+    }
+    fn read<'a, 's, T: Item + Copy, F>(accessor: Accessor<'a>, get_buffer_data: F, convert: impl Fn(T) -> f32) -> Option<Vec<[f32; 3]>>
+    where
+        F: Clone + Fn(gltf::Buffer<'a>) -> Option<&'s [u8]>,
+    {
+        AccessorIter::<[T; 3]>::new(accessor, get_buffer_data).map(|iter| iter.map(|p| p.map(&convert)).collect())
+    }
+    let normalized = accessor.normalized();
+    // Normalized signed values reach -1 one step early, so they are clamped there
+    let unit = |c: f32, max: f32, signed: bool| match (normalized, signed) {
+        (false, _) => c,
+        (true, false) => c / max,
+        (true, true) => (c / max).max(-1.0),
+    };
+    Ok(match accessor.data_type() {
+        DataType::F32 => read(accessor, get_buffer_data, |c: f32| c),
+        DataType::I8 => read(accessor, get_buffer_data, |c: i8| unit(c as f32, i8::MAX as f32, true)),
+        DataType::U8 => read(accessor, get_buffer_data, |c: u8| unit(c as f32, u8::MAX as f32, false)),
+        DataType::I16 => read(accessor, get_buffer_data, |c: i16| unit(c as f32, i16::MAX as f32, true)),
+        DataType::U16 => read(accessor, get_buffer_data, |c: u16| unit(c as f32, u16::MAX as f32, false)),
+        DataType::U32 => read(accessor, get_buffer_data, |c: u32| unit(c as f32, u32::MAX as f32, false)),
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Rotation applied to the aligned mesh before slicing
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum Transform {
+    /// Axis and angle quantized to 256 steps, the `trans` of `p3d_process`
+    Quantized([u8; 4]),
+    /// Axis and angle in the finer 8 bytes of `TransEncoding::V2`
+    QuantizedV2([u8; 8]),
+    /// Rotation by `angle_deg` degrees around `axis` (any non-zero length)
+    AxisAngle { axis: [f64; 3], angle_deg: f64 },
+    /// Rotation by a quaternion of any non-zero length
+    Quaternion(#[cfg_attr(feature = "serde", serde(with = "serde_impl::quaternion"))] Quaternion<f64>),
+    /// Rotation matrix, which must be orthonormal with determinant 1 up to rounding
+    Matrix(#[cfg_attr(feature = "serde", serde(with = "serde_impl::matrix3"))] Matrix3<f64>),
+}
 
-    #[test]
-    fn test_load_and_process_glb() {
-        let glb_bytes = include_bytes!("../../test-ht.glb");
-        let result = p3d_process(
-            glb_bytes,
-            InputFileType::Glb,
-            AlgoType::Grid2d,
-            20,
-            10,
-            None,
-        );
-        assert!(result.is_ok(), "Processing test-ht.glb failed: {:?}", result.err());
-        if let Ok(output_strings) = result {
-            assert!(!output_strings.is_empty(), "Processing test-ht.glb produced no output strings");
+impl From<[u8; 4]> for Transform {
+    fn from(rot: [u8; 4]) -> Self {
+        Transform::Quantized(rot)
+    }
+}
+
+impl From<[u8; 8]> for Transform {
+    fn from(rot: [u8; 8]) -> Self {
+        Transform::QuantizedV2(rot)
+    }
+}
+
+/// Byte encodings of a quantized rotation, the `trans` a miner varies. Chains that store 4
+/// bytes keep decoding them with `V1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum TransEncoding {
+    /// 4 bytes, `Transform::Quantized`: `b[0..3] * 45 / 256` is the axis (not normalized, so
+    /// only the first octant, and 0 0 0 has none) and `b[3] * 45 / 256 * 360 / 256` degrees
+    /// the angle, 256 steps of about 0.25 degrees up to 63.
+    V1,
+    /// 8 bytes, `Transform::QuantizedV2`, little endian: the axis from the `u16` of bytes 0-1
+    /// as the azimuth `phi = 2 pi (a + 0.5) / 65536` and the `u16` of bytes 2-3 as the height
+    /// `z = 1 - 2 (h + 0.5) / 65536`, `(sqrt(1 - z²) cos phi, sqrt(1 - z²) sin phi, z)`, every
+    /// step covering the same area of the sphere; the angle from the `u32` of bytes 4-7,
+    /// `2 pi t / 2^32` radians. Every value is a valid rotation.
+    V2,
+}
+
+impl TransEncoding {
+    /// Bytes of an encoded rotation
+    pub fn byte_len(self) -> usize {
+        match self {
+            TransEncoding::V1 => 4,
+            TransEncoding::V2 => 8,
         }
     }
 
-    #[test]
-    fn test_malformed_glb_data() {
-        let malformed_glb_bytes: &[u8] = b"this is not a glb";
-        let result = p3d_process(
-            malformed_glb_bytes,
-            InputFileType::Glb,
-            AlgoType::Grid2d,
-            20,
-            10,
-            None,
-        );
-        assert!(matches!(result, Err(P3DError::GltfError(_))), "Malformed GLB data did not produce GltfError: {:?}", result);
+    /// The rotation `bytes` encode, `InvalidParams` unless there are `byte_len` of them
+    pub fn decode(self, bytes: &[u8]) -> Result<Transform, P3DError> {
+        if bytes.len() != self.byte_len() {
+            return Err(P3DError::InvalidParams { param: "transform", reason: "has the wrong length for the rotation encoding" });
+        }
+        let mut rot = [0u8; 8];
+        rot[..bytes.len()].copy_from_slice(bytes);
+        Ok(match self {
+            TransEncoding::V1 => Transform::Quantized([rot[0], rot[1], rot[2], rot[3]]),
+            TransEncoding::V2 => Transform::QuantizedV2(rot),
+        })
     }
+}
 
-    #[test]
-    fn test_gltf_no_geometry() {
-        let no_geometry_gltf_json = r#"
-        {
-          "asset": { "version": "2.0" },
-          "scene": 0,
-          "scenes": [ { "nodes": [] } ],
-          "meshes": []
+impl From<Quaternion<f64>> for Transform {
+    fn from(q: Quaternion<f64>) -> Self {
+        Transform::Quaternion(q)
+    }
+}
+
+impl From<Matrix3<f64>> for Transform {
+    fn from(m: Matrix3<f64>) -> Self {
+        Transform::Matrix(m)
+    }
+}
+
+/// Everything `p3d_process_cfg` needs besides the input, named instead of the positional
+/// `par1`/`par2` of `p3d_process_n`. The defaults match `self_test`: `Grid2dV3a`, depth 10,
+/// an 8 x 8 grid and 10 sections.
+///
+/// ```ignore
+/// let params = P3DParams::default().algo(AlgoType::Grid2dV3).n_sections(12);
+/// let hashes = p3d_process_cfg(input, InputFileType::Glb, &params)?;
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(default))]
+pub struct P3DParams {
+    pub algo: AlgoType,
+    /// Maximum number of hashes
+    pub depth: usize,
+    /// Cells along each side of the grid (`par1`)
+    pub grid_size: i16,
+    /// Number of sections (`par2`)
+    pub n_sections: i16,
+    /// Rotation applied to the aligned mesh before slicing
+    pub transform: Option<Transform>,
+    pub options: ProcessOptions,
+}
+
+impl Default for P3DParams {
+    fn default() -> Self {
+        Self {
+            algo: AlgoType::Grid2dV3a,
+            depth: 10,
+            grid_size: 8,
+            n_sections: 10,
+            transform: None,
+            options: ProcessOptions::default(),
         }
-        "#;
-        let no_geometry_gltf_bytes = no_geometry_gltf_json.as_bytes();
-        let result = p3d_process(
-            no_geometry_gltf_bytes,
-            InputFileType::Gltf,
-            AlgoType::Grid2d,
-            20,
-            10,
-            None,
-        );
-        assert!(matches!(result, Err(P3DError::GltfError(_))), "glTF with no geometry did not produce GltfError: {:?}", result);
-        if let Err(P3DError::GltfError(msg)) = result {
-            assert!(msg.contains("No valid geometry"), "Error message for no geometry is incorrect: {}", msg);
-        } else {
-            panic!("Expected GltfError for glTF with no geometry, but got Ok or other error: {:?}", result);
+    }
+}
+
+impl P3DParams {
+    pub fn algo(mut self, algo: AlgoType) -> Self {
+        self.algo = algo;
+        self
+    }
+
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn grid_size(mut self, grid_size: i16) -> Self {
+        self.grid_size = grid_size;
+        self
+    }
+
+    pub fn n_sections(mut self, n_sections: i16) -> Self {
+        self.n_sections = n_sections;
+        self
+    }
+
+    pub fn transform(mut self, transform: impl Into<Transform>) -> Self {
+        self.transform = Some(transform.into());
+        self
+    }
+
+    pub fn options(mut self, options: ProcessOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+pub fn p3d_process(input: &[u8], file_type: InputFileType, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError> {
+    p3d_process_n(input, file_type, algo, 10, par1, par2, trans)
+}
+
+/// Processes `input` like `p3d_process` and checks the result against a stored descriptor.
+/// The verdict is exact: the same hashes in the same order.
+pub fn verify_against(input: &[u8], file_type: InputFileType, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>, reference: &[String]) -> Result<bool, P3DError> {
+    let hashes = p3d_process(input, file_type, algo, par1, par2, trans)?;
+    Ok(hashes.as_slice() == reference)
+}
+
+/// Whether every hash of `expected` is among the hashes `p3d_process_cfg` gives for `input`
+/// with `params`, in any order. Claims that can't hold (more hashes than `params.depth`,
+/// repeated or malformed hashes) are rejected without processing the input. With `Grid2dV3a`
+/// sliced along z and without `fixed_point_scores`, the ranking stops as soon as every claimed
+/// hash ranks or one provably doesn't, instead of totalling every candidate; other settings
+/// rank as usual, without the hex encoding and the intermediate results of the detailed API.
+pub fn p3d_verify(input: &[u8], file_type: InputFileType, params: &P3DParams, expected: &[String]) -> Result<bool, P3DError> {
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    if expected.len() > params.depth {
+        return Ok(false);
+    }
+    let mut claimed: Vec<RawHash> = Vec::with_capacity(expected.len());
+    for hex in expected.iter() {
+        match from_hex(hex) {
+            Some(hash) if !claimed.contains(&hash) => claimed.push(hash),
+            _ => return Ok(false),
+        }
+    }
+    if claimed.is_empty() {
+        return Ok(true);
+    }
+    let opts = &params.options;
+    if params.algo == AlgoType::Grid2dV3a && opts.slice_axis == SliceAxis::Z && !opts.fixed_point_scores {
+        return checked_verdict(checked_geometry(load_geometry(input, file_type, opts)?, opts)?, params, &claimed);
+    }
+    let hashes = p3d_process_cfg_raw(input, file_type, params)?;
+    Ok(claimed.iter().all(|hash| hashes.contains(hash)))
+}
+
+// `checked_hashes` of `Grid2dV3a` along z, cut short by `verify_sections`
+fn checked_verdict(geometry: Geometry, params: &P3DParams, claimed: &[RawHash]) -> Result<bool, P3DError> {
+    let opts = &params.options;
+    let _geometry = charge_geometry(&geometry, opts)?;
+    if soup::supported(params.algo, &geometry, opts) {
+        let soup = Soup::pose(geometry, params.transform, opts)?;
+        return verify_sections(soup.slice(params.algo, params.depth, params.n_sections, opts)?, params, claimed);
+    }
+    let _mesh = charge_mesh(&geometry, opts)?;
+    let mesh = P3DMesh::from_mesh(geometry_mesh(geometry)?, opts)?;
+    let sections = match transform_matrix(params.transform)? {
+        Some(rot) => {
+            let mut posed = mesh.mesh.clone();
+            posed.apply_transformation(rot);
+            slice_mesh(&posed, &mesh.face_materials, &params.algo, params.depth, params.n_sections, opts)?
+        }
+        None => slice_mesh(&mesh.mesh, &mesh.face_materials, &params.algo, params.depth, params.n_sections, opts)?,
+    };
+    verify_sections(sections, params, claimed)
+}
+
+pub fn p3d_process_n(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError>
+{
+    p3d_process_transform(input, file_type, algo, depth, par1, par2, trans.map(Transform::from))
+}
+
+/// Same as `p3d_process_n`, failing with `P3DError::Cancelled` soon after `cancel` is
+/// cancelled instead of running to completion, e.g. to bound the time spent on an input.
+pub fn p3d_process_cancellable(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>, cancel: &Cancel) -> Result<Vec<String>, P3DError>
+{
+    let options = ProcessOptions { cancel: Some(cancel.clone()), ..Default::default() };
+    let params = P3DParams { algo, depth, grid_size: par1, n_sections: par2, transform: trans.map(Transform::from), options };
+    p3d_process_cfg(input, file_type, &params)
+}
+
+/// Same as `p3d_process_n`, with the rotation given as a `Transform`, so it can be exact
+/// (`Transform::AxisAngle`) instead of quantized.
+pub fn p3d_process_transform(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, transform: Option<Transform>) -> Result<Vec<String>, P3DError>
+{
+    let params = P3DParams { algo, depth, grid_size: par1, n_sections: par2, transform, options: ProcessOptions::default() };
+    p3d_process_cfg(input, file_type, &params)
+}
+
+/// Hashes of `input` with the parameters and options of `params`. The Grid2d algorithms are
+/// sliced straight from the triangles unless an option needs the half-edge mesh (loops,
+/// materials, orientation, normalization...); the hashes are the same either way.
+pub fn p3d_process_cfg(input: &[u8], file_type: InputFileType, params: &P3DParams) -> Result<Vec<String>, P3DError>
+{
+    p3d_process_cfg_raw(input, file_type, params).map(|hashes| hashes.iter().map(to_hex).collect())
+}
+
+/// `p3d_process_cfg` with the hashes as the digest bytes their hex strings encode
+pub fn p3d_process_cfg_raw(input: &[u8], file_type: InputFileType, params: &P3DParams) -> Result<Vec<RawHash>, P3DError>
+{
+    if params.algo == AlgoType::CloudGrid {
+        let result = cloud::process_cloud(input, file_type, params)?;
+        return Ok(result.hashes.iter().filter_map(|h| from_hex(h)).collect());
+    }
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    geometry_hashes(load_geometry(input, file_type, &params.options)?, params)
+}
+
+// `p3d_process_cfg_raw` of loaded geometry, sliced from the triangles when `Soup` can
+fn geometry_hashes(geometry: Geometry, params: &P3DParams) -> Result<Vec<RawHash>, P3DError> {
+    checked_hashes(checked_geometry(geometry, &params.options)?, params)
+}
+
+// `geometry_hashes` of geometry `checked_geometry` went through
+fn checked_hashes(geometry: Geometry, params: &P3DParams) -> Result<Vec<RawHash>, P3DError> {
+    let opts = &params.options;
+    let _geometry = charge_geometry(&geometry, opts)?;
+    if params.algo == AlgoType::FixedGrid {
+        let result = fixed::process_fixed(geometry, params)?;
+        return Ok(result.hashes.iter().filter_map(|h| from_hex(h)).collect());
+    }
+    if soup::supported(params.algo, &geometry, opts) {
+        let soup = Soup::pose(geometry, params.transform, opts)?;
+        return Ok(soup.rank(params.algo, params.depth, params.grid_size, params.n_sections, opts)?.hashes);
+    }
+    let _mesh = charge_mesh(&geometry, opts)?;
+    P3DMesh::from_mesh(geometry_mesh(geometry)?, opts)?
+        .process_raw(params.algo, params.depth, params.grid_size, params.n_sections, params.transform)
+}
+
+/// `p3d_process_n` with the hashes as the digest bytes their hex strings encode, saving the
+/// hex encoding and decoding for callers working with bytes.
+pub fn p3d_process_raw(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<RawHash>, P3DError>
+{
+    let params = P3DParams { algo, depth, grid_size: par1, n_sections: par2, transform: trans.map(Transform::from), options: ProcessOptions::default() };
+    p3d_process_cfg_raw(input, file_type, &params)
+}
+
+/// `p3d_process_cfg` of every connected part of the object on its own (triangles sharing
+/// vertices, after welding and repairs), largest surface area first. Every part is aligned by
+/// itself, so its hashes don't depend on the other parts, nor on where they are. A part
+/// without hashes (a flat bit of debris, say) doesn't fail the others. `CloudGrid` has no
+/// parts and fails with `P3DError::InvalidParams`.
+pub fn p3d_process_components(input: &[u8], file_type: InputFileType, params: &P3DParams) -> Result<Vec<ComponentHashes>, P3DError>
+{
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    if params.algo == AlgoType::CloudGrid {
+        return Err(P3DError::InvalidParams { param: "algo", reason: "CloudGrid hashes points, not connected parts" });
+    }
+    let opts = &params.options;
+    let geometry = load_geometry(input, file_type, opts)?;
+    let geometry = checked_geometry(geometry, opts)?;
+    Ok(components::split_components(geometry).into_iter()
+        .map(|(area, part)| ComponentHashes {
+            faces: part.indices.len() / 3,
+            area,
+            hashes: degenerate::check_geometry_span(&part.positions, &part.indices)
+                .and_then(|_| checked_hashes(part, params))
+                .map(|hashes| hashes.iter().map(to_hex).collect()),
+        })
+        .collect())
+}
+
+/// `p3d_process_cfg` with the intermediate geometry, see `p3d_process_detailed`
+pub fn p3d_process_cfg_detailed(input: &[u8], file_type: InputFileType, params: &P3DParams) -> Result<P3DResult, P3DError>
+{
+    if params.algo == AlgoType::CloudGrid {
+        return cloud::process_cloud(input, file_type, params);
+    }
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    geometry_result(load_geometry(input, file_type, &params.options)?, params)
+}
+
+// `p3d_process_cfg_detailed` of loaded geometry
+fn geometry_result(geometry: Geometry, params: &P3DParams) -> Result<P3DResult, P3DError> {
+    let opts = &params.options;
+    let geometry = checked_geometry(geometry, opts)?;
+    let _geometry = charge_geometry(&geometry, opts)?;
+    if params.algo == AlgoType::FixedGrid {
+        return fixed::process_fixed(geometry, params);
+    }
+    if soup::supported(params.algo, &geometry, opts) {
+        let soup = Soup::pose(geometry, params.transform, opts)?;
+        let ranked = soup.rank(params.algo, params.depth, params.grid_size, params.n_sections, opts)?;
+        return Ok(soup.result(ranked, opts));
+    }
+    let _mesh = charge_mesh(&geometry, opts)?;
+    P3DMesh::from_mesh(geometry_mesh(geometry)?, opts)?
+        .process_transform(params.algo, params.depth, params.grid_size, params.n_sections, params.transform)
+}
+
+/// `p3d_process_cfg` on geometry the caller already has in memory, e.g. the vertex and index
+/// buffers of an engine or CAD kernel, without writing it out in a file format first. Of the
+/// glTF specific options, `view_camera` fails with `P3DError::UnsupportedFileType` and
+/// `mesh_selector` is ignored.
+pub fn p3d_process_source<S: MeshSource + ?Sized>(source: &S, params: &P3DParams) -> Result<Vec<String>, P3DError> {
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    let geometry = source_geometry(source, &params.options)?;
+    if params.algo == AlgoType::CloudGrid {
+        return cloud::process_points(geometry.positions, params).map(|result| result.hashes);
+    }
+    geometry_hashes(geometry, params).map(|hashes| hashes.iter().map(to_hex).collect())
+}
+
+/// `p3d_process_source` with the intermediate geometry, see `p3d_process_detailed`
+pub fn p3d_process_source_detailed<S: MeshSource + ?Sized>(source: &S, params: &P3DParams) -> Result<P3DResult, P3DError> {
+    check_params(params.algo, params.depth, params.grid_size, params.n_sections)?;
+    let geometry = source_geometry(source, &params.options)?;
+    if params.algo == AlgoType::CloudGrid {
+        return cloud::process_points(geometry.positions, params);
+    }
+    geometry_result(geometry, params)
+}
+
+/// `p3d_process_source` on the x, y, z of every vertex and the vertex indices of every triangle
+pub fn p3d_process_mesh(positions: &[f64], indices: &[u32], params: &P3DParams) -> Result<Vec<String>, P3DError> {
+    if !indices.chunks_exact(3).remainder().is_empty() {
+        return Err(P3DError::MalformedIndices { indices: indices.len(), vertices: positions.len() / 3 });
+    }
+    if !positions.chunks_exact(3).remainder().is_empty() {
+        return Err(P3DError::InvalidMesh { reason: "coordinate count is not a multiple of 3", indices: indices.len(), vertices: positions.len() / 3 });
+    }
+    p3d_process_source(&(positions, indices), params)
+}
+
+pub fn p3d_process_opts(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>, opts: &ProcessOptions) -> Result<Vec<String>, P3DError>
+{
+    p3d_process_detailed(input, file_type, algo, depth, par1, par2, trans, opts).map(|res| res.hashes)
+}
+
+/// Hashes of the same input under each of `rotations`, in the same order, as `p3d_process_n`
+/// would give them one by one. The input is parsed and aligned once; each rotation then only
+/// costs the slicing and ranking.
+pub fn p3d_process_rotations(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, rotations: &[[u8;4]]) -> Result<Vec<Vec<String>>, P3DError>
+{
+    let mesh = P3DMesh::load(input, file_type)?;
+    rotations.iter()
+        .map(|&rot| mesh.process(algo, depth, par1, par2, Some(rot)))
+        .collect()
+}
+
+/// Same as `p3d_process_opts`, with the intermediate geometry alongside the hashes: the
+/// section contours, the grid bounds and the transform and bounding box of the aligned mesh.
+pub fn p3d_process_detailed(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>, opts: &ProcessOptions) -> Result<P3DResult, P3DError>
+{
+    let params = P3DParams { algo, depth, grid_size: par1, n_sections: par2, transform: trans.map(Transform::from), options: opts.clone() };
+    p3d_process_cfg_detailed(input, file_type, &params)
+}
+
+/// Same as `p3d_process_n` for glTF files referencing external buffers (`.bin` files):
+/// `resolver` gets the URI of every buffer that is neither embedded (`data:` URI) nor the GLB
+/// chunk, and returns its bytes, or `None` to fail with a glTF `P3DError::ParseError`. Lets
+/// callers without a file system (`no_std`, WASM) supply the buffers themselves.
+pub fn p3d_process_with_resolver(input: &[u8], file_type: InputFileType, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>, resolver: &dyn Fn(&str) -> Option<Vec<u8>>) -> Result<Vec<String>, P3DError>
+{
+    check_params(algo, depth, par1, par2)?;
+    P3DMesh::load_with_resolver(input, file_type, &ProcessOptions::default(), resolver)?.process(algo, depth, par1, par2, trans)
+}
+
+/// A parsed and PIT aligned mesh, for running several algorithms or parameter sets on the same
+/// input without loading and aligning it each time. `process` gives the same result as
+/// `p3d_process_n` on the original input.
+///
+/// Without a rotation the work of earlier calls is kept for parameter sweeps: the z extents of
+/// the edges (or vertices) the sections are indexed by, whatever the number of sections, and
+/// the contours of every number of sections, so another grid size only costs the ranking.
+/// `clear_cache` drops them.
+#[derive(Clone)]
+pub struct P3DMesh {
+    mesh: Mesh,
+    face_materials: Vec<u32>,
+    shift: Vector3<f64>,
+    transform: Matrix4<f64>,
+    opts: ProcessOptions,
+    cache: RefCell<SliceCache>,
+}
+
+// What `P3DMesh` keeps of the slicings of the aligned mesh, by whether the edges or the
+// vertices are cut (`slices_edges`)
+#[derive(Clone, Default)]
+struct SliceCache {
+    ranges: [Option<ZRanges>; 2],
+    // Sections by the kind of cut and the number of sections asked for
+    sections: Vec<((bool, i16), Sections)>,
+}
+
+impl P3DMesh {
+    pub fn load(input: &[u8], file_type: InputFileType) -> Result<Self, P3DError> {
+        Self::load_opts(input, file_type, &ProcessOptions::default())
+    }
+
+    pub fn load_opts(input: &[u8], file_type: InputFileType, opts: &ProcessOptions) -> Result<Self, P3DError> {
+        Self::from_mesh(load_mesh(input, file_type, opts)?, opts)
+    }
+
+    /// `load_opts` resolving external glTF buffers, see `p3d_process_with_resolver`
+    pub fn load_with_resolver(input: &[u8], file_type: InputFileType, opts: &ProcessOptions, resolver: &dyn Fn(&str) -> Option<Vec<u8>>) -> Result<Self, P3DError> {
+        Self::from_geometry(load_geometry_with(input, file_type, opts, Some(resolver))?, opts)
+    }
+
+    /// `load_opts` of geometry supplied by the caller, see `p3d_process_source`
+    pub fn from_source<S: MeshSource + ?Sized>(source: &S, opts: &ProcessOptions) -> Result<Self, P3DError> {
+        Self::from_geometry(source_geometry(source, opts)?, opts)
+    }
+
+    // Builds and aligns the mesh of geometry a loader already produced
+    fn from_geometry(geometry: Geometry, opts: &ProcessOptions) -> Result<Self, P3DError> {
+        Self::from_mesh(build_mesh_opts(geometry, opts)?, opts)
+    }
+
+    fn from_mesh((mut mesh, face_materials): (Mesh, Vec<u32>), opts: &ProcessOptions) -> Result<Self, P3DError> {
+        let (shift, transform) = pose_mesh(&mut mesh, None, opts)?;
+        Ok(Self { mesh, face_materials, shift, transform, opts: opts.clone(), cache: RefCell::default() })
+    }
+
+    /// Drops the slicings kept from earlier calls
+    pub fn clear_cache(&self) {
+        self.cache.replace(SliceCache::default());
+    }
+
+    // `rank_posed` of the aligned mesh, from the kept sections when they were cut before.
+    // Voxels, normals and other axes than Z slice their own way.
+    fn rank_aligned(&self, algo: AlgoType, depth: usize, par1: i16, par2: i16) -> Result<Ranked, P3DError> {
+        if self.opts.slice_axis != SliceAxis::Z || matches!(algo, AlgoType::Voxel | AlgoType::CloudGrid | AlgoType::FixedGrid | AlgoType::GridNormals) {
+            return rank_posed(&self.mesh, &self.face_materials, algo, depth, par1, par2, &self.opts);
+        }
+        check_params(algo, depth, par1, par2)?;
+        let sections = self.sections(algo, depth, par2)?;
+        Ok(canonical_ranks(rank_sections(sections, algo, depth, par1, &self.opts)?, &self.opts))
+    }
+
+    fn sections(&self, algo: AlgoType, depth: usize, n_sections: i16) -> Result<Sections, P3DError> {
+        let edges = slices_edges(&algo);
+        let key = (edges, n_sections);
+        let mut cache = self.cache.borrow_mut();
+        if let Some((_, sections)) = cache.sections.iter().find(|(k, _)| *k == key) {
+            return Ok(sections.clone());
+        }
+        let ranges = cache.ranges[edges as usize].get_or_insert_with(|| ZRanges::new(&self.mesh, edges));
+        let sections = slice_mesh_ranges(&self.mesh, &self.face_materials, &algo, depth, n_sections, Some(ranges), &self.opts)?;
+        cache.sections.push((key, sections.clone()));
+        Ok(sections)
+    }
+
+    pub fn process(&self, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError> {
+        self.process_raw(algo, depth, par1, par2, trans.map(Transform::from)).map(|hashes| hashes.iter().map(to_hex).collect())
+    }
+
+    /// The hashes of `process_transform` as digest bytes, see `p3d_process_raw`
+    pub fn process_raw(&self, algo: AlgoType, depth: usize, par1: i16, par2: i16, transform: Option<Transform>) -> Result<Vec<RawHash>, P3DError> {
+        let ranked = match transform_matrix(transform)? {
+            Some(rot) => {
+                let mut mesh = self.mesh.clone();
+                mesh.apply_transformation(rot);
+                rank_posed(&mesh, &self.face_materials, algo, depth, par1, par2, &self.opts)?
+            }
+            None => self.rank_aligned(algo, depth, par1, par2)?,
+        };
+        Ok(ranked.hashes)
+    }
+
+    pub fn process_detailed(&self, algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<P3DResult, P3DError> {
+        self.process_transform(algo, depth, par1, par2, trans.map(Transform::from))
+    }
+
+    /// Same as `process_detailed`, rotating by a `Transform` (quantized or exact)
+    pub fn process_transform(&self, algo: AlgoType, depth: usize, par1: i16, par2: i16, transform: Option<Transform>) -> Result<P3DResult, P3DError> {
+        // The rotation comes last in `pose_mesh`, so it can be applied to the aligned mesh
+        match transform_matrix(transform)? {
+            Some(rot) => {
+                let mut mesh = self.mesh.clone();
+                mesh.apply_transformation(rot);
+                process_posed(&mesh, &self.face_materials, algo, depth, par1, par2, self.shift, rot * self.transform, &self.opts)
+            }
+            None => {
+                let ranked = self.rank_aligned(algo, depth, par1, par2)?;
+                Ok(mesh_result(&self.mesh, ranked, self.shift, self.transform, &self.opts))
+            }
+        }
+    }
+
+    /// `p3d_shape_descriptor` of the aligned mesh
+    pub fn shape_descriptor(&self, shells: usize, bands: usize) -> Result<Vec<f64>, P3DError> {
+        shape_descriptor::shape_descriptor(&self.mesh, shells, bands)
+    }
+
+    /// Tries `rotations` in order and returns the first whose hashes `predicate` accepts, with
+    /// those hashes, or `None` once they run out. Each try only costs the slicing and ranking of
+    /// the already aligned mesh; an error (e.g. `P3DError::Cancelled`) ends the search.
+    pub fn search_rotations<I, P>(&self, algo: AlgoType, depth: usize, par1: i16, par2: i16, rotations: I, mut predicate: P) -> Result<Option<([u8; 4], Vec<String>)>, P3DError>
+        where I: IntoIterator<Item = [u8; 4]>, P: FnMut(&[String]) -> bool
+    {
+        check_params(algo, depth, par1, par2)?;
+        for rot in rotations {
+            let hashes = self.process(algo, depth, par1, par2, Some(rot))?;
+            if predicate(&hashes) {
+                return Ok(Some((rot, hashes)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Cross-sections of the aligned (and optionally rotated) mesh as filled 2D polygons: the
+/// area inside every non-empty section, bottom to top, triangulated by ear clipping. The
+/// loops of a section are traced apart, and holes are cut out of the loops around them.
+/// Fails with `InvalidParams` for fewer than 1 section.
+pub fn section_polygons(input: &[u8], file_type: InputFileType, n_sections: i16, trans: Option<[u8;4]>) -> Result<Vec<Vec<[Point2<f64>; 3]>>, P3DError> {
+    section_polygons_opts(input, file_type, n_sections, trans, &ProcessOptions::default())
+}
+
+fn section_polygons_opts(input: &[u8], file_type: InputFileType, n_sections: i16, trans: Option<[u8;4]>, opts: &ProcessOptions) -> Result<Vec<Vec<[Point2<f64>; 3]>>, P3DError> {
+    check_params(AlgoType::Grid2dV3a, 1, 1, n_sections)?;
+    let opts = ProcessOptions { trace_loops: true, ..opts.clone() };
+    let (mut mesh, face_materials) = load_mesh(input, file_type, &opts)?;
+    pose_mesh(&mut mesh, trans.map(Transform::from), &opts)?;
+    let sections = slice_mesh(&mesh, &face_materials, &AlgoType::Grid2dV3a, n_sections as usize, n_sections, &opts)?;
+    Ok(sections.loops.iter().map(|loops| triangulate::fill_loops(loops)).collect())
+}
+
+/// The mesh `p3d_process` slices, after alignment and the optional rotation, as OBJ text.
+/// For comparing the geometry stage against other implementations, e.g. in MeshLab.
+pub fn dump_aligned_obj(input: &[u8], file_type: InputFileType, trans: Option<[u8;4]>) -> Result<String, P3DError> {
+    let opts = ProcessOptions::default();
+    let (mut mesh, _) = load_mesh(input, file_type, &opts)?;
+    pose_mesh(&mut mesh, trans.map(Transform::from), &opts)?;
+    Ok(obj_export::mesh_obj(&mesh))
+}
+
+/// Rotation-invariant global shape descriptor of the aligned mesh for similarity search:
+/// `shells` x `bands` spherical harmonic energies of the surface, shell by shell from the
+/// center outwards, the same for the object in any pose and at any scale. Compare them with
+/// `shape_descriptor_distance`, or hash them with `shape_descriptor_hash`; 8 shells of 8
+/// bands are a good start. Fails with `InvalidParams` outside 1 to 64 shells and 1 to 32 bands.
+pub fn p3d_shape_descriptor(input: &[u8], file_type: InputFileType, shells: usize, bands: usize, opts: &ProcessOptions) -> Result<Vec<f64>, P3DError> {
+    P3DMesh::load_opts(input, file_type, opts)?.shape_descriptor(shells, bands)
+}
+
+/// The section contours `p3d_process` ranks (`Grid2dV3a` sections), as OBJ polylines in the
+/// coordinates of `dump_aligned_obj`: one object per non-empty section, bottom to top.
+/// Fails with `InvalidParams` for fewer than 1 section.
+pub fn dump_sections_obj(input: &[u8], file_type: InputFileType, n_sections: i16, trans: Option<[u8;4]>) -> Result<String, P3DError> {
+    check_params(AlgoType::Grid2dV3a, 1, 1, n_sections)?;
+    let opts = ProcessOptions::default();
+    let (mut mesh, face_materials) = load_mesh(input, file_type, &opts)?;
+    pose_mesh(&mut mesh, trans.map(Transform::from), &opts)?;
+    let sections = slice_mesh(&mesh, &face_materials, &AlgoType::Grid2dV3a, n_sections as usize, n_sections, &opts)?;
+    Ok(obj_export::sections_obj(&sections.centers, &sections.heights))
+}
+
+/// Like `p3d_process`, with a section every `spacing` units along the aligned Z axis instead
+/// of a fixed number of sections, so the section density doesn't depend on the object size.
+/// Takes `floor(extent / spacing)` sections, less one if the last would touch the top.
+pub fn p3d_process_spacing(input: &[u8], file_type: InputFileType, algo: AlgoType, spacing: f64, grid_size: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError> {
+    let depth = 10;
+    check_params(algo, depth, grid_size, 1)?;
+    if matches!(algo, AlgoType::CloudGrid | AlgoType::Voxel | AlgoType::FixedGrid | AlgoType::GridNormals) {
+        return Err(P3DError::InvalidParams { param: "algo", reason: "has no section spacing" });
+    }
+    if !(spacing > 0.0 && spacing.is_finite()) {
+        return Err(P3DError::InvalidParams { param: "spacing", reason: "must be positive and finite" });
+    }
+    let opts = ProcessOptions::default();
+    let (mut mesh, face_materials) = load_mesh(input, file_type, &opts)?;
+    pose_mesh(&mut mesh, trans.map(Transform::from), &opts)?;
+    let sections = slice_mesh_spacing(&mesh, &face_materials, &algo, depth, spacing, &opts)?;
+    Ok(find_top(&algo, &sections, depth, grid_size, HashAlgo::Sha256, CellStatistic::StdDev, None).iter().map(to_hex).collect())
+}
+
+// Everything after loading: alignment, normalization, rotation, slicing and ranking
+#[cfg(any(feature = "zip", feature = "cache"))]
+fn process_mesh(mut mesh: Mesh, face_materials: &[u32], algo: AlgoType, depth: usize, par1: i16, par2: i16, trans: Option<[u8;4]>, opts: &ProcessOptions) -> Result<P3DResult, P3DError>
+{
+    let (shift, transform) = pose_mesh(&mut mesh, trans.map(Transform::from), opts)?;
+    process_posed(&mesh, face_materials, algo, depth, par1, par2, shift, transform, opts)
+}
+
+// The grid size and number of sections are cast to `usize`, so negatives must not get there;
+// the depth sizes the buffers of the ranking
+pub(crate) fn check_params(algo: AlgoType, depth: usize, par1: i16, par2: i16) -> Result<(), P3DError> {
+    if !(1..=MAX_DEPTH).contains(&depth) {
+        return Err(P3DError::InvalidParams { param: "depth", reason: "must be between 1 and MAX_DEPTH" });
+    }
+    // Spectr keeps `par1 / 2` harmonics, none below 2
+    let min_grid = if algo == AlgoType::Spectr { 2 } else { 1 };
+    if par1 < min_grid {
+        return Err(P3DError::InvalidParams {
+            param: "par1",
+            reason: if min_grid == 2 { "(grid size) of Spectr must be at least 2" } else { "(grid size) must be at least 1" },
+        });
+    }
+    if par2 < 1 {
+        return Err(P3DError::InvalidParams { param: "par2", reason: "(number of sections) must be at least 1" });
+    }
+    Ok(())
+}
+
+// What `rank_posed` found: the hashes (and scores) with the sections they were ranked from
+struct Ranked {
+    hashes: Vec<RawHash>,
+    scores: Vec<u32>,
+    // Empty unless `ProcessOptions::hash_details` is set
+    details: Vec<HashDetail>,
+    sections: Sections,
+    grid_size: i16,
+}
+
+// Rotation turning `axis` to Z by cycling the coordinates
+fn axis_to_z(axis: SliceAxis) -> Matrix4<f64> {
+    match axis {
+        // (x, y, z) -> (y, z, x)
+        SliceAxis::X => Matrix4::new(
+            0.0, 0.0, 1.0, 0.0,
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ),
+        // (x, y, z) -> (z, x, y)
+        SliceAxis::Y => Matrix4::new(
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ),
+        SliceAxis::Z | SliceAxis::All => Matrix4::identity(),
+    }
+}
+
+// Sectioning and ranking of a mesh `pose_mesh` has already been applied to, along
+// `ProcessOptions::slice_axis`
+fn rank_posed(mesh: &Mesh, face_materials: &[u32], algo: AlgoType, depth: usize, par1: i16, par2: i16, opts: &ProcessOptions) -> Result<Ranked, P3DError>
+{
+    if algo == AlgoType::CloudGrid {
+        return Err(P3DError::InvalidParams { param: "algo", reason: "CloudGrid hashes the input points, it can't run on a loaded mesh" });
+    }
+    if algo == AlgoType::FixedGrid {
+        return Err(P3DError::InvalidParams { param: "algo", reason: "FixedGrid aligns the input itself, it can't run on a loaded mesh" });
+    }
+    Ok(canonical_ranks(rank_axis(mesh, face_materials, algo, depth, par1, par2, opts)?, opts))
+}
+
+// The hashes (and their scores) sorted when `ProcessOptions::canonical_order` is set
+fn canonical_ranks(ranked: Ranked, opts: &ProcessOptions) -> Ranked {
+    // In the order of the ranking, before the sort
+    trace_event!("rank", "grid_size={} sections={} hashes={}", ranked.grid_size, ranked.sections.centers.len(), ranked.hashes.len());
+    #[cfg(feature = "trace")]
+    for (rank, hash) in ranked.hashes.iter().enumerate() {
+        trace_event!("rank", "rank={} hash={} score={:?}", rank, to_hex(hash), ranked.scores.get(rank));
+    }
+    if !opts.canonical_order {
+        return ranked;
+    }
+    let Ranked { hashes, scores, details, sections, grid_size } = ranked;
+    // Byte order is also the order of the hex strings
+    let mut order: Vec<usize> = (0..hashes.len()).collect();
+    order.sort_by_key(|&i| (hashes[i], scores.get(i).copied()));
+    let details = order.iter()
+        .filter_map(|&i| details.get(i).cloned())
+        .enumerate()
+        .map(|(rank, detail)| HashDetail { rank, ..detail })
+        .collect();
+    let scores = order.iter().filter_map(|&i| scores.get(i).copied()).collect();
+    let hashes = order.iter().map(|&i| hashes[i]).collect();
+    Ranked { hashes, scores, details, sections, grid_size }
+}
+
+// `rank_posed` before `ProcessOptions::canonical_order`, which would break up the ranks
+// `SliceAxis::All` pairs
+fn rank_axis(mesh: &Mesh, face_materials: &[u32], algo: AlgoType, depth: usize, par1: i16, par2: i16, opts: &ProcessOptions) -> Result<Ranked, P3DError>
+{
+    check_params(algo, depth, par1, par2)?;
+    let along = |axis: SliceAxis| {
+        let mut turned = mesh.clone();
+        turned.apply_transformation(axis_to_z(axis));
+        rank_along_z(&turned, face_materials, algo, depth, par1, par2, opts)
+    };
+    match opts.slice_axis {
+        SliceAxis::Z => rank_along_z(mesh, face_materials, algo, depth, par1, par2, opts),
+        SliceAxis::X | SliceAxis::Y => along(opts.slice_axis),
+        SliceAxis::All => {
+            let x = along(SliceAxis::X)?;
+            let y = along(SliceAxis::Y)?;
+            let z = rank_along_z(mesh, face_materials, algo, depth, par1, par2, opts)?;
+            let n = x.hashes.len().min(y.hashes.len()).min(z.hashes.len());
+            let hashes = (0..n)
+                .map(|r| digest(opts.hash_algo, [&x.hashes[r][..], &y.hashes[r][..], &z.hashes[r][..]]))
+                .collect();
+            let scores = if opts.fixed_point_scores {
+                (0..n).map(|r| x.scores[r].saturating_add(y.scores[r]).saturating_add(z.scores[r])).collect()
+            } else {
+                Vec::new()
+            };
+            Ok(Ranked { hashes, scores, details: Vec::new(), ..z })
+        }
+    }
+}
+
+fn rank_along_z(mesh: &Mesh, face_materials: &[u32], algo: AlgoType, depth: usize, par1: i16, par2: i16, opts: &ProcessOptions) -> Result<Ranked, P3DError>
+{
+    if algo == AlgoType::Voxel {
+        // Sliced only for the sections of a `P3DResult`
+        let sections = slice_mesh(mesh, face_materials, &AlgoType::Grid2dV3a, depth, par2, opts)?;
+        check_limit("voxel bytes", (par1 as usize).pow(2).saturating_mul(par2 as usize), opts.limits.max_allocation)?;
+        report_progress(opts, Stage::Hash, 0, 1);
+        let hashes = voxel::find_top_voxel(mesh, depth, par1 as usize, par2 as usize, opts.hash_algo, opts.cancel.as_ref());
+        check_cancel(opts)?;
+        report_progress(opts, Stage::Hash, 1, 1);
+        return Ok(Ranked { hashes, scores: Vec::new(), details: Vec::new(), sections, grid_size: par1 });
+    }
+    if algo == AlgoType::GridNormals {
+        // The sections give the planes and the grid, the mesh what they cross
+        let sections = slice_mesh(mesh, face_materials, &algo, depth, par2, opts)?;
+        let grid_size = match opts.grid_size {
+            GridSize::Fixed => par1,
+            GridSize::Auto => auto_grid_size(&sections).unwrap_or(par1),
+        };
+        report_progress(opts, Stage::Hash, 0, 1);
+        let hashes = grid_normals::find_top_grid_normals(mesh, &sections.heights, depth, grid_size, &sections.rect, opts.hash_algo, opts.cancel.as_ref());
+        check_cancel(opts)?;
+        report_progress(opts, Stage::Hash, 1, 1);
+        return Ok(Ranked { hashes, scores: Vec::new(), details: Vec::new(), sections, grid_size });
+    }
+    let n_sections: i16 = par2;
+
+    let sections = slice_mesh(mesh, face_materials, &algo, depth, n_sections, opts)?;
+    rank_sections(sections, algo, depth, par1, opts)
+}
+
+// Ranking of the sections of a slicing, with the grid size and filters of `opts`
+fn rank_sections(mut sections: Sections, algo: AlgoType, depth: usize, par1: i16, opts: &ProcessOptions) -> Result<Ranked, P3DError>
+{
+    let _sections = charge(opts, "section bytes", section_bytes(&sections))?;
+    let grid_size = prepare_sections(&mut sections, par1, opts);
+    report_progress(opts, Stage::Hash, 0, 1);
+    let cancel = opts.cancel.as_ref();
+    let (hashes, scores, details) = if opts.fixed_point_scores || opts.hash_details {
+        find_top_scored(&algo, &sections, depth, grid_size, opts, cancel)
+    } else {
+        (find_top(&algo, &sections, depth, grid_size, opts.hash_algo, opts.cell_statistic, cancel), Vec::new(), Vec::new())
+    };
+    // A cancelled ranking stops early and its hashes are incomplete
+    check_cancel(opts)?;
+    report_progress(opts, Stage::Hash, 1, 1);
+    Ok(Ranked { hashes, scores, details, sections, grid_size })
+}
+
+// `rank_sections` of `Grid2dV3a` for `p3d_verify`: whether every hash of `claimed` ranks in the
+// top `params.depth`, told without totalling every candidate
+fn verify_sections(mut sections: Sections, params: &P3DParams, claimed: &[RawHash]) -> Result<bool, P3DError> {
+    let opts = &params.options;
+    let _sections = charge(opts, "section bytes", section_bytes(&sections))?;
+    let grid_size = prepare_sections(&mut sections, params.grid_size, opts);
+    report_progress(opts, Stage::Hash, 0, 1);
+    let candidates = candidates_4(&sections.centers, grid_size as usize, sections.rect.clone(), opts.cancel.as_ref());
+    let (holds, _) = verify_candidates_4(&candidates, params.depth, sections.n_sections as usize, opts.hash_algo, claimed, opts.cancel.as_ref());
+    check_cancel(opts)?;
+    report_progress(opts, Stage::Hash, 1, 1);
+    Ok(holds)
+}
+
+// The grid size `opts` ranks `sections` with, and the sections filtered and snapped by it
+fn prepare_sections(sections: &mut Sections, par1: i16, opts: &ProcessOptions) -> i16 {
+    let grid_size: i16 = match opts.grid_size {
+        GridSize::Fixed => par1,
+        GridSize::Auto => auto_grid_size(sections).unwrap_or(par1),
+    };
+    filter_section_support(sections, grid_size, opts.min_section_support);
+    snap_sections(sections, opts.boundary_snap);
+    grid_size
+}
+
+// `rank_posed` with the intermediate geometry of a `P3DResult`
+fn process_posed(mesh: &Mesh, face_materials: &[u32], algo: AlgoType, depth: usize, par1: i16, par2: i16, shift: Vector3<f64>, transform: Matrix4<f64>, opts: &ProcessOptions) -> Result<P3DResult, P3DError>
+{
+    let ranked = rank_posed(mesh, face_materials, algo, depth, par1, par2, opts)?;
+    Ok(mesh_result(mesh, ranked, shift, transform, opts))
+}
+
+// `P3DResult` of a ranking of the posed `mesh`
+fn mesh_result(mesh: &Mesh, ranked: Ranked, shift: Vector3<f64>, transform: Matrix4<f64>, opts: &ProcessOptions) -> P3DResult {
+    let faces: Vec<FaceID> = mesh.face_iter().collect();
+    let robustness = robustness(&face_triangles(mesh, &faces), &ranked.sections, ranked.grid_size);
+    let vertices = || mesh.vertex_iter()
+        .map(|vid| {
+            let p = mesh.vertex_position(vid);
+            [p.x, p.y, p.z]
+        })
+        .collect();
+    posed_result(ranked, (shift, transform), mesh.extreme_coordinates(), robustness, vertices, opts)
+}
+
+// `P3DResult` of a ranking, `vertices` giving the posed vertices for `debug_geometry`
+fn posed_result<V>(ranked: Ranked, (shift, transform): (Vector3<f64>, Matrix4<f64>), (v_min, v_max): (Vector3<f64>, Vector3<f64>), robustness: f32, vertices: V, opts: &ProcessOptions) -> P3DResult
+    where V: FnOnce() -> Vec<[f64; 3]>
+{
+    let Ranked { hashes, scores, details, sections, grid_size } = ranked;
+    let (vertices, occupancy) = if opts.debug_geometry {
+        (vertices(), grid_occupancy(&sections, grid_size))
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    P3DResult {
+        hashes: hashes.iter().map(to_hex).collect(),
+        centers: sections.centers,
+        section_heights: sections.heights,
+        rect: sections.rect.bounds(),
+        materials: sections.materials,
+        section_loops: sections.loops,
+        shift,
+        transform,
+        v_min,
+        v_max,
+        out_of_bounds: sections.out_of_bounds,
+        robustness,
+        scores,
+        grid_size,
+        vertices,
+        occupancy,
+        hash_details: details,
+        peak_allocation: opts.allocations.as_ref().map_or(0, Allocations::peak),
+    }
+}
+
+// Contour points in every cell of the `grid_size` x `grid_size` grid, per section, rows first.
+// Points outside the `Rect` are not counted.
+fn grid_occupancy(sections: &Sections, grid_size: i16) -> Vec<Vec<u32>> {
+    let n = grid_size as i32;
+    sections.centers.iter()
+        .map(|cntr| {
+            let mut cells = vec![0u32; (n * n) as usize];
+            for p in cntr.iter() {
+                let (i, j) = sections.rect.cell(p, n);
+                if (0..n).contains(&i) && (0..n).contains(&j) {
+                    cells[(j * n + i) as usize] += 1;
+                }
+            }
+            cells
+        })
+        .collect()
+}
+
+// Grid size for `GridSize::Auto`: the grid has as many columns as rows, so cells keep the
+// aspect ratio of the `Rect`; the size makes the geometric mean of the cell sides equal to the
+// mean distance between consecutive contour points, clamped to `AUTO_GRID_MIN..=AUTO_GRID_MAX`.
+// `None` without contour segments or with a flat `Rect`.
+fn auto_grid_size(sections: &Sections) -> Option<i16> {
+    let (mut length, mut segments) = (0.0f64, 0usize);
+    for cntr in sections.centers.iter() {
+        for w in cntr.windows(2) {
+            let (dx, dy) = (w[1].x - w[0].x, w[1].y - w[0].y);
+            length += libm::sqrt(dx * dx + dy * dy);
+            segments += 1;
+        }
+    }
+    let area = sections.rect.width() * sections.rect.height();
+    if segments == 0 || !(length > 0.0) || !(area > 0.0) {
+        return None;
+    }
+    let n = libm::round(libm::sqrt(area) / (length / segments as f64));
+    Some(n.max(AUTO_GRID_MIN as f64).min(AUTO_GRID_MAX as f64) as i16)
+}
+
+// Average of three scores in [0, 1], each low when the descriptor is likely to be unstable:
+// - the share of the requested sections that have a contour,
+// - how far apart the principal moments are (the PIT axes of a near-symmetric mesh are arbitrary),
+// - how many grid cells the contours touch on average, relative to the grid size.
+fn robustness(triangles: &Array3<f64>, sections: &Sections, grid_size: i16) -> f32 {
+    let populated = sections.centers.iter().filter(|c| !c.is_empty()).count();
+    let coverage = if sections.n_sections > 0 {
+        (populated as f64 / sections.n_sections as f64).min(1.0)
+    } else {
+        0.0
+    };
+
+    let (_center, inertia) = algo_grid::surface_mass_properties(triangles);
+    let m = algo_grid::principal_moments(inertia);
+    let separation = if m[2] > 0.0 {
+        // A 10% relative gap between neighbouring moments counts as fully separated
+        (((m[1] - m[0]).min(m[2] - m[1]) / m[2]) * 10.0).min(1.0)
+    } else {
+        0.0
+    };
+
+    let occupancy = if populated > 0 && grid_size > 0 {
+        let n = grid_size as i32;
+        let cells: usize = sections.centers.iter()
+            .filter(|c| !c.is_empty())
+            .map(|c| c.iter().map(|p| sections.rect.cell(p, n)).collect::<BTreeSet<(i32, i32)>>().len())
+            .sum();
+        (cells as f64 / populated as f64 / grid_size as f64).min(1.0)
+    } else {
+        0.0
+    };
+
+    ((coverage + separation + occupancy) / 3.0) as f32
+}
+
+// Geometry as read from the input, before the mesh is built
+#[derive(Clone)]
+struct Geometry {
+    positions: Vec<f64>,
+    indices: Vec<u32>,
+    // Material of every triangle, empty when the format has none
+    materials: Vec<u32>,
+}
+
+// Positions as f64, in the view of `ProcessOptions::view_camera` if set
+fn gltf_positions(gltf_data: &Gltf, positions: Vec<[f32; 3]>, opts: &ProcessOptions) -> Result<Vec<f64>, P3DError> {
+    let view = match opts.view_camera {
+        Some(camera) => camera::camera_view(gltf_data, camera)?,
+        None => Matrix4::identity(),
+    };
+    Ok(positions.into_iter()
+        .flat_map(|pos| {
+            let p = view.transform_point(Point3::new(pos[0] as f64, pos[1] as f64, pos[2] as f64));
+            [p.x, p.y, p.z]
+        })
+        .collect())
+}
+
+// Alignment, normalization and rotation in place, returns the shift and the transform applied
+fn pose_mesh(mesh: &mut Mesh, trans: Option<Transform>, opts: &ProcessOptions) -> Result<(Vector3<f64>, Matrix4<f64>), P3DError> {
+    report_progress(opts, Stage::Transform, 0, 1);
+    if mesh.no_faces() == 0 {
+        return Err(P3DError::EmptyMesh);
+    }
+    if opts.check_self_intersection && self_intersection::find_self_intersection(mesh).is_some() {
+        return Err(P3DError::SelfIntersecting);
+    }
+    // A camera view fixes the slicing axis, the mesh was already rotated when loaded
+    let (shift, mut transform) = if opts.view_camera.is_some() {
+        (Vector3::new(0.0, 0.0, 0.0), Matrix4::identity())
+    } else if let Some(alignment) = opts.alignment {
+        mesh.apply_transformation(alignment);
+        (Vector3::new(0.0, 0.0, 0.0), alignment)
+    } else if opts.align || opts.normalization == Normalization::Full {
+        let _triangles = charge(opts, "triangle bytes", triangle_bytes(mesh.no_faces()))?;
+        let (shift, mut tr) = pit_transform_in(mesh, opts.inertia, opts.inertia_region.as_ref(), opts.canonical_order)?;
+        mesh.translate(shift);
+        mesh.apply_transformation(tr);
+        if opts.orient_axes || opts.reflection != Reflection::Unspecified {
+            let flip = axis_orientation(mesh, &tr, opts.reflection);
+            mesh.apply_transformation(flip);
+            tr = flip * tr;
+        }
+        (shift, tr)
+    } else {
+        (Vector3::new(0.0, 0.0, 0.0), Matrix4::identity())
+    };
+    if let Some(norm) = normalization_matrix(mesh, opts.normalization)? {
+        mesh.apply_transformation(norm);
+        transform = norm * transform;
+    }
+    if let Some(rot) = transform_matrix(trans)? {
+        mesh.apply_transformation(rot);
+        transform = rot * transform;
+    }
+    // A flat mesh has no volume to align and its sections are segments, whichever axis it's flat along
+    let (v_min, v_max) = mesh.extreme_coordinates();
+    check_flat(v_min, v_max)?;
+    report_progress(opts, Stage::Transform, 1, 1);
+    Ok((shift, transform))
+}
+
+// Fails with `FlatObject` when a bounding box is flat along some axis (or not finite)
+fn check_flat(v_min: Vector3<f64>, v_max: Vector3<f64>) -> Result<(), P3DError> {
+    let size = v_max - v_min;
+    let thickness = size.x.min(size.y).min(size.z);
+    let extent = size.magnitude();
+    if !(thickness > FLAT_EPSILON * extent) {
+        return Err(P3DError::FlatObject { thickness, extent });
+    }
+    Ok(())
+}
+
+// Fetches a buffer referenced by URI, relative to the glTF file
+type UriLoader<'l> = &'l dyn Fn(&str) -> Option<Vec<u8>>;
+
+// Data of every buffer by index: the GLB blob for the buffer without a URI (only the first one
+// may lack it), the decoded `data:` URI, or whatever `load_uri` returns for other URIs. Those can't be resolved without a loader (`no_std` has no files).
+fn buffer_data(gltf_data: &Gltf, load_uri: Option<UriLoader>) -> Result<Vec<Option<Vec<u8>>>, P3DError> {
+    gltf_data.buffers()
+        .map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Bin => gltf_data.blob.clone().map(Some)
+                .ok_or_else(|| gltf_error("buffer without a URI and no GLB binary chunk")),
+            gltf::buffer::Source::Uri(uri) => match data_uri::decode(uri) {
+                Some(data) => data.map(Some),
+                None => match load_uri {
+                    Some(load) => load(uri).map(Some)
+                        .ok_or_else(|| gltf_error("buffer URI not found")),
+                    None => Err(gltf_error("external buffers are not supported, embed them as data URIs or use GLB")),
+                },
+            },
+        })
+        .collect()
+}
+
+// Triangles of the selected meshes of a glTF document (JSON or GLB), every instance in world
+// space (or posed), with the material of every triangle. Empty when there is nothing to slice.
+fn gltf_geometry(gltf_data: &Gltf, opts: &ProcessOptions, load_uri: Option<UriLoader>) -> Result<(Vec<[f32; 3]>, Vec<u32>, Vec<u32>), P3DError> {
+    let mut materials: Vec<u32> = Vec::new();
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let buffers = buffer_data(gltf_data, load_uri)?;
+    check_limit("input bytes", buffers.iter().flatten().map(|b| b.len()).sum(), opts.limits.max_input_bytes)?;
+    let posed = match opts.pose.as_ref() {
+        Some(pose) => Some(skinning::posed_node_transforms(gltf_data, pose, |buffer| buffers[buffer.index()].as_deref())?),
+        None => None,
+    };
+    let globals = posed.clone().unwrap_or_else(|| skinning::node_transforms(gltf_data));
+
+    for (mesh, node) in selected_instances(gltf_data, opts.mesh_selector.as_ref())? {
+        // Posed skins already put the vertices in world space
+        let world = node.filter(|node| posed.is_none() || node.skin().is_none())
+            .map(|node| globals[node.index()])
+            .filter(|m| *m != Matrix4::identity());
+        for primitive in mesh.primitives() {
+            // Points and lines make no faces
+            if !matches!(primitive.mode(), Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan) {
+                continue;
+            }
+            #[cfg(feature = "draco")]
+            let decoded = draco::decode_primitive(gltf_data, &primitive, &buffers, opts)?;
+            #[cfg(not(feature = "draco"))]
+            let decoded: Option<(Vec<[f32; 3]>, Vec<u32>)> = None;
+            // Primitives are concatenated, their indices shifted past the vertices before them
+            let base = positions.len();
+            let (mode, primitive_indices) = match decoded {
+                // A decoded primitive is a triangle list, whatever mode it declares
+                Some((decoded_positions, decoded_indices)) => {
+                    let count = base.saturating_add(decoded_positions.len());
+                    check_limit("vertices", count, opts.limits.max_vertices)?;
+                    check_vertex_count(count)?;
+                    positions.extend(decoded_positions);
+                    (Mode::Triangles, Some(decoded_indices.into_iter().map(|i| i.saturating_add(base as u32)).collect()))
+                }
+                None => {
+                    // Instancing can multiply a small file, so the limits are checked as it grows
+                    let count = base.saturating_add(primitive.get(&Semantic::Positions).map(|a| a.count()).unwrap_or(0));
+                    check_limit("vertices", count, opts.limits.max_vertices)?;
+                    check_vertex_count(count)?;
+                    let get_buffer_data = |buffer: gltf::Buffer| buffers[buffer.index()].as_deref();
+                    match primitive_positions(&primitive, get_buffer_data)? {
+                        Some(p) => positions.extend(p),
+                        None => continue,
+                    }
+                    // Any index type, shifted in u32 now that the vertices are known to fit: an
+                    // index past them saturates and stays out of range for `check_geometry`
+                    let primitive_indices: Option<Vec<u32>> = primitive.reader(get_buffer_data).read_indices()
+                        .map(|indices_iter| indices_iter.into_u32().map(|i| i.saturating_add(base as u32)).collect());
+                    (primitive.mode(), primitive_indices)
+                }
+            };
+            if let Some(globals) = posed.as_ref() {
+                skinning::skin_positions(gltf_data, &mesh, &primitive, globals, &mut positions[base..], |buffer| buffers[buffer.index()].as_deref())?;
+            }
+            if let Some(m) = world {
+                for p in positions[base..].iter_mut() {
+                    let w = m.transform_point(Point3::new(p[0] as f64, p[1] as f64, p[2] as f64));
+                    *p = [w.x as f32, w.y as f32, w.z as f32];
+                }
+            }
+            let start = indices.len();
+            // Non-indexed: every vertex is used once, in order
+            let vertices = primitive_indices.unwrap_or_else(|| (base as u32..positions.len() as u32).collect());
+            indices.extend(triangle_list(mode, vertices));
+            // A mirroring node matrix turns the faces inside out, unless their winding is too
+            if matches!(world, Some(m) if m.determinant() < 0.0) {
+                for t in indices[start..].chunks_exact_mut(3) {
+                    t.swap(1, 2);
+                }
+            }
+            if indices.len() / 3 > opts.max_faces {
+                return Err(P3DError::MeshTooLarge { faces: indices.len() / 3, limit: opts.max_faces });
+            }
+            let material = primitive.material().index().map(|i| i as u32).unwrap_or(NO_MATERIAL);
+            materials.extend(repeat(material).take((indices.len() - start) / 3));
+        }
+    }
+
+    Ok((positions, indices, materials))
+}
+
+// The input as the loaders read it: decompressed when gzipped (`gzip` feature), the
+// decompressed size bounded by the same limit
+fn plain_input<'i>(input: &'i [u8], opts: &ProcessOptions) -> Result<Cow<'i, [u8]>, P3DError> {
+    check_limit("input bytes", input.len(), opts.limits.max_input_bytes)?;
+    #[cfg(feature = "gzip")]
+    {
+        if gzip::is_gzip(input) {
+            return Ok(Cow::Owned(gzip::decompress(input, opts.limits.max_input_bytes)?));
+        }
+    }
+    Ok(Cow::Borrowed(input))
+}
+
+fn load_geometry(input: &[u8], file_type: InputFileType, opts: &ProcessOptions) -> Result<Geometry, P3DError> {
+    load_geometry_with(input, file_type, opts, None)
+}
+
+fn load_geometry_with(input: &[u8], file_type: InputFileType, opts: &ProcessOptions, load_uri: Option<UriLoader>) -> Result<Geometry, P3DError> {
+    report_progress(opts, Stage::Parse, 0, 1);
+    let input = &*plain_input(input, opts)?;
+    let file_type = file_type.resolve(input)?;
+    // Only glTF has cameras to view the model from
+    if opts.view_camera.is_some() && !matches!(file_type, InputFileType::Gltf | InputFileType::Glb) {
+        return Err(P3DError::UnsupportedFileType);
+    }
+    let mut materials: Vec<u32> = Vec::new();
+    let (model_vertices, model_indices): (Vec<f64>, Vec<u32>) = match file_type {
+        InputFileType::Obj => {
+            // Vertices split at normal or texture seams share their position again
+            let (positions, indices) = obj_geometry(input)?;
+            weld::merge_coincident(positions, indices)
+        }
+        InputFileType::Stl => stl::parse(input)?,
+        InputFileType::Ply => ply::parse(input, &opts.limits)?,
+        InputFileType::Xyz => (xyz::parse(input)?, Vec::new()),
+        InputFileType::Off => off::parse(input)?,
+        InputFileType::ThreeMf => three_mf_geometry(input, opts)?,
+        InputFileType::Gltf | InputFileType::Glb => {
+            #[cfg(feature = "draco")]
+            let parsed = draco::parse(input);
+            #[cfg(not(feature = "draco"))]
+            let parsed = Gltf::from_slice(input);
+            let gltf_data = parsed.map_err(|e| P3DError::ParseError {
+                format: Format::Gltf,
+                offset: match e {
+                    gltf::Error::Deserialize(e) => Offset::Line(e.line()),
+                    _ => Offset::Unknown,
+                },
+                reason: if file_type == InputFileType::Glb { "not valid GLB" } else { "not valid glTF" },
+            })?;
+            let (positions, indices, primitive_materials) = gltf_geometry(&gltf_data, opts, load_uri)?;
+            if positions.is_empty() || indices.is_empty() {
+                // Without the feature a required extension fails the parsing, but Draco may also
+                // be merely used
+                return Err(gltf_error(if !cfg!(feature = "draco") && gltf_data.extensions_used().any(|e| e == DRACO_EXTENSION) {
+                    "no geometry, Draco compressed primitives need the `draco` feature"
+                } else {
+                    "no geometry (vertices and indices)"
+                }));
+            }
+            materials = primitive_materials;
+
+            (gltf_positions(&gltf_data, positions, opts)?, indices)
+        }
+        // Resolved above
+        InputFileType::Auto => return Err(P3DError::UnsupportedFileType),
+    };
+
+    trace_event!("parse", "file_type={:?} bytes={} vertices={} faces={}", file_type, input.len(), model_vertices.len() / 3, model_indices.len() / 3);
+    report_progress(opts, Stage::Parse, 1, 1);
+    Ok(Geometry {
+        positions: model_vertices,
+        indices: model_indices,
+        materials,
+    })
+}
+
+#[cfg(feature = "zip")]
+fn three_mf_geometry(input: &[u8], opts: &ProcessOptions) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    three_mf::parse(input, &opts.limits)
+}
+
+#[cfg(not(feature = "zip"))]
+fn three_mf_geometry(_input: &[u8], _opts: &ProcessOptions) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    Err(P3DError::UnsupportedFileType)
+}
+
+// Coordinates are read as f32 like the `obj` crate reads them, so the feature doesn't change
+// the hashes
+#[cfg(feature = "obj_parser")]
+fn obj_geometry(input: &[u8]) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    let model = obj_parser::parse(input, Precision::F32)?;
+    Ok((model.positions, model.indices))
+}
+
+#[cfg(not(feature = "obj_parser"))]
+fn obj_geometry(input: &[u8]) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    obj_crate_geometry(input)
+}
+
+// OBJ through the `obj` crate: f32 positions, and only triangles with normals are accepted
+#[cfg_attr(all(feature = "obj_parser", not(test)), allow(dead_code))]
+fn obj_crate_geometry(input: &[u8]) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    let model: Obj<Vertex, u32> = load_obj(input).map_err(|e| P3DError::InvalidObject(e))?;
+    let vertices = model.vertices
+        .iter()
+        .flat_map(|v| v.position.iter())
+        .map(|v| <f64 as NumCast>::from(*v).unwrap())
+        .collect();
+    Ok((vertices, model.indices))
+}
+
+fn load_mesh(input: &[u8], file_type: InputFileType, opts: &ProcessOptions) -> Result<(Mesh, Vec<u32>), P3DError> {
+    build_mesh_opts(load_geometry(input, file_type, opts)?, opts)
+}
+
+fn build_mesh_opts(geometry: Geometry, opts: &ProcessOptions) -> Result<(Mesh, Vec<u32>), P3DError> {
+    geometry_mesh(checked_geometry(geometry, opts)?)
+}
+
+// The geometry a mesh is built from: within the limits of `opts`, valid and repaired
+fn checked_geometry(geometry: Geometry, opts: &ProcessOptions) -> Result<Geometry, P3DError> {
+    report_progress(opts, Stage::BuildMesh, 0, 1);
+    let faces = geometry.indices.len() / 3;
+    if faces > opts.max_faces {
+        return Err(P3DError::MeshTooLarge { faces, limit: opts.max_faces });
+    }
+    let vertices = geometry.positions.len() / 3;
+    check_limit("vertices", vertices, opts.limits.max_vertices)?;
+    check_vertex_count(vertices)?;
+    check_limit("allocation bytes", mesh_allocation(vertices, faces), opts.limits.max_allocation)?;
+    let geometry = clean_geometry(geometry, opts.weld_epsilon, opts.repair)?;
+    let geometry = match opts.decimation {
+        Some(target) => decimated(geometry, target)?,
+        None => geometry,
+    };
+    degenerate::check_geometry_span(&geometry.positions, &geometry.indices)?;
+    report_progress(opts, Stage::BuildMesh, 1, 1);
+    Ok(geometry)
+}
+
+fn decimated(geometry: Geometry, target: Decimation) -> Result<Geometry, P3DError> {
+    if let Decimation::MaxError(e) = target {
+        if !(e.is_finite() && e >= 0.0) {
+            return Err(P3DError::InvalidParams { param: "decimation", reason: "error must be positive and finite" });
+        }
+    }
+    let (positions, indices, kept) = decimate::decimate(&geometry.positions, &geometry.indices, target);
+    let materials = geometry.materials.into_iter().zip(kept).filter(|(_, keep)| *keep).map(|(m, _)| m).collect();
+    Ok(Geometry { positions, indices, materials })
+}
+
+#[cfg(test)]
+fn build_mesh(geometry: Geometry, weld_epsilon: Option<f64>, repair: Repair) -> Result<(Mesh, Vec<u32>), P3DError> {
+    geometry_mesh(clean_geometry(geometry, weld_epsilon, repair)?)
+}
+
+fn invalid_mesh(geometry: &Geometry, reason: &'static str) -> P3DError {
+    P3DError::InvalidMesh { reason, indices: geometry.indices.len(), vertices: geometry.positions.len() / 3 }
+}
+
+// Validation, welding and repairs of the geometry, in the order the mesh is built from
+fn clean_geometry(geometry: Geometry, weld_epsilon: Option<f64>, repair: Repair) -> Result<Geometry, P3DError> {
+    if geometry.indices.len() % 3 != 0 {
+        return Err(P3DError::MalformedIndices { indices: geometry.indices.len(), vertices: geometry.positions.len() / 3 });
+    }
+    // `Mesh::new` panics on indices past the last vertex, and its own errors say little
+    let n_vertices = geometry.positions.len() / 3;
+    let max_index = geometry.indices.iter().max().cloned();
+    if geometry.positions.len() % 3 != 0 {
+        return Err(invalid_mesh(&geometry, "coordinate count is not a multiple of 3"));
+    }
+    if max_index.map_or(false, |i| i as usize >= n_vertices) {
+        return Err(invalid_mesh(&geometry, "index out of range"));
+    }
+    // Would turn the alignment and every section into NaN
+    let finite = |v: u32| geometry.positions[v as usize * 3..v as usize * 3 + 3].iter().all(|c| c.is_finite());
+    if let Some(&vertex) = geometry.indices.iter().find(|&&v| !finite(v)) {
+        return Err(P3DError::NonFiniteCoordinate { vertex: vertex as usize });
+    }
+    let (positions, indices, materials) = match weld_epsilon {
+        Some(epsilon) => {
+            let (positions, indices, kept) = weld::weld(&geometry.positions, &geometry.indices, epsilon);
+            let materials = geometry.materials.into_iter().zip(kept).filter(|(_, keep)| *keep).map(|(m, _)| m).collect();
+            (positions, indices, materials)
+        }
+        None => (geometry.positions, geometry.indices, geometry.materials),
+    };
+    let (indices, materials) = if repair.drop_degenerate {
+        let (indices, kept) = weld::drop_degenerate(&positions, &indices);
+        (indices, materials.into_iter().zip(kept).filter(|(_, keep)| *keep).map(|(m, _)| m).collect())
+    } else {
+        (indices, materials)
+    };
+    let (indices, materials) = if repair.largest_component {
+        let (indices, kept) = components::largest_component(&positions, &indices);
+        (indices, materials.into_iter().zip(kept).filter(|(_, keep)| *keep).map(|(m, _)| m).collect())
+    } else {
+        (indices, materials)
+    };
+    let (positions, indices) = if repair.prune_unused { weld::prune_unused(positions, indices) } else { (positions, indices) };
+    Ok(Geometry { positions, indices, materials })
+}
+
+// Charges of the geometry and the mesh built from it, held until the hashes are done
+fn charge_geometry(geometry: &Geometry, opts: &ProcessOptions) -> Result<Option<Charge>, P3DError> {
+    charge(opts, "geometry bytes", geometry_bytes(geometry.positions.len() / 3, geometry.indices.len() / 3))
+}
+
+fn charge_mesh(geometry: &Geometry, opts: &ProcessOptions) -> Result<Option<Charge>, P3DError> {
+    charge(opts, "mesh bytes", mesh_bytes(geometry.positions.len() / 3, geometry.indices.len() / 3))
+}
+
+// Mesh of geometry `clean_geometry` went through, with the material of every face
+fn geometry_mesh(geometry: Geometry) -> Result<(Mesh, Vec<u32>), P3DError> {
+    let error = invalid_mesh(&geometry, "mesh build failed");
+    let mesh = MeshBuilder::new()
+        .with_indices(geometry.indices)
+        .with_positions(geometry.positions)
+        .build()
+        .map_err(|_| error)?;
+    Ok((mesh, geometry.materials))
+}
+
+// Translation and rotation bringing the mesh to its principal inertia axes
+fn pit_transform(mesh: &Mesh, weighting: InertiaWeighting) -> Result<(Vector3<f64>, Matrix4<f64>), P3DError> {
+    pit_transform_in(mesh, weighting, None, false)
+}
+
+fn in_region(p: &Vector3<f64>, region: &(Vector3<f64>, Vector3<f64>)) -> bool {
+    let (min, max) = region;
+    p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y && p.z >= min.z && p.z <= max.z
+}
+
+fn face_triangles(mesh: &Mesh, faces: &[FaceID]) -> Array3<f64> {
+    let mut triangles: Array3<f64> = Array3::zeros((faces.len(), 3, 3));
+
+    for (i, &fid) in faces.iter().enumerate() {
+        let vs = mesh.face_vertices(fid);
+        let v1 = mesh.vertex_position(vs.0);
+        let v2 = mesh.vertex_position(vs.1);
+        let v3 = mesh.vertex_position(vs.2);
+        triangles.slice_mut(s![i, .., ..])
+            .assign(
+                &arr2(&[
+                    [v1.x as f64, v1.y as f64, v1.z as f64],
+                    [v2.x as f64, v2.y as f64, v2.z as f64],
+                    [v3.x as f64, v3.y as f64, v3.z as f64],
+                ]
+                ));
+    }
+    triangles
+}
+
+fn canonical_triangles(mesh: &Mesh, faces: &[FaceID]) -> Array3<f64> {
+    let corner = |v: VertexID| {
+        let p = mesh.vertex_position(v);
+        [p.x, p.y, p.z]
+    };
+    canonical_order_triangles(faces.iter()
+        .map(|&fid| {
+            let (a, b, c) = mesh.face_vertices(fid);
+            [corner(a), corner(b), corner(c)]
+        })
+        .collect())
+}
+
+// Every triangle started at its smallest corner, the triangles in order of those corners
+fn canonical_order_triangles(mut corners: Vec<[[f64; 3]; 3]>) -> Array3<f64> {
+    for t in corners.iter_mut() {
+        let first = (0..3).min_by(|&i, &j| t[i].partial_cmp(&t[j]).unwrap_or(core::cmp::Ordering::Equal)).unwrap_or(0);
+        t.rotate_left(first);
+    }
+    corners.sort_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+    triangle_array(&corners)
+}
+
+fn triangle_array(corners: &[[[f64; 3]; 3]]) -> Array3<f64> {
+    let mut triangles: Array3<f64> = Array3::zeros((corners.len(), 3, 3));
+    for (i, t) in corners.iter().enumerate() {
+        triangles.slice_mut(s![i, .., ..]).assign(&arr2(t));
+    }
+    triangles
+}
+
+// Same as `pit_transform`, computed only from the faces (or vertices) inside `region`.
+// The volume integrals depend on which corner a triangle starts at, `canonical` starts every
+// triangle at its smallest corner and takes them in order of those corners.
+fn pit_transform_in(mesh: &Mesh, weighting: InertiaWeighting, region: Option<&(Vector3<f64>, Vector3<f64>)>, canonical: bool) -> Result<(Vector3<f64>, Matrix4<f64>), P3DError> {
+    let faces: Vec<FaceID> = mesh.face_iter()
+        .filter(|&fid| region.map_or(true, |r| in_region(&mesh.face_center(fid), r)))
+        .collect();
+    if faces.is_empty() {
+        return Err(P3DError::DegenerateMesh);
+    }
+    let triangles = if canonical { canonical_triangles(mesh, &faces) } else { face_triangles(mesh, &faces) };
+    let vertices = || mesh.vertex_iter()
+        .map(|vid| mesh.vertex_position(vid))
+        .filter(|p| region.map_or(true, |r| in_region(p, r)))
+        .collect();
+    weighted_pit(triangles, vertices, weighting)
+}
+
+// Alignment of `pit_transform_in` from the triangles, or the `vertices` with
+// `InertiaWeighting::Vertices`
+fn weighted_pit<V>(triangles: Array3<f64>, vertices: V, weighting: InertiaWeighting) -> Result<(Vector3<f64>, Matrix4<f64>), P3DError>
+    where V: FnOnce() -> Vec<Vector3<f64>>
+{
+    let (center, inertia) = weighted_inertia(triangles, vertices, weighting)?;
+    pit_matrices(&algo_grid::inertia_transform(center, inertia))
+}
+
+// Center and inertia tensor (layout of `algo_grid::mass_properties`) `weighted_pit` aligns by
+fn weighted_inertia<V>(triangles: Array3<f64>, vertices: V, weighting: InertiaWeighting) -> Result<(Array1<f64>, Array2<f64>), P3DError>
+    where V: FnOnce() -> Vec<Vector3<f64>>
+{
+    let (center, inertia) = match weighting {
+        // Not checked: with inverted or overlapping parts the volume tensor isn't that of a
+        // solid and may have a zero moment, and flat objects are rejected before
+        InertiaWeighting::Volume => {
+            let (center, inertia) = algo_grid::mass_properties(triangles);
+            trace_event!("align", "weighting={:?} moments={:?}", weighting, algo_grid::principal_moments(inertia.clone()));
+            return Ok((center, inertia));
+        }
+        InertiaWeighting::Surface => algo_grid::surface_mass_properties(&triangles),
+        InertiaWeighting::Vertices => {
+            let vertices = vertices();
+            if vertices.is_empty() {
+                return Err(P3DError::DegenerateMesh);
+            }
+            let mut points: Array2<f64> = Array2::zeros((vertices.len(), 3));
+            for (i, p) in vertices.iter().enumerate() {
+                points.row_mut(i).assign(&arr1(&[p.x, p.y, p.z]));
+            }
+            algo_grid::point_mass_properties(&points)
+        }
+    };
+    trace_event!("align", "weighting={:?} moments={:?}", weighting, algo_grid::principal_moments(inertia.clone()));
+    degenerate::check_inertia(&inertia)?;
+    Ok((center, inertia))
+}
+
+// Translation and rotation of a 4x4 principal inertia transform
+fn pit_matrices(pit: &Array2<f64>) -> Result<(Vector3<f64>, Matrix4<f64>), P3DError> {
+    let a: Matrix3<f64> = Matrix3::new(
+        pit[[0, 0]], pit[[0, 1]], pit[[0, 2]],
+        pit[[1, 0]], pit[[1, 1]], pit[[1, 2]],
+        pit[[2, 0]], pit[[2, 1]], pit[[2, 2]],
+    );
+
+    let b = a.invert().ok_or(P3DError::MathError)?;
+
+    let tr: Matrix4<f64> = Matrix4::new(
+        b.x[0], b.x[1], b.x[2], 0.0,
+        b.y[0], b.y[1], b.y[2], 0.0,
+        b.z[0], b.z[1], b.z[2], 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    );
+
+    let shift = Vector3::new(pit[[0, 3]], pit[[1, 3]], pit[[2, 3]]);
+
+    Ok((shift, tr))
+}
+
+// Axis flips for `ProcessOptions::orient_axes` and `reflection`: x and y point to where the
+// third moment of the (centered) vertices along them is positive. z is their cross product,
+// after `rotation` (the PIT pose) is made proper for `Reflection::Distinguish`, or oriented by
+// its own third moment for `Reflection::Ignore`. The principal axes only fix the directions, so
+// without this the signs are whatever the eigen solver returns.
+fn axis_orientation(mesh: &Mesh, rotation: &Matrix4<f64>, reflection: Reflection) -> Matrix4<f64> {
+    let mut skew = [sum::CompensatedSum::default(), sum::CompensatedSum::default(), sum::CompensatedSum::default()];
+    for vid in mesh.vertex_iter() {
+        let p = mesh.vertex_position(vid);
+        for (axis, s) in skew.iter_mut().enumerate() {
+            s.add(p[axis] * p[axis] * p[axis]);
+        }
+    }
+    let sign = |s: &sum::CompensatedSum| if s.value() < 0.0 { -1.0 } else { 1.0 };
+    let (sx, sy) = (sign(&skew[0]), sign(&skew[1]));
+    let sz = match reflection {
+        Reflection::Unspecified => sx * sy,
+        Reflection::Distinguish => if rotation.determinant() < 0.0 { -sx * sy } else { sx * sy },
+        Reflection::Ignore => sign(&skew[2]),
+    };
+    Matrix4::from_nonuniform_scale(sx, sy, sz)
+}
+
+/// Moves `mesh` to its principal inertia axes (volume weighted), the alignment `p3d_process`
+/// applies before slicing, so meshes aligned by another pipeline hash the same. The center of
+/// mass goes to the origin, then the mesh is rotated by the inverse of the principal axes of
+/// `principal_inertia_transform`. Fails with `P3DError::MathError` when they are singular.
+pub fn align_mesh(mesh: &mut Mesh) -> Result<(), P3DError> {
+    let (shift, tr) = pit_transform(mesh, InertiaWeighting::Volume)?;
+    mesh.translate(shift);
+    mesh.apply_transformation(tr);
+    Ok(())
+}
+
+// Radius of gyration of the surface of `mesh` as a thin shell of uniform density, around its
+// own centroid. Over a triangle (a, b, c) with centroid g the mean of |p|^2 is
+// (|a|^2 + |b|^2 + |c|^2 + 9 |g|^2) / 12.
+fn surface_gyration_radius(mesh: &Mesh) -> f64 {
+    let (mut area, mut first, mut second) = (sum::CompensatedSum::default(), [sum::CompensatedSum::default(); 3], sum::CompensatedSum::default());
+    for face_id in mesh.face_iter() {
+        let (a, b, c) = mesh.face_positions(face_id);
+        let w = (b - a).cross(c - a).magnitude() / 2.0;
+        let g = (a + b + c) / 3.0;
+        area.add(w);
+        for (axis, f) in first.iter_mut().enumerate() {
+            f.add(w * g[axis]);
+        }
+        second.add(w * (a.magnitude2() + b.magnitude2() + c.magnitude2() + 9.0 * g.magnitude2()) / 12.0);
+    }
+    let area = area.value();
+    let center = Vector3::new(first[0].value(), first[1].value(), first[2].value()) / area;
+    libm::sqrt((second.value() / area - center.magnitude2()).max(0.0))
+}
+
+fn normalization_matrix(mesh: &Mesh, normalization: Normalization) -> Result<Option<Matrix4<f64>>, P3DError> {
+    if normalization == Normalization::Gyration {
+        let radius = surface_gyration_radius(mesh);
+        if !(radius > 0.0 && radius.is_finite()) {
+            return Err(P3DError::DegenerateMesh);
+        }
+        return Ok(Some(Matrix4::from_scale(1.0 / radius)));
+    }
+    let (v_min, v_max) = mesh.extreme_coordinates();
+    let center = match normalization {
+        Normalization::None | Normalization::Gyration => return Ok(None),
+        Normalization::ScaleOnly => Vector3::new(0.0, 0.0, 0.0),
+        Normalization::ScaleAndCenter | Normalization::Full => (v_min + v_max) / 2.0,
+    };
+    let diag = (v_max - v_min).magnitude();
+    if !(diag > 0.0) {
+        return Err(P3DError::DegenerateMesh);
+    }
+    Ok(Some(Matrix4::from_scale(1.0 / diag) * Matrix4::from_translation(-center)))
+}
+
+fn rotation_matrix(trans: Option<[u8;4]>) -> Option<Matrix4<f64>> {
+    let k = 45.0 / 256.0;
+    trans.map(|rot| {
+        let axis_normalized = Vector3::new(
+            rot[0] as f64 * k,
+            rot[1] as f64 * k,
+            rot[2] as f64 * k,
+        ).normalize();
+        Mat4::from_axis_angle(
+            axis_normalized,
+            Deg(rot[3] as f64 * k * 360.0 / 256.0),
+        )
+    })
+}
+
+// `TransEncoding::V2`
+fn rotation_matrix_v2(rot: [u8; 8]) -> Matrix4<f64> {
+    let azimuth = u16::from_le_bytes([rot[0], rot[1]]) as f64;
+    let height = u16::from_le_bytes([rot[2], rot[3]]) as f64;
+    let angle = u32::from_le_bytes([rot[4], rot[5], rot[6], rot[7]]) as f64;
+    let phi = 2.0 * core::f64::consts::PI * (azimuth + 0.5) / 65536.0;
+    let z = 1.0 - 2.0 * (height + 0.5) / 65536.0;
+    let r = libm::sqrt(1.0 - z * z);
+    let axis = Vector3::new(r * libm::cos(phi), r * libm::sin(phi), z);
+    Mat4::from_axis_angle(axis, Rad(2.0 * core::f64::consts::PI * angle / 4294967296.0))
+}
+
+// How far `Transform::Matrix` may be from orthonormal, summed over the columns of M^T M - I
+const ROTATION_TOLERANCE: f64 = 1e-6;
+
+impl Transform {
+    fn matrix(&self) -> Result<Matrix4<f64>, P3DError> {
+        match *self {
+            Transform::Quantized(rot) => Ok(rotation_matrix(Some(rot)).unwrap()),
+            Transform::QuantizedV2(rot) => Ok(rotation_matrix_v2(rot)),
+            Transform::AxisAngle { axis, angle_deg } => {
+                let axis = Vector3::from(axis);
+                let length = axis.magnitude();
+                if !(length > 0.0 && length.is_finite() && angle_deg.is_finite()) {
+                    return Err(P3DError::MathError);
+                }
+                Ok(Mat4::from_axis_angle(axis / length, Deg(angle_deg)))
+            }
+            Transform::Quaternion(q) => {
+                let length = q.magnitude();
+                if !(length > 0.0 && length.is_finite()) {
+                    return Err(P3DError::MathError);
+                }
+                Ok(Matrix4::from(q / length))
+            }
+            Transform::Matrix(m) => {
+                let d = m.transpose() * m - Matrix3::identity();
+                let error = d.x.magnitude() + d.y.magnitude() + d.z.magnitude();
+                if !(error < ROTATION_TOLERANCE && m.determinant() > 0.0) {
+                    return Err(P3DError::MathError);
+                }
+                Ok(Matrix4::from(m))
+            }
+        }
+    }
+}
+
+fn transform_matrix(transform: Option<Transform>) -> Result<Option<Matrix4<f64>>, P3DError> {
+    transform.map(|t| t.matrix()).transpose()
+}
+
+#[cfg(feature = "std")]
+fn rotate_mesh(mesh: &mut Mesh, trans: Option<[u8;4]>) {
+    if let Some(rot) = rotation_matrix(trans) {
+        mesh.apply_transformation(rot);
+    }
+}
+
+#[derive(Clone)]
+struct Sections {
+    centers: Vec<Vec<Vec2>>,
+    heights: Vec<f64>,
+    materials: Vec<Vec<u32>>,
+    // Loops of every section, empty unless `trace_loops` is set
+    loops: Vec<Vec<Vec<Vec2>>>,
+    rect: Rect,
+    // Number of sections actually taken, may be below the requested one (see `min_step`)
+    n_sections: i16,
+    out_of_bounds: usize,
+}
+
+// Bins per section of the profile `adaptive_heights` places sections by
+const ADAPTIVE_BINS: usize = 16;
+
+// Heights of `n_sections` planes strictly between `z_min` and `z_max` for
+// `SectionPlacement::Adaptive`. Between two heights the cross-section area changes by at most
+// the area of the faces in between projected on the XY plane, so that area is spread over
+// fine bins along z, every face evenly over its own z range. As much weight again is spread
+// evenly over all bins, so featureless stretches still get sections. The planes cut the
+// total weight into `n_sections + 1` equal parts.
+fn adaptive_heights(mesh: &Mesh, z_min: f64, z_max: f64, n_sections: usize) -> Vec<f64> {
+    let bins = n_sections.max(1) * ADAPTIVE_BINS;
+    let bin_size = (z_max - z_min) / bins as f64;
+    let bin = |z: f64| (((z - z_min) / bin_size) as usize).min(bins - 1);
+    let mut weights = vec![0.0f64; bins];
+    for face_id in mesh.face_iter() {
+        let (a, b, c) = mesh.face_positions(face_id);
+        let projected = ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)).abs() / 2.0;
+        if !(projected > 0.0) || !projected.is_finite() {
+            continue;
+        }
+        let (lo, hi) = (a.z.min(b.z).min(c.z), a.z.max(b.z).max(c.z));
+        let (first, last) = (bin(lo), bin(hi));
+        if first == last {
+            weights[first] += projected;
+            continue;
+        }
+        for (k, w) in weights.iter_mut().enumerate().take(last + 1).skip(first) {
+            let overlap = hi.min(z_min + (k + 1) as f64 * bin_size) - lo.max(z_min + k as f64 * bin_size);
+            if overlap > 0.0 {
+                *w += projected * overlap / (hi - lo);
+            }
+        }
+    }
+    let total: f64 = weights.iter().sum();
+    let even = if total > 0.0 { total / bins as f64 } else { 1.0 };
+    let mut cumulative = Vec::with_capacity(bins);
+    let mut sum = 0.0;
+    for w in weights.iter() {
+        sum += w + even;
+        cumulative.push(sum);
+    }
+    (1..=n_sections)
+        .map(|k| {
+            let target = sum * k as f64 / (n_sections + 1) as f64;
+            let i = cumulative.partition_point(|&c| c < target).min(bins - 1);
+            let before = if i == 0 { 0.0 } else { cumulative[i - 1] };
+            let within = ((target - before) / (weights[i] + even)).max(0.0).min(1.0);
+            z_min + (i as f64 + within) * bin_size
+        })
+        .collect()
+}
+
+fn section_step(extent: f64, n_sections: i16, min_step: f64) -> Result<(i16, f64), P3DError> {
+    let step = extent / (1.0f64 + n_sections as f64);
+    if step >= min_step {
+        return Ok((n_sections, step));
+    }
+    let n = libm::floor(extent / min_step) - 1.0;
+    if !(n >= 1.0) {
+        return Err(P3DError::DegenerateMesh);
+    }
+    let n = n as i16;
+    Ok((n, extent / (1.0f64 + n as f64)))
+}
+
+// Applies `policy` to the points of `cntr` outside `rect`, returns how many there were
+fn bound_contour(cntr: &mut Vec<Vec2>, tags: &mut Vec<u32>, rect: &Rect, policy: OobPolicy) -> usize {
+    let outside = cntr.iter().filter(|p| !rect.contains(p)).count();
+    if outside == 0 {
+        return 0;
+    }
+    match policy {
+        OobPolicy::Keep => {}
+        OobPolicy::Clamp => cntr.iter_mut().for_each(|p| *p = rect.clamp(p)),
+        OobPolicy::Drop => {
+            if !tags.is_empty() {
+                *tags = cntr.iter().zip(tags.iter()).filter(|(p, _)| rect.contains(p)).map(|(_, t)| *t).collect();
+            }
+            cntr.retain(|p| rect.contains(p));
+        }
+    }
+    outside
+}
+
+fn slice_mesh(mesh: &Mesh, face_materials: &[u32], algo: &AlgoType, depth: usize, n_sections: i16, opts: &ProcessOptions) -> Result<Sections, P3DError> {
+    slice_mesh_ranges(mesh, face_materials, algo, depth, n_sections, None, opts)
+}
+
+// `slice_mesh` with the `ZRanges` of the mesh when they are already known
+fn slice_mesh_ranges(mesh: &Mesh, face_materials: &[u32], algo: &AlgoType, depth: usize, n_sections: i16, ranges: Option<&ZRanges>, opts: &ProcessOptions) -> Result<Sections, P3DError> {
+    let (v_min, v_max) = mesh.extreme_coordinates();
+    let (n_sections, step) = section_step(v_max.z - v_min.z, n_sections, opts.min_step)?;
+    match ranges {
+        Some(ranges) => slice_mesh_steps(mesh, face_materials, ranges, depth, n_sections, step, opts),
+        None => slice_mesh_steps(mesh, face_materials, &ZRanges::new(mesh, slices_edges(algo)), depth, n_sections, step, opts),
+    }
+}
+
+// Whether `algo` cuts its sections from the edges crossing the planes (`intersect_2`) rather
+// than from the vertices near them
+fn slices_edges(algo: &AlgoType) -> bool {
+    matches!(algo, AlgoType::Grid2dV3a | AlgoType::Skeleton | AlgoType::CellStats | AlgoType::GridNormals)
+}
+
+// Sections every `spacing` units from the bottom, as many as fit strictly below the top
+fn slice_mesh_spacing(mesh: &Mesh, face_materials: &[u32], algo: &AlgoType, depth: usize, spacing: f64, opts: &ProcessOptions) -> Result<Sections, P3DError> {
+    let (v_min, v_max) = mesh.extreme_coordinates();
+    let extent = v_max.z - v_min.z;
+    if !(spacing >= opts.min_step) {
+        return Err(P3DError::DegenerateMesh);
+    }
+    let mut n = libm::floor(extent / spacing);
+    if n * spacing >= extent {
+        n -= 1.0;
+    }
+    if !(n >= 1.0) || n > i16::MAX as f64 {
+        return Err(P3DError::DegenerateMesh);
+    }
+    slice_mesh_steps(mesh, face_materials, &ZRanges::new(mesh, slices_edges(algo)), depth, n as i16, spacing, opts)
+}
+
+fn point_order(a: &Vec2, b: &Vec2) -> core::cmp::Ordering {
+    (a.x, a.y).partial_cmp(&(b.x, b.y)).unwrap_or(core::cmp::Ordering::Equal)
+}
+
+// One section of `slice_mesh_steps`, before empty ones are dropped
+struct SectionCut {
+    z: f64,
+    cntr: Vec<Vec2>,
+    tags: Vec<u32>,
+    loops: Vec<Vec<Vec2>>,
+    out_of_bounds: usize,
+}
+
+// Sections cut from the edges or the vertices, whichever `ranges` are of
+fn slice_mesh_steps(mesh: &Mesh, face_materials: &[u32], ranges: &ZRanges, depth: usize, n_sections: i16, step: f64, opts: &ProcessOptions) -> Result<Sections, P3DError> {
+    check_limit("sections", n_sections.max(0) as usize, opts.limits.max_sections)?;
+    let (v_min, v_max) = mesh.extreme_coordinates();
+    let rect = Rect::new(v_min.x, v_max.x, v_min.y, v_max.y);
+    let face_tags: BTreeMap<FaceID, u32> = if opts.trace_loops && opts.tag_materials {
+        mesh.face_iter().zip(face_materials.iter().cloned()).collect()
+    } else {
+        BTreeMap::new()
+    };
+
+    let materials: &[u32] = if opts.tag_materials && !opts.trace_loops { face_materials } else { &[] };
+    let (heights, index) = match opts.section_placement {
+        SectionPlacement::Uniform => {
+            let heights = (0..n_sections).map(|n| v_min.z + (n as f64 + 1.0f64) * step).collect();
+            (heights, SectionIndex::new(mesh, ranges, v_min.z, step, n_sections.max(0) as usize, materials))
+        }
+        SectionPlacement::Adaptive => {
+            let heights = adaptive_heights(mesh, v_min.z, v_max.z, n_sections.max(0) as usize);
+            let index = SectionIndex::with_heights(mesh, ranges, &heights, materials);
+            (heights, index)
+        }
+    };
+
+    let faces = if opts.trace_loops { loops::mesh_faces(mesh) } else { Vec::new() };
+    // Sections are independent of each other, and cut from data copied out of the mesh
+    let cut = |n: i16| {
+        let z_sect = heights[n as usize];
+        if matches!(&opts.cancel, Some(cancel) if cancel.is_cancelled()) {
+            return SectionCut { z: z_sect, cntr: Vec::new(), tags: Vec::new(), loops: Vec::new(), out_of_bounds: 0 };
+        }
+        let mut traced: Vec<Vec<Vec2>> = Vec::new();
+        let (cntr, tags) = if opts.trace_loops {
+            let mut cntr = Vec::new();
+            let mut tags = Vec::new();
+            for l in loops::section_loops(&faces, z_sect) {
+                cntr.extend(l.iter().map(|(p, _)| *p));
+                if opts.tag_materials {
+                    tags.extend(l.iter().map(|(_, f)| face_tags.get(f).cloned().unwrap_or(NO_MATERIAL)));
+                }
+                traced.push(l.into_iter().map(|(p, _)| p).collect());
+            }
+            (cntr, tags)
+        } else if opts.tag_materials {
+            let mut sect = if ranges.edges() {
+                index.intersect_2_tagged(n as usize, z_sect, step * opts.intersect_tolerance)
+            } else {
+                index.intersect_tagged(n as usize, z_sect)
+            };
+            if opts.canonical_order {
+                sect.sort_by(|a, b| point_order(&a.0, &b.0).then(a.1.cmp(&b.1)));
+            }
+            get_contour_tagged(sect)
+        } else {
+            let mut sect = if ranges.edges() {
+                index.intersect_2(n as usize, z_sect, step * opts.intersect_tolerance)
+            } else {
+                index.intersect(n as usize, z_sect)
+            };
+            if opts.canonical_order {
+                sect.sort_by(point_order);
+            }
+            (get_contour(sect), Vec::new())
+        };
+        finish_cut(z_sect, cntr, tags, traced, &rect, opts)
+    };
+    let n_cut = AtomicUsize::new(0);
+    let cut = |n: i16| {
+        let section = cut(n);
+        report_section(opts, &n_cut, n_sections.max(0) as usize);
+        section
+    };
+    report_progress(opts, Stage::Slice, 0, n_sections.max(0) as usize);
+    #[cfg(feature = "parallel")]
+    let cuts: Vec<SectionCut> = {
+        use rayon::prelude::*;
+        (0..n_sections).into_par_iter().map(cut).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let cuts: Vec<SectionCut> = (0..n_sections).map(cut).collect();
+    collect_sections(cuts, rect, n_sections, depth, opts)
+}
+
+// Simplification and `OobPolicy` applied to the contour of the section at `z`
+fn finish_cut(z: f64, cntr: Vec<Vec2>, tags: Vec<u32>, loops: Vec<Vec<Vec2>>, rect: &Rect, opts: &ProcessOptions) -> SectionCut {
+    let (cntr, tags) = match opts.simplify_tolerance {
+        Some(tolerance) => {
+            let keep = simplify_indices(&cntr, tolerance);
+            let tags = if tags.is_empty() { tags } else { keep.iter().map(|&i| tags[i]).collect() };
+            (keep.iter().map(|&i| cntr[i]).collect(), tags)
+        }
+        None => (cntr, tags),
+    };
+    let (mut cntr, mut tags) = (cntr, tags);
+    let out_of_bounds = bound_contour(&mut cntr, &mut tags, rect, opts.out_of_bounds);
+    SectionCut { z, cntr, tags, loops, out_of_bounds }
+}
+
+// `Sections` of the cuts of a slicing, bottom to top, without the empty ones
+fn collect_sections(cuts: Vec<SectionCut>, rect: Rect, n_sections: i16, depth: usize, opts: &ProcessOptions) -> Result<Sections, P3DError> {
+    // Sections cut after a cancellation are left empty
+    check_cancel(opts)?;
+
+    let mut out_of_bounds = 0;
+    let mut centers: Vec<Vec<Vec2>> = Vec::with_capacity(depth);
+    let mut heights: Vec<f64> = Vec::with_capacity(depth);
+    let mut materials: Vec<Vec<u32>> = Vec::new();
+    let mut section_loops: Vec<Vec<Vec<Vec2>>> = Vec::new();
+    for cut in cuts {
+        // Before sections without points are dropped: `Spectr` gives no hashes when any is
+        trace_event!("sections", "z={} points={} loops={} out_of_bounds={}", cut.z, cut.cntr.len(), cut.loops.len(), cut.out_of_bounds);
+        out_of_bounds += cut.out_of_bounds;
+        if cut.cntr.len() > 0 {
+            centers.push(cut.cntr);
+            heights.push(cut.z);
+            if opts.tag_materials {
+                materials.push(cut.tags);
+            }
+            if opts.trace_loops {
+                section_loops.push(cut.loops);
+            }
+        }
+    }
+    Ok(Sections { centers, heights, materials, loops: section_loops, rect, n_sections, out_of_bounds })
+}
+
+// Drops contour points in grid cells hit by fewer than `min_support` sections, then sections left empty
+fn filter_section_support(sections: &mut Sections, grid_size: i16, min_support: usize) {
+    if min_support <= 1 || grid_size <= 0 {
+        return;
+    }
+    let n = grid_size as i32;
+    let mut support: BTreeMap<(i32, i32), usize> = BTreeMap::new();
+    for cntr in sections.centers.iter() {
+        let cells: BTreeSet<(i32, i32)> = cntr.iter().map(|p| sections.rect.cell(p, n)).collect();
+        for cell in cells {
+            *support.entry(cell).or_insert(0) += 1;
+        }
+    }
+    let supported = |p: &Vec2| support.get(&sections.rect.cell(p, n)).cloned().unwrap_or(0) >= min_support;
+
+    let mut centers = Vec::with_capacity(sections.centers.len());
+    let mut heights = Vec::with_capacity(sections.heights.len());
+    let mut materials = Vec::with_capacity(sections.materials.len());
+    let mut loops = Vec::with_capacity(sections.loops.len());
+    for (k, cntr) in sections.centers.iter().enumerate() {
+        let keep: Vec<usize> = (0..cntr.len()).filter(|&i| supported(&cntr[i])).collect();
+        if keep.is_empty() {
+            continue;
+        }
+        centers.push(keep.iter().map(|&i| cntr[i]).collect());
+        heights.push(sections.heights[k]);
+        if let Some(tags) = sections.materials.get(k) {
+            materials.push(keep.iter().map(|&i| tags[i]).collect());
+        }
+        if let Some(l) = sections.loops.get(k) {
+            loops.push(l.clone());
+        }
+    }
+    sections.centers = centers;
+    sections.heights = heights;
+    sections.materials = materials;
+    sections.loops = loops;
+}
+
+fn snap_sections(sections: &mut Sections, step: f64) {
+    if !(step > 0.0) {
+        return;
+    }
+    let rect = &sections.rect;
+    for cntr in sections.centers.iter_mut() {
+        cntr.iter_mut().for_each(|p| *p = rect.snap(p, step));
+    }
+}
+
+fn find_top(algo: &AlgoType, sections: &Sections, depth: usize, grid_size: i16, hash_algo: HashAlgo, statistic: CellStatistic, cancel: Option<&Cancel>) -> Vec<RawHash> {
+    let centers = &sections.centers;
+    let n_sections = sections.n_sections;
+    let rect = sections.rect.clone();
+    match algo {
+        AlgoType::Grid2dV2 => find_top_std_2(centers, depth as usize, n_sections as usize, grid_size as usize, rect, hash_algo, cancel),
+        AlgoType::Grid2dV3 => find_top_std_3(centers, depth as usize, n_sections as usize, grid_size as usize, rect, hash_algo, cancel),
+        AlgoType::Grid2dV3a => find_top_std_4(centers, depth as usize, n_sections as usize, grid_size as usize, rect, hash_algo, cancel),
+        AlgoType::Skeleton => skeleton::find_top_skeleton(centers, grid_size, &rect, hash_algo),
+        AlgoType::Spectr => find_spectr(centers, depth, n_sections as usize, grid_size, rect, hash_algo),
+        AlgoType::CellStats => cell_stats::find_top_cell_stats(centers, depth, grid_size, &rect, statistic, hash_algo),
+        // Hashed from the mesh or the points, not from sections
+        AlgoType::Voxel | AlgoType::CloudGrid | AlgoType::FixedGrid | AlgoType::GridNormals => Vec::new(),
+        _ => find_top_std(centers, depth as usize, grid_size, rect, hash_algo, cancel),
+    }
+}
+
+// `find_top` with the fixed-point score (`ProcessOptions::fixed_point_scores`, which also
+// ranks by them) and the `HashDetail` (`ProcessOptions::hash_details`) of every hash, each empty
+// unless asked for. Algorithms without a total score give neither.
+fn find_top_scored(algo: &AlgoType, sections: &Sections, depth: usize, grid_size: i16, opts: &ProcessOptions, cancel: Option<&Cancel>) -> (Vec<RawHash>, Vec<u32>, Vec<HashDetail>) {
+    let centers = &sections.centers;
+    let n_sections = sections.n_sections as usize;
+    let rect = sections.rect.clone();
+    let cell_area = (rect.width() / grid_size as f64) * (rect.height() / grid_size as f64);
+    let scale = if opts.fixed_point_scores { Some(algo_grid::SCORE_SCALE / cell_area) } else { None };
+    let scored = match algo {
+        AlgoType::Grid2dV3 => find_top_std_3_scored(centers, depth, n_sections, grid_size as usize, rect, scale, opts.hash_algo, cancel),
+        AlgoType::Grid2dV3a => find_top_std_4_scored(centers, depth, n_sections, grid_size as usize, rect, scale, opts.hash_algo, cancel),
+        _ => return (find_top(algo, sections, depth, grid_size, opts.hash_algo, opts.cell_statistic, cancel), Vec::new(), Vec::new()),
+    };
+    let scores = match scale {
+        Some(k) => scored.iter().map(|(_, detail)| algo_grid::fixed_point(detail.score, k)).collect(),
+        None => Vec::new(),
+    };
+    let hashes = scored.iter().map(|(hash, _)| *hash).collect();
+    let details = if opts.hash_details { scored.into_iter().map(|(_, detail)| detail).collect() } else { Vec::new() };
+    (hashes, scores, details)
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    pub load: Duration,
+    pub pit: Duration,
+    pub slicing: Duration,
+    pub ranking: Duration,
+    pub total: Duration,
+}
+
+/// Same pipeline as `p3d_process`, with the wall-clock time spent in each stage:
+/// `load` covers parsing and mesh building, `pit` the principal inertia alignment and the
+/// optional rotation, `slicing` the sections/contours and `ranking` the grid search.
+#[cfg(feature = "std")]
+pub fn p3d_process_timed(input: &[u8], file_type: InputFileType, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<(Vec<String>, Timings), P3DError> {
+    let depth = 10;
+    let grid_size: i16 = par1;
+    let n_sections: i16 = par2;
+    check_params(algo, depth, par1, par2)?;
+    let mut timings = Timings::default();
+
+    let start = Instant::now();
+    let (mut mesh, face_materials) = load_mesh(input, file_type, &ProcessOptions::default())?;
+    timings.load = start.elapsed();
+
+    let t = Instant::now();
+    align_mesh(&mut mesh)?;
+    rotate_mesh(&mut mesh, trans);
+    timings.pit = t.elapsed();
+
+    let t = Instant::now();
+    let sections = slice_mesh(&mesh, &face_materials, &algo, depth, n_sections, &ProcessOptions::default())?;
+    timings.slicing = t.elapsed();
+
+    let t = Instant::now();
+    let res = find_top(&algo, &sections, depth, grid_size, HashAlgo::Sha256, CellStatistic::StdDev, None).iter().map(to_hex).collect();
+    timings.ranking = t.elapsed();
+
+    timings.total = start.elapsed();
+    Ok((res, timings))
+}
+
+/// Processes a GLB file by memory-mapping it instead of reading it into memory.
+///
+/// The mapping is only valid as long as the file is not modified: truncating or rewriting
+/// it while it is processed is undefined behaviour (typically a SIGBUS).
+#[cfg(feature = "mmap")]
+pub fn p3d_process_mmap(path: &std::path::Path, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError> {
+    let file = std::fs::File::open(path).map_err(|e| P3DError::Io(e))?;
+    // Safety: the caller guarantees the file isn't truncated while it's mapped, see above
+    let map = unsafe { memmap2::Mmap::map(&file) }.map_err(|e| P3DError::Io(e))?;
+    p3d_process(&map[..], InputFileType::Glb, algo, par1, par2, trans)
+}
+
+/// Processes a glTF/GLB model stored in a ZIP archive, with its buffers resolved from the same
+/// archive (relative to the model entry). `entry` names the model, by default the first
+/// `.gltf` or `.glb` entry is taken.
+#[cfg(feature = "zip")]
+pub fn p3d_process_zip(archive: &[u8], entry: Option<&str>, algo: AlgoType, par1: i16, par2: i16, trans: Option<[u8;4]>) -> Result<Vec<String>, P3DError> {
+    use alloc::string::ToString;
+    use core::cell::RefCell;
+
+    let opts = ProcessOptions::default();
+    let read_entry = |zip: &mut zip::ZipArchive<std::io::Cursor<&[u8]>>, name: &str| {
+        three_mf::read_entry(zip, name, opts.limits.max_input_bytes)
+    };
+
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(archive)).map_err(|e| P3DError::Zip(e))?;
+    let name = match entry {
+        Some(name) => name.to_string(),
+        None => zip.file_names()
+            .filter(|name| {
+                let name = name.to_ascii_lowercase();
+                name.ends_with(".gltf") || name.ends_with(".glb")
+            })
+            .min()
+            .ok_or(P3DError::Zip(zip::result::ZipError::FileNotFound))?
+            .to_string(),
+    };
+    let file_type = if name.to_ascii_lowercase().ends_with(".glb") { InputFileType::Glb } else { InputFileType::Gltf };
+    let input = read_entry(&mut zip, &name)?;
+
+    let dir = match name.rfind('/') {
+        Some(i) => &name[..=i],
+        None => "",
+    };
+    let zip = RefCell::new(zip);
+    let load_uri = |uri: &str| read_entry(&mut zip.borrow_mut(), &format!("{}{}", dir, uri)).ok();
+
+    let geometry = load_geometry_with(&input, file_type, &opts, Some(&load_uri))?;
+    let (mesh, face_materials) = build_mesh_opts(geometry, &opts)?;
+    process_mesh(mesh, &face_materials, algo, 10, par1, par2, trans, &opts).map(|res| res.hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_load_and_process_glb() {
+        let glb_bytes = include_bytes!("../test-ht.glb");
+        let result = p3d_process(
+            glb_bytes,
+            InputFileType::Glb,
+            AlgoType::Grid2d,
+            20,
+            10,
+            None,
+        );
+        assert!(result.is_ok(), "Processing test-ht.glb failed: {:?}", result.err());
+        if let Ok(output_strings) = result {
+            assert!(!output_strings.is_empty(), "Processing test-ht.glb produced no output strings");
+        }
+    }
+
+    #[test]
+    fn test_malformed_glb_data() {
+        let malformed_glb_bytes: &[u8] = b"this is not a glb";
+        let result = p3d_process(
+            malformed_glb_bytes,
+            InputFileType::Glb,
+            AlgoType::Grid2d,
+            20,
+            10,
+            None,
+        );
+        assert!(matches!(result, Err(P3DError::ParseError { format: Format::Gltf, reason: "not valid GLB", .. })), "Malformed GLB data did not produce a glTF ParseError: {:?}", result);
+    }
+
+    #[test]
+    fn test_gltf_no_geometry() {
+        let no_geometry_gltf_json = r#"
+        {
+          "asset": { "version": "2.0" },
+          "scene": 0,
+          "scenes": [ { "nodes": [] } ],
+          "meshes": []
+        }
+        "#;
+        let no_geometry_gltf_bytes = no_geometry_gltf_json.as_bytes();
+        let result = p3d_process(
+            no_geometry_gltf_bytes,
+            InputFileType::Gltf,
+            AlgoType::Grid2d,
+            20,
+            10,
+            None,
+        );
+        match result {
+            Err(P3DError::ParseError { format: Format::Gltf, offset: Offset::Unknown, reason }) => {
+                assert!(reason.starts_with("no geometry"), "Error reason for no geometry is incorrect: {}", reason);
+            }
+            other => panic!("Expected a glTF ParseError for glTF with no geometry, but got Ok or other error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_combine_identical_descriptors() {
+        let d: Vec<String> = vec!["aa".to_string(), "bb".to_string(), "cc".to_string()];
+        let combined = combine_descriptors(&[d.clone(), d.clone()]);
+        assert_eq!(combined, d);
+    }
+
+    #[test]
+    fn test_combine_disjoint_descriptors() {
+        let a: Vec<String> = vec!["aa".to_string(), "bb".to_string()];
+        let b: Vec<String> = vec!["cc".to_string(), "dd".to_string()];
+        let combined = combine_descriptors(&[a, b]);
+        // Top-2 of the union: both first-ranked labels tie and beat the second-ranked ones
+        assert_eq!(combined, vec!["aa".to_string(), "cc".to_string()]);
+    }
+
+    #[test]
+    fn test_descriptor_diff() {
+        let a: Vec<String> = vec!["aa".to_string(), "bb".to_string(), "cc".to_string()];
+        let b: Vec<String> = vec!["aa".to_string(), "dd".to_string(), "cc".to_string()];
+        let diff = descriptor_diff(&a, &b);
+        assert_eq!(diff.only_a, vec!["bb".to_string()]);
+        assert_eq!(diff.only_b, vec!["dd".to_string()]);
+        assert_eq!(diff.common, vec![("aa".to_string(), 0, 0), ("cc".to_string(), 2, 2)]);
+        assert_eq!(diff.rank_changes().count(), 0);
+    }
+
+    #[test]
+    fn test_gltf_unsupported_position_type() {
+        // POSITION declared as FLOAT VEC2
+        let gltf_json = r#"
+        {
+          "asset": { "version": "2.0" },
+          "buffers": [ { "byteLength": 18, "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAAAAAAAA" } ],
+          "bufferViews": [ { "buffer": 0, "byteLength": 18 } ],
+          "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC2", "min": [0, 0], "max": [0, 0] }
+          ],
+          "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 } } ] } ]
+        }
+        "#;
+        let result = p3d_process(
+            gltf_json.as_bytes(),
+            InputFileType::Gltf,
+            AlgoType::Grid2d,
+            20,
+            10,
+            None,
+        );
+        assert!(matches!(result, Err(P3DError::ParseError { format: Format::Gltf, .. })), "Unsupported POSITION type did not produce a glTF ParseError: {:?}", result);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_process_timed() {
+        let glb_bytes = include_bytes!("../test-ht.glb");
+        let (hashes, timings) = p3d_process_timed(glb_bytes, InputFileType::Glb, AlgoType::Grid2d, 20, 10, None).unwrap();
+        let expected = p3d_process(glb_bytes, InputFileType::Glb, AlgoType::Grid2d, 20, 10, None).unwrap();
+        assert_eq!(hashes, expected);
+
+        // Only what the clock can't get wrong: the stages lie within the total
+        let stages = [timings.load, timings.pit, timings.slicing, timings.ranking];
+        assert!(timings.total > Duration::ZERO, "Total not measured: {:?}", timings);
+        assert!(stages.iter().all(|&d| d <= timings.total), "Stage longer than the total: {:?}", timings);
+        let sum: Duration = stages.iter().sum();
+        assert!(sum <= timings.total + Duration::from_millis(1), "Stage timings exceed total: {:?}", timings);
+    }
+
+    // Outward-facing triangles of a box over vertices `1 + x + 2y + 4z` (x, y, z in {0, 1})
+    const BOX_FACES: [[u32; 3]; 12] = [
+        [1, 3, 4], [1, 4, 2], [5, 6, 8], [5, 8, 7],
+        [1, 2, 6], [1, 6, 5], [3, 7, 8], [3, 8, 4],
+        [1, 5, 7], [1, 7, 3], [2, 4, 8], [2, 8, 6],
+    ];
+
+    fn box_vertices(min: [f64; 3], size: [f64; 3]) -> Vec<[f64; 3]> {
+        (0..8).map(|i| [
+            min[0] + (i & 1) as f64 * size[0],
+            min[1] + ((i >> 1) & 1) as f64 * size[1],
+            min[2] + ((i >> 2) & 1) as f64 * size[2],
+        ]).collect()
+    }
+
+    // Triangle mesh as OBJ text, `indices` are 0-based
+    fn geometry_obj(positions: &[f64], indices: &[u32]) -> String {
+        let mut obj = String::new();
+        for v in positions.chunks(3) {
+            obj.push_str(&format!("v {} {} {}\n", v[0], v[1], v[2]));
+        }
+        obj.push_str("vn 0 0 1\n");
+        for f in indices.chunks(3) {
+            obj.push_str(&format!("f {}//1 {}//1 {}//1\n", f[0] + 1, f[1] + 1, f[2] + 1));
+        }
+        obj
+    }
+
+    // Closed axis-aligned box, as OBJ text
+    fn box_obj(sx: f64, sy: f64, sz: f64) -> String {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [sx, sy, sz]);
+        geometry_obj(&positions, &indices)
+    }
+
+    // Closed axis-aligned box, as positions and 0-based indices appended to the given buffers
+    fn push_box(positions: &mut Vec<f64>, indices: &mut Vec<u32>, min: [f64; 3], size: [f64; 3]) {
+        let base = (positions.len() / 3) as u32;
+        positions.extend(box_vertices(min, size).iter().flat_map(|v| v.iter().cloned()));
+        indices.extend(BOX_FACES.iter().flat_map(|f| f.iter().map(|i| base + i - 1)));
+    }
+
+    #[test]
+    fn test_thin_mesh_min_step() {
+        let obj = box_obj(1e-5, 1e-5, 1e-5);
+        let opts = ProcessOptions { min_step: 1e-6, ..Default::default() };
+
+        // 100 sections would be 1e-7 apart, so fewer sections are taken
+        let result = p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 100, None, &opts);
+        assert!(result.is_ok(), "Thin mesh failed: {:?}", result.err());
+
+        // Not even one section fits above the floor
+        let opts = ProcessOptions { min_step: 1e-4, ..Default::default() };
+        let result = p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 100, None, &opts);
+        assert!(matches!(result, Err(P3DError::DegenerateMesh)), "Expected DegenerateMesh: {:?}", result);
+    }
+
+    #[test]
+    fn test_section_step_floor() {
+        assert_eq!(section_step(10.0, 9, 0.5).unwrap(), (9, 1.0));
+        assert_eq!(section_step(10.0, 99, 1.0).unwrap(), (9, 1.0));
+        assert!(matches!(section_step(1e-12, 10, 1e-9), Err(P3DError::DegenerateMesh)));
+    }
+
+    #[test]
+    fn test_algo_names() {
+        for &(algo, name) in AlgoType::NAMES.iter() {
+            assert_eq!(algo.to_string(), name);
+            assert_eq!(name.parse::<AlgoType>().unwrap(), algo);
+        }
+        assert_eq!("Grid2D_V3A".parse::<AlgoType>().unwrap(), AlgoType::Grid2dV3a);
+        assert!(matches!("grid3d".parse::<AlgoType>(), Err(P3DError::InvalidParams { .. })));
+    }
+
+    #[test]
+    fn test_file_type_from_extension() {
+        assert_eq!(InputFileType::from_extension("GLB"), Some(InputFileType::Glb));
+        assert_eq!(InputFileType::from_extension("3mf"), Some(InputFileType::ThreeMf));
+        assert_eq!(InputFileType::from_extension(".obj"), None);
+        assert_eq!(InputFileType::from_extension("dae"), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_params_serde_round_trip() {
+        for &(algo, name) in AlgoType::NAMES.iter() {
+            assert_eq!(serde_json::to_string(&algo).unwrap(), format!("\"{}\"", name));
+        }
+        assert_eq!(serde_json::to_string(&InputFileType::ThreeMf).unwrap(), "\"three_mf\"");
+
+        let options = ProcessOptions {
+            inertia_region: Some((Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0))),
+            alignment: Some(Matrix4::from_angle_z(Deg(30.0))),
+            mesh_selector: Some(MeshSelector::Node("body".into())),
+            hash_algo: HashAlgo::Sha3_256,
+            limits: Limits { max_sections: 64, ..Default::default() },
+            cancel: Some(Cancel::new(|| false)),
+            ..Default::default()
+        };
+        let params = P3DParams::default().algo(AlgoType::Spectr).n_sections(12).options(options)
+            .transform(Transform::Quaternion(Quaternion::new(0.5, 0.5, 0.5, 0.5)));
+        let json = serde_json::to_string(&params).unwrap();
+        let decoded: P3DParams = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+        assert!(decoded.options.cancel.is_none());
+        assert_eq!(decoded.transform, params.transform);
+
+        // Config files need only give what differs from the defaults
+        let decoded: P3DParams = serde_json::from_str(r#"{"algo": "grid2d_v3", "options": {"hash_algo": "keccak256", "limits": {"max_vertices": 1000}}}"#).unwrap();
+        assert_eq!(decoded.algo, AlgoType::Grid2dV3);
+        assert_eq!((decoded.depth, decoded.grid_size), (10, 8));
+        assert_eq!(decoded.options.hash_algo, HashAlgo::Keccak256);
+        assert_eq!(decoded.options.limits, Limits { max_vertices: 1000, ..Default::default() });
+        assert!(decoded.options.align);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_result_serde_round_trip() {
+        let glb_bytes = include_bytes!("../test-ht.glb");
+        let result = p3d_process_detailed(glb_bytes, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 12, Some([1, 2, 3, 4]), &ProcessOptions::default()).unwrap();
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.contains("\"transform\":[["), "Matrix4 not encoded as nested arrays: {}", json);
+        let decoded: P3DResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, result);
+    }
+
+    #[test]
+    fn test_debug_geometry() {
+        let glb = include_bytes!("../test-ht.glb");
+        let plain = p3d_process_detailed(glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 10, None, &ProcessOptions::default()).unwrap();
+        assert!(plain.vertices.is_empty() && plain.occupancy.is_empty());
+
+        let opts = ProcessOptions { debug_geometry: true, ..Default::default() };
+        let res = p3d_process_detailed(glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 10, None, &opts).unwrap();
+        assert_eq!(res.hashes, plain.hashes);
+        let (mesh, _) = load_mesh(glb, InputFileType::Glb, &opts).unwrap();
+        assert_eq!(res.vertices.len(), mesh.no_vertices());
+        for v in res.vertices.iter() {
+            for axis in 0..3 {
+                assert!(v[axis] >= res.v_min[axis] && v[axis] <= res.v_max[axis]);
+            }
+        }
+        assert_eq!(res.occupancy.len(), res.centers.len());
+        assert!(res.occupancy.iter().all(|cells| cells.len() == 64));
+        // Every point is in a cell or out of bounds
+        let counted: u32 = res.occupancy.iter().flatten().sum();
+        let points: usize = res.centers.iter().map(|c| c.len()).sum();
+        assert_eq!(counted as usize + res.out_of_bounds, points);
+
+        let json: serde_json::Value = serde_json::from_str(&res.sections_json()).unwrap();
+        let sections = json["sections"].as_array().unwrap();
+        assert_eq!(json["grid_size"], 8);
+        assert_eq!(sections.len(), res.centers.len());
+        assert_eq!(sections[0]["z"].as_f64().unwrap(), res.section_heights[0]);
+        assert_eq!(sections[0]["contour"][0][0].as_f64().unwrap(), res.centers[0][0].x);
+        assert_eq!(sections[0]["cells"].as_array().unwrap().len(), 64);
+        assert!(serde_json::from_str::<serde_json::Value>(&plain.sections_json()).unwrap()["sections"][0].get("cells").is_none());
+        let svg = res.sections_svg();
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<polyline ").count(), res.centers.len());
+        assert_eq!(svg.matches("<rect ").count(), res.occupancy.iter().flatten().filter(|&&c| c > 0).count());
+    }
+
+    #[test]
+    fn test_contour_material_tags() {
+        // Two separate boxes with a material each, cut halfway up
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [3.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        let materials: Vec<u32> = repeat(0).take(12).chain(repeat(1).take(12)).collect();
+        let mesh = MeshBuilder::new()
+            .with_indices(indices)
+            .with_positions(positions)
+            .build()
+            .unwrap();
+
+        let sect = SectionIndex::new(&mesh, &ZRanges::new(&mesh, true), 0.0, 0.5, 1, &materials).intersect_2_tagged(0, 0.5, 0.005);
+        let (cntr, tags) = get_contour_tagged(sect);
+        assert_eq!(cntr.len(), tags.len());
+        for (p, &tag) in cntr.iter().zip(tags.iter()) {
+            let expected = if p.x < 2.0 { 0 } else { 1 };
+            assert_eq!(tag, expected, "Point {:?} tagged {}", p, tag);
+        }
+        assert!(tags.contains(&0) && tags.contains(&1));
+    }
+
+    #[test]
+    fn test_section_index() {
+        // Boxes at different heights, some planes through their vertices, none below or above
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [3.0, 0.0, 1.5], [1.0, 1.0, 2.5]);
+        let mesh = MeshBuilder::new().with_indices(indices).with_positions(positions).build().unwrap();
+
+        let (z0, step, n_sections) = (-0.75, 0.25, 20);
+        let edges = SectionIndex::new(&mesh, &ZRanges::new(&mesh, true), z0, step, n_sections, &[]);
+        let vertices = SectionIndex::new(&mesh, &ZRanges::new(&mesh, false), z0, step, n_sections, &[]);
+        for n in 0..n_sections {
+            let z = z0 + (n as f64 + 1.0) * step;
+            let crossing = mesh.edge_iter().filter(|&e| {
+                let (p1, p2) = mesh.edge_positions(e);
+                p2.z >= z && p1.z <= z || p2.z <= z && p1.z >= z
+            }).count();
+            let near = mesh.vertex_iter().filter(|&v| (mesh.vertex_position(v).z - z).abs() < 0.15).count();
+            assert_eq!(edges.intersect_2(n, z, 0.001).len(), crossing, "section {}", n);
+            assert_eq!(vertices.intersect(n, z).len(), near, "section {}", n);
+        }
+    }
+
+    #[test]
+    fn test_chain_order() {
+        // The quadratic selection `chain_order` replaces
+        fn reference(points: &[Vec2]) -> Vec<usize> {
+            let len = points.len();
+            let mut ii: Vec<usize> = (0..len).collect();
+            for i in 0..len - 1 {
+                let p = points[ii[i]];
+                let j = (i + 1..len).min_by_key(|&k| ((p.distance2(points[ii[k]]) as f32) * 10000.0) as u32).unwrap();
+                ii.swap(i + 1, j);
+            }
+            ii
+        }
+
+        // Two noisy rings far apart, with repeated points, and a wide spread of scales
+        let mut seed: u64 = 7;
+        let mut noise = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+        };
+        for &scale in [0.01, 1.0, 300.0].iter() {
+            let mut points = Vec::new();
+            for k in 0..600 {
+                let a = k as f64 * 0.37;
+                let (cx, r) = if k % 3 == 0 { (5.0, 1.0) } else { (0.0, 2.0) };
+                let p = Vec2::new((cx + r * a.cos() + 0.05 * noise()) * scale, (r * a.sin() + 0.05 * noise()) * scale);
+                points.push(p);
+                if k % 50 == 0 {
+                    points.push(p);
+                }
+            }
+            assert_eq!(contour::chain_order(&points), reference(&points), "scale {}", scale);
+        }
+    }
+
+    // Two stacked boxes forming an L, scaled by `k` and moved by `t`
+    fn l_shape_obj(k: f64, t: [f64; 3]) -> String {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 0.0], [2.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 1.0], [1.0, 1.0, 2.0]);
+        let positions: Vec<f64> = positions.iter().enumerate().map(|(i, v)| v * k + t[i % 3]).collect();
+        geometry_obj(&positions, &indices)
+    }
+
+    #[test]
+    fn test_normalization_invariance() {
+        let process = |obj: String, normalization: Normalization| {
+            let opts = ProcessOptions { align: false, normalization, ..Default::default() };
+            p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap()
+        };
+
+        let original = l_shape_obj(1.0, [0.0; 3]);
+        let scaled = l_shape_obj(2.0, [0.0; 3]);
+        let moved = l_shape_obj(2.0, [4.0, -2.0, 8.0]);
+
+        let reference = process(original.clone(), Normalization::ScaleAndCenter);
+        assert!(!reference.is_empty());
+        assert_eq!(process(moved, Normalization::ScaleAndCenter), reference);
+        assert_eq!(process(scaled.clone(), Normalization::ScaleOnly), process(original.clone(), Normalization::ScaleOnly));
+
+        let opts = ProcessOptions { normalization: Normalization::Full, ..Default::default() };
+        let full = p3d_process_opts(original.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap();
+        let full_scaled = p3d_process_opts(scaled.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap();
+        assert_eq!(full, full_scaled);
+
+        // Millimeters and meters, aligned
+        let opts = ProcessOptions { normalization: Normalization::ScaleOnly, ..Default::default() };
+        let meters = p3d_process_opts(l_shape_obj(1.0, [0.0; 3]).as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap();
+        let millimeters = p3d_process_opts(l_shape_obj(1000.0, [0.0; 3]).as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap();
+        assert_eq!(meters, millimeters);
+
+        // Same for the radius of gyration, which scales with the mesh wherever it is
+        let opts = ProcessOptions { normalization: Normalization::Gyration, ..Default::default() };
+        let meters = p3d_process_opts(l_shape_obj(1.0, [0.0; 3]).as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap();
+        let millimeters = p3d_process_opts(l_shape_obj(1000.0, [0.0; 3]).as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap();
+        assert_eq!(meters, millimeters);
+        let (mesh, _) = load_mesh(l_shape_obj(3.0, [5.0, 1.0, 0.0]).as_bytes(), InputFileType::Obj, &opts).unwrap();
+        let (unit, _) = load_mesh(l_shape_obj(1.0, [0.0; 3]).as_bytes(), InputFileType::Obj, &opts).unwrap();
+        assert!((surface_gyration_radius(&mesh) - 3.0 * surface_gyration_radius(&unit)).abs() < 1e-9);
+        // A 2 x 2 x 2 cube has its surface at a mean square distance of 1 + 2/3 from the center
+        let (cube, _) = load_mesh(box_obj(2.0, 2.0, 2.0).as_bytes(), InputFileType::Obj, &opts).unwrap();
+        assert!((surface_gyration_radius(&cube) - libm::sqrt(5.0 / 3.0)).abs() < 1e-9);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_process_mmap() {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("test-ht.glb");
+        let mapped = p3d_process_mmap(&path, AlgoType::Grid2d, 20, 10, None).unwrap();
+        let glb_bytes = include_bytes!("../test-ht.glb");
+        let in_memory = p3d_process(glb_bytes, InputFileType::Glb, AlgoType::Grid2d, 20, 10, None).unwrap();
+        assert_eq!(mapped, in_memory);
+    }
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    fn u32_bytes(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    // Binary glTF container around a JSON document and its BIN chunk
+    fn make_glb(json: &str, bin: &[u8]) -> Vec<u8> {
+        let mut json = json.as_bytes().to_vec();
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        let mut bin = bin.to_vec();
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        let mut glb = Vec::with_capacity(total);
+        glb.extend_from_slice(b"glTF");
+        glb.extend_from_slice(&2u32.to_le_bytes());
+        glb.extend_from_slice(&(total as u32).to_le_bytes());
+        glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"JSON");
+        glb.extend(json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend(bin);
+        glb
+    }
+
+    // Outward-facing faces of the tetrahedra below
+    const TETRA_FACES: [u32; 12] = [0, 2, 1, 0, 1, 3, 0, 3, 2, 1, 2, 3];
+
+    // GLB with two meshes, "first" and "second", each a differently stretched tetrahedron
+    fn two_mesh_glb() -> Vec<u8> {
+        two_tetra_glb(r#""meshes": [
+            { "name": "first", "primitives": [ { "attributes": { "POSITION": 0 }, "indices": 2 } ] },
+            { "name": "second", "primitives": [ { "attributes": { "POSITION": 1 }, "indices": 2 } ] }
+        ]"#)
+    }
+
+    // The two tetrahedra of `two_mesh_glb` as accessors 0 and 1 (indices in 2), with the
+    // meshes, nodes and scenes given in `members`
+    fn two_tetra_glb(members: &str) -> Vec<u8> {
+        let mut bin = f32_bytes(&[0., 0., 0., 1., 0., 0., 0., 1., 0., 0., 0., 1.]);
+        bin.extend(f32_bytes(&[0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.]));
+        bin.extend(u32_bytes(&TETRA_FACES));
+        let json = r#"{
+          "asset": { "version": "2.0" },
+          "buffers": [ { "byteLength": 144 } ],
+          "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 96 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 48 }
+          ],
+          "accessors": [
+            { "bufferView": 0, "byteOffset": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 1] },
+            { "bufferView": 0, "byteOffset": 48, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [2, 1, 3] },
+            { "bufferView": 1, "componentType": 5125, "count": 12, "type": "SCALAR" }
+          ],
+          MEMBERS
+        }"#;
+        make_glb(&json.replace("MEMBERS", members), &bin)
+    }
+
+    #[test]
+    fn test_gltf_data_uri_buffers() {
+        // Second tetrahedron of `two_tetra_glb` and its indices, base64 encoded
+        let gltf = |uri: &str| r#"{
+          "asset": { "version": "2.0" },
+          "buffers": [ { "byteLength": 96, "uri": "URI" } ],
+          "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 48, "byteLength": 48 }
+          ],
+          "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [2, 1, 3] },
+            { "bufferView": 1, "componentType": 5125, "count": 12, "type": "SCALAR" }
+          ],
+          "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 }, "indices": 1 } ] } ]
+        }"#.replace("URI", uri);
+        let embedded = gltf("data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAAAAQAAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAEBAAAAAAAIAAAABAAAAAAAAAAEAAAADAAAAAAAAAAMAAAACAAAAAQAAAAIAAAADAAAA");
+        let glb = two_tetra_glb(r#""meshes": [ { "primitives": [ { "attributes": { "POSITION": 1 }, "indices": 2 } ] } ]"#);
+        let expected = p3d_process(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert_eq!(p3d_process(embedded.as_bytes(), InputFileType::Gltf, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+
+        match p3d_process(gltf("tetra.bin").as_bytes(), InputFileType::Gltf, AlgoType::Grid2dV3a, 8, 4, None) {
+            Err(P3DError::ParseError { format: Format::Gltf, reason, .. }) => assert!(reason.starts_with("external buffers"), "{}", reason),
+            other => panic!("Expected a glTF ParseError for an external buffer, got {:?}", other),
+        }
+        // Supplied by a resolver instead
+        let mut bin = f32_bytes(&[0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.]);
+        bin.extend(u32_bytes(&TETRA_FACES));
+        let resolver = |uri: &str| if uri == "tetra.bin" { Some(bin.clone()) } else { None };
+        let resolved = p3d_process_with_resolver(gltf("tetra.bin").as_bytes(), InputFileType::Gltf, AlgoType::Grid2dV3a, 10, 8, 4, None, &resolver).unwrap();
+        assert_eq!(resolved, expected);
+        match p3d_process_with_resolver(gltf("other.bin").as_bytes(), InputFileType::Gltf, AlgoType::Grid2dV3a, 10, 8, 4, None, &resolver) {
+            Err(P3DError::ParseError { format: Format::Gltf, reason, .. }) => assert_eq!(reason, "buffer URI not found"),
+            other => panic!("Expected a glTF ParseError for an unresolved buffer, got {:?}", other),
+        }
+        let broken = gltf("data:application/octet-stream;base64,AAA*");
+        assert!(matches!(p3d_process(broken.as_bytes(), InputFileType::Gltf, AlgoType::Grid2dV3a, 8, 4, None), Err(P3DError::ParseError { format: Format::Gltf, .. })));
+        assert_eq!(data_uri::decode("data:;base64,YWJjZA==").unwrap().unwrap(), b"abcd".to_vec());
+        assert_eq!(data_uri::decode("data:;base64,YWJjZA").unwrap().unwrap(), b"abcd".to_vec());
+        assert!(data_uri::decode("buffer.bin").is_none());
+    }
+
+    #[test]
+    fn test_gltf_two_buffers() {
+        // Positions and indices of the second tetrahedron of `two_tetra_glb` in separate buffers
+        let positions = "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAAAAQAAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAEBA";
+        let indices = "data:application/octet-stream;base64,AAAAAAIAAAABAAAAAAAAAAEAAAADAAAAAAAAAAMAAAACAAAAAQAAAAIAAAADAAAA";
+        let json = |buffers: &str| r#"{
+          "asset": { "version": "2.0" },
+          "buffers": [ BUFFERS ],
+          "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 48 },
+            { "buffer": 1, "byteOffset": 0, "byteLength": 48 }
+          ],
+          "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [2, 1, 3] },
+            { "bufferView": 1, "componentType": 5125, "count": 12, "type": "SCALAR" }
+          ],
+          "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 }, "indices": 1 } ] } ]
+        }"#.replace("BUFFERS", buffers);
+        let glb = two_tetra_glb(r#""meshes": [ { "primitives": [ { "attributes": { "POSITION": 1 }, "indices": 2 } ] } ]"#);
+        let expected = p3d_process(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+
+        let gltf = json(&format!(r#"{{ "byteLength": 48, "uri": "{}" }}, {{ "byteLength": 48, "uri": "{}" }}"#, positions, indices));
+        assert_eq!(p3d_process(gltf.as_bytes(), InputFileType::Gltf, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+
+        // The blob only backs the first buffer, the second one is read from its own URI
+        let bin = f32_bytes(&[0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.]);
+        let mixed = make_glb(&json(&format!(r#"{{ "byteLength": 48 }}, {{ "byteLength": 48, "uri": "{}" }}"#, indices)), &bin);
+        assert_eq!(p3d_process(&mixed, InputFileType::Glb, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+
+        let no_blob = json(&format!(r#"{{ "byteLength": 48 }}, {{ "byteLength": 48, "uri": "{}" }}"#, indices));
+        match p3d_process(no_blob.as_bytes(), InputFileType::Gltf, AlgoType::Grid2dV3a, 8, 4, None) {
+            Err(P3DError::ParseError { format: Format::Gltf, reason, .. }) => assert!(reason.contains("no GLB binary chunk"), "{}", reason),
+            other => panic!("Expected a glTF ParseError for a buffer without data, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_gltf_quantized_positions() {
+        // The second tetrahedron of `two_tetra_glb` as SHORT positions (rows padded to 8 bytes)
+        // and UNSIGNED_BYTE indices
+        let tetra = |component: u32, normalized: bool, stride: usize, coords: Vec<u8>| {
+            let mut bin = coords;
+            let positions = bin.len();
+            bin.extend(TETRA_FACES.iter().map(|&i| i as u8));
+            let json = format!(r#"{{
+              "asset": {{ "version": "2.0" }},
+              "buffers": [ {{ "byteLength": {} }} ],
+              "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": {}, "byteStride": {} }},
+                {{ "buffer": 0, "byteOffset": {}, "byteLength": 12 }}
+              ],
+              "accessors": [
+                {{ "bufferView": 0, "componentType": {}, "normalized": {}, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 1] }},
+                {{ "bufferView": 1, "componentType": 5121, "count": 12, "type": "SCALAR" }}
+              ],
+              "meshes": [ {{ "primitives": [ {{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }} ] }} ]
+            }}"#, bin.len(), positions, stride, positions, component, normalized);
+            make_glb(&json, &bin)
+        };
+        let shorts: Vec<u8> = [0i16, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 3].chunks(3)
+            .flat_map(|p| p.iter().flat_map(|c| c.to_le_bytes()).chain([0, 0]))
+            .collect();
+        let glb = two_tetra_glb(r#""meshes": [ { "primitives": [ { "attributes": { "POSITION": 1 }, "indices": 2 } ] } ]"#);
+        let expected = p3d_process(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert_eq!(p3d_process(&tetra(5122, false, 8, shorts), InputFileType::Glb, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+
+        // Normalized BYTE: 127 is 1, and -128 clamps to -1 like -127
+        let bytes: Vec<u8> = [0i8, 0, 0, 127, 0, 0, 0, -128, 0, 0, 0, 64].chunks(3)
+            .flat_map(|p| p.iter().map(|&c| c as u8).chain([0]))
+            .collect();
+        let geometry = load_geometry(&tetra(5120, true, 4, bytes), InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        assert_eq!(&geometry.positions[3..9], &[1.0, 0.0, 0.0, 0.0, -1.0, 0.0]);
+        assert_eq!(geometry.positions[11], (64.0f32 / 127.0) as f64);
+    }
+
+    #[test]
+    fn test_too_many_vertices() {
+        assert_eq!(vertex_id(MAX_VERTICES - 1).unwrap(), u32::MAX - 1);
+        assert!(matches!(vertex_id(MAX_VERTICES), Err(P3DError::TooManyVertices { vertices, limit }) if vertices == MAX_VERTICES + 1 && limit == MAX_VERTICES));
+        assert!(check_vertex_count(MAX_VERTICES).is_ok());
+        let err = check_vertex_count(MAX_VERTICES + 1).unwrap_err();
+        assert_eq!(err.code(), 27);
+        assert!(err.to_string().starts_with("4294967296 vertices"), "{}", err);
+
+        // A negative PLY index no longer turns into vertex 0
+        let ply = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                   element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 -1\n";
+        match load_geometry(ply.as_bytes(), InputFileType::Ply, &ProcessOptions::default()) {
+            Err(P3DError::ParseError { format: Format::Ply, offset, reason }) => {
+                assert_eq!(reason, "face index out of range");
+                // The face row, after the header and the three vertex rows
+                assert_eq!(offset, Offset::Byte(ply.find("3 0 1 -1").unwrap()));
+            }
+            other => panic!("Expected a PLY ParseError for a negative index, got {:?}", other.map(|g| g.indices)),
+        }
+    }
+
+    #[test]
+    fn test_gltf_mesh_selector() {
+        let glb = two_mesh_glb();
+        let process = |selector: MeshSelector| {
+            let opts = ProcessOptions { mesh_selector: Some(selector), ..Default::default() };
+            p3d_process_opts(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts)
+        };
+
+        let by_name = process(MeshSelector::Name("second".to_string())).unwrap();
+        let by_index = process(MeshSelector::Index(1)).unwrap();
+        assert_eq!(by_name, by_index);
+
+        match process(MeshSelector::Name("missing".to_string())) {
+            Err(P3DError::ParseError { format: Format::Gltf, reason, .. }) => assert_eq!(reason, "no mesh matches the selector"),
+            other => panic!("Expected a glTF ParseError for unknown mesh name, got {:?}", other),
+        }
+
+        // The second mesh instanced twice, by the untransformed node "b" and the moved node "c"
+        let scene = two_tetra_glb(r#""meshes": [
+            { "name": "first", "primitives": [ { "attributes": { "POSITION": 0 }, "indices": 2 } ] },
+            { "name": "second", "primitives": [ { "attributes": { "POSITION": 1 }, "indices": 2 } ] }
+          ],
+          "nodes": [
+            { "name": "a", "mesh": 0 },
+            { "name": "b", "mesh": 1 },
+            { "name": "c", "mesh": 1, "translation": [5, 0, 0] }
+          ],
+          "scenes": [ { "nodes": [0, 1, 2] } ]"#);
+        let process_scene = |selector: Option<MeshSelector>| {
+            let opts = ProcessOptions { mesh_selector: selector, ..Default::default() };
+            p3d_process_opts(&scene, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts)
+        };
+        assert_eq!(process_scene(Some(MeshSelector::Node("b".to_string()))).unwrap(), by_index);
+        let both = process_scene(Some(MeshSelector::Index(1))).unwrap();
+        assert_ne!(both, by_index);
+        assert_eq!(process_scene(Some(MeshSelector::All)).unwrap(), process_scene(None).unwrap());
+        match process_scene(Some(MeshSelector::Node("d".to_string()))) {
+            Err(P3DError::ParseError { format: Format::Gltf, reason, .. }) => assert_eq!(reason, "no node with a mesh matches the selector"),
+            other => panic!("Expected a glTF ParseError for unknown node name, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_gltf_mirrored_node() {
+        // The second tetrahedron mirrored in x by its node: the faces must still face outwards
+        let glb = two_tetra_glb(r#""meshes": [ { "primitives": [ { "attributes": { "POSITION": 1 }, "indices": 2 } ] } ],
+          "nodes": [ { "mesh": 0, "scale": [-1, 1, 1] } ],
+          "scenes": [ { "nodes": [0] } ]"#);
+        let geometry = load_geometry(&glb, InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        assert_eq!(geometry.indices, vec![0, 1, 2, 0, 3, 1, 0, 2, 3, 1, 3, 2]);
+        let p = |i: u32| Vector3::new(geometry.positions[i as usize * 3], geometry.positions[i as usize * 3 + 1], geometry.positions[i as usize * 3 + 2]);
+        let centroid = (p(0) + p(1) + p(2) + p(3)) / 4.0;
+        for t in geometry.indices.chunks(3) {
+            let normal = (p(t[1]) - p(t[0])).cross(p(t[2]) - p(t[0]));
+            assert!(normal.dot(p(t[0]) - centroid) > 0.0, "Face {:?} turned inwards", t);
+        }
+        assert_eq!(geometry.positions[3], -2.0);
+    }
+
+    #[cfg(feature = "draco")]
+    #[test]
+    fn test_gltf_draco_primitive() {
+        // The second tetrahedron of `two_tetra_glb` behind the Draco extension, "compressed"
+        // as its vertex count, positions and indices for the stand-in decoder below
+        let positions = [0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.];
+        let mut bin = u32_bytes(&[4]);
+        bin.extend(f32_bytes(&positions));
+        bin.extend(u32_bytes(&TETRA_FACES));
+        let json = r#"{
+          "asset": { "version": "2.0" },
+          "extensionsUsed": [ "KHR_draco_mesh_compression" ],
+          "extensionsRequired": [ "KHR_draco_mesh_compression" ],
+          "buffers": [ { "byteLength": 100 } ],
+          "bufferViews": [ { "buffer": 0, "byteOffset": 0, "byteLength": 100 } ],
+          "accessors": [
+            { "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [2, 1, 3] },
+            { "componentType": 5125, "count": 12, "type": "SCALAR" }
+          ],
+          "meshes": [ { "primitives": [ {
+            "attributes": { "POSITION": 0 }, "indices": 1,
+            "extensions": { "KHR_draco_mesh_compression": { "bufferView": 0, "attributes": { "POSITION": 3 } } }
+          } ] } ]
+        }"#;
+        let glb = make_glb(json, &bin);
+        let decoder = DracoDecoder::new(|data: &[u8], position: u32| {
+            assert_eq!(position, 3);
+            let word = |i: usize| [data[4 * i], data[4 * i + 1], data[4 * i + 2], data[4 * i + 3]];
+            let n = u32::from_le_bytes(word(0)) as usize;
+            let positions = (0..n).map(|v| [0, 1, 2].map(|c| f32::from_le_bytes(word(1 + 3 * v + c)))).collect();
+            let indices = (1 + 3 * n..data.len() / 4).map(|i| u32::from_le_bytes(word(i))).collect();
+            Some((positions, indices))
+        });
+
+        let opts = ProcessOptions { draco: Some(decoder), ..Default::default() };
+        let decoded = p3d_process_opts(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts).unwrap();
+        let plain = ProcessOptions { mesh_selector: Some(MeshSelector::Index(1)), ..Default::default() };
+        let expected = p3d_process_opts(&two_mesh_glb(), InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 4, None, &plain).unwrap();
+        assert_eq!(decoded, expected);
+
+        match p3d_process_opts(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 4, None, &ProcessOptions::default()) {
+            Err(P3DError::ParseError { format: Format::Gltf, reason, .. }) => assert!(reason.contains("ProcessOptions::draco"), "{}", reason),
+            other => panic!("Expected a glTF ParseError without a decoder, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_simplify_circle_contour() {
+        let n = 1000;
+        let mut circle: Vec<Vec2> = (0..n)
+            .map(|i| {
+                let a = i as f64 / n as f64 * 2.0 * core::f64::consts::PI;
+                Vec2::new(libm::cos(a), libm::sin(a))
+            })
+            .collect();
+        circle.push(circle[0]);
+
+        let tolerance = 0.01;
+        let simplified: Vec<Vec2> = simplify_indices(&circle, tolerance).iter().map(|&i| circle[i]).collect();
+        assert!(simplified.len() < circle.len() / 10, "Only reduced to {} points", simplified.len());
+        assert_eq!(simplified.first(), simplified.last());
+
+        // Every original point stays within the tolerance of the simplified loop
+        for p in circle.iter() {
+            let d = simplified.windows(2)
+                .map(|w| {
+                    let (ab, ap) = (w[1] - w[0], *p - w[0]);
+                    let t = (ap.dot(ab) / ab.magnitude2()).max(0.0).min(1.0);
+                    (ap - ab * t).magnitude()
+                })
+                .fold(f64::MAX, f64::min);
+            assert!(d <= tolerance, "Point {:?} is {} away from the simplified contour", p, d);
+        }
+    }
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(self_test(), Ok(()));
+        // A golden for every algorithm, in `AlgoType` order
+        let covered: Vec<AlgoType> = self_test::GOLDEN.iter().map(|(algo, _)| *algo).collect();
+        assert_eq!(covered, AlgoType::NAMES.iter().map(|(algo, _)| *algo).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_compensated_sum() {
+        assert_eq!(sum::compensated_sum([1e16, 1.0, -1e16]), 1.0);
+        assert_eq!(sum::compensated_sum([0.1; 10]), 1.0);
+    }
+
+    #[test]
+    fn test_inertia_weighting_golden() {
+        // The surface and vertex weightings aren't covered by `self_test`
+        let glb = include_bytes!("../test-ht.glb");
+        let golden: [(InertiaWeighting, &[&str]); 2] = [
+            (InertiaWeighting::Surface, &[
+                "887a69c1397d29c9971848b2e40aa7a16ea9050918db98421a92ddda9b7aa734",
+                "92ce6da93b8519b5220d2f74e9a2706892542bf539a1cc2dc23458af031b97d1",
+                "1c5714d17a26e5096d91d31ddc628dbd5a4d7492184b6798775caa3bd9ec0198",
+                "b407eeeab23e29381e76946d16057796abce31f3ab7887e3063badb9c5d41f80",
+            ]),
+            (InertiaWeighting::Vertices, &[
+                "b4a19576cffe1251f7983e2a9aca18546b0715c0436a7360072739995840a042",
+                "3872744a93e833d96564c82cf3e639f15775565141167336e7e502eafe9209b1",
+                "48c01844e90cb74017a0078d11a916124a772b952fa2145d14a72c90f8d749f4",
+                "e723bf94fef55b6ac08b4a4308c36bc31b78bf21e37d2264a060586287e19f87",
+            ]),
+        ];
+        for (inertia, expected) in golden.iter() {
+            let opts = ProcessOptions { inertia: *inertia, ..Default::default() };
+            let hashes = p3d_process_opts(glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 10, None, &opts).unwrap();
+            assert_eq!(hashes, expected.to_vec(), "{:?}", inertia);
+        }
+    }
+
+    #[test]
+    fn test_self_test_detects_corrupted_golden() {
+        let mut corrupted: Vec<(AlgoType, Vec<&str>)> = self_test::GOLDEN.iter()
+            .map(|(algo, hashes)| (*algo, hashes.to_vec()))
+            .collect();
+        corrupted[0].1[0] = "0000000000000000000000000000000000000000000000000000000000000000";
+        let golden: Vec<(AlgoType, &[&str])> = corrupted.iter().map(|(algo, hashes)| (*algo, hashes.as_slice())).collect();
+        let err = self_test::self_test_with(&golden).unwrap_err();
+        assert!(err.contains(&format!("{:?}", corrupted[0].0)), "Mismatch not reported: {}", err);
+    }
+
+    #[test]
+    #[ignore]
+    fn print_self_test_golden() {
+        extern crate std;
+        for &(algo, _) in AlgoType::NAMES.iter() {
+            let hashes = p3d_process_n(include_bytes!("../test-ht.glb"), InputFileType::Glb, algo, 10, 8, 10, None).unwrap();
+            std::println!("    (AlgoType::{:?}, &[", algo);
+            for h in hashes.iter() {
+                std::println!("        \"{}\",", h);
+            }
+            std::println!("    ]),");
+        }
+    }
+
+    // Splits every triangle at its centroid `depth` times, keeps the surface unchanged
+    fn split_triangles(positions: &mut Vec<f64>, indices: Vec<u32>, depth: usize) -> Vec<u32> {
+        let mut indices = indices;
+        for _ in 0..depth {
+            let mut split = Vec::new();
+            for f in indices.chunks(3) {
+                let c = (positions.len() / 3) as u32;
+                for k in 0..3 {
+                    let centroid: f64 = f.iter().map(|&i| positions[i as usize * 3 + k]).sum::<f64>() / 3.0;
+                    positions.push(centroid);
+                }
+                split.extend_from_slice(&[f[0], f[1], c, f[1], f[2], c, f[2], f[0], c]);
+            }
+            indices = split;
+        }
+        indices
+    }
+
+    #[test]
+    fn test_inertia_weighting() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        let plain = geometry_obj(&positions, &indices);
+
+        // Top face (triangles 2 and 3) densely tessellated
+        let top: Vec<u32> = indices.drain(6..12).collect();
+        let top = split_triangles(&mut positions, top, 5);
+        indices.extend(top);
+        let dense = geometry_obj(&positions, &indices);
+
+        let opts = ProcessOptions::default();
+        let (plain_mesh, _) = load_mesh(plain.as_bytes(), InputFileType::Obj, &opts).unwrap();
+        let (dense_mesh, _) = load_mesh(dense.as_bytes(), InputFileType::Obj, &opts).unwrap();
+
+        let close = |a: Vector3<f64>, b: Vector3<f64>| (a - b).magnitude() < 1e-9;
+        for weighting in [InertiaWeighting::Volume, InertiaWeighting::Surface].iter() {
+            let (plain_shift, _) = pit_transform(&plain_mesh, *weighting).unwrap();
+            let (dense_shift, _) = pit_transform(&dense_mesh, *weighting).unwrap();
+            assert!(close(plain_shift, dense_shift), "{:?} depends on tessellation: {:?} vs {:?}", weighting, plain_shift, dense_shift);
+        }
+
+        let (surface_shift, _) = pit_transform(&dense_mesh, InertiaWeighting::Surface).unwrap();
+        let (vertex_shift, _) = pit_transform(&dense_mesh, InertiaWeighting::Vertices).unwrap();
+        assert!(close(surface_shift, Vector3::new(-1.5, -1.0, -0.5)), "Surface center: {:?}", surface_shift);
+        // Vertices crowd the top face and pull the center up
+        assert!(vertex_shift.z < -0.9, "Vertex center: {:?}", vertex_shift);
+    }
+
+    #[test]
+    fn test_verify_against() {
+        let obj = box_obj(3.0, 2.0, 1.0);
+        let reference = p3d_process(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 10, None).unwrap();
+        assert!(verify_against(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 10, None, &reference).unwrap());
+
+        // Near miss: one hash differs in its last digit
+        let mut near = reference.clone();
+        let last = near[0].pop().unwrap();
+        near[0].push(if last == '0' { '1' } else { '0' });
+        assert!(!verify_against(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 10, None, &near).unwrap());
+    }
+
+    #[test]
+    fn test_p3d_verify() {
+        let obj = box_obj(3.0, 2.0, 1.0);
+        let params = P3DParams::default();
+        let hashes = p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap();
+        assert!(!hashes.is_empty());
+        let verify = |input: &[u8], expected: &[String]| p3d_verify(input, InputFileType::Obj, &params, expected).unwrap();
+
+        // In any order and case
+        let mut claim: Vec<String> = hashes.iter().rev().map(|h| h.to_uppercase()).collect();
+        assert!(verify(obj.as_bytes(), &claim));
+        assert!(verify(obj.as_bytes(), &hashes[..1]));
+        let last = claim[0].pop().unwrap();
+        claim[0].push(if last == '0' { '1' } else { '0' });
+        assert!(!verify(obj.as_bytes(), &claim));
+
+        // Claims rejected before the input is even parsed
+        assert!(!verify(b"not an obj", &[hashes[0].clone(), hashes[0].clone()]));
+        assert!(!verify(b"not an obj", &[String::from("00")]));
+        assert!(!verify(b"not an obj", &vec![hashes[0].clone(); params.depth + 1]));
+        assert!(p3d_verify(b"not an obj", InputFileType::Obj, &params, &[hashes[0].clone()]).is_err());
+    }
+
+    #[test]
+    fn test_verify_short_circuit() {
+        let obj = sphere_obj(1.0, 12, 18);
+        let opts = ProcessOptions::default();
+        let mesh = P3DMesh::load_opts(obj.as_bytes(), InputFileType::Obj, &opts).unwrap();
+        let sections = slice_mesh(&mesh.mesh, &mesh.face_materials, &AlgoType::Grid2dV3a, 10, 14, &opts).unwrap();
+        let n = sections.n_sections as usize;
+        let hashes = find_top_std_4(&sections.centers, 10, n, 8, sections.rect.clone(), opts.hash_algo, None);
+        assert_eq!(hashes.len(), 10);
+        let candidates = candidates_4(&sections.centers, 8, sections.rect.clone(), None);
+        let verify = |claimed: &[RawHash]| verify_candidates_4(&candidates, 10, n, opts.hash_algo, claimed, None);
+
+        // The full ranking totals all 2^n candidates, the verdicts come after a fraction of them
+        let absent = [0u8; 32];
+        for (claimed, expected) in [(&hashes[..1], true), (&hashes[..], true), (&[absent][..], false), (&[hashes[0], absent][..], false)] {
+            let (holds, totalled) = verify(claimed);
+            assert_eq!(holds, expected);
+            assert!(totalled * 16 < 1 << n, "Totalled {} of {} candidates", totalled, 1 << n);
+        }
+        let params = P3DParams { n_sections: 14, ..Default::default() };
+        let claimed: Vec<String> = p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap().into_iter().rev().collect();
+        assert!(p3d_verify(obj.as_bytes(), InputFileType::Obj, &params, &claimed).unwrap());
+    }
+
+    #[test]
+    fn test_p3d_compare() {
+        let params = P3DParams::default();
+        let compare = |a: &str, b: &str| p3d_compare(a.as_bytes(), InputFileType::Obj, b.as_bytes(), InputFileType::Obj, &params).unwrap();
+        let sphere = sphere_obj(1.0, 24, 32);
+
+        let same = compare(&sphere, &sphere);
+        assert_eq!(same.score, 1.0);
+        assert_eq!(same.hash_overlap.len(), params.depth);
+
+        let close = compare(&sphere, &sphere_obj(1.0, 24, 33));
+        let far = compare(&sphere, &l_shape_obj(1.0, [0.0; 3]));
+        assert!(close.grid_overlap > far.grid_overlap);
+        assert!(close.score > far.score);
+        assert!(far.score < 0.5);
+    }
+
+    #[test]
+    fn test_out_of_bounds_policy() {
+        let rect = Rect::new(0.0, 1.0, 0.0, 1.0);
+        let contour = vec![Vec2::new(0.5, 0.0), Vec2::new(1.0 + 1e-9, 0.5), Vec2::new(0.5, 1.0)];
+
+        let mut cntr = contour.clone();
+        assert_eq!(bound_contour(&mut cntr, &mut vec![], &rect, OobPolicy::Keep), 1);
+        assert_eq!(cntr, contour);
+
+        let mut cntr = contour.clone();
+        assert_eq!(bound_contour(&mut cntr, &mut vec![], &rect, OobPolicy::Clamp), 1);
+        assert_eq!(cntr[1], Vec2::new(1.0, 0.5));
+
+        let mut cntr = contour.clone();
+        let mut tags = vec![1, 2, 3];
+        assert_eq!(bound_contour(&mut cntr, &mut tags, &rect, OobPolicy::Drop), 1);
+        assert_eq!(cntr, vec![contour[0], contour[2]]);
+        assert_eq!(tags, vec![1, 3]);
+    }
+
+    // Tetrahedron skinned to two bones, the apex follows bone 1 (one unit up the Y axis), which
+    // an animation turns by 90 degrees around Z between t = 0 and t = 1
+    fn skinned_glb() -> Vec<u8> {
+        let h = core::f32::consts::FRAC_1_SQRT_2;
+        let mut bin = f32_bytes(&[0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 2., 0.]);
+        bin.extend(u32_bytes(&TETRA_FACES));
+        bin.extend([0u16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0].iter().flat_map(|v| v.to_le_bytes()));
+        bin.extend(f32_bytes(&[1., 0., 0., 0., 1., 0., 0., 0., 1., 0., 0., 0., 1., 0., 0., 0.]));
+        bin.extend(f32_bytes(&[1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.]));
+        bin.extend(f32_bytes(&[1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., -1., 0., 1.]));
+        bin.extend(f32_bytes(&[0., 1.]));
+        bin.extend(f32_bytes(&[0., 0., 0., 1., 0., 0., h, h]));
+        let json = r#"{
+          "asset": { "version": "2.0" },
+          "buffers": [ { "byteLength": 360 } ],
+          "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 48, "byteLength": 48 },
+            { "buffer": 0, "byteOffset": 96, "byteLength": 32 },
+            { "buffer": 0, "byteOffset": 128, "byteLength": 64 },
+            { "buffer": 0, "byteOffset": 192, "byteLength": 128 },
+            { "buffer": 0, "byteOffset": 320, "byteLength": 8 },
+            { "buffer": 0, "byteOffset": 328, "byteLength": 32 }
+          ],
+          "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [1, 2, 1] },
+            { "bufferView": 1, "componentType": 5125, "count": 12, "type": "SCALAR" },
+            { "bufferView": 2, "componentType": 5123, "count": 4, "type": "VEC4" },
+            { "bufferView": 3, "componentType": 5126, "count": 4, "type": "VEC4" },
+            { "bufferView": 4, "componentType": 5126, "count": 2, "type": "MAT4" },
+            { "bufferView": 5, "componentType": 5126, "count": 2, "type": "SCALAR", "min": [0], "max": [1] },
+            { "bufferView": 6, "componentType": 5126, "count": 2, "type": "VEC4" }
+          ],
+          "meshes": [
+            { "primitives": [ { "attributes": { "POSITION": 0, "JOINTS_0": 2, "WEIGHTS_0": 3 }, "indices": 1 } ] }
+          ],
+          "skins": [ { "inverseBindMatrices": 4, "joints": [1, 2] } ],
+          "nodes": [
+            { "mesh": 0, "skin": 0 },
+            { "children": [2] },
+            { "translation": [0, 1, 0] }
+          ],
+          "scenes": [ { "nodes": [0, 1] } ],
+          "animations": [ {
+            "channels": [ { "sampler": 0, "target": { "node": 2, "path": "rotation" } } ],
+            "samplers": [ { "input": 5, "output": 6, "interpolation": "LINEAR" } ]
+          } ]
+        }"#;
+        make_glb(json, &bin)
+    }
+
+    #[test]
+    fn test_gltf_animation_pose() {
+        let glb = skinned_glb();
+        let bind = load_geometry(&glb, InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        assert_eq!(&bind.positions[9..12], &[0.0, 2.0, 0.0]);
+
+        let opts = ProcessOptions { pose: Some(AnimationPose { animation: 0, time: 0.5 }), ..Default::default() };
+        let posed = load_geometry(&glb, InputFileType::Glb, &opts).unwrap();
+        let h = core::f64::consts::FRAC_1_SQRT_2;
+        // The base stays with bone 0, the apex turns 45 degrees around the bone 1 joint
+        assert_eq!(&posed.positions[..9], &bind.positions[..9]);
+        let expected = [-h, 1.0 + h, 0.0];
+        for (p, e) in posed.positions[9..12].iter().zip(expected.iter()) {
+            assert!((p - e).abs() < 1e-6, "Apex at {:?}, expected {:?}", &posed.positions[9..12], expected);
+        }
+
+        let opts = ProcessOptions { pose: Some(AnimationPose { animation: 1, time: 0.0 }), ..Default::default() };
+        assert!(matches!(load_geometry(&glb, InputFileType::Glb, &opts), Err(P3DError::ParseError { format: Format::Gltf, .. })));
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_process_zip() {
+        use std::io::Write;
+
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        let positions: Vec<f32> = positions.iter().map(|&v| v as f32).collect();
+        let mut bin = f32_bytes(&positions);
+        bin.extend(u32_bytes(&indices));
+        let gltf = |buffer: &str| format!(r#"{{
+          "asset": {{ "version": "2.0" }},
+          "buffers": [ {{ {}"byteLength": 240 }} ],
+          "bufferViews": [
+            {{ "buffer": 0, "byteOffset": 0, "byteLength": 96 }},
+            {{ "buffer": 0, "byteOffset": 96, "byteLength": 144 }}
+          ],
+          "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 8, "type": "VEC3", "min": [0, 0, 0], "max": [3, 2, 1] }},
+            {{ "bufferView": 1, "componentType": 5125, "count": 36, "type": "SCALAR" }}
+          ],
+          "meshes": [ {{ "primitives": [ {{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }} ] }} ]
+        }}"#, buffer);
+
+        let mut archive = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut archive));
+            let options = zip::write::FileOptions::default();
+            zip.start_file("model/box.gltf", options).unwrap();
+            zip.write_all(gltf(r#""uri": "box.bin", "#).as_bytes()).unwrap();
+            zip.start_file("model/box.bin", options).unwrap();
+            zip.write_all(&bin).unwrap();
+            zip.finish().unwrap();
+        }
+
+        let expected = p3d_process(&make_glb(&gltf(""), &bin), InputFileType::Glb, AlgoType::Grid2dV3a, 8, 10, None).unwrap();
+        let from_zip = p3d_process_zip(&archive, None, AlgoType::Grid2dV3a, 8, 10, None).unwrap();
+        assert_eq!(from_zip, expected);
+        let named = p3d_process_zip(&archive, Some("model/box.gltf"), AlgoType::Grid2dV3a, 8, 10, None).unwrap();
+        assert_eq!(named, expected);
+        assert!(matches!(p3d_process_zip(&archive, Some("missing.gltf"), AlgoType::Grid2dV3a, 8, 10, None), Err(P3DError::Zip(_))));
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_three_mf() {
+        use std::io::Write;
+
+        // A unit tetrahedron stretched by a component and a build item into the second
+        // tetrahedron of `two_tetra_glb`, plus a support that isn't part of the shape
+        let triangles: String = TETRA_FACES.chunks(3)
+            .map(|t| format!(r#"<triangle v1="{}" v2="{}" v3="{}"/>"#, t[0], t[1], t[2]))
+            .collect();
+        let model = |item: &str| format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+            <model unit="millimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+              <!-- <item objectid="3"/> -->
+              <resources>
+                <object id="1" type="model"><mesh>
+                  <vertices>
+                    <vertex x="0" y="0" z="0"/><vertex x="1" y="0" z="0"/>
+                    <vertex x="0" y="1" z="0"/><vertex x="0" y="0" z="1"/>
+                  </vertices>
+                  <triangles>{}</triangles>
+                </mesh></object>
+                <object id="2"><components><component objectid="1" transform="2 0 0 0 1 0 0 0 1 0 0 0"/></components></object>
+                <object id="3" type="support"><mesh><vertices><vertex x="9" y="9" z="9"/></vertices></mesh></object>
+              </resources>
+              <build>{}</build>
+            </model>"#, triangles, item);
+        let package = |model: &str| {
+            let mut archive = Vec::new();
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut archive));
+            let options = zip::write::FileOptions::default();
+            zip.start_file("_rels/.rels", options).unwrap();
+            zip.write_all(br#"<?xml version="1.0"?><Relationships><Relationship Target="/3D/part.model" Id="rel0" Type="http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel"/></Relationships>"#).unwrap();
+            zip.start_file("3D/part.model", options).unwrap();
+            zip.write_all(model.as_bytes()).unwrap();
+            zip.finish().unwrap();
+            drop(zip);
+            archive
+        };
+
+        let three_mf = package(&model(r#"<item objectid="2" transform="1 0 0 0 1 0 0 0 3 0 0 0"/>"#));
+        let geometry = load_geometry(&three_mf, InputFileType::ThreeMf, &ProcessOptions::default()).unwrap();
+        assert_eq!(geometry.positions, vec![0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.]);
+        assert_eq!(geometry.indices, TETRA_FACES.to_vec());
+        let opts = ProcessOptions { mesh_selector: Some(MeshSelector::Index(1)), ..Default::default() };
+        let expected = p3d_process_opts(&two_mesh_glb(), InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts).unwrap();
+        let hashes = p3d_process(&three_mf, InputFileType::ThreeMf, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert_eq!(hashes, expected);
+
+        // Mirrored instances keep their faces outwards
+        let mirrored = package(&model(r#"<item objectid="1" transform="-1 0 0 0 1 0 0 0 1 0 0 0"/>"#));
+        let geometry = load_geometry(&mirrored, InputFileType::ThreeMf, &ProcessOptions::default()).unwrap();
+        assert_eq!(&geometry.indices[..3], &[0, 1, 2]);
+
+        let missing = package(&model(r#"<item objectid="7"/>"#));
+        assert!(matches!(p3d_process(&missing, InputFileType::ThreeMf, AlgoType::Grid2dV3a, 8, 4, None), Err(P3DError::ParseError { format: Format::ThreeMf, reason, .. }) if reason.contains("object")));
+    }
+
+    #[test]
+    fn test_min_section_support() {
+        let square: Vec<Vec2> = vec![Vec2::new(0.1, 0.1), Vec2::new(0.9, 0.1), Vec2::new(0.9, 0.9), Vec2::new(0.1, 0.9)];
+        let mut spiked = square.clone();
+        spiked.insert(2, Vec2::new(0.5, 0.5));
+        let sections = || Sections {
+            centers: vec![square.clone(), spiked.clone(), square.clone()],
+            heights: vec![1.0, 2.0, 3.0],
+            materials: Vec::new(),
+            loops: Vec::new(),
+            rect: Rect::new(0.0, 1.0, 0.0, 1.0),
+            n_sections: 3,
+            out_of_bounds: 0,
+        };
+
+        let mut kept = sections();
+        filter_section_support(&mut kept, 4, 1);
+        assert_eq!(kept.centers[1], spiked);
+
+        // The spike is the only point in its cell, and only the second section has it
+        let mut filtered = sections();
+        filter_section_support(&mut filtered, 4, 2);
+        assert_eq!(filtered.centers, vec![square.clone(), square.clone(), square.clone()]);
+        assert_eq!(filtered.heights, vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_repair() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        let (clean, _) = build_mesh_opts(Geometry { positions: positions.clone(), indices: indices.clone(), materials: Vec::new() }, &ProcessOptions::default()).unwrap();
+
+        // A sliver on the edge from vertex 0 to 1 through a midpoint, and a stray vertex
+        positions.extend_from_slice(&[1.5, 0.0, 0.0, 9.0, 9.0, 9.0]);
+        indices.extend_from_slice(&[0, 8, 1]);
+        let materials: Vec<u32> = (0..13).collect();
+        let geometry = Geometry { positions, indices, materials };
+        let opts = ProcessOptions { repair: Repair::all(), inertia: InertiaWeighting::Vertices, ..Default::default() };
+        let (repaired, materials) = build_mesh_opts(geometry.clone(), &opts).unwrap();
+        assert_eq!(repaired.no_faces(), 12);
+        assert_eq!(repaired.no_vertices(), 8);
+        assert_eq!(materials, (0..12).collect::<Vec<u32>>());
+        let pit = |mesh: &Mesh| pit_transform(mesh, InertiaWeighting::Vertices).unwrap();
+        assert_eq!(pit(&repaired), pit(&clean));
+
+        let opts = ProcessOptions { repair: Repair { drop_degenerate: true, ..Default::default() }, ..Default::default() };
+        let (unpruned, _) = build_mesh_opts(geometry, &opts).unwrap();
+        assert_eq!(unpruned.no_faces(), 12);
+        assert_eq!(unpruned.no_vertices(), 10);
+    }
+
+    #[test]
+    fn test_components() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.5]);
+        let body = geometry_obj(&positions, &indices);
+        // Debris: a small box and a flat triangle, away from the body
+        let (mut debris, mut debris_indices) = (Vec::new(), Vec::new());
+        push_box(&mut debris, &mut debris_indices, [0.0; 3], [0.2, 0.3, 0.5]);
+        let base = (positions.len() / 3) as u32;
+        push_box(&mut positions, &mut indices, [8.0, 5.0, 1.0], [0.2, 0.3, 0.5]);
+        positions.extend_from_slice(&[-4.0, 0.0, 0.0, -4.5, 0.0, 0.0, -4.0, 0.5, 0.0]);
+        indices.extend_from_slice(&[base + 8, base + 9, base + 10]);
+        let scan = geometry_obj(&positions, &indices);
+
+        let params = P3DParams::default().grid_size(8).n_sections(6);
+        let hashes = p3d_process_cfg(body.as_bytes(), InputFileType::Obj, &params).unwrap();
+        assert_ne!(p3d_process_cfg(scan.as_bytes(), InputFileType::Obj, &params).unwrap(), hashes);
+        let repair = Repair { largest_component: true, prune_unused: true, ..Default::default() };
+        let cleaned = P3DParams { options: ProcessOptions { repair, ..Default::default() }, ..params.clone() };
+        assert_eq!(p3d_process_cfg(scan.as_bytes(), InputFileType::Obj, &cleaned).unwrap(), hashes);
+
+        let parts = p3d_process_components(scan.as_bytes(), InputFileType::Obj, &params).unwrap();
+        assert_eq!(parts.iter().map(|p| p.faces).collect::<Vec<_>>(), vec![12, 12, 1]);
+        assert!((parts[0].area - 2.0 * (6.0 + 4.5 + 3.0)).abs() < 1e-9);
+        assert!(parts[0].area > parts[1].area && parts[1].area > parts[2].area);
+        assert_eq!(parts[0].hashes.as_ref().unwrap(), &hashes);
+        let alone = p3d_process_cfg(geometry_obj(&debris, &debris_indices).as_bytes(), InputFileType::Obj, &params).unwrap();
+        assert_eq!(parts[1].hashes.as_ref().unwrap(), &alone);
+        assert!(matches!(parts[2].hashes, Err(P3DError::FlatObject { .. })));
+
+        let (components, count) = components::face_components(positions.len() / 3, &indices);
+        assert_eq!(count, 3);
+        assert_eq!(components[..12], [0; 12]);
+        assert!(matches!(
+            p3d_process_components(scan.as_bytes(), InputFileType::Obj, &params.clone().algo(AlgoType::CloudGrid)),
+            Err(P3DError::InvalidParams { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decimation() {
+        let obj = sphere_obj(1.0, 24, 32);
+        let geometry = load_geometry(obj.as_bytes(), InputFileType::Obj, &ProcessOptions::default()).unwrap();
+        let faces = geometry.indices.len() / 3;
+        let (positions, indices, kept) = decimate::decimate(&geometry.positions, &geometry.indices, Decimation::Faces(300));
+        assert!(indices.len() / 3 <= 300 && indices.len() / 3 > 250, "{} faces left", indices.len() / 3);
+        assert_eq!(kept.iter().filter(|k| **k).count(), indices.len() / 3);
+        assert!(positions.chunks(3).all(|p| (libm::sqrt(p[0] * p[0] + p[1] * p[1] + p[2] * p[2]) - 1.0).abs() < 0.05));
+        // Still closed: every edge between two faces
+        let mut edges: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+        for t in indices.chunks(3) {
+            for k in 0..3 {
+                let (u, v) = (t[k], t[(k + 1) % 3]);
+                *edges.entry((u.min(v), u.max(v))).or_insert(0) += 1;
+            }
+        }
+        assert!(edges.values().all(|&n| n == 2));
+        assert_eq!(decimate::decimate(&geometry.positions, &geometry.indices, Decimation::Faces(300)), (positions, indices, kept));
+
+        let (_, exact, _) = decimate::decimate(&geometry.positions, &geometry.indices, Decimation::MaxError(0.0));
+        assert_eq!(exact.len() / 3, faces);
+        let (_, coarse, _) = decimate::decimate(&geometry.positions, &geometry.indices, Decimation::MaxError(0.01));
+        assert!(coarse.len() / 3 < faces);
+
+        // Open meshes keep their border
+        let (_, open, _) = decimate::decimate(&geometry.positions, &geometry.indices[3..], Decimation::Faces(0));
+        assert!(open.contains(&0) && open.chunks(3).all(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0]));
+
+        let params = P3DParams::default().grid_size(8).n_sections(10);
+        let decimated = P3DParams { options: ProcessOptions { decimation: Some(Decimation::Faces(400)), ..Default::default() }, ..params.clone() };
+        let hashes = p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &decimated).unwrap();
+        assert_eq!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &decimated).unwrap(), hashes);
+        let invalid = P3DParams { options: ProcessOptions { decimation: Some(Decimation::MaxError(-1.0)), ..Default::default() }, ..params };
+        assert!(matches!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &invalid), Err(P3DError::InvalidParams { .. })));
+    }
+
+    #[test]
+    fn test_grid_normals() {
+        // A 1 x 2 x 4 box with a flat top or a low pyramid on it: every section is below the
+        // roof, so the contours are the same rectangles, but the roof tilts the vertex normals
+        // at the top of the walls the sections interpolate
+        let box_with_top = |roof: f64| {
+            let mut positions: Vec<f64> = box_vertices([0.0; 3], [1.0, 2.0, 4.0]).into_iter().flatten().collect();
+            let mut faces: Vec<[u32; 3]> = BOX_FACES[..].to_vec();
+            if roof > 0.0 {
+                positions.extend([0.5, 1.0, 4.0 + roof]);
+                faces.retain(|f| f != &[5, 6, 8] && f != &[5, 8, 7]);
+                faces.extend([[5, 6, 9], [6, 8, 9], [8, 7, 9], [7, 5, 9]]);
+            }
+            let indices: Vec<u32> = faces.iter().flat_map(|f| f.iter().map(|i| i - 1)).collect();
+            geometry_obj(&positions, &indices)
+        };
+        let opts = ProcessOptions { align: false, ..Default::default() };
+        let process = |obj: &str, algo| p3d_process_opts(obj.as_bytes(), InputFileType::Obj, algo, 6, 4, 10, None, &opts).unwrap();
+        let (flat, roofed) = (box_with_top(0.0), box_with_top(0.2));
+        assert_eq!(process(&flat, AlgoType::Grid2dV3a), process(&roofed, AlgoType::Grid2dV3a));
+
+        let hashes = process(&flat, AlgoType::GridNormals);
+        assert!(!hashes.is_empty() && hashes.len() <= 6);
+        assert_eq!(process(&flat, AlgoType::GridNormals), hashes);
+        assert_ne!(process(&roofed, AlgoType::GridNormals), hashes);
+        assert_ne!(process(&flat, AlgoType::CellStats), hashes);
+
+        // Planes, grid and mesh are those of the slicing, kept or not
+        let mesh = P3DMesh::load_opts(flat.as_bytes(), InputFileType::Obj, &opts).unwrap();
+        assert_eq!(mesh.process(AlgoType::GridNormals, 6, 4, 10, None).unwrap(), hashes);
+        assert_eq!("grid_normals".parse::<AlgoType>().unwrap(), AlgoType::GridNormals);
+        assert!(matches!(p3d_process_spacing(flat.as_bytes(), InputFileType::Obj, AlgoType::GridNormals, 0.5, 4, None), Err(P3DError::InvalidParams { .. })));
+    }
+
+    #[test]
+    fn test_cell_stats() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let statistics = [CellStatistic::Count, CellStatistic::Mean, CellStatistic::StdDev, CellStatistic::Skewness, CellStatistic::Entropy];
+        let hashes: Vec<Vec<String>> = statistics.iter()
+            .map(|&cell_statistic| {
+                let opts = ProcessOptions { cell_statistic, ..Default::default() };
+                let hashes = p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::CellStats, 6, 8, 10, None, &opts).unwrap();
+                assert!(!hashes.is_empty() && hashes.len() <= 6);
+                assert_eq!(p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::CellStats, 6, 8, 10, None, &opts).unwrap(), hashes);
+                hashes
+            })
+            .collect();
+        for (i, a) in hashes.iter().enumerate() {
+            assert!(hashes[i + 1..].iter().all(|b| b != a), "{:?} hashes like another statistic", statistics[i]);
+        }
+        // The default statistic, also from its name
+        let params = P3DParams::default().algo("cell_stats".parse().unwrap()).grid_size(8).n_sections(10).depth(6);
+        assert_eq!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap(), hashes[2]);
+
+        // A point at the center of a cell, then one in every sub-cell
+        let rect = Rect::new(0.0, 4.0, 0.0, 4.0);
+        let center = vec![vec![Vec2::new(0.5, 0.5)]];
+        let spread = vec![(0..16).map(|k| Vec2::new((k % 4) as f64 / 4.0 + 0.125, (k / 4) as f64 / 4.0 + 0.125)).collect::<Vec<_>>()];
+        let single = |centers: &Vec<Vec<Vec2>>, statistic, depth| cell_stats::find_top_cell_stats(centers, depth, 4, &rect, statistic, HashAlgo::Sha256);
+        let mut cells = vec![0u8; 16];
+        cells[0] = 1;
+        assert_eq!(single(&center, CellStatistic::Mean, 1), vec![digest(HashAlgo::Sha256, [cells.as_slice()])]);
+        cells[0] = 2;
+        assert_eq!(single(&spread, CellStatistic::Entropy, 1), vec![digest(HashAlgo::Sha256, [cells.as_slice()])]);
+    }
+
+    #[test]
+    fn test_adaptive_sections() {
+        // Steps at z = 0, 1 and 2, straight walls in between
+        let (mesh, _) = load_mesh(l_shape_obj(1.0, [0.0; 3]).as_bytes(), InputFileType::Obj, &ProcessOptions::default()).unwrap();
+        let heights = adaptive_heights(&mesh, 0.0, 2.0, 10);
+        assert_eq!(heights.len(), 10);
+        assert!(heights.windows(2).all(|w| w[0] < w[1]));
+        assert!(heights[0] > 0.0 && heights[9] < 2.0);
+        let near_step = heights.iter().filter(|&&z| (z - 1.0).abs() < 0.2).count();
+        assert!(near_step > 2, "{:?}", heights);
+        assert!(heights[1] < 2.0 / 11.0, "{:?}", heights);
+
+        let obj = sphere_obj(1.0, 24, 32);
+        let process = |section_placement: SectionPlacement| {
+            let options = ProcessOptions { section_placement, ..Default::default() };
+            p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &P3DParams::default().options(options)).unwrap()
+        };
+        let adaptive = process(SectionPlacement::Adaptive);
+        assert!(!adaptive.is_empty());
+        assert_eq!(adaptive, process(SectionPlacement::Adaptive));
+        assert_ne!(adaptive, process(SectionPlacement::Uniform));
+    }
+
+    #[test]
+    fn test_slice_axis() {
+        let obj = sphere_obj(1.0, 24, 32);
+        let process = |slice_axis: SliceAxis, canonical_order: bool| {
+            let options = ProcessOptions { slice_axis, canonical_order, ..Default::default() };
+            p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &P3DParams::default().options(options)).unwrap()
+        };
+        let (x, y, z) = (process(SliceAxis::X, false), process(SliceAxis::Y, false), process(SliceAxis::Z, false));
+        assert_eq!(z, p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &P3DParams::default()).unwrap());
+        assert!(x != z && y != z && x != y);
+
+        let all = process(SliceAxis::All, false);
+        assert_eq!(all.len(), x.len().min(y.len()).min(z.len()));
+        assert!(!all.is_empty());
+        assert!(all.iter().all(|h| !x.contains(h) && !y.contains(h) && !z.contains(h)));
+        let canonical = process(SliceAxis::All, true);
+        assert_eq!(canonical.len(), all.len());
+        assert!(canonical.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_p3d_validate() {
+        let validate = |positions: &[f64], indices: &[u32]| p3d_validate(geometry_obj(positions, indices).as_bytes(), InputFileType::Obj).unwrap();
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [1.0, 0.0, 0.0], [3.0, 2.0, 1.0]);
+
+        let report = validate(&positions, &indices);
+        assert!(report.is_watertight());
+        assert_eq!((report.vertices, report.faces, report.components, report.degenerate_faces), (8, 12, 1, 0));
+        assert_eq!((report.min, report.max), ([1.0, 0.0, 0.0], [4.0, 2.0, 1.0]));
+        assert!((report.volume.abs() - 6.0).abs() < 1e-12);
+
+        let report = validate(&positions, &indices[3..]);
+        assert!(!report.is_watertight());
+        assert_eq!((report.boundary_edges, report.non_manifold_edges), (3, 0));
+
+        let mut fin = indices.clone();
+        fin.extend_from_slice(&indices[..3]);
+        assert_eq!(validate(&positions, &fin).non_manifold_edges, 3);
+
+        push_box(&mut positions, &mut indices, [5.0, 0.0, 0.0], [1.0, 1.0, 1.0]);
+        let report = validate(&positions, &indices);
+        assert!(report.is_watertight());
+        assert_eq!(report.components, 2);
+        assert!(matches!(p3d_validate(b"v 0 0 0\n", InputFileType::Obj), Err(P3DError::EmptyMesh)));
+    }
+
+    #[test]
+    fn test_malformed_indices() {
+        let geometry = Geometry {
+            positions: vec![0., 0., 0., 1., 0., 0., 0., 1., 0., 0., 0., 1.],
+            indices: vec![0, 2, 1, 0, 1, 3, 0],
+            materials: Vec::new(),
+        };
+        match build_mesh(geometry, None, Repair::default()) {
+            Err(P3DError::MalformedIndices { indices, vertices }) => assert_eq!((indices, vertices), (7, 4)),
+            other => panic!("Expected MalformedIndices, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_skeleton_follows_bend() {
+        // Rod of unit boxes, straight up to z = 4, then leaning towards +X by half a unit per box
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for k in 0..8 {
+            let x = if k < 4 { 0.0 } else { (k - 3) as f64 * 0.5 };
+            push_box(&mut positions, &mut indices, [x, 0.0, k as f64], [1.0, 1.0, 1.0]);
+        }
+        let obj = geometry_obj(&positions, &indices);
+
+        let opts = ProcessOptions { align: false, ..Default::default() };
+        let res = p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Skeleton, 10, 8, 10, None, &opts).unwrap();
+        assert_eq!(res.hashes.len(), 1);
+
+        let axis = skeleton::skeleton(&res.centers);
+        for (c, z) in axis.iter().zip(res.section_heights.iter()) {
+            let expected = if *z < 4.0 { 0.5 } else { 0.5 + (libm::floor(*z) - 3.0) * 0.5 };
+            assert!((c.x - expected).abs() < 1e-6 && (c.y - 0.5).abs() < 1e-6, "Centroid {:?} at z = {}, expected x = {}", c, z, expected);
+        }
+    }
+
+    #[test]
+    fn test_self_intersection_check() {
+        let opts = ProcessOptions { check_self_intersection: true, ..Default::default() };
+        let single = box_obj(2.0, 2.0, 2.0);
+        assert!(p3d_process_opts(single.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 10, None, &opts).is_ok());
+
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [2.0, 2.0, 2.0]);
+        push_box(&mut positions, &mut indices, [0.7, 0.6, 0.5], [2.0, 2.0, 2.0]);
+        let overlapping = geometry_obj(&positions, &indices);
+        let result = p3d_process_opts(overlapping.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 10, None, &opts);
+        assert!(matches!(result, Err(P3DError::SelfIntersecting)), "Expected SelfIntersecting, got {:?}", result);
+
+        let unchecked = ProcessOptions::default();
+        assert!(p3d_process_opts(overlapping.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 10, None, &unchecked).is_ok());
+    }
+
+    #[test]
+    fn test_inertia_region() {
+        // Same body with a differently placed thin appendage
+        let with_spike = |min: [f64; 3], size: [f64; 3]| {
+            let mut positions = Vec::new();
+            let mut indices = Vec::new();
+            push_box(&mut positions, &mut indices, [0.0; 3], [4.0, 2.0, 1.0]);
+            push_box(&mut positions, &mut indices, min, size);
+            let obj = geometry_obj(&positions, &indices);
+            load_mesh(obj.as_bytes(), InputFileType::Obj, &ProcessOptions::default()).unwrap().0
+        };
+        let a = with_spike([5.0, 0.0, 0.0], [0.3, 0.3, 3.0]);
+        let b = with_spike([5.0, 1.5, 0.0], [0.3, 0.3, 5.0]);
+
+        let close = |x: Matrix4<f64>, y: Matrix4<f64>| {
+            let (x, y): ([[f64; 4]; 4], [[f64; 4]; 4]) = (x.into(), y.into());
+            x.iter().flatten().zip(y.iter().flatten()).all(|(p, q)| (p - q).abs() < 1e-9)
+        };
+        let (_, tr_a) = pit_transform(&a, InertiaWeighting::Volume).unwrap();
+        let (_, tr_b) = pit_transform(&b, InertiaWeighting::Volume).unwrap();
+        assert!(!close(tr_a, tr_b), "The appendage should move the axes");
+
+        let body = (Vector3::new(-0.1, -0.1, -0.1), Vector3::new(4.1, 2.1, 1.1));
+        let (shift_a, tr_a) = pit_transform_in(&a, InertiaWeighting::Volume, Some(&body), false).unwrap();
+        let (shift_b, tr_b) = pit_transform_in(&b, InertiaWeighting::Volume, Some(&body), false).unwrap();
+        assert!(close(tr_a, tr_b), "Masked alignment still depends on the appendage");
+        assert!((shift_a - shift_b).magnitude() < 1e-9);
+
+        let nowhere = (Vector3::new(10.0, 10.0, 10.0), Vector3::new(11.0, 11.0, 11.0));
+        assert!(matches!(pit_transform_in(&a, InertiaWeighting::Volume, Some(&nowhere), false), Err(P3DError::DegenerateMesh)));
+    }
+
+    #[test]
+    fn test_invalid_mesh_message() {
+        let geometry = Geometry {
+            positions: vec![0., 0., 0., 1., 0., 0., 0., 1., 0., 0., 0., 1.],
+            indices: vec![0, 2, 1, 0, 1, 9],
+            materials: Vec::new(),
+        };
+        match build_mesh(geometry, None, Repair::default()) {
+            Err(err @ P3DError::InvalidMesh { .. }) => {
+                assert!(matches!(err, P3DError::InvalidMesh { reason: "index out of range", indices: 6, vertices: 4 }));
+                assert_eq!(err.to_string(), "invalid mesh: index out of range (6 indices, 4 vertices)");
+            }
+            other => panic!("Expected InvalidMesh, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn test_boundary_snap() {
+        // Cell boundary at x = 0.25, points a hair on either side of it
+        let sections = |step: f64| {
+            let mut sections = Sections {
+                centers: vec![vec![Vec2::new(0.25 - 1e-12, 0.5), Vec2::new(0.25 + 1e-12, 0.5)]],
+                heights: vec![1.0],
+                materials: Vec::new(),
+                loops: Vec::new(),
+                rect: Rect::new(0.0, 1.0, 0.0, 1.0),
+                n_sections: 1,
+                out_of_bounds: 0,
+            };
+            snap_sections(&mut sections, step);
+            sections
+        };
+
+        let raw = sections(0.0);
+        assert_ne!(raw.rect.cell(&raw.centers[0][0], 4), raw.rect.cell(&raw.centers[0][1], 4));
+
+        let snapped = sections(1.0 / 64.0);
+        assert_eq!(snapped.centers[0][0], Vec2::new(0.25, 0.5));
+        assert_eq!(snapped.rect.cell(&snapped.centers[0][0], 4), (1, 2));
+        assert_eq!(snapped.rect.cell(&snapped.centers[0][1], 4), (1, 2));
+    }
+
+    #[test]
+    fn test_section_polygons_area() {
+        let obj = box_obj(2.0, 2.0, 3.0);
+        let opts = ProcessOptions { align: false, ..Default::default() };
+        let polygons = section_polygons_opts(obj.as_bytes(), InputFileType::Obj, 5, None, &opts).unwrap();
+        assert_eq!(polygons.len(), 5);
+        for triangles in polygons.iter() {
+            let area: f64 = triangles.iter().map(|t| triangulate::signed_area(t)).sum();
+            assert!((area - 4.0).abs() < 1e-9, "Section area {}, expected 4", area);
+        }
+
+        // Concave L-shaped loop, clockwise
+        let l_loop = [
+            Vec2::new(0.0, 0.0), Vec2::new(0.0, 2.0), Vec2::new(1.0, 2.0),
+            Vec2::new(1.0, 1.0), Vec2::new(2.0, 1.0), Vec2::new(2.0, 0.0), Vec2::new(0.0, 0.0),
+        ];
+        let triangles = triangulate::ear_clip(&l_loop);
+        assert_eq!(triangles.len(), 4);
+        let area: f64 = triangles.iter().map(|t| triangulate::signed_area(t)).sum();
+        assert!((area - 3.0).abs() < 1e-12);
+
+        // Square tube, 4 x 4 around a 2 x 2 hole
+        let square = |lo: f64, hi: f64| [[lo, lo], [hi, lo], [hi, hi], [lo, hi]];
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for (k, corners) in [square(0.0, 4.0), square(1.0, 3.0)].iter().enumerate() {
+            let base = (positions.len() / 3) as u32;
+            for z in [0.0, 2.0].iter() {
+                positions.extend(corners.iter().flat_map(|c| vec![c[0], c[1], *z]));
+            }
+            for i in 0..4u32 {
+                let (a, b) = (base + i, base + (i + 1) % 4);
+                let quad = if k == 0 { [a, b, b + 4, a, b + 4, a + 4] } else { [b, a, a + 4, b, a + 4, b + 4] };
+                indices.extend_from_slice(&quad);
+            }
+        }
+        let tube = geometry_obj(&positions, &indices);
+        let polygons = section_polygons_opts(tube.as_bytes(), InputFileType::Obj, 3, None, &opts).unwrap();
+        assert_eq!(polygons.len(), 3);
+        for triangles in polygons.iter() {
+            let area: f64 = triangles.iter().map(|t| triangulate::signed_area(t)).sum();
+            assert!((area - 12.0).abs() < 1e-9, "Section area {}, expected 12", area);
+        }
+
+        // Two holes side by side, and an island in the second
+        let rect = |x0: f64, y0: f64, x1: f64, y1: f64, ccw: bool| {
+            let mut l = vec![Vec2::new(x0, y0), Vec2::new(x1, y0), Vec2::new(x1, y1), Vec2::new(x0, y1)];
+            if !ccw {
+                l.reverse();
+            }
+            l.push(l[0]);
+            l
+        };
+        let loops = [
+            rect(0.0, 0.0, 7.0, 3.0, true),
+            rect(1.0, 1.0, 2.0, 2.0, false),
+            rect(3.0, 0.5, 6.0, 2.5, false),
+            rect(4.0, 1.0, 5.0, 2.0, true),
+        ];
+        let triangles = triangulate::fill_loops(&loops);
+        assert!(triangles.iter().all(|t| triangulate::signed_area(t) > 0.0));
+        let area: f64 = triangles.iter().map(|t| triangulate::signed_area(t)).sum();
+        assert!((area - (21.0 - 1.0 - 6.0 + 1.0)).abs() < 1e-12, "Area {}", area);
+
+        assert!(matches!(
+            section_polygons_opts(obj.as_bytes(), InputFileType::Obj, -1, None, &opts),
+            Err(P3DError::InvalidParams { .. })
+        ));
+    }
+
+    #[test]
+    fn test_process_spacing() {
+        let obj = box_obj(2.0, 3.0, 10.0);
+        let (mesh, face_materials) = load_mesh(obj.as_bytes(), InputFileType::Obj, &ProcessOptions::default()).unwrap();
+        let sections = slice_mesh_spacing(&mesh, &face_materials, &AlgoType::Grid2dV3a, 10, 1.0, &ProcessOptions::default()).unwrap();
+        assert_eq!(sections.n_sections, 9);
+        assert_eq!(sections.heights, (1..10).map(|k| k as f64).collect::<Vec<f64>>());
+
+        assert!(p3d_process_spacing(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 1.0, 8, None).is_ok());
+        assert!(matches!(
+            p3d_process_spacing(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 100.0, 8, None),
+            Err(P3DError::DegenerateMesh)
+        ));
+    }
+
+    #[cfg(feature = "cache")]
+    #[test]
+    fn test_mesh_cache() {
+        let dir = std::env::temp_dir().join(format!("p3d-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("box.obj");
+        std::fs::write(&path, box_obj(3.0, 2.0, 1.0)).unwrap();
+
+        let mut cache = MeshCache::new();
+        let first = cache.process(&path, AlgoType::Grid2dV3a, 8, 10, None).unwrap();
+        let second = cache.process(&path, AlgoType::Grid2dV3a, 8, 10, None).unwrap();
+        assert_eq!(first, second);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        // Rewritten with another mesh and a later modification time
+        std::fs::write(&path, box_obj(1.0, 2.0, 3.0)).unwrap();
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(10)).unwrap();
+        let results = p3d_process_paths(&[path.as_path()], &mut cache, AlgoType::Grid2dV3a, 8, 10, None);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+        assert!(results[0].is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // UV sphere of radius `r`, as OBJ text
+    fn sphere_obj(r: f64, rings: u32, segments: u32) -> String {
+        let mut positions = vec![0.0, 0.0, r];
+        for i in 1..rings {
+            let theta = core::f64::consts::PI * i as f64 / rings as f64;
+            for j in 0..segments {
+                let phi = 2.0 * core::f64::consts::PI * j as f64 / segments as f64;
+                positions.extend([r * theta.sin() * phi.cos(), r * theta.sin() * phi.sin(), r * theta.cos()]);
+            }
+        }
+        positions.extend([0.0, 0.0, -r]);
+        let bottom = (positions.len() / 3 - 1) as u32;
+        let ring = |i: u32, j: u32| 1 + (i - 1) * segments + j % segments;
+        let mut indices = Vec::new();
+        for j in 0..segments {
+            indices.extend([0, ring(1, j), ring(1, j + 1)]);
+            indices.extend([bottom, ring(rings - 1, j + 1), ring(rings - 1, j)]);
+        }
+        for i in 1..rings - 1 {
+            for j in 0..segments {
+                indices.extend([ring(i, j), ring(i + 1, j), ring(i + 1, j + 1)]);
+                indices.extend([ring(i, j), ring(i + 1, j + 1), ring(i, j + 1)]);
+            }
+        }
+        geometry_obj(&positions, &indices)
+    }
+
+    #[test]
+    fn test_robustness() {
+        let process = |obj: String| p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 10, None, &ProcessOptions::default()).unwrap();
+        let asymmetric = process(box_obj(3.0, 2.0, 1.0));
+        let sphere = process(sphere_obj(1.0, 16, 24));
+
+        for r in [&asymmetric, &sphere] {
+            assert!((0.0..=1.0).contains(&r.robustness), "Robustness out of range: {}", r.robustness);
+        }
+        assert!(asymmetric.robustness > sphere.robustness, "Box {} <= sphere {}", asymmetric.robustness, sphere.robustness);
+    }
+
+    // The tetrahedron of `two_mesh_glb` ("second") seen by a camera turned to look down +X
+    fn camera_glb() -> Vec<u8> {
+        let h = core::f32::consts::FRAC_1_SQRT_2;
+        let mut bin = f32_bytes(&[0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.]);
+        bin.extend(u32_bytes(&TETRA_FACES));
+        let json = format!(r#"{{
+          "asset": {{ "version": "2.0" }},
+          "buffers": [ {{ "byteLength": 96 }} ],
+          "bufferViews": [
+            {{ "buffer": 0, "byteOffset": 0, "byteLength": 48 }},
+            {{ "buffer": 0, "byteOffset": 48, "byteLength": 48 }}
+          ],
+          "accessors": [
+            {{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [2, 1, 3] }},
+            {{ "bufferView": 1, "componentType": 5125, "count": 12, "type": "SCALAR" }}
+          ],
+          "meshes": [ {{ "primitives": [ {{ "attributes": {{ "POSITION": 0 }}, "indices": 1 }} ] }} ],
+          "cameras": [ {{ "type": "perspective", "perspective": {{ "yfov": 0.8, "znear": 0.1 }} }} ],
+          "nodes": [
+            {{ "mesh": 0 }},
+            {{ "translation": [-5, 0, 0], "children": [2] }},
+            {{ "camera": 0, "rotation": [0, {}, 0, {}] }}
+          ],
+          "scenes": [ {{ "nodes": [0, 1] }} ]
+        }}"#, -h, h);
+        make_glb(&json, &bin)
+    }
+
+    #[test]
+    fn test_view_camera_axis() {
+        let glb = camera_glb();
+        let opts = ProcessOptions { view_camera: Some(0), ..Default::default() };
+        let viewed = load_geometry(&glb, InputFileType::Glb, &opts).unwrap();
+        // The camera forward (+X) becomes +Z, its up (+Y) stays
+        let expected = [0., 0., 0., 0., 0., 2., 0., 1., 0., -3., 0., 0.];
+        for (p, e) in viewed.positions.iter().zip(expected.iter()) {
+            assert!((p - e).abs() < 1e-6, "Viewed positions {:?}, expected {:?}", viewed.positions, expected);
+        }
+
+        // Sliced along the forward vector: the Z extent is the X extent of the input
+        let res = p3d_process_detailed(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts).unwrap();
+        assert!((res.v_max.z - res.v_min.z - 2.0).abs() < 1e-6, "Z extent {:?} .. {:?}", res.v_min, res.v_max);
+        assert_eq!(res.transform, Matrix4::identity());
+
+        let opts = ProcessOptions { view_camera: Some(1), ..Default::default() };
+        assert!(matches!(load_geometry(&glb, InputFileType::Glb, &opts), Err(P3DError::ParseError { format: Format::Gltf, .. })));
+    }
+
+    #[cfg(feature = "obj_parser")]
+    #[test]
+    fn test_obj_parser() {
+        let obj = "# comment\nmtllib box.mtl\no part\ng top\n\
+            v 0 0 0.1234567890123\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 9 9 9\nvt 0 0\n\
+            usemtl red\ns off\nf 1/1 2/1 3/1 4/1\ng bottom\nf -5 -3 -2 # relative\n";
+        let model = obj_parser::parse(obj.as_bytes(), Precision::F64).unwrap();
+        // The quad is split into a fan, the unused vertex 5 is dropped, and f64 precision is kept
+        assert_eq!(model.indices, vec![0, 1, 2, 0, 2, 3, 0, 2, 3]);
+        assert_eq!(model.positions.len(), 12);
+        assert_eq!(model.positions[2], 0.1234567890123);
+
+        for bad in ["v 1 2\n", "v 0 0 0\nf 1 2 3\n", "v 0 0 0\nv 1 0 0\nf 1 2\n", "v 0 0 x\n"] {
+            assert!(matches!(obj_parser::parse(bad.as_bytes(), Precision::F64), Err(P3DError::ParseError { format: Format::Obj, .. })), "Accepted {:?}", bad);
+        }
+    }
+
+    #[cfg(feature = "obj_parser")]
+    #[test]
+    fn test_obj_concave_face() {
+        // L shaped hexagon starting next to the reflex corner (1, 1), where a fan folds over
+        let corners = [[1.0, 2.0], [0.0, 2.0], [0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0]];
+        for (flip, z) in [(false, 1.0), (true, -1.0)] {
+            let mut obj = String::new();
+            for c in corners.iter() {
+                obj.push_str(&format!("v {} {} 0\n", c[0], c[1]));
+            }
+            obj.push_str(if flip { "f 6 5 4 3 2 1\n" } else { "f 1 2 3 4 5 6\n" });
+            let model = obj_parser::parse(obj.as_bytes(), Precision::F64).unwrap();
+            assert_eq!(model.indices.len(), 4 * 3);
+            let mut area = 0.0;
+            for t in model.indices.chunks(3) {
+                let p = |k: usize| Vec2::new(model.positions[t[k] as usize * 3], model.positions[t[k] as usize * 3 + 1]);
+                let a = triangulate::signed_area(&[p(0), p(1), p(2)]) * z;
+                assert!(a > 0.0, "Folded triangle {:?}", t);
+                area += a;
+            }
+            assert!((area - 3.0).abs() < 1e-12);
+        }
+    }
+
+    #[cfg(feature = "obj_parser")]
+    #[test]
+    fn test_obj_stream() {
+        let params = P3DParams::default();
+        let mut obj = sphere_obj(1.0, 12, 16);
+        // Positions no face uses, a duplicate, normals and CRLF line ends
+        obj = format!("v 9 9 9\r\nvn 0 0 1\r\n{}v 0 0 1\nf 1 2 3", obj.replace("\n", "\r\n"));
+        let whole = p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap();
+        assert!(!whole.is_empty());
+        for size in [1, 7, 4096] {
+            let mut stream = ObjStream::new(Precision::F32, &params.options);
+            for chunk in obj.as_bytes().chunks(size) {
+                stream.feed(chunk).unwrap();
+            }
+            let mesh = stream.finish().unwrap();
+            assert_eq!(mesh.process(params.algo, params.depth, params.grid_size, params.n_sections, None).unwrap(), whole);
+        }
+        #[cfg(feature = "std")]
+        assert_eq!(p3d_process_obj_reader(obj.as_bytes(), Precision::F32, &params).unwrap(), whole);
+
+        let mut stream = ObjStream::new(Precision::F64, &params.options);
+        stream.feed(obj.as_bytes()).unwrap();
+        assert!(!stream.finish().unwrap().process(params.algo, 10, 8, 10, None).unwrap().is_empty());
+
+        // Errors carry the line they were found on, across chunks
+        let mut stream = ObjStream::new(Precision::F64, &params.options);
+        stream.feed(b"v 0 0 0\nv 1 0").unwrap();
+        stream.feed(b" 0\nf 1 2 5\n").unwrap_err();
+        let mut stream = ObjStream::new(Precision::F64, &params.options);
+        stream.feed(b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 x").unwrap();
+        assert!(matches!(stream.finish(), Err(P3DError::ParseError { format: Format::Obj, offset: Offset::Line(4), .. })));
+        let limits = Limits { max_input_bytes: 10, ..Default::default() };
+        let mut stream = ObjStream::new(Precision::F64, &ProcessOptions { limits, ..Default::default() });
+        assert!(matches!(stream.feed(obj.as_bytes()), Err(P3DError::LimitExceeded { .. })));
+    }
+
+    #[cfg(feature = "obj_parser")]
+    #[test]
+    fn test_obj_parser_parity() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        // Coordinates f32 can't hold exactly, so the two must round them the same way
+        push_box(&mut positions, &mut indices, [0.1, -0.2, 0.3], [3.3, 2.7, 1.1]);
+        push_box(&mut positions, &mut indices, [0.5, 0.25, 1.4], [1.0, 1.0 / 3.0, 2.5123456789]);
+        assert!(positions.iter().any(|&c| c as f32 as f64 != c));
+        let obj = geometry_obj(&positions, &indices);
+
+        let (old_positions, old_indices) = obj_crate_geometry(obj.as_bytes()).unwrap();
+        let (new_positions, new_indices) = obj_geometry(obj.as_bytes()).unwrap();
+        assert_eq!(old_positions, new_positions);
+        assert_eq!(old_indices, new_indices);
+        assert_ne!(obj_parser::parse(obj.as_bytes(), Precision::F64).unwrap().positions, new_positions);
+    }
+
+    #[test]
+    fn test_obj_split_seams() {
+        // Box with a normal and texture coordinate per side, so every corner is used with three
+        let mut obj = String::new();
+        for v in box_vertices([0.0; 3], [3.0, 2.0, 1.0]) {
+            obj.push_str(&format!("v {} {} {}\n", v[0], v[1], v[2]));
+        }
+        for side in 0..6 {
+            obj.push_str(&format!("vn {} {} {}\nvt {} 0\n", side % 3, (side + 1) % 3, (side + 2) % 3, side));
+        }
+        for (k, f) in BOX_FACES.iter().enumerate() {
+            let side = k / 2 + 1;
+            obj.push_str(&format!("f {}/{s}/{s} {}/{s}/{s} {}/{s}/{s}\n", f[0], f[1], f[2], s = side));
+        }
+
+        let (expanded, _) = obj_geometry(obj.as_bytes()).unwrap();
+        assert!(expanded.len() > 8 * 3);
+        let (mesh, _) = load_mesh(obj.as_bytes(), InputFileType::Obj, &ProcessOptions::default()).unwrap();
+        assert_eq!(mesh.no_faces(), BOX_FACES.len());
+        assert_eq!(mesh.no_vertices(), 8);
+        assert!(mesh.is_closed());
+
+        // Files without seams load as before
+        let plain = box_obj(3.0, 2.0, 1.0);
+        let (positions, indices) = obj_geometry(plain.as_bytes()).unwrap();
+        assert_eq!(load_geometry(plain.as_bytes(), InputFileType::Obj, &ProcessOptions::default()).unwrap().positions, positions);
+        assert_eq!(weld::merge_coincident(positions.clone(), indices.clone()), (positions, indices));
+    }
+
+    #[test]
+    fn test_fixed_point_scores() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let opts = ProcessOptions { fixed_point_scores: true, ..Default::default() };
+        let run = || p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap();
+        let (a, b) = (run(), run());
+        assert_eq!((&a.hashes, &a.scores), (&b.hashes, &b.scores));
+        assert!(!a.hashes.is_empty());
+        assert_eq!(a.scores.len(), a.hashes.len());
+        assert!(a.scores.windows(2).all(|w| w[0] < w[1]), "Scores not ascending: {:?}", a.scores);
+
+        // Same ranking as the float scores
+        let float = p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &ProcessOptions::default()).unwrap();
+        assert_eq!(a.hashes, float.hashes);
+        assert!(float.scores.is_empty());
+    }
+
+    #[test]
+    fn test_hash_details() {
+        let obj = sphere_obj(1.0, 12, 16);
+        let run = |algo: AlgoType, opts: ProcessOptions| p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, algo, 10, 8, 6, None, &opts).unwrap();
+        let plain = run(AlgoType::Grid2dV3a, ProcessOptions::default());
+        assert!(plain.hash_details.is_empty());
+
+        for algo in [AlgoType::Grid2dV3, AlgoType::Grid2dV3a] {
+            let res = run(algo, ProcessOptions { hash_details: true, ..Default::default() });
+            assert_eq!(res.hashes, run(algo, ProcessOptions::default()).hashes);
+            assert_eq!(res.hash_details.len(), res.hashes.len());
+            assert!(res.hash_details.windows(2).all(|w| w[0].score <= w[1].score));
+            for (rank, detail) in res.hash_details.iter().enumerate() {
+                assert_eq!(detail.rank, rank);
+                assert!(!detail.sections.is_empty());
+                assert!(detail.sections.windows(2).all(|w| w[0].section < w[1].section));
+                let total: f64 = detail.sections.iter().map(|s| s.score).sum();
+                assert!((total - detail.score).abs() <= 1e-9 * total.max(1.0));
+                for section in detail.sections.iter() {
+                    assert!(section.section < res.centers.len());
+                    assert!(section.cells.iter().all(|c| (0..8).contains(&c[0]) && (0..8).contains(&c[1])), "{:?}", section.cells);
+                }
+            }
+        }
+
+        let fixed = run(AlgoType::Grid2dV3a, ProcessOptions { hash_details: true, fixed_point_scores: true, ..Default::default() });
+        assert_eq!(fixed.scores.len(), fixed.hash_details.len());
+        let canonical = run(AlgoType::Grid2dV3a, ProcessOptions { hash_details: true, canonical_order: true, ..Default::default() });
+        assert!(canonical.hashes.windows(2).all(|w| w[0] < w[1]));
+        assert!(canonical.hash_details.iter().enumerate().all(|(rank, d)| d.rank == rank));
+        assert!(run(AlgoType::Grid2d, ProcessOptions { hash_details: true, ..Default::default() }).hash_details.is_empty());
+    }
+
+    #[test]
+    fn test_spectr_invariance() {
+        let n = 64;
+        let star: Vec<Vec2> = (0..n)
+            .map(|j| {
+                let t = (j as f64 + 0.5) * 2.0 * core::f64::consts::PI / n as f64;
+                let r = 1.0 + 0.3 * libm::cos(3.0 * t) + 0.1 * libm::sin(2.0 * t);
+                Vec2::new(r * libm::cos(t), r * libm::sin(t))
+            })
+            .collect();
+        // Quarter turn (a whole number of 16 bins), scaled and moved
+        let turned: Vec<Vec2> = star.iter().map(|p| Vec2::new(5.0 - 2.0 * p.y, 3.0 + 2.0 * p.x)).collect();
+        let rect = Rect::new(-10.0, 10.0, -10.0, 10.0);
+
+        let a = find_spectr(&vec![star.clone(), star.clone()], 10, 2, 16, rect.clone(), HashAlgo::Sha256);
+        let b = find_spectr(&vec![turned.clone(), turned], 10, 2, 16, rect.clone(), HashAlgo::Sha256);
+        assert!(!a.is_empty());
+        assert_eq!(a, b);
+
+        // Empty and single point sections give zero harmonics instead of NaN
+        let c = find_spectr(&vec![star.clone(), Vec::new(), vec![Vec2::new(1.0, 1.0)]], 10, 3, 16, rect.clone(), HashAlgo::Sha256);
+        assert!(!c.is_empty());
+
+        // Sections cut short count as empty ones rather than dropping every hash
+        let short = find_spectr(&vec![star.clone()], 10, 2, 16, rect.clone(), HashAlgo::Sha256);
+        assert_eq!(short, find_spectr(&vec![star, Vec::new()], 10, 2, 16, rect.clone(), HashAlgo::Sha256));
+        assert_eq!(short.len(), 8);
+        assert!(!find_spectr(&Vec::new(), 10, 2, 16, rect, HashAlgo::Sha256).is_empty());
+
+        let glb = include_bytes!("../test-ht.glb");
+        let spectr = p3d_process_n(glb, InputFileType::Glb, AlgoType::Spectr, 10, 8, 10, None).unwrap();
+        let grid = p3d_process_n(glb, InputFileType::Glb, AlgoType::Grid2d, 10, 8, 10, None).unwrap();
+        assert_ne!(spectr, grid);
+    }
+
+    #[test]
+    fn test_gltf_merges_meshes_and_primitives() {
+        let glb = two_mesh_glb();
+        let both = load_geometry(&glb, InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        assert_eq!(both.positions.len(), 8 * 3);
+        let second: Vec<u32> = TETRA_FACES.iter().map(|i| i + 4).collect();
+        assert_eq!(&both.indices[..12], &TETRA_FACES);
+        assert_eq!(&both.indices[12..], &second[..]);
+
+        // The same two tetrahedra as primitives of a single mesh
+        let glb = two_tetra_glb(r#""meshes": [ { "primitives": [
+            { "attributes": { "POSITION": 0 }, "indices": 2 }, { "attributes": { "POSITION": 1 }, "indices": 2 }
+        ] } ]"#);
+        let merged = load_geometry(&glb, InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        assert_eq!(merged.positions, both.positions);
+        assert_eq!(merged.indices, both.indices);
+    }
+
+    #[test]
+    fn test_gltf_node_transforms() {
+        // Mesh 0 instanced twice: under a translated parent with a non-uniform scale, and turned
+        // a quarter around Z. Node 4 is outside the scene, node 3 has no mesh.
+        let h = core::f32::consts::FRAC_1_SQRT_2;
+        let glb = two_tetra_glb(&format!(r#""meshes": [ {{ "primitives": [ {{ "attributes": {{ "POSITION": 0 }}, "indices": 2 }} ] }} ],
+          "nodes": [
+            {{ "translation": [10, 0, 0], "children": [1] }},
+            {{ "mesh": 0, "scale": [2, 1, 1] }},
+            {{ "mesh": 0, "rotation": [0, 0, {}, {}] }},
+            {{ "translation": [5, 5, 5] }},
+            {{ "mesh": 0, "translation": [-9, 0, 0] }}
+          ],
+          "scenes": [ {{ "nodes": [0, 2, 3] }} ],
+          "scene": 0"#, h, h));
+        let geometry = load_geometry(&glb, InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        let expected = [
+            10., 0., 0., 12., 0., 0., 10., 1., 0., 10., 0., 1.,
+            0., 0., 0., 0., 1., 0., -1., 0., 0., 0., 0., 1.,
+        ];
+        assert_eq!(geometry.positions.len(), expected.len());
+        for (p, e) in geometry.positions.iter().zip(expected.iter()) {
+            assert!((p - e).abs() < 1e-6, "Positions {:?}, expected {:?}", geometry.positions, expected);
+        }
+        assert_eq!(&geometry.indices[12..], &TETRA_FACES.iter().map(|i| i + 4).collect::<Vec<u32>>()[..]);
+    }
+
+    // Binary STL with one facet per triangle of `positions`/`indices`
+    fn geometry_stl(positions: &[f64], indices: &[u32]) -> Vec<u8> {
+        let mut stl = vec![0u8; 80];
+        stl.extend_from_slice(&((indices.len() / 3) as u32).to_le_bytes());
+        for f in indices.chunks(3) {
+            stl.extend(f32_bytes(&[0.0; 3]));
+            for &i in f {
+                let v = &positions[i as usize * 3..i as usize * 3 + 3];
+                stl.extend(f32_bytes(&[v[0] as f32, v[1] as f32, v[2] as f32]));
+            }
+            stl.extend_from_slice(&[0, 0]);
+        }
+        stl
+    }
+
+    #[test]
+    fn test_stl_input() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        let binary = geometry_stl(&positions, &indices);
+
+        // Welded back to the 8 corners of the box
+        let (welded, welded_indices) = stl::parse(&binary).unwrap();
+        assert_eq!(welded.len(), 8 * 3);
+        assert_eq!(welded_indices.len(), indices.len());
+
+        let mut ascii = String::from("solid box\n");
+        for f in indices.chunks(3) {
+            ascii.push_str("  facet normal 0 0 0\n    outer loop\n");
+            for &i in f {
+                let v = &positions[i as usize * 3..i as usize * 3 + 3];
+                ascii.push_str(&format!("      vertex {} {} {}\n", v[0], v[1], v[2]));
+            }
+            ascii.push_str("    endloop\n  endfacet\n");
+        }
+        ascii.push_str("endsolid box\n");
+
+        let obj = geometry_obj(&positions, &indices);
+        let expected = p3d_process(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert!(!expected.is_empty());
+        let mut padded = binary.clone();
+        padded.extend_from_slice(&[0; 16]);
+        for stl in [binary.clone(), padded, ascii.into_bytes()] {
+            assert_eq!(p3d_process(&stl, InputFileType::Stl, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+        }
+        // Cut short of the triangles it counts
+        assert!(matches!(stl::parse(&binary[..binary.len() - 1]), Err(P3DError::ParseError { format: Format::Stl, .. })));
+
+        assert!(matches!(stl::parse(b"solid x\nvertex 0 0 0\nendsolid x\n"), Err(P3DError::ParseError { format: Format::Stl, .. })));
+        assert!(matches!(stl::parse(&[1u8; 90]), Err(P3DError::ParseError { format: Format::Stl, .. })));
+    }
+
+    #[test]
+    fn test_voxel() {
+        let params = P3DParams::default().algo(AlgoType::Voxel);
+        let process = |obj: &str| p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap();
+        let sphere = process(&sphere_obj(1.0, 24, 32));
+        assert!(!sphere.is_empty() && sphere.len() <= params.depth);
+        assert_eq!(sphere, process(&sphere_obj(1.0, 24, 32)));
+        assert_ne!(sphere, process(&l_shape_obj(1.0, [0.0; 3])));
+
+        // Every edge crossing is owned by exactly one of the triangles sharing it
+        let square = [[[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 1.0]], [[0.0, 0.0, 1.0], [1.0, 1.0, 1.0], [0.0, 1.0, 1.0]]];
+        for p in [(0.5, 0.5), (0.25, 0.5), (0.5, 0.0), (1.0, 1.0), (0.0, 0.0)] {
+            let hits = square.iter().filter(|t| voxel::crossing(t, p.0, p.1).is_some()).count();
+            assert!(hits <= 1, "{:?} crossed {} times", p, hits);
+        }
+        assert_eq!(voxel::crossing(&square[0], 0.75, 0.25), Some(1.0));
+    }
+
+    #[test]
+    fn test_point_cloud() {
+        // Points filling an L shape, as XYZ, as an OBJ without faces and as a PLY without faces
+        let mut points = Vec::new();
+        for i in 0..30 {
+            for j in 0..10 {
+                for k in 0..20 {
+                    let p = [i as f64 * 0.1, j as f64 * 0.1 + 0.01 * (i % 3) as f64, k as f64 * 0.1];
+                    if p[0] < 1.0 || p[2] < 0.5 {
+                        points.push(p);
+                    }
+                }
+            }
+        }
+        let xyz: String = points.iter().map(|p| format!("{},{},{} 255 0 0\n", p[0], p[1], p[2])).collect();
+        let obj: String = points.iter().map(|p| format!("v {} {} {}\n", p[0], p[1], p[2])).collect();
+        let mut ply = format!("ply\nformat ascii 1.0\nelement vertex {}\nproperty double x\nproperty double y\nproperty double z\nend_header\n", points.len());
+        ply.push_str(&obj.replace("v ", ""));
+
+        let params = P3DParams::default().algo(AlgoType::CloudGrid);
+        let hashes = p3d_process_cfg(format!("# scan\n\n{}", xyz).as_bytes(), InputFileType::Xyz, &params).unwrap();
+        assert!(hashes.len() > 1 && hashes.len() <= params.depth);
+        assert_eq!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap(), hashes);
+        assert_eq!(p3d_process_cfg(ply.as_bytes(), InputFileType::Ply, &params).unwrap(), hashes);
+        let raw = p3d_process_cfg_raw(xyz.as_bytes(), InputFileType::Xyz, &params).unwrap();
+        assert_eq!(raw.iter().map(to_hex).collect::<Vec<_>>(), hashes);
+
+        let detailed = p3d_process_cfg_detailed(xyz.as_bytes(), InputFileType::Xyz, &params.clone().options(ProcessOptions { debug_geometry: true, ..Default::default() })).unwrap();
+        assert_eq!(detailed.hashes, hashes);
+        assert_eq!(detailed.occupancy.len(), params.n_sections as usize);
+        assert_eq!(detailed.occupancy.iter().flatten().sum::<u32>() as usize, points.len());
+
+        // Meshes can be hashed by their vertices, points can't be sliced
+        assert!(!p3d_process_cfg(sphere_obj(1.0, 12, 16).as_bytes(), InputFileType::Obj, &params).unwrap().is_empty());
+        assert!(matches!(p3d_process(xyz.as_bytes(), InputFileType::Xyz, AlgoType::Grid2dV3a, 8, 10, None), Err(P3DError::EmptyMesh)));
+        let mesh = P3DMesh::load(sphere_obj(1.0, 12, 16).as_bytes(), InputFileType::Obj).unwrap();
+        assert!(matches!(mesh.process(AlgoType::CloudGrid, 10, 8, 10, None), Err(P3DError::InvalidParams { .. })));
+        assert!(matches!(p3d_process_cfg(b"0 0 0\n1 2\n", InputFileType::Xyz, &params), Err(P3DError::ParseError { format: Format::Xyz, offset: Offset::Line(2), .. })));
+    }
+
+    #[test]
+    fn test_ply_input() {
+        // Box with quad faces, an extra vertex property and an element that is skipped
+        let size = [3.0, 2.0, 1.0];
+        let corners: Vec<[f64; 3]> = (0..8)
+            .map(|i| [size[0] * (i & 1) as f64, size[1] * ((i >> 1) & 1) as f64, size[2] * ((i >> 2) & 1) as f64])
+            .collect();
+        let quads: [[u32; 4]; 6] = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        let header = |format: &str| format!(
+            "ply\nformat {} 1.0\ncomment box\nelement vertex 8\nproperty float x\nproperty float y\nproperty float z\n\
+             property uchar red\nelement face 6\nproperty list uchar int vertex_indices\n\
+             element edge 1\nproperty int vertex1\nproperty int vertex2\nend_header\n", format);
+
+        let mut ascii = header("ascii");
+        for c in corners.iter() {
+            ascii.push_str(&format!("{} {} {} 255\n", c[0], c[1], c[2]));
+        }
+        for q in quads.iter() {
+            ascii.push_str(&format!("4 {} {} {} {}\n", q[0], q[1], q[2], q[3]));
+        }
+        ascii.push_str("0 1\n");
+
+        let binary = |big_endian: bool| {
+            let order = |mut bytes: [u8; 4]| {
+                if big_endian {
+                    bytes.reverse();
+                }
+                bytes
+            };
+            let mut binary = header(if big_endian { "binary_big_endian" } else { "binary_little_endian" }).into_bytes();
+            for c in corners.iter() {
+                for v in c.iter() {
+                    binary.extend(order((*v as f32).to_le_bytes()));
+                }
+                binary.push(255);
+            }
+            for q in quads.iter() {
+                binary.push(4);
+                for i in q.iter() {
+                    binary.extend(order((*i as i32).to_le_bytes()));
+                }
+            }
+            binary.extend(order(0i32.to_le_bytes()));
+            binary.extend(order(1i32.to_le_bytes()));
+            binary
+        };
+
+        let (positions, indices) = ply::parse(ascii.as_bytes(), &Limits::default()).unwrap();
+        assert_eq!(positions.len(), 8 * 3);
+        assert_eq!(indices.len(), 12 * 3);
+        assert_eq!(&indices[..6], &[0, 2, 3, 0, 3, 1]);
+        for big_endian in [false, true] {
+            assert_eq!(ply::parse(&binary(big_endian), &Limits::default()).unwrap(), (positions.clone(), indices.clone()));
+        }
+
+        let obj = geometry_obj(&positions, &indices);
+        let expected = p3d_process(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert!(!expected.is_empty());
+        for ply in [ascii.into_bytes(), binary(false), binary(true)] {
+            assert_eq!(p3d_process(&ply, InputFileType::Ply, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+        }
+
+        assert!(matches!(ply::parse(b"ply\nformat binary_middle_endian 1.0\nend_header\n", &Limits::default()), Err(P3DError::ParseError { format: Format::Ply, .. })));
+        // Vertices only: a point cloud, no faces to slice
+        let cloud = b"ply\nformat ascii 1.0\nelement vertex 2\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n1 2 3\n";
+        assert_eq!(ply::parse(cloud, &Limits::default()).unwrap(), (vec![0.0, 0.0, 0.0, 1.0, 2.0, 3.0], Vec::new()));
+        assert!(matches!(p3d_process(cloud, InputFileType::Ply, AlgoType::Grid2dV3a, 8, 4, None), Err(P3DError::EmptyMesh)));
+        assert!(matches!(ply::parse(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nend_header\n0\n", &Limits::default()), Err(P3DError::ParseError { format: Format::Ply, .. })));
+        let truncated = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                          element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0 1 0 0 0 1 0\n3 0 1\n";
+        assert!(matches!(ply::parse(truncated, &Limits::default()), Err(P3DError::ParseError { format: Format::Ply, .. })));
+        let out_of_range = b"ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                             element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0 1 0 0 0 1 0\n3 0 1 7\n";
+        assert!(matches!(ply::parse(out_of_range, &Limits::default()), Err(P3DError::ParseError { format: Format::Ply, .. })));
+        // List counts that are huge, negative or fractional fail before anything is allocated
+        for count in ["1e19", "4294967295", "-1", "2.5", "NaN"] {
+            let ply = format!("ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                               element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0 1 0 0 0 1 0\n{} 0 1 2\n", count);
+            assert!(matches!(ply::parse(ply.as_bytes(), &Limits::default()), Err(P3DError::ParseError { format: Format::Ply, .. })), "Accepted count {}", count);
+        }
+        // Coordinates must be scalars, an empty list has no value to take
+        let listed = b"ply\nformat ascii 1.0\nelement vertex 1\nproperty list uchar float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n";
+        assert!(matches!(ply::parse(listed, &Limits::default()), Err(P3DError::ParseError { format: Format::Ply, .. })));
+        // Element counts are bounded by the rest of the body, or skipped when the rows are empty,
+        // so a huge one fails or passes at once
+        let triangle = "element vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                        element face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0 1 0 0 0 1 0\n3 0 1 2\n";
+        let empty = format!("ply\nformat ascii 1.0\nelement junk 1000000000000000000\n{}", triangle);
+        assert_eq!(ply::parse(empty.as_bytes(), &Limits::default()).unwrap().1, vec![0, 1, 2]);
+        for format in ["ascii", "binary_little_endian"] {
+            let huge = format!("ply\nformat {} 1.0\nelement junk 1000000000000000000\nproperty uchar a\n{}", format, triangle);
+            assert!(matches!(ply::parse(huge.as_bytes(), &Limits::default()), Err(P3DError::ParseError { format: Format::Ply, .. })), "Accepted {}", format);
+        }
+        let limits = Limits { max_vertices: 2, ..Default::default() };
+        let ply = format!("ply\nformat ascii 1.0\n{}", triangle);
+        assert!(matches!(ply::parse(ply.as_bytes(), &limits), Err(P3DError::LimitExceeded { what: "vertices", .. })));
+    }
+
+    #[test]
+    fn test_process_source() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 1.0], [1.0, 1.0, 2.0]);
+        let obj = geometry_obj(&positions, &indices);
+        for algo in [AlgoType::Grid2dV3a, AlgoType::Spectr] {
+            let params = P3DParams::default().algo(algo).grid_size(8).n_sections(6);
+            let expected = p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap();
+            assert_eq!(p3d_process_mesh(&positions, &indices, &params).unwrap(), expected);
+            let single: Vec<f32> = positions.iter().map(|&c| c as f32).collect();
+            assert_eq!(p3d_process_source(&(&single[..], &indices[..]), &params).unwrap(), expected);
+        }
+
+        // Interleaved vertices with 16-bit indices, as an engine would store them
+        struct Interleaved {
+            vertices: Vec<([f32; 3], [f32; 2])>,
+            indices: Vec<u16>,
+        }
+        impl MeshSource for Interleaved {
+            fn vertex_count(&self) -> usize {
+                self.vertices.len()
+            }
+            fn position(&self, i: usize) -> [f64; 3] {
+                let p = self.vertices[i].0;
+                [p[0] as f64, p[1] as f64, p[2] as f64]
+            }
+            fn triangle_count(&self) -> usize {
+                self.indices.len() / 3
+            }
+            fn triangle(&self, i: usize) -> [u32; 3] {
+                [self.indices[3 * i] as u32, self.indices[3 * i + 1] as u32, self.indices[3 * i + 2] as u32]
+            }
+        }
+        let engine = Interleaved {
+            vertices: positions.chunks(3).map(|p| ([p[0] as f32, p[1] as f32, p[2] as f32], [0.0; 2])).collect(),
+            indices: indices.iter().map(|&i| i as u16).collect(),
+        };
+        let params = P3DParams::default().algo(AlgoType::Grid2dV3a).grid_size(8).n_sections(6);
+        let detailed = p3d_process_source_detailed(&engine, &params).unwrap();
+        assert_eq!(detailed.hashes, p3d_process_mesh(&positions, &indices, &params).unwrap());
+        let mesh = P3DMesh::from_source(&engine, &ProcessOptions::default()).unwrap();
+        assert_eq!(mesh.process(AlgoType::Grid2dV3a, 10, 8, 6, None).unwrap(), detailed.hashes);
+
+        // Vertices alone are a point cloud
+        let xyz: String = positions.chunks(3).map(|p| format!("{} {} {}\n", p[0], p[1], p[2])).collect();
+        let cloud = P3DParams::default().algo(AlgoType::CloudGrid).grid_size(4).n_sections(4);
+        let expected = p3d_process_cfg(xyz.as_bytes(), InputFileType::Xyz, &cloud).unwrap();
+        assert_eq!(p3d_process_mesh(&positions, &[], &cloud).unwrap(), expected);
+
+        assert!(matches!(p3d_process_mesh(&positions, &indices[1..], &params), Err(P3DError::MalformedIndices { .. })));
+        assert!(matches!(p3d_process_mesh(&positions[1..], &indices, &params), Err(P3DError::InvalidMesh { .. })));
+        assert!(matches!(p3d_process_mesh(&positions[..9], &indices, &params), Err(P3DError::InvalidMesh { .. })));
+    }
+
+    #[test]
+    fn test_fixed_grid() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 1.0], [1.0, 1.0, 5.0]);
+        push_box(&mut positions, &mut indices, [1.0, 0.0, 5.0], [1.5, 0.5, 1.0]);
+        let params = P3DParams::default().algo(AlgoType::FixedGrid).grid_size(8).n_sections(6);
+        let hashes = p3d_process_mesh(&positions, &indices, &params).unwrap();
+        // Integer arithmetic gives these on every platform
+        assert_eq!(hashes.len(), params.depth);
+        assert_eq!(hashes[0], "064a3b430d853b1c65d4f9f5aee3644fa0276e4e2b42faf75fa7bf5a474775b7");
+        assert_eq!(hashes[9], "5242bdc9129cffff7a24c8daf0bce93adb6343e7e667e9a319bd2c4096fa9f46");
+
+        // The sums are exact, so the order of the faces doesn't matter, nor does scaling by a
+        // power of two and moving by a multiple of the lattice unit
+        let reversed: Vec<u32> = indices.chunks(3).rev().flatten().cloned().collect();
+        assert_eq!(p3d_process_mesh(&positions, &reversed, &params).unwrap(), hashes);
+        let moved: Vec<f64> = positions.iter().enumerate().map(|(i, &c)| c * 4.0 + [8.0, -16.0, 32.0][i % 3]).collect();
+        assert_eq!(p3d_process_mesh(&moved, &indices, &params).unwrap(), hashes);
+        let turned: Vec<f64> = positions.chunks(3).flat_map(|p| vec![-p[1], p[0], p[2]]).collect();
+        assert_eq!(p3d_process_mesh(&turned, &indices, &params).unwrap(), hashes);
+
+        let obj = geometry_obj(&positions, &indices);
+        assert_eq!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap(), hashes);
+        let detailed = p3d_process_cfg_detailed(obj.as_bytes(), InputFileType::Obj, &params).unwrap();
+        assert_eq!(detailed.hashes, hashes);
+        assert_eq!(detailed.section_heights.len(), 6);
+        assert!(detailed.v_max.z - detailed.v_min.z > 5.9);
+        assert_ne!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params.clone().transform([1, 2, 3, 4])).unwrap(), hashes);
+
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        assert!(matches!(mesh.process(AlgoType::FixedGrid, 10, 8, 6, None), Err(P3DError::InvalidParams { .. })));
+        assert!(matches!(p3d_process_mesh(&positions[..24], &indices[..36], &params.clone().n_sections(4)), Ok(_)));
+        let flat: Vec<f64> = positions.chunks(3).flat_map(|p| vec![p[0], p[1], 0.0]).collect();
+        assert!(matches!(p3d_process_mesh(&flat, &indices, &params), Err(P3DError::FlatObject { .. })));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        let params = P3DParams::default().algo(AlgoType::Grid2dV3a).grid_size(8).n_sections(6);
+        // An unused vertex may be anything
+        let mut unused = positions.clone();
+        unused.extend_from_slice(&[f64::NAN, 0.0, 0.0]);
+        assert_eq!(p3d_process_mesh(&unused, &indices, &params).unwrap(), p3d_process_mesh(&positions, &indices, &params).unwrap());
+        positions[3 * 5 + 1] = f64::INFINITY;
+        let err = p3d_process_mesh(&positions, &indices, &params).unwrap_err();
+        assert!(matches!(err, P3DError::NonFiniteCoordinate { vertex: 5 }));
+        assert_eq!(err.code(), 24);
+        assert_eq!(err.to_string(), "vertex 5 has a non-finite coordinate");
+
+        assert_eq!(P3DError::EmptyMesh.code(), 7);
+        assert_eq!(P3DError::InvalidParams { param: "par1", reason: "must be at least 1" }.code(), 13);
+        assert_eq!(P3DError::LimitExceeded { what: "vertices", value: 12, limit: 10 }.to_string(),
+            "12 vertices, over the limit of 10");
+        let parse_error = |format, offset| P3DError::ParseError { format, offset, reason: "missing face count" };
+        assert_eq!(parse_error(Format::Off, Offset::Line(2)).to_string(), "OFF: missing face count on line 2");
+        assert_eq!(parse_error(Format::Ply, Offset::Byte(40)).to_string(), "PLY: missing face count at byte 40");
+        assert_eq!(parse_error(Format::Gltf, Offset::Unknown).to_string(), "glTF: missing face count");
+        // Each format keeps the code of the variant it had before
+        let codes: Vec<u16> = [Format::Gltf, Format::Obj, Format::Stl, Format::Ply, Format::Xyz, Format::ThreeMf, Format::Off, Format::Gzip]
+            .iter().map(|&format| parse_error(format, Offset::Unknown).code()).collect();
+        assert_eq!(codes, vec![5, 10, 11, 17, 20, 21, 22, 23]);
+    }
+
+    #[test]
+    fn test_off_input() {
+        // Box with quad faces, the counts on the keyword line as in ModelNet
+        let size = [3.0, 2.0, 1.0];
+        let mut off = String::from("OFF8 6 0\n# box\n");
+        for i in 0..8 {
+            off.push_str(&format!("{} {} {}\n", size[0] * (i & 1) as f64, size[1] * ((i >> 1) & 1) as f64, size[2] * ((i >> 2) & 1) as f64));
+        }
+        for q in [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]].iter() {
+            off.push_str(&format!("4 {} {} {} {}\n", q[0], q[1], q[2], q[3]));
+        }
+
+        let (positions, indices) = off::parse(off.as_bytes()).unwrap();
+        assert_eq!(positions.len(), 8 * 3);
+        assert_eq!(&indices[..6], &[0, 2, 3, 0, 3, 1]);
+        let split = off.replacen("OFF", "COFF\n", 1);
+        assert_eq!(off::parse(split.as_bytes()).unwrap(), (positions.clone(), indices.clone()));
+
+        let obj = geometry_obj(&positions, &indices);
+        let expected = p3d_process(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert_eq!(p3d_process(off.as_bytes(), InputFileType::Off, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+
+        assert!(matches!(off::parse(b"4OFF\n1 0 0\n0 0 0 0\n"), Err(P3DError::ParseError { format: Format::Off, .. })));
+        assert!(matches!(off::parse(b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n"), Err(P3DError::ParseError { format: Format::Off, offset: Offset::Line(6), .. })));
+        assert!(matches!(off::parse(b"OFF\n3 1 0\n0 0 0\n1 0 0\n"), Err(P3DError::ParseError { format: Format::Off, .. })));
+
+        #[cfg(feature = "gzip")]
+        {
+            // Single member, no optional header fields (the CRC isn't checked)
+            let mut gz = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 255];
+            gz.extend(miniz_oxide::deflate::compress_to_vec(off.as_bytes(), 6));
+            gz.extend_from_slice(&[0; 4]);
+            gz.extend_from_slice(&(off.len() as u32).to_le_bytes());
+            assert_eq!(p3d_process(&gz, InputFileType::Off, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+            assert_eq!(p3d_process(&gz, InputFileType::Auto, AlgoType::Grid2dV3a, 8, 4, None).unwrap(), expected);
+
+            let opts = ProcessOptions { limits: Limits { max_input_bytes: off.len() - 1, ..Default::default() }, ..Default::default() };
+            assert!(matches!(p3d_process_opts(&gz, InputFileType::Off, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts), Err(P3DError::LimitExceeded { .. })));
+            let truncated = &gz[..gz.len() - 12];
+            assert!(matches!(p3d_process(truncated, InputFileType::Off, AlgoType::Grid2dV3a, 8, 4, None), Err(P3DError::ParseError { format: Format::Gzip, .. })));
+        }
+    }
+
+    #[test]
+    fn test_detect_file_type() {
+        let detect = |input: &[u8]| InputFileType::detect(input);
+        let glb = include_bytes!("../test-ht.glb");
+        assert_eq!(detect(glb), Some(InputFileType::Glb));
+        assert_eq!(detect(b" {\"asset\": {\"version\": \"2.0\"}}"), Some(InputFileType::Gltf));
+        assert_eq!(detect(b"PK\x03\x04\x14\0"), Some(InputFileType::ThreeMf));
+        assert_eq!(detect(b"ply\nformat ascii 1.0\n"), Some(InputFileType::Ply));
+        assert_eq!(detect(b"solid box\n  facet normal 0 0 1\n"), Some(InputFileType::Stl));
+        // Binary STL whose header starts like an ASCII one
+        let mut binary = b"solid exported".to_vec();
+        binary.resize(80, b' ');
+        binary.extend_from_slice(&1u32.to_le_bytes());
+        binary.resize(84 + 50, 0);
+        assert_eq!(detect(&binary), Some(InputFileType::Stl));
+        assert_eq!(detect(b"# ModelNet\nOFF8 6 0\n"), Some(InputFileType::Off));
+        assert_eq!(detect(b"COFF\n"), Some(InputFileType::Off));
+        assert_eq!(detect(b"\xef\xbb\xbf# exported\n\nmtllib box.mtl\nv 0 0 0\n"), Some(InputFileType::Obj));
+        assert_eq!(detect(b"# scan\n-1.5,2,3\n"), Some(InputFileType::Xyz));
+        assert_eq!(detect(b"<?xml version=\"1.0\"?>"), None);
+        assert_eq!(detect(&[0xff, 0xfe, 0, 1]), None);
+        assert_eq!(detect(b""), None);
+
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let expected = p3d_process(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 6, None).unwrap();
+        assert_eq!(p3d_process(obj.as_bytes(), InputFileType::Auto, AlgoType::Grid2dV3a, 8, 6, None).unwrap(), expected);
+        let params = P3DParams::default().algo(AlgoType::CloudGrid);
+        assert_eq!(p3d_process_cfg(obj.as_bytes(), InputFileType::Auto, &params).unwrap(), p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap());
+        assert!(matches!(p3d_process(b"<x3d/>", InputFileType::Auto, AlgoType::Grid2dV3a, 8, 6, None), Err(P3DError::UnsupportedFileType)));
+    }
+
+    #[test]
+    fn test_auto_grid_size() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [4.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 1.0, 0.0], [1.0, 2.0, 1.0]);
+
+        let auto = ProcessOptions { grid_size: GridSize::Auto, ..Default::default() };
+        let process = |p: &[f64], par1: i16, opts: &ProcessOptions| {
+            p3d_process_detailed(geometry_obj(p, &indices).as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, par1, 6, None, opts).unwrap()
+        };
+        let res = process(&positions, 8, &auto);
+        assert!((AUTO_GRID_MIN..=AUTO_GRID_MAX).contains(&res.grid_size));
+        assert_eq!(res.grid_size, process(&positions, 20, &auto).grid_size);
+
+        // The reported size reproduces the hashes as a fixed `par1`
+        let fixed = process(&positions, res.grid_size, &ProcessOptions::default());
+        assert_eq!(fixed.grid_size, res.grid_size);
+        assert_eq!(fixed.hashes, res.hashes);
+        assert_eq!(process(&positions, 5, &ProcessOptions::default()).grid_size, 5);
+    }
+
+    #[test]
+    fn test_gltf_non_indexed_primitive() {
+        // Every face of the tetrahedron written out with its own three vertices
+        let corners = [0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.];
+        let soup: Vec<f32> = TETRA_FACES.iter().flat_map(|&i| corners[i as usize * 3..i as usize * 3 + 3].to_vec()).collect();
+        let json = r#"{
+          "asset": { "version": "2.0" },
+          "buffers": [ { "byteLength": 144 } ],
+          "bufferViews": [ { "buffer": 0, "byteOffset": 0, "byteLength": 144 } ],
+          "accessors": [
+            { "bufferView": 0, "componentType": 5126, "count": 12, "type": "VEC3", "min": [0, 0, 0], "max": [2, 1, 3] }
+          ],
+          "meshes": [ { "primitives": [ { "attributes": { "POSITION": 0 } } ] } ]
+        }"#;
+        let glb = make_glb(json, &f32_bytes(&soup));
+        let geometry = load_geometry(&glb, InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        assert_eq!(geometry.indices, (0..12).collect::<Vec<u32>>());
+
+        let hashes = p3d_process(&glb, InputFileType::Glb, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert!(!hashes.is_empty());
+    }
+
+    #[test]
+    fn test_gltf_primitive_modes() {
+        // A closed tetrahedron as one strip (0 1 2 3 0 1) and as the same four faces listed
+        let corners = f32_bytes(&[0., 0., 0., 2., 0., 0., 0., 1., 0., 0., 0., 3.]);
+        let glb = |indices: &[u32], mode: u32| {
+            let mut bin = corners.clone();
+            bin.extend(u32_bytes(indices));
+            let json = format!(r#"{{
+              "asset": {{ "version": "2.0" }},
+              "buffers": [ {{ "byteLength": {} }} ],
+              "bufferViews": [
+                {{ "buffer": 0, "byteOffset": 0, "byteLength": 48 }},
+                {{ "buffer": 0, "byteOffset": 48, "byteLength": {} }}
+              ],
+              "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [2, 1, 3] }},
+                {{ "bufferView": 1, "componentType": 5125, "count": {}, "type": "SCALAR" }}
+              ],
+              "meshes": [ {{ "primitives": [
+                {{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "mode": {} }},
+                {{ "attributes": {{ "POSITION": 0 }}, "indices": 1, "mode": 1 }}
+              ] }} ]
+            }}"#, bin.len(), indices.len() * 4, indices.len(), mode);
+            make_glb(&json, &bin)
+        };
+        let strip = glb(&[0, 1, 2, 3, 0, 1], 5);
+        let list = glb(&[0, 1, 2, 1, 3, 2, 2, 3, 0, 3, 1, 0], 4);
+
+        // The LINES primitive is skipped
+        let geometry = load_geometry(&strip, InputFileType::Glb, &ProcessOptions::default()).unwrap();
+        assert_eq!(geometry.indices, vec![0, 1, 2, 1, 3, 2, 2, 3, 0, 3, 1, 0]);
+        let process = |glb: &[u8]| p3d_process(glb, InputFileType::Glb, AlgoType::Grid2dV3a, 8, 4, None).unwrap();
+        assert_eq!(process(&strip), process(&list));
+
+        assert_eq!(triangle_list(Mode::TriangleFan, vec![0, 1, 2, 3]), vec![1, 2, 0, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_p3d_mesh_reuse() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        for algo in [AlgoType::Grid2d, AlgoType::Grid2dV3a, AlgoType::Spectr] {
+            for trans in [None, Some([1, 2, 3, 4])] {
+                let expected = p3d_process_n(obj.as_bytes(), InputFileType::Obj, algo, 10, 8, 6, trans).unwrap();
+                assert_eq!(mesh.process(algo, 10, 8, 6, trans).unwrap(), expected, "{:?} {:?}", algo, trans);
+            }
+        }
+    }
+
+    #[test]
+    fn test_p3d_mesh_sweep() {
+        let obj = sphere_obj(1.0, 12, 16);
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        let sweep = [(AlgoType::Grid2dV3a, 8, 6), (AlgoType::Grid2dV3a, 4, 6), (AlgoType::Skeleton, 6, 6), (AlgoType::Grid2dV3, 8, 6),
+            (AlgoType::Grid2dV3a, 8, 10), (AlgoType::Grid2dV3a, 12, 6), (AlgoType::Voxel, 8, 6)];
+        for &(algo, grid_size, n_sections) in sweep.iter().chain(sweep.iter().rev()) {
+            let expected = p3d_process_n(obj.as_bytes(), InputFileType::Obj, algo, 10, grid_size, n_sections, None).unwrap();
+            assert_eq!(mesh.process(algo, 10, grid_size, n_sections, None).unwrap(), expected, "{:?} {} {}", algo, grid_size, n_sections);
+        }
+        // Edge and vertex cuts of 6 sections, edge cuts of 10; the voxels are not kept
+        assert_eq!(mesh.cache.borrow().sections.len(), 3);
+        let detailed = mesh.process_detailed(AlgoType::Grid2dV3a, 10, 5, 10, None).unwrap();
+        let fresh = p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 5, 10, None, &ProcessOptions::default()).unwrap();
+        assert_eq!((detailed.hashes, detailed.centers, detailed.robustness), (fresh.hashes, fresh.centers, fresh.robustness));
+        mesh.clear_cache();
+        assert!(mesh.cache.borrow().sections.is_empty());
+    }
+
+    #[test]
+    fn test_detailed_result() {
+        let obj = box_obj(3.0, 2.0, 1.0);
+        let res = p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 4, None, &ProcessOptions::default()).unwrap();
+        assert_eq!(res.hashes, p3d_process(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 4, None).unwrap());
+        assert_eq!(res.centers.len(), res.section_heights.len());
+        // The grid spans the aligned bounding box in x and y, every contour point lies on it
+        assert_eq!(res.rect, [res.v_min.x, res.v_max.x, res.v_min.y, res.v_max.y]);
+        for p in res.centers.iter().flatten() {
+            assert!(p.x >= res.rect[0] - 1e-9 && p.x <= res.rect[1] + 1e-9 && p.y >= res.rect[2] - 1e-9 && p.y <= res.rect[3] + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_weld_seams() {
+        // Box with every face on its own four vertices, as split at normal seams
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 2.0, 1.0]);
+        let mut split = Vec::new();
+        let mut split_indices = Vec::new();
+        for (k, f) in indices.iter().enumerate() {
+            split.extend_from_slice(&positions[*f as usize * 3..*f as usize * 3 + 3]);
+            split_indices.push(k as u32);
+        }
+        // Jitter far below the tolerance
+        split[0] += 1e-9;
+
+        let (welded, welded_indices, kept) = weld::weld(&split, &split_indices, DEFAULT_WELD_EPSILON);
+        assert_eq!(welded.len(), 8 * 3);
+        assert!(kept.iter().all(|k| *k));
+
+        // Same topology and positions whatever the vertex order
+        let n = split.len() / 3;
+        let reversed: Vec<f64> = (0..n).rev().flat_map(|i| split[i * 3..i * 3 + 3].to_vec()).collect();
+        let reversed_indices: Vec<u32> = split_indices.iter().map(|i| (n - 1) as u32 - i).collect();
+        let (welded_rev, welded_rev_indices, _) = weld::weld(&reversed, &reversed_indices, DEFAULT_WELD_EPSILON);
+        let corners = |p: &[f64], idx: &[u32]| -> Vec<Vec<[u64; 3]>> {
+            idx.chunks(3).map(|t| t.iter().map(|&i| {
+                let v = &p[i as usize * 3..i as usize * 3 + 3];
+                [v[0].to_bits(), v[1].to_bits(), v[2].to_bits()]
+            }).collect()).collect()
+        };
+        assert_eq!(corners(&welded, &welded_indices), corners(&welded_rev, &welded_rev_indices));
+        let edges = |idx: &[u32]| idx.chunks(3).flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])]).collect::<BTreeSet<_>>().len();
+        assert_eq!(edges(&welded_indices), edges(&welded_rev_indices));
+
+        let obj = geometry_obj(&split, &split_indices);
+        let opts = ProcessOptions { weld_epsilon: Some(DEFAULT_WELD_EPSILON), ..Default::default() };
+        let (mesh, _) = load_mesh(obj.as_bytes(), InputFileType::Obj, &opts).unwrap();
+        assert_eq!(mesh.no_vertices(), 8);
+    }
+
+    #[test]
+    fn test_section_loops() {
+        // Open square tube: outer wall 4 x 4, inner wall 2 x 2, cut at half height
+        let square = |lo: f64, hi: f64| [[lo, lo], [hi, lo], [hi, hi], [lo, hi]];
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        for (k, corners) in [square(0.0, 4.0), square(1.0, 3.0)].iter().enumerate() {
+            let base = (positions.len() / 3) as u32;
+            for z in [0.0, 2.0].iter() {
+                positions.extend(corners.iter().flat_map(|c| vec![c[0], c[1], *z]));
+            }
+            for i in 0..4u32 {
+                let (a, b) = (base + i, base + (i + 1) % 4);
+                let quad = if k == 0 { [a, b, b + 4, a, b + 4, a + 4] } else { [b, a, a + 4, b, a + 4, b + 4] };
+                indices.extend_from_slice(&quad);
+            }
+        }
+        let opts = ProcessOptions::default();
+        let (mesh, _) = load_mesh(geometry_obj(&positions, &indices).as_bytes(), InputFileType::Obj, &opts).unwrap();
+        let loops = loops::section_loops(&loops::mesh_faces(&mesh), 1.0);
+        assert_eq!(loops.len(), 2);
+        // Both closed, the outer one first and counter-clockwise, the hole clockwise
+        for (k, l) in loops.iter().enumerate() {
+            assert_eq!(l.first().unwrap().0, l.last().unwrap().0);
+            let polygon: Vec<Vec2> = l.iter().map(|(p, _)| *p).collect();
+            assert_eq!(triangulate::signed_area(&polygon) > 0.0, k == 0);
+        }
+        assert_eq!(loops[0][0].0, Vec2::new(0.0, 0.0));
+        assert_eq!(loops[1][0].0, Vec2::new(1.0, 1.0));
+
+        // Two separate boxes give two loops in every section
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [1.0, 1.0, 10.0]);
+        push_box(&mut positions, &mut indices, [3.0, 0.0, 0.0], [2.0, 1.0, 10.0]);
+        let obj = geometry_obj(&positions, &indices);
+        let opts = ProcessOptions { trace_loops: true, tag_materials: true, ..Default::default() };
+        let result = p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts).unwrap();
+        assert_eq!(result.section_loops.len(), result.centers.len());
+        for (k, loops) in result.section_loops.iter().enumerate() {
+            assert_eq!(loops.len(), 2);
+            assert_eq!(loops.iter().map(|l| l.len()).sum::<usize>(), result.centers[k].len());
+            assert_eq!(result.materials[k].len(), result.centers[k].len());
+        }
+        assert!(p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 4, None, &ProcessOptions::default())
+            .unwrap().section_loops.is_empty());
+    }
+
+    #[test]
+    fn test_align_mesh() {
+        let obj = l_shape_obj(1.0, [3.0, -2.0, 5.0]);
+        let opts = ProcessOptions::default();
+        let (mut mesh, _) = load_mesh(obj.as_bytes(), InputFileType::Obj, &opts).unwrap();
+        let (mut posed, _) = load_mesh(obj.as_bytes(), InputFileType::Obj, &opts).unwrap();
+        let triangles = face_triangles(&mesh, &mesh.face_iter().collect::<Vec<_>>());
+        let pit = principal_inertia_transform(triangles);
+        let (shift, _) = pose_mesh(&mut posed, None, &opts).unwrap();
+        assert_eq!(shift, Vector3::new(pit[[0, 3]], pit[[1, 3]], pit[[2, 3]]));
+
+        align_mesh(&mut mesh).unwrap();
+        for (a, b) in mesh.vertex_iter().zip(posed.vertex_iter()) {
+            assert_eq!(mesh.vertex_position(a), posed.vertex_position(b));
+        }
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 0.0], [2.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 1.0], [1.0, 1.0, 2.0]);
+        let weighting = InertiaWeighting::Volume;
+
+        // The pose `p3d_process` slices in
+        let params = P3DParams::default().grid_size(8).n_sections(6);
+        let debug = P3DParams { options: ProcessOptions { debug_geometry: true, ..Default::default() }, ..params.clone() };
+        let posed = p3d_process_source_detailed(&(&positions[..], &indices[..]), &debug).unwrap().vertices;
+        let mut canonical = positions.clone();
+        let transform = canonicalize::canonicalize(&mut canonical, &indices, weighting).unwrap();
+        assert_eq!(posed.len(), canonical.len() / 3);
+        for (p, q) in canonical.chunks(3).zip(posed.iter()) {
+            assert!((0..3).all(|i| (p[i] - q[i]).abs() < 1e-12), "{:?} {:?}", p, q);
+        }
+        let aligned = P3DParams { options: ProcessOptions { alignment: Some(transform), ..Default::default() }, ..params.clone() };
+        assert_eq!(p3d_process_mesh(&positions, &indices, &aligned).unwrap(), p3d_process_mesh(&positions, &indices, &params).unwrap());
+
+        // Centered on the principal axes, which the moments don't depend on. The volume tensor
+        // only approximates that, the surface one is exact.
+        let weighting = InertiaWeighting::Surface;
+        let mut canonical = positions.clone();
+        let transform = canonicalize::canonicalize(&mut canonical, &indices, weighting).unwrap();
+        let inertia = canonicalize::inertia_tensor(&canonical, &indices, weighting).unwrap();
+        let original = canonicalize::inertia_tensor(&positions, &indices, weighting).unwrap();
+        for i in 0..3 {
+            assert!(inertia.center[i].abs() < 1e-12);
+            assert!((inertia.principal_moments()[i] - original.principal_moments()[i]).abs() < 1e-9);
+            for j in (0..3).filter(|&j| j != i) {
+                assert!(inertia.tensor[i][j].abs() < 1e-9 && (original.tensor[i][j] - original.tensor[j][i]).abs() < 1e-12);
+            }
+        }
+        // The L reaches out along x at the bottom and along z at the side, so x and z are
+        // anti-correlated and their (negated) product of inertia positive
+        assert!(original.tensor[0][2] > 0.0);
+
+        let mut back = canonical.clone();
+        canonicalize::apply(&mut back, &transform.invert().unwrap());
+        assert!(back.iter().zip(positions.iter()).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert!(matches!(
+            canonicalize::alignment_transform(&positions, &indices[..4], weighting),
+            Err(P3DError::MalformedIndices { .. })
+        ));
+    }
+
+    #[test]
+    fn test_axis_angle_transform() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let algo = AlgoType::Grid2dV3a;
+        let quantized = p3d_process_n(obj.as_bytes(), InputFileType::Obj, algo, 10, 8, 6, Some([1, 2, 3, 4])).unwrap();
+        let same = p3d_process_transform(obj.as_bytes(), InputFileType::Obj, algo, 10, 8, 6, Some(Transform::Quantized([1, 2, 3, 4]))).unwrap();
+        assert_eq!(quantized, same);
+
+        // An exact quarter turn around Z doesn't change the sections, only their orientation
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        let turned = mesh.process_transform(algo, 10, 8, 6, Some(Transform::AxisAngle { axis: [0.0, 0.0, 2.0], angle_deg: 90.0 })).unwrap();
+        let plain = mesh.process_detailed(algo, 10, 8, 6, None).unwrap();
+        assert_eq!(turned.section_heights, plain.section_heights);
+        let rot = Matrix4::from_angle_z(Deg(90.0));
+        assert!((turned.transform - rot * plain.transform).x.magnitude() < 1e-12);
+
+        let zero = Transform::AxisAngle { axis: [0.0; 3], angle_deg: 10.0 };
+        assert!(matches!(mesh.process_transform(algo, 10, 8, 6, Some(zero)), Err(P3DError::MathError)));
+    }
+
+    #[test]
+    fn test_quaternion_transform() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        let algo = AlgoType::Grid2dV3a;
+        let axis_angle = Transform::AxisAngle { axis: [1.0, 2.0, 3.0], angle_deg: 33.0 };
+        let expected = mesh.process_transform(algo, 10, 8, 6, Some(axis_angle)).unwrap();
+
+        let axis = Vector3::new(1.0, 2.0, 3.0).normalize();
+        let q = Quaternion::from_axis_angle(axis, Deg(33.0));
+        let m = Matrix3::from_axis_angle(axis, Deg(33.0));
+        for transform in [Transform::from(q * 2.5), Transform::from(m)] {
+            let result = mesh.process_transform(algo, 10, 8, 6, Some(transform)).unwrap();
+            for (a, b) in result.section_heights.iter().zip(expected.section_heights.iter()) {
+                assert!((a - b).abs() < 1e-12);
+            }
+            assert!((result.transform - expected.transform).x.magnitude() < 1e-12);
+        }
+
+        let zero = Transform::Quaternion(Quaternion::new(0.0, 0.0, 0.0, 0.0));
+        let mirror = Transform::Matrix(Matrix3::from_diagonal(Vector3::new(1.0, 1.0, -1.0)));
+        let scaled = Transform::Matrix(m * 2.0);
+        for bad in [zero, mirror, scaled] {
+            assert!(matches!(mesh.process_transform(algo, 10, 8, 6, Some(bad)), Err(P3DError::MathError)));
+        }
+    }
+
+    #[test]
+    fn test_trans_encoding() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        let algo = AlgoType::Grid2dV3a;
+        let v1 = TransEncoding::V1.decode(&[10, 20, 30, 40]).unwrap();
+        assert_eq!(v1, Transform::Quantized([10, 20, 30, 40]));
+        assert_eq!(mesh.process_raw(algo, 10, 8, 6, Some(v1)).unwrap().iter().map(to_hex).collect::<Vec<_>>(), mesh.process(algo, 10, 8, 6, Some([10, 20, 30, 40])).unwrap());
+
+        // Azimuth step 0 and the middle height are (about) the x axis, 2^30 a quarter turn
+        let bytes = [0, 0, 0xff, 0x7f, 0, 0, 0, 0x40];
+        let v2 = TransEncoding::V2.decode(&bytes).unwrap();
+        assert_eq!(v2, Transform::from(bytes));
+        let phi = core::f64::consts::PI / 65536.0;
+        let axis_angle = Transform::AxisAngle { axis: [libm::cos(phi), libm::sin(phi), 1.0 / 65536.0], angle_deg: 90.0 };
+        let (a, b) = (v2.matrix().unwrap(), axis_angle.matrix().unwrap());
+        assert!((a - b).x.magnitude() + (a - b).y.magnitude() + (a - b).z.magnitude() < 1e-12, "{:?}", a);
+        let expected = mesh.process_transform(algo, 10, 8, 6, Some(axis_angle)).unwrap();
+        let result = mesh.process_transform(algo, 10, 8, 6, Some(v2)).unwrap();
+        assert!((result.transform - expected.transform).x.magnitude() < 1e-12);
+
+        // Neighboring codes are distinct rotations, and any 8 bytes decode
+        let next = TransEncoding::V2.decode(&[0, 0, 0xff, 0x7f, 1, 0, 0, 0x40]).unwrap();
+        assert_ne!(next.matrix().unwrap(), a);
+        assert!(TransEncoding::V2.decode(&[0xff; 8]).unwrap().matrix().is_ok());
+        assert!(matches!(TransEncoding::V2.decode(&[1, 2, 3, 4]), Err(P3DError::InvalidParams { .. })));
+        assert!(matches!(TransEncoding::V1.decode(&bytes), Err(P3DError::InvalidParams { .. })));
+        assert_eq!((TransEncoding::V1.byte_len(), TransEncoding::V2.byte_len()), (4, 8));
+    }
+
+    #[test]
+    fn test_shape_descriptor() {
+        // Unaligned, so pose and scale reach the descriptor
+        let opts = ProcessOptions { align: false, ..Default::default() };
+        let describe = |obj: &str| p3d_shape_descriptor(obj.as_bytes(), InputFileType::Obj, 8, 6, &opts).unwrap();
+        let l_shape = describe(&l_shape_obj(1.0, [0.0; 3]));
+        assert_eq!(l_shape.len(), 48);
+        assert!(l_shape.iter().all(|v| v.is_finite() && *v >= 0.0));
+        assert_eq!(describe(&l_shape_obj(1.0, [0.0; 3])), l_shape);
+
+        let m = Matrix3::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), Deg(40.0));
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 0.0], [2.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 1.0], [1.0, 1.0, 2.0]);
+        let moved: Vec<f64> = positions.chunks(3)
+            .flat_map(|p| {
+                let q = m * Vector3::new(p[0], p[1], p[2]) * 3.0 + Vector3::new(5.0, -2.0, 1.0);
+                [q.x, q.y, q.z]
+            })
+            .collect();
+        let posed = describe(&geometry_obj(&moved, &indices));
+        let cube = describe(&box_obj(1.0, 1.0, 1.0));
+        let near = shape_descriptor_distance(&l_shape, &posed);
+        let far = shape_descriptor_distance(&l_shape, &cube);
+        assert!(near < far / 10.0, "{} vs {}", near, far);
+
+        let hash = shape_descriptor_hash(&l_shape, HashAlgo::Sha256);
+        assert_eq!(hash.len(), 64);
+        assert_ne!(shape_descriptor_hash(&cube, HashAlgo::Sha256), hash);
+        let mesh = P3DMesh::load_opts(l_shape_obj(1.0, [0.0; 3]).as_bytes(), InputFileType::Obj, &opts).unwrap();
+        assert_eq!(mesh.shape_descriptor(8, 6).unwrap(), l_shape);
+        assert!(matches!(mesh.shape_descriptor(0, 6), Err(P3DError::InvalidParams { .. })));
+        assert!(matches!(mesh.shape_descriptor(8, 33), Err(P3DError::InvalidParams { .. })));
+
+        // A strip of long slivers gets samples by its area, not by the length of its edges
+        let positions: Vec<f64> = (0..=50).flat_map(|i| vec![i as f64 * 1e-3, 0.0, 0.0, i as f64 * 1e-3, 1.0, 0.0]).collect();
+        let indices: Vec<u32> = (0..50).flat_map(|i| vec![2 * i, 2 * i + 2, 2 * i + 1, 2 * i + 1, 2 * i + 2, 2 * i + 3]).collect();
+        let (strip, _) = build_mesh(Geometry { positions, indices, materials: Vec::new() }, None, Repair::default()).unwrap();
+        let samples = shape_descriptor::surface_samples(&strip);
+        assert!(samples.len() < 2 * 4096, "{} samples for 100 slivers", samples.len());
+        assert!((samples.iter().map(|(_, w)| w).sum::<f64>() - 0.05).abs() < 1e-12);
+    }
+
+    #[cfg(feature = "testvec")]
+    #[test]
+    fn test_test_vectors() {
+        let vectors = test_vectors(7).unwrap();
+        assert_eq!(vectors.len(), 3 * 11);
+        assert_eq!(vectors[0].name, "ellipsoid/grid2d");
+        assert_eq!(vectors[32].name, "torus/grid_normals");
+        assert_ne!(test_vectors(8).unwrap()[0].input, vectors[0].input);
+        for v in vectors.iter() {
+            assert!(p3d_validate(v.input.as_bytes(), InputFileType::Obj).unwrap().is_watertight(), "{}", v.name);
+            assert_eq!(p3d_process_cfg(v.input.as_bytes(), InputFileType::Obj, &v.params()).unwrap(), v.hashes);
+            if v.algo == AlgoType::Grid2dV3a {
+                assert_eq!(v.hashes.len(), 10, "{}", v.name);
+            }
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&test_vectors_json(&vectors)).unwrap();
+        let first = &json.as_array().unwrap()[0];
+        assert_eq!(first["algo"], "grid2d");
+        assert_eq!(first["shape"], "ellipsoid");
+        assert_eq!(first["file_type"], "obj");
+        assert_eq!(first["input"], vectors[0].input.as_str());
+        assert_eq!(first["hashes"][0], vectors[0].hashes[0].as_str());
+    }
+
+    #[cfg(all(feature = "trace", feature = "std"))]
+    #[test]
+    fn test_trace_events() {
+        struct Capture;
+        static EVENTS: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        impl log::Log for Capture {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &log::Record) {
+                EVENTS.lock().unwrap().push(format!("{} {}", record.target(), record.args()));
+            }
+            fn flush(&self) {}
+        }
+        static CAPTURE: Capture = Capture;
+        log::set_logger(&CAPTURE).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        // Other tests log too, the events of this call are only checked to be among theirs
+        let params = P3DParams::default().options(ProcessOptions { fixed_point_scores: true, ..Default::default() });
+        let hashes = p3d_process_cfg(box_obj(7.0, 5.0, 3.0).as_bytes(), InputFileType::Obj, &params).unwrap();
+        let events = EVENTS.lock().unwrap().clone();
+        assert!(events.iter().any(|e| e.starts_with("p3d::parse file_type=Obj ") && e.ends_with(" vertices=8 faces=12")));
+        assert!(events.iter().any(|e| e.starts_with("p3d::align weighting=Volume moments=")));
+        assert!(events.iter().filter(|e| e.starts_with("p3d::sections z=")).count() >= 10);
+        assert!(events.iter().any(|e| e.starts_with(&format!("p3d::rank rank=0 hash={} score=Some(", hashes[0]))));
+    }
+
+    #[test]
+    fn test_process_rotations() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let rotations = [[1, 2, 3, 4], [0, 0, 1, 64], [1, 2, 3, 4], [9, 0, 200, 17]];
+        let batch = p3d_process_rotations(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, &rotations).unwrap();
+        assert_eq!(batch.len(), rotations.len());
+        for (rot, hashes) in rotations.iter().zip(batch.iter()) {
+            assert_eq!(hashes, &p3d_process_n(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, Some(*rot)).unwrap());
+        }
+        assert!(p3d_process_rotations(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, &[]).unwrap().is_empty());
+
+        // The search stops at the first rotation giving the wanted top hash
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        let target = batch[3][0].clone();
+        let mut tried = 0;
+        let found = mesh.search_rotations(AlgoType::Grid2dV3a, 10, 8, 6, rotations.iter().cloned(), |hashes| {
+            tried += 1;
+            hashes[0] == target
+        }).unwrap();
+        let first = batch.iter().position(|hashes| hashes[0] == target).unwrap();
+        assert_eq!(found, Some((rotations[first], batch[first].clone())));
+        assert_eq!(tried, first + 1);
+        assert_eq!(mesh.search_rotations(AlgoType::Grid2dV3a, 10, 8, 6, rotations.iter().cloned(), |_| false).unwrap(), None);
+    }
+
+    #[test]
+    fn test_process_cfg() {
+        let glb = include_bytes!("../test-ht.glb");
+        let params = P3DParams::default();
+        assert_eq!(
+            p3d_process_cfg(glb, InputFileType::Glb, &params).unwrap(),
+            p3d_process_n(glb, InputFileType::Glb, AlgoType::Grid2dV3a, 10, 8, 10, None).unwrap()
+        );
+        let params = P3DParams::default().algo(AlgoType::Grid2dV3).depth(3).grid_size(6).n_sections(5).transform([10, 20, 30, 40]);
+        assert_eq!(
+            p3d_process_cfg(glb, InputFileType::Glb, &params).unwrap(),
+            p3d_process_n(glb, InputFileType::Glb, AlgoType::Grid2dV3, 3, 6, 5, Some([10, 20, 30, 40])).unwrap()
+        );
+        let params = P3DParams::default().n_sections(0);
+        assert!(matches!(p3d_process_cfg(glb, InputFileType::Glb, &params), Err(P3DError::InvalidParams { .. })));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let process = |depth: usize, par1: i16, par2: i16| p3d_process_n(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, depth, par1, par2, None);
+        for &(depth, par1, par2, name) in [(10, 0, 6, "par1"), (10, i16::MIN, 6, "par1"), (10, 8, 0, "par2"), (10, 8, -3, "par2"), (0, 8, 6, "depth")].iter() {
+            match process(depth, par1, par2) {
+                Err(P3DError::InvalidParams { param, .. }) => assert_eq!(param, name),
+                other => panic!("Expected InvalidParams for {:?}, got {:?}", (depth, par1, par2), other),
+            }
+        }
+        assert!(process(1, 1, 1).is_ok());
+
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap();
+        assert!(matches!(mesh.process(AlgoType::Grid2dV3, 10, -1, 6, None), Err(P3DError::InvalidParams { .. })));
+        assert!(matches!(p3d_process_spacing(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 0.5, 0, None), Err(P3DError::InvalidParams { .. })));
+        for &spacing in [0.0, -1.0, f64::NAN, f64::INFINITY].iter() {
+            assert!(matches!(p3d_process_spacing(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, spacing, 8, None), Err(P3DError::InvalidParams { .. })));
+        }
+        assert!(matches!(process(MAX_DEPTH + 1, 8, 6), Err(P3DError::InvalidParams { param: "depth", .. })));
+        assert!(process(MAX_DEPTH, 8, 6).is_ok());
+        // Spectr would have no harmonic to hash
+        let spectr = P3DParams::default().algo(AlgoType::Spectr).grid_size(1);
+        assert!(matches!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &spectr), Err(P3DError::InvalidParams { param: "par1", .. })));
+        assert!(p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &spectr.grid_size(2)).is_ok());
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let trans = [1u8, 2, 3, 4];
+        let mut list = P3DStringList { strings: core::ptr::null_mut(), len: 0 };
+        let code = unsafe { p3d_process_c(obj.as_ptr(), obj.len(), 0, 3, 10, 8, 6, trans.as_ptr(), &mut list) };
+        assert_eq!(code, 0);
+        let expected = p3d_process_n(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, Some(trans)).unwrap();
+        let hashes: Vec<String> = (0..list.len)
+            .map(|i| unsafe { alloc::ffi::CString::from(core::ffi::CStr::from_ptr(*list.strings.add(i))) }.into_string().unwrap())
+            .collect();
+        assert_eq!(hashes, expected);
+        unsafe { p3d_free_string_list(&mut list) };
+        assert!(list.strings.is_null() && list.len == 0);
+
+        let mut untouched = P3DStringList { strings: core::ptr::null_mut(), len: 0 };
+        assert_eq!(unsafe { p3d_process_c(obj.as_ptr(), obj.len(), 9, 3, 10, 8, 6, core::ptr::null(), &mut untouched) }, -4);
+        assert_eq!(unsafe { p3d_process_c(obj.as_ptr(), obj.len(), 0, 11, 10, 8, 6, core::ptr::null(), &mut untouched) }, -13);
+        assert_eq!(unsafe { p3d_process_c(obj.as_ptr(), obj.len(), 3, 3, 10, 8, 6, core::ptr::null(), &mut untouched) }, -11);
+        assert!(untouched.strings.is_null());
+        // The generated header documents the code of every algorithm
+        let header = include_str!("../include/p3d.h");
+        for (code, (algo, _)) in AlgoType::NAMES.iter().enumerate() {
+            assert!(header.contains(&format!("{} `{:?}`", code, algo)), "include/p3d.h lacks algo {} {:?}", code, algo);
+        }
+
+        let name = |code: i32| unsafe { core::ffi::CStr::from_ptr(p3d_error_name(code)) }.to_str().unwrap();
+        assert_eq!(name(-11), "StlError");
+        assert_eq!(name(-24), "NonFiniteCoordinate");
+        assert_eq!(name(-26), "DegenerateGeometry");
+        assert_eq!(name(-27), "TooManyVertices");
+        assert_eq!(name(0), "Ok");
+        assert_eq!(name(-28), "Panic");
+        assert!(p3d_error_name(-29).is_null() && p3d_error_name(3).is_null());
+        let len = unsafe { p3d_last_error_message(core::ptr::null_mut(), 0) };
+        let mut buf = vec![0 as core::ffi::c_char; len + 1];
+        assert_eq!(unsafe { p3d_last_error_message(buf.as_mut_ptr(), buf.len()) }, len);
+        let message = unsafe { core::ffi::CStr::from_ptr(buf.as_ptr()) }.to_str().unwrap();
+        assert!(message.starts_with("STL: "), "{}", message);
+        let mut short = [0x7f as core::ffi::c_char; 4];
+        unsafe { p3d_last_error_message(short.as_mut_ptr(), short.len()) };
+        assert_eq!(unsafe { core::ffi::CStr::from_ptr(short.as_ptr()) }.to_bytes(), b"STL");
+        // Cleared by a successful call
+        assert_eq!(unsafe { p3d_process_c(obj.as_ptr(), obj.len(), 0, 3, 10, 8, 6, core::ptr::null(), &mut list) }, 0);
+        unsafe { p3d_free_string_list(&mut list) };
+        assert_eq!(unsafe { p3d_last_error_message(core::ptr::null_mut(), 0) }, 0);
+
+        // A panic is reported like an error, with its message
+        let payload = std::panic::catch_unwind(|| panic!("section {} out of range", 3)).unwrap_err();
+        assert_eq!(ffi::panicked(&*payload), -28);
+        let mut buf = [0 as core::ffi::c_char; 64];
+        unsafe { p3d_last_error_message(buf.as_mut_ptr(), buf.len()) };
+        assert_eq!(unsafe { core::ffi::CStr::from_ptr(buf.as_ptr()) }.to_bytes(), b"panic: section 3 out of range");
+    }
+
+    #[test]
+    fn test_intersect_tolerance() {
+        let obj = sphere_obj(1.0, 12, 16);
+        let process = |tolerance: f64| {
+            let opts = ProcessOptions { intersect_tolerance: tolerance, ..Default::default() };
+            p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap()
+        };
+        let default = process(DEFAULT_INTERSECT_TOLERANCE);
+        assert_eq!(default.hashes, p3d_process_n(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None).unwrap());
+
+        // The sphere's edges are shorter along Z than a step, so every crossing snaps to an edge end
+        let snapped = process(1.0);
+        let mesh = P3DMesh::load(obj.as_bytes(), InputFileType::Obj).unwrap().mesh;
+        let vertices: Vec<Vec2> = mesh.vertex_iter().map(|v| mesh.vertex_position(v)).map(|p| Vec2::new(p.x, p.y)).collect();
+        assert!(snapped.centers.iter().flatten().all(|p| vertices.contains(p)));
+        assert!(!default.centers.iter().flatten().all(|p| vertices.contains(p)));
+    }
+
+    #[test]
+    fn test_max_faces() {
+        let obj = box_obj(1.0, 2.0, 3.0);
+        let opts = ProcessOptions { max_faces: 11, ..Default::default() };
+        match p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts) {
+            Err(P3DError::MeshTooLarge { faces, limit }) => assert_eq!((faces, limit), (12, 11)),
+            other => panic!("Expected MeshTooLarge, got {:?}", other),
+        }
+        let opts = ProcessOptions { max_faces: 12, ..Default::default() };
+        assert!(p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).is_ok());
+
+        // Rejected from the index count alone, before the (here invalid) mesh is built
+        let geometry = Geometry { positions: vec![0.0; 9], indices: vec![0, 1, 7].repeat(1000), materials: Vec::new() };
+        let opts = ProcessOptions { max_faces: 999, ..Default::default() };
+        assert!(matches!(build_mesh_opts(geometry, &opts), Err(P3DError::MeshTooLarge { faces: 1000, limit: 999 })));
+    }
+
+    #[test]
+    fn test_raw_hashes() {
+        let obj = box_obj(1.0, 2.0, 3.0);
+        for algo in [AlgoType::Grid2d, AlgoType::Grid2dV3a, AlgoType::Spectr, AlgoType::Skeleton] {
+            let hex = p3d_process_n(obj.as_bytes(), InputFileType::Obj, algo, 10, 8, 6, Some([1, 2, 3, 4])).unwrap();
+            let raw = p3d_process_raw(obj.as_bytes(), InputFileType::Obj, algo, 10, 8, 6, Some([1, 2, 3, 4])).unwrap();
+            assert!(!raw.is_empty() || algo != AlgoType::Grid2dV3a);
+            let encoded: Vec<String> = raw.iter().map(|h| h.iter().map(|b| format!("{:02x}", b)).collect()).collect();
+            assert_eq!(encoded, hex, "{:?}", algo);
+        }
+    }
+
+    #[test]
+    fn test_hash_algo() {
+        // Digests of the empty message
+        let empty: [&[u8]; 0] = [];
+        for (hash_algo, expected) in [
+            (HashAlgo::Sha256, "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            (HashAlgo::Blake2b256, "0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8"),
+            (HashAlgo::Keccak256, "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"),
+            (HashAlgo::Sha3_256, "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"),
+        ] {
+            assert_eq!(to_hex(&algo_grid::digest(hash_algo, empty)), expected, "{:?}", hash_algo);
+        }
+
+        let obj = box_obj(1.0, 2.0, 3.0);
+        let process = |hash_algo: HashAlgo| {
+            let opts = ProcessOptions { hash_algo, ..Default::default() };
+            p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts).unwrap()
+        };
+        let sha256 = process(HashAlgo::Sha256);
+        assert_eq!(sha256, p3d_process_n(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None).unwrap());
+        let keccak = process(HashAlgo::Keccak256);
+        assert_eq!(keccak.len(), sha256.len());
+        assert!(keccak.iter().all(|h| h.len() == 64 && !sha256.contains(h)));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let obj = box_obj(1.0, 2.0, 3.0);
+        let token = Cancel::token();
+        let expected = p3d_process_n(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None).unwrap();
+        assert_eq!(p3d_process_cancellable(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &token).unwrap(), expected);
+        token.clone().cancel();
+        assert!(matches!(p3d_process_cancellable(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &token), Err(P3DError::Cancelled)));
+
+        // `Grid2dV2` ranks every combination of two candidates per section, 2^40 of them here:
+        // a check giving up after a few calls stops it
+        let calls = alloc::sync::Arc::new(core::sync::atomic::AtomicUsize::new(0));
+        let counted = calls.clone();
+        let cancel = Cancel::new(move || counted.fetch_add(1, core::sync::atomic::Ordering::Relaxed) >= 20);
+        let result = p3d_process_cancellable(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV2, 10, 8, 40, None, &cancel);
+        assert!(matches!(result, Err(P3DError::Cancelled)), "{:?}", result);
+        assert!(cancel.is_cancelled());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_progress() {
+        let obj = box_obj(1.0, 2.0, 3.0);
+        let events = alloc::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let progress = Progress::new(move |stage, percent| recorded.lock().unwrap().push((stage, percent)));
+        let run = |params: P3DParams| {
+            events.lock().unwrap().clear();
+            let options = ProcessOptions { progress: Some(progress.clone()), ..params.options.clone() };
+            let hashes = p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params.clone().options(options)).unwrap();
+            assert_eq!(hashes, p3d_process_cfg(obj.as_bytes(), InputFileType::Obj, &params).unwrap());
+            events.lock().unwrap().clone()
+        };
+
+        // The triangle soup path, then the mesh one (`SliceAxis::X` needs the mesh)
+        let stages = [Stage::Parse, Stage::BuildMesh, Stage::Transform, Stage::Slice, Stage::Hash];
+        let params = P3DParams { n_sections: 4, ..Default::default() };
+        for slice_axis in [SliceAxis::Z, SliceAxis::X] {
+            let events = run(params.clone().options(ProcessOptions { slice_axis, ..Default::default() }));
+            for stage in stages.iter() {
+                let percents: Vec<u8> = events.iter().filter(|(s, _)| s == stage).map(|&(_, p)| p).collect();
+                assert_eq!(percents.first(), Some(&0), "{:?}: {:?}", stage, events);
+                assert_eq!(percents.last(), Some(&100), "{:?}: {:?}", stage, events);
+            }
+            // Parallel slicing may report the sections out of order
+            let mut slices: Vec<u8> = events.iter().filter(|(s, _)| *s == Stage::Slice).map(|&(_, p)| p).collect();
+            slices.sort();
+            assert_eq!(slices, [0, 25, 50, 75, 100]);
+            // In order, each stage once
+            let order: Vec<Stage> = events.iter().map(|&(s, _)| s).fold(Vec::new(), |mut order, s| {
+                if order.last() != Some(&s) {
+                    order.push(s);
+                }
+                order
+            });
+            assert_eq!(order, stages);
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let obj = box_obj(1.0, 2.0, 3.0);
+        let process = |limits: Limits| {
+            let opts = ProcessOptions { limits, ..Default::default() };
+            p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &opts)
+        };
+        assert!(process(Limits::default()).is_ok());
+        let exceeded = |result: Result<Vec<String>, P3DError>| match result {
+            Err(P3DError::LimitExceeded { what, value, limit }) => (what, value, limit),
+            other => panic!("Expected LimitExceeded, got {:?}", other),
+        };
+        let bytes = obj.len();
+        assert_eq!(exceeded(process(Limits { max_input_bytes: bytes - 1, ..Default::default() })), ("input bytes", bytes, bytes - 1));
+        assert_eq!(exceeded(process(Limits { max_vertices: 7, ..Default::default() })), ("vertices", 8, 7));
+        assert_eq!(exceeded(process(Limits { max_sections: 5, ..Default::default() })), ("sections", 6, 5));
+        assert_eq!(exceeded(process(Limits { max_allocation: 1000, ..Default::default() })).0, "allocation bytes");
+
+        // Ten instances of a tetrahedron stop growing at the vertex limit
+        let nodes: Vec<String> = (0..10).map(|i| format!(r#"{{ "mesh": 0, "translation": [{}, 0, 0] }}"#, i * 2)).collect();
+        let scene: Vec<String> = (0..10).map(|i| i.to_string()).collect();
+        let glb = two_tetra_glb(&format!(r#""meshes": [ {{ "primitives": [ {{ "attributes": {{ "POSITION": 0 }}, "indices": 2 }} ] }} ],
+          "nodes": [ {} ], "scenes": [ {{ "nodes": [{}] }} ], "scene": 0"#, nodes.join(", "), scene.join(", ")));
+        let opts = ProcessOptions { limits: Limits { max_vertices: 30, ..Default::default() }, ..Default::default() };
+        assert!(matches!(load_geometry(&glb, InputFileType::Glb, &opts), Err(P3DError::LimitExceeded { what: "vertices", value: 32, limit: 30 })));
+        assert_eq!(load_geometry(&glb, InputFileType::Glb, &ProcessOptions::default()).unwrap().positions.len(), 40 * 3);
+    }
+
+    #[test]
+    fn test_allocations() {
+        let obj = box_obj(1.0, 2.0, 3.0);
+        let allocations = Allocations::new();
+        // Loops go through the mesh
+        let opts = ProcessOptions { trace_loops: true, allocations: Some(allocations.clone()), ..Default::default() };
+        let process = |opts: &ProcessOptions| p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, opts);
+        let result = process(&opts).unwrap();
+        let plain = process(&ProcessOptions { trace_loops: true, ..Default::default() }).unwrap();
+        assert_eq!(result.hashes, plain.hashes);
+        assert_eq!(plain.peak_allocation, 0);
+        assert_eq!(result.peak_allocation, allocations.peak());
+        assert!(allocations.peak() > mesh_allocation(8, 12));
+        assert_eq!(allocations.current(), 0);
+
+        let capped = Limits { max_allocation: allocations.peak() - 1, ..Default::default() };
+        match process(&ProcessOptions { limits: capped, allocations: Some(Allocations::new()), ..opts.clone() }) {
+            Err(P3DError::LimitExceeded { value, limit, .. }) => assert!(value > limit && limit == capped.max_allocation),
+            other => panic!("Expected LimitExceeded, got {:?}", other),
+        }
+        // Without accounting only the estimate of the mesh counts
+        assert!(process(&ProcessOptions { limits: capped, trace_loops: true, ..Default::default() }).is_ok());
+
+        let cloud = Allocations::new();
+        let params = P3DParams::default().algo(AlgoType::CloudGrid);
+        let params = P3DParams { options: ProcessOptions { allocations: Some(cloud.clone()), ..Default::default() }, ..params };
+        assert_eq!(p3d_process_cfg_detailed(obj.as_bytes(), InputFileType::Obj, &params).unwrap().peak_allocation, 8 * 48);
+        assert_eq!(cloud.current(), 0);
+    }
+
+    #[test]
+    fn test_dump_obj() {
+        let obj = l_shape_obj(1.0, [0.0; 3]);
+        let trans = Some([1, 2, 3, 4]);
+        let aligned = dump_aligned_obj(obj.as_bytes(), InputFileType::Obj, trans).unwrap();
+        let result = p3d_process_detailed(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, trans, &ProcessOptions::default()).unwrap();
+
+        // The dump loads back as the aligned mesh, which is already on its axes, up to the f32
+        // rounding of OBJ coordinates
+        let opts = ProcessOptions { align: false, ..Default::default() };
+        let (reloaded, _) = load_mesh(aligned.as_bytes(), InputFileType::Obj, &opts).unwrap();
+        assert_eq!(reloaded.no_faces(), 24);
+        let (v_min, v_max) = reloaded.extreme_coordinates();
+        for (p, q) in [(v_min, result.v_min), (v_max, result.v_max)] {
+            assert!((p - q).magnitude() < 1e-5, "Reloaded extreme {:?}, expected {:?}", p, q);
+        }
+
+        let sections = dump_sections_obj(obj.as_bytes(), InputFileType::Obj, 6, trans).unwrap();
+        assert_eq!(sections.lines().filter(|l| l.starts_with("o ")).count(), result.centers.len());
+        let first: Vec<&str> = sections.lines().skip(1).take_while(|l| l.starts_with("v ")).collect();
+        assert_eq!(first.len(), result.centers[0].len());
+        assert_eq!(first[0], format!("v {} {} {}", result.centers[0][0].x, result.centers[0][0].y, result.section_heights[0]));
+        for n_sections in [0, -1, i16::MIN] {
+            assert!(matches!(dump_sections_obj(obj.as_bytes(), InputFileType::Obj, n_sections, trans), Err(P3DError::InvalidParams { .. })));
+        }
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        // A lopsided sphere (so its principal axes are well defined) with its vertices and faces
+        // listed in another order
+        let obj = sphere_obj(1.0, 12, 18);
+        let numbers = |prefix: &str| -> Vec<f64> {
+            obj.lines().filter(|l| l.starts_with(prefix))
+                .flat_map(|l| l[2..].split_whitespace().map(|t| t.split('/').next().unwrap().parse::<f64>().unwrap()).collect::<Vec<_>>())
+                .collect()
+        };
+        let positions: Vec<f64> = numbers("v ").iter().enumerate()
+            .map(|(i, v)| v * [1.0, 1.4, 2.1][i % 3] + if i % 3 == 0 { 0.3 * v * v } else { 0.0 })
+            .collect();
+        let indices: Vec<u32> = numbers("f ").iter().map(|&i| i as u32 - 1).collect();
+        let n = positions.len() / 3;
+        let perm: Vec<u32> = (0..n as u32).map(|i| (i * 7 + 3) % n as u32).collect();
+        let mut shuffled = vec![0.0; positions.len()];
+        for i in 0..n {
+            shuffled[perm[i] as usize * 3..perm[i] as usize * 3 + 3].copy_from_slice(&positions[i * 3..i * 3 + 3]);
+        }
+        let mut faces: Vec<[u32; 3]> = indices.chunks(3).map(|t| [perm[t[1] as usize], perm[t[2] as usize], perm[t[0] as usize]]).collect();
+        faces.reverse();
+        let shuffled_indices: Vec<u32> = faces.iter().flatten().cloned().collect();
+
+        let opts = ProcessOptions { canonical_order: true, ..Default::default() };
+        for algo in [AlgoType::Grid2dV3, AlgoType::Grid2dV3a].iter() {
+            let process = |p: &[f64], i: &[u32]| p3d_process_opts(geometry_obj(p, i).as_bytes(), InputFileType::Obj, *algo, 10, 8, 6, None, &opts).unwrap();
+            let hashes = process(&positions, &indices);
+            assert!(hashes.windows(2).all(|w| w[0] <= w[1]));
+            assert_eq!(hashes, process(&shuffled, &shuffled_indices), "{:?}", algo);
+        }
+    }
+
+    #[test]
+    fn test_reflection() {
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [6.0, 3.0, 1.5]);
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 1.5], [1.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 3.0, 0.0], [2.0, 1.0, 1.0]);
+        let rotation = Matrix4::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), Deg(70.0));
+        let turned: Vec<f64> = positions.chunks(3)
+            .flat_map(|v| {
+                let p = rotation.transform_point(Point3::new(v[0], v[1], v[2]));
+                [p.x, p.y, p.z]
+            })
+            .collect();
+        // Mirrored in x, with the faces turned so they still face outwards
+        let mirrored: Vec<f64> = positions.chunks(3).flat_map(|v| [-v[0], v[1], v[2]]).collect();
+        let mirrored_indices: Vec<u32> = indices.chunks(3).flat_map(|t| [t[0], t[2], t[1]]).collect();
+        // Positions of the vertices once posed, sorted as the mesh may order them differently,
+        // and the transform. Built from the positions as they are, OBJ text would round them to f32.
+        let pose = |p: &[f64], i: &[u32], reflection: Reflection| {
+            let opts = ProcessOptions { reflection, inertia: InertiaWeighting::Surface, ..Default::default() };
+            let geometry = Geometry { positions: p.to_vec(), indices: i.to_vec(), materials: Vec::new() };
+            let (mut mesh, _) = build_mesh_opts(geometry, &opts).unwrap();
+            let (_, transform) = pose_mesh(&mut mesh, None, &opts).unwrap();
+            let mut posed: Vec<Vector3<f64>> = mesh.vertex_iter().map(|v| mesh.vertex_position(v)).collect();
+            posed.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+            (posed, transform)
+        };
+        let same = |a: &[Vector3<f64>], b: &[Vector3<f64>]| a.iter().zip(b.iter()).all(|(p, q)| (p - q).magnitude() < 1e-9);
+
+        let (original, transform) = pose(&positions, &indices, Reflection::Distinguish);
+        assert!(transform.determinant() > 0.0);
+        assert!(same(&original, &pose(&turned, &indices, Reflection::Distinguish).0));
+        let (mirror, transform) = pose(&mirrored, &mirrored_indices, Reflection::Distinguish);
+        assert!(transform.determinant() > 0.0);
+        assert!(!same(&original, &mirror));
+
+        let (original, _) = pose(&positions, &indices, Reflection::Ignore);
+        assert!(same(&original, &pose(&turned, &indices, Reflection::Ignore).0));
+        assert!(same(&original, &pose(&mirrored, &mirrored_indices, Reflection::Ignore).0));
+    }
+
+    #[test]
+    fn test_provided_alignment_and_orient_axes() {
+        // Lopsided along every axis, so each third moment has a clear sign
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [6.0, 3.0, 1.5]);
+        push_box(&mut positions, &mut indices, [0.0, 0.0, 1.5], [1.0, 1.0, 1.0]);
+        push_box(&mut positions, &mut indices, [0.0, 3.0, 0.0], [2.0, 1.0, 1.0]);
+        let process = |p: &[f64], opts: &ProcessOptions| {
+            p3d_process_opts(geometry_obj(p, &indices).as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, opts).unwrap()
+        };
+
+        let unaligned = ProcessOptions { align: false, ..Default::default() };
+        let identity = ProcessOptions { alignment: Some(Matrix4::identity()), ..Default::default() };
+        assert_eq!(process(&positions, &identity), process(&positions, &unaligned));
+
+        // A provided transform is applied as it is, instead of the PIT
+        let rotation = Matrix4::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), Deg(70.0));
+        let rotated: Vec<f64> = positions.chunks(3)
+            .flat_map(|v| {
+                let p = rotation.transform_point(Point3::new(v[0], v[1], v[2]));
+                [p.x, p.y, p.z]
+            })
+            .collect();
+        let provided = ProcessOptions { alignment: rotation.invert(), ..Default::default() };
+        assert_eq!(process(&rotated, &provided), process(&positions, &unaligned));
+        let result = p3d_process_detailed(geometry_obj(&rotated, &indices).as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 6, None, &provided).unwrap();
+        assert_eq!(result.transform, rotation.invert().unwrap());
+        assert_eq!(result.shift, Vector3::new(0.0, 0.0, 0.0));
+
+        // `orient_axes` only reverses axes of the PIT pose, leaving positive third moments along x and y
+        let oriented = ProcessOptions { orient_axes: true, ..Default::default() };
+        let pose = |opts: &ProcessOptions| {
+            let (mut mesh, _) = load_mesh(geometry_obj(&rotated, &indices).as_bytes(), InputFileType::Obj, opts).unwrap();
+            let (_, transform) = pose_mesh(&mut mesh, None, opts).unwrap();
+            (mesh, transform)
+        };
+        let (plain, plain_transform) = pose(&ProcessOptions::default());
+        let (mesh, transform) = pose(&oriented);
+        let skew = |axis: usize| sum::compensated_sum(mesh.vertex_iter().map(|v| mesh.vertex_position(v)[axis].powi(3)));
+        assert!(skew(0) > 0.0 && skew(1) > 0.0);
+        assert!(transform.determinant() * plain_transform.determinant() > 0.0);
+        for (a, b) in plain.vertex_iter().zip(mesh.vertex_iter()) {
+            let (p, q) = (plain.vertex_position(a), mesh.vertex_position(b));
+            for axis in 0..3 {
+                assert!((p[axis].abs() - q[axis].abs()).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_and_flat_mesh() {
+        let process = |obj: &str| p3d_process(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 8, 4, None);
+        let triangle = geometry_obj(&[0., 0., 0., 1., 0., 0., 0., 1., 0.], &[0, 1, 2]);
+        let quad = geometry_obj(&[0., 0., 0., 2., 0., 0., 2., 1., 0., 0., 1., 0.], &[0, 1, 2, 0, 2, 3]);
+        for obj in [&triangle, &quad] {
+            assert!(matches!(process(obj), Err(P3DError::FlatObject { .. })), "Flat mesh accepted: {:?}", process(obj));
+        }
+        match process(&triangle) {
+            Err(P3DError::FlatObject { thickness, extent }) => assert!(thickness == 0.0 && (extent - 2f64.sqrt()).abs() < 1e-12),
+            other => panic!("{:?}", other),
+        }
+        // Also when it would be sliced across its plane without alignment, whichever way it's tilted
+        let opts = ProcessOptions { align: false, ..Default::default() };
+        let upright = geometry_obj(&[0., 0., 0., 2., 0., 0., 2., 0., 1., 0., 0., 1.], &[0, 1, 2, 0, 2, 3]);
+        let tilted = geometry_obj(&[0., 0., 0., 2., 1., 0., 2., 2., 1., 0., 1., 1.], &[0, 1, 2, 0, 2, 3]);
+        for obj in [&upright, &tilted] {
+            assert!(matches!(
+                p3d_process_opts(obj.as_bytes(), InputFileType::Obj, AlgoType::Grid2dV3a, 10, 8, 4, None, &opts),
+                Err(P3DError::FlatObject { .. })
+            ));
+        }
+        // Faces along a line, as after a projection
+        let needle = geometry_obj(&[0., 0., 0., 1., 1., 1., 2., 2., 2., 3., 3., 3.], &[0, 1, 2, 1, 2, 3]);
+        let err = process(&needle).unwrap_err();
+        assert!(matches!(err, P3DError::DegenerateGeometry { rank: 1, moments } if moments[0].abs() < 1e-9 && moments[2] > 1.0), "{:?}", err);
+        assert_eq!(err.code(), 26);
+        let point = geometry_obj(&[1., 2., 3., 1., 2., 3., 1., 2., 3.], &[0, 1, 2]);
+        assert!(matches!(process(&point), Err(P3DError::DegenerateGeometry { rank: 0, .. })));
+        // A flat point cloud too
+        let cloud = "0 0 0\n1 0 0\n0 1 0\n1 1 0\n";
+        assert!(matches!(
+            p3d_process_n(cloud.as_bytes(), InputFileType::Xyz, AlgoType::CloudGrid, 10, 8, 4, None),
+            Err(P3DError::FlatObject { .. })
+        ));
+
+        assert!(matches!(process("v 0 0 0\nv 1 0 0\nv 0 1 0\n"), Err(P3DError::EmptyMesh)));
+    }
+
+    #[test]
+    fn test_soup_matches_mesh() {
+        // `p3d_process_cfg_detailed` slices these without building the mesh
+        let stretched: Vec<String> = sphere_obj(1.0, 12, 18).lines()
+            .map(|l| match l.strip_prefix("v ") {
+                Some(v) => {
+                    let c: Vec<f64> = v.split_whitespace().map(|t| t.parse().unwrap()).collect();
+                    format!("v {} {} {}", c[0] * 1.1 + 0.3 * c[0] * c[0], c[1] * 1.4, c[2] * 2.1)
+                }
+                None => l.to_string(),
+            })
+            .collect();
+        // Faces of opposite orientation leave the mesh a degenerate edge where they meet
+        let mut positions = Vec::new();
+        let mut indices = Vec::new();
+        push_box(&mut positions, &mut indices, [0.0; 3], [3.0, 1.5, 1.0]);
+        push_box(&mut positions, &mut indices, [0.5, 0.0, 1.0], [1.0, 1.0, 2.0]);
+        for t in indices.chunks_mut(9) {
+            t.swap(1, 2);
+        }
+        let inputs = [stretched.join("\n"), l_shape_obj(1.3, [0.4, -0.2, 0.7]), geometry_obj(&positions, &indices)];
+        let options = [
+            ProcessOptions::default(),
+            ProcessOptions { canonical_order: true, ..Default::default() },
+            ProcessOptions { inertia: InertiaWeighting::Surface, fixed_point_scores: true, debug_geometry: true, ..Default::default() },
+            ProcessOptions { inertia: InertiaWeighting::Vertices, grid_size: GridSize::Auto, ..Default::default() },
+            ProcessOptions { align: false, simplify_tolerance: Some(0.01), ..Default::default() },
+        ];
+        for input in inputs.iter() {
+            for algo in [AlgoType::Grid2d, AlgoType::Grid2dV2, AlgoType::Grid2dV3, AlgoType::Grid2dV3a] {
+                for opts in options.iter() {
+                    for transform in [None, Some(Transform::from([10, 20, 30, 40]))] {
+                        let params = P3DParams { algo, depth: 10, grid_size: 8, n_sections: 12, transform, options: opts.clone() };
+                        let geometry = load_geometry(input.as_bytes(), InputFileType::Obj, opts).unwrap();
+                        assert!(soup::supported(algo, &geometry, opts));
+                        let fast = p3d_process_cfg_detailed(input.as_bytes(), InputFileType::Obj, &params).unwrap();
+                        let meshed = P3DMesh::load_opts(input.as_bytes(), InputFileType::Obj, opts).unwrap()
+                            .process_transform(algo, 10, 8, 12, transform).unwrap();
+                        assert!(!fast.centers.is_empty());
+                        assert_eq!(fast, meshed, "{:?} {:?}", algo, opts);
+                    }
+                }
+            }
         }
     }
 }
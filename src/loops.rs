@@ -0,0 +1,143 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use cgmath::Point2;
+use tri_mesh::mesh::Mesh;
+use tri_mesh::prelude::{FaceID, Vector3, VertexID};
+
+use crate::triangulate::signed_area;
+
+type Vec2 = Point2<f64>;
+type EdgeKey = (VertexID, VertexID);
+type Chain = Vec<(Vec2, FaceID)>;
+
+// A face with its corners, what `section_loops` reads of the mesh
+pub(crate) type Face = (FaceID, [(VertexID, Vector3<f64>); 3]);
+
+// The faces of a mesh in its iteration order, copied out of the mesh (which can't be shared
+// between threads) to trace sections in parallel
+pub(crate) fn mesh_faces(mesh: &Mesh) -> Vec<Face> {
+    mesh.face_iter()
+        .map(|face| {
+            let (a, b, c) = mesh.face_vertices(face);
+            (face, [a, b, c].map(|v| (v, mesh.vertex_position(v))))
+        })
+        .collect()
+}
+
+fn edge_key(a: VertexID, b: VertexID) -> EdgeKey {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+// Whether `p` is inside the closed `chain` (even-odd rule)
+fn encloses(chain: &[(Vec2, FaceID)], p: Vec2) -> bool {
+    let n = chain.len();
+    (0..n).filter(|&i| {
+        let (a, b) = (chain[i].0, chain[(i + 1) % n].0);
+        (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x)
+    }).count() % 2 == 1
+}
+
+// Closed loops (and, for open meshes, open chains) where the plane `z = z_sect` cuts the mesh,
+// every point tagged with the face leading to the next one. Points come from the crossed edges
+// and two are linked when they share a face, so separate parts and holes give separate loops.
+// Vertices on the plane count as above it, so every face is crossed by none or two edges.
+//
+// Closed loops are counter-clockwise around material and clockwise around holes (loops inside
+// an odd number of others), start at their smallest point (by x, then y) and end with it
+// repeated, like `get_contour`. Loops come largest (by area) first, so before the holes in them.
+pub(crate) fn section_loops(faces: &[Face], z_sect: f64) -> Vec<Vec<(Vec2, FaceID)>> {
+    let mut points: BTreeMap<EdgeKey, Vec2> = BTreeMap::new();
+    let mut links: BTreeMap<EdgeKey, Vec<(EdgeKey, FaceID)>> = BTreeMap::new();
+
+    for &(face, [a, b, c]) in faces.iter() {
+        let mut crossed: Vec<EdgeKey> = Vec::with_capacity(2);
+        for &((u, pu), (v, pv)) in [(a, b), (b, c), (c, a)].iter() {
+            if (pu.z >= z_sect) == (pv.z >= z_sect) {
+                continue;
+            }
+            let key = edge_key(u, v);
+            let t = (z_sect - pu.z) / (pv.z - pu.z);
+            points.entry(key).or_insert_with(|| Vec2::new(pu.x + t * (pv.x - pu.x), pu.y + t * (pv.y - pu.y)));
+            crossed.push(key);
+        }
+        if crossed.len() == 2 {
+            links.entry(crossed[0]).or_insert_with(Vec::new).push((crossed[1], face));
+            links.entry(crossed[1]).or_insert_with(Vec::new).push((crossed[0], face));
+        }
+    }
+
+    // Chains start at their dead ends, loops anywhere
+    let mut starts: Vec<EdgeKey> = links.iter().filter(|(_, l)| l.len() == 1).map(|(k, _)| *k).collect();
+    starts.extend(links.keys().cloned());
+    let mut used_faces: BTreeMap<FaceID, ()> = BTreeMap::new();
+    let mut loops: Vec<(bool, Vec<(Vec2, FaceID)>)> = Vec::new();
+    for start in starts {
+        let mut chain: Vec<(Vec2, FaceID)> = Vec::new();
+        let mut current = start;
+        let mut closed = false;
+        loop {
+            let next = links[&current].iter().find(|(_, face)| !used_faces.contains_key(face)).cloned();
+            match next {
+                Some((key, face)) => {
+                    used_faces.insert(face, ());
+                    chain.push((points[&current], face));
+                    current = key;
+                    if current == start {
+                        closed = true;
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+        if chain.is_empty() {
+            continue;
+        }
+        if !closed {
+            let last_face = chain[chain.len() - 1].1;
+            chain.push((points[&current], last_face));
+        }
+        loops.push((closed, chain));
+    }
+
+    let loops: Vec<(bool, f64, Chain)> = loops.into_iter()
+        .map(|(closed, chain)| {
+            let polygon: Vec<Vec2> = chain.iter().map(|(p, _)| *p).collect();
+            (closed, signed_area(&polygon), chain)
+        })
+        .collect();
+    // A closed loop inside an odd number of larger closed loops bounds a hole
+    let holes: Vec<bool> = loops.iter()
+        .map(|(closed, area, chain)| {
+            *closed && loops.iter()
+                .filter(|(other_closed, other_area, other)| *other_closed && other_area.abs() > area.abs() && encloses(other, chain[0].0))
+                .count() % 2 == 1
+        })
+        .collect();
+
+    let mut loops: Vec<(f64, Vec<(Vec2, FaceID)>)> = loops.into_iter().zip(holes)
+        .map(|((closed, area, mut chain), hole)| {
+            if closed {
+                if (area >= 0.0) == hole {
+                    // Walking backwards each point leads on through the face of the previous one
+                    let faces: Vec<FaceID> = chain.iter().map(|(_, f)| *f).collect();
+                    chain.reverse();
+                    let n = chain.len();
+                    for (i, item) in chain.iter_mut().enumerate() {
+                        item.1 = faces[(2 * n - 2 - i) % n];
+                    }
+                }
+                let first = (0..chain.len())
+                    .min_by(|&i, &j| (chain[i].0.x, chain[i].0.y).partial_cmp(&(chain[j].0.x, chain[j].0.y)).unwrap_or(core::cmp::Ordering::Equal))
+                    .unwrap_or(0);
+                chain.rotate_left(first);
+                chain.push(chain[0]);
+            }
+            (area.abs(), chain)
+        })
+        .collect();
+    loops.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(core::cmp::Ordering::Equal)
+        .then_with(|| (a.1[0].0.x, a.1[0].0.y).partial_cmp(&(b.1[0].0.x, b.1[0].0.y)).unwrap_or(core::cmp::Ordering::Equal)));
+    loops.into_iter().map(|(_, chain)| chain).collect()
+}
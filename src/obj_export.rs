@@ -0,0 +1,47 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use cgmath::Point2;
+use tri_mesh::mesh::Mesh;
+
+// Coordinates are written with `{}`, which round-trips f64 exactly
+
+// The mesh as OBJ text: a `v` line per vertex in `vertex_iter` order, then a `vn` and an `f`
+// line per face. The normals are there for the obj crate, which rejects faces without them.
+pub(crate) fn mesh_obj(mesh: &Mesh) -> String {
+    let mut obj = String::new();
+    let ids: BTreeMap<_, usize> = mesh.vertex_iter().enumerate().map(|(i, v)| (v, i + 1)).collect();
+    for v in mesh.vertex_iter() {
+        let p = mesh.vertex_position(v);
+        let _ = writeln!(obj, "v {} {} {}", p.x, p.y, p.z);
+    }
+    for (k, f) in mesh.face_iter().enumerate() {
+        let (a, b, c) = mesh.face_vertices(f);
+        let n = mesh.face_normal(f);
+        let _ = writeln!(obj, "vn {} {} {}", n.x, n.y, n.z);
+        let _ = writeln!(obj, "f {a}//{k} {b}//{k} {c}//{k}", a = ids[&a], b = ids[&b], c = ids[&c], k = k + 1);
+    }
+    obj
+}
+
+// Section contours as OBJ polylines: every contour is an object of `v` lines at its height
+// and one `l` line through them in order
+pub(crate) fn sections_obj(centers: &[Vec<Point2<f64>>], heights: &[f64]) -> String {
+    let mut obj = String::new();
+    let mut base = 1;
+    for (k, (cntr, z)) in centers.iter().zip(heights.iter()).enumerate() {
+        let _ = writeln!(obj, "o section_{}", k);
+        for p in cntr.iter() {
+            let _ = writeln!(obj, "v {} {} {}", p.x, p.y, z);
+        }
+        obj.push('l');
+        for i in 0..cntr.len() {
+            let _ = write!(obj, " {}", base + i);
+        }
+        obj.push('\n');
+        base += cntr.len();
+    }
+    obj
+}
@@ -0,0 +1,145 @@
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use cgmath::Point2;
+
+use crate::io::Precision;
+use crate::triangulate::ear_clip_indices;
+use crate::error::{line_error, utf8_error};
+use crate::{vertex_id, Format, P3DError};
+
+// Geometry of an OBJ file: f64 positions (x, y, z per vertex) and triangle indices
+pub(crate) struct ObjGeometry {
+    pub(crate) positions: Vec<f64>,
+    pub(crate) indices: Vec<u32>,
+}
+
+pub(crate) fn syntax_error(line: usize, reason: &'static str) -> P3DError {
+    line_error(Format::Obj, line, reason)
+}
+
+// A coordinate of a `v` line. `Precision::F32` parses it straight to f32, as the `obj` crate
+// does, rather than rounding the f64 value, which could round differently.
+pub(crate) fn coordinate(token: &str, precision: Precision, line: usize) -> Result<f64, P3DError> {
+    let parsed = match precision {
+        Precision::F64 => token.parse::<f64>().ok(),
+        Precision::F32 => token.parse::<f32>().ok().map(f64::from),
+    };
+    parsed.ok_or_else(|| syntax_error(line, "bad coordinate"))
+}
+
+// Resolves a 1-based (or negative, relative to the end) OBJ index against `count` elements
+pub(crate) fn resolve_index(token: &str, count: usize, line: usize) -> Result<usize, P3DError> {
+    let i: i64 = token.parse().map_err(|_| syntax_error(line, "bad index"))?;
+    let resolved = if i > 0 { i - 1 } else { count as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(syntax_error(line, "index out of range"));
+    }
+    Ok(resolved as usize)
+}
+
+// Triangles of a polygon face, wound like the face. Convex faces are fanned from the first
+// corner; concave ones, which a fan would fold over themselves, are ear clipped in the plane
+// of the face. `point` gives the position of a corner.
+pub(crate) fn triangulate_face(corners: &[u32], point: impl Fn(u32) -> [f64; 3]) -> Vec<[u32; 3]> {
+    let fan = || (1..corners.len() - 1).map(|k| [corners[0], corners[k], corners[k + 1]]).collect();
+    if corners.len() == 3 {
+        return fan();
+    }
+    // Newell normal, then drop its dominant axis
+    let mut normal = [0.0f64; 3];
+    for (k, &id) in corners.iter().enumerate() {
+        let (p, q) = (point(id), point(corners[(k + 1) % corners.len()]));
+        for (a, n) in normal.iter_mut().enumerate() {
+            let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+            *n += (p[b] - q[b]) * (p[c] + q[c]);
+        }
+    }
+    let axis = (0..3).fold(0, |best, a| if normal[a].abs() > normal[best].abs() { a } else { best });
+    if normal[axis] == 0.0 || !normal[axis].is_finite() {
+        return fan();
+    }
+    let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+    let polygon: Vec<Point2<f64>> = corners.iter().map(|&id| {
+        let p = point(id);
+        Point2::new(p[u], p[v])
+    }).collect();
+
+    // Counter-clockwise in the projection when the face is wound round +axis
+    let sign = normal[axis].signum();
+    let n = polygon.len();
+    let convex = (0..n).all(|k| {
+        let (a, b, c) = (polygon[(k + n - 1) % n], polygon[k], polygon[(k + 1) % n]);
+        ((b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)) * sign >= 0.0
+    });
+    if convex {
+        return fan();
+    }
+    ear_clip_indices(&polygon).iter()
+        .map(|t| if sign > 0.0 { [corners[t[0]], corners[t[1]], corners[t[2]]] } else { [corners[t[0]], corners[t[2]], corners[t[1]]] })
+        .collect()
+}
+
+// Reads vertices and faces only; texture coordinates, groups, objects, smoothing groups and
+// materials are accepted and ignored. Polygons are triangulated by `triangulate_face`. Like the `obj` crate, a
+// vertex is emitted for every distinct (position, normal) pair, in order of first use by a face,
+// so unused positions are dropped. Coordinates are read with `precision`.
+pub(crate) fn parse(input: &[u8], precision: Precision) -> Result<ObjGeometry, P3DError> {
+    let text = core::str::from_utf8(input).map_err(|e| utf8_error(Format::Obj, e))?;
+    let mut points: Vec<[f64; 3]> = Vec::new();
+    let mut normal_count = 0usize;
+    let mut vertex_ids: BTreeMap<(usize, Option<usize>), u32> = BTreeMap::new();
+    let mut positions: Vec<f64> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for (n, raw) in text.lines().enumerate() {
+        let line = n + 1;
+        let content = raw.split('#').next().unwrap_or("");
+        let mut tokens = content.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut p = [0.0; 3];
+                for c in p.iter_mut() {
+                    let token = tokens.next().ok_or_else(|| syntax_error(line, "vertex needs 3 coordinates"))?;
+                    *c = coordinate(token, precision, line)?;
+                }
+                points.push(p);
+            }
+            Some("vn") => normal_count += 1,
+            Some("f") => {
+                let mut corners: Vec<u32> = Vec::new();
+                for corner in tokens {
+                    let mut parts = corner.split('/');
+                    let pi = resolve_index(parts.next().unwrap_or(""), points.len(), line)?;
+                    let _texture = parts.next();
+                    let ni = match parts.next() {
+                        Some(t) if !t.is_empty() => Some(resolve_index(t, normal_count, line)?),
+                        _ => None,
+                    };
+                    let next_id = vertex_ids.len();
+                    let id = match vertex_ids.entry((pi, ni)) {
+                        Entry::Occupied(entry) => *entry.get(),
+                        Entry::Vacant(entry) => {
+                            positions.extend_from_slice(&points[pi]);
+                            *entry.insert(vertex_id(next_id)?)
+                        }
+                    };
+                    corners.push(id);
+                }
+                if corners.len() < 3 {
+                    return Err(syntax_error(line, "face needs at least 3 vertices"));
+                }
+                let point = |id: u32| {
+                    let i = id as usize * 3;
+                    [positions[i], positions[i + 1], positions[i + 2]]
+                };
+                for triangle in triangulate_face(&corners, point) {
+                    indices.extend_from_slice(&triangle);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(ObjGeometry { positions, indices })
+}
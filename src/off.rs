@@ -0,0 +1,77 @@
+use alloc::vec::Vec;
+
+use crate::error::{line_error, utf8_error};
+use crate::{Format, Offset, P3DError};
+
+fn off_error(line: usize, reason: &'static str) -> P3DError {
+    line_error(Format::Off, line, reason)
+}
+
+// The number `token` holds, `missing` and `bad` the reasons when there is none
+fn number<T: core::str::FromStr>(token: Option<&str>, line: usize, missing: &'static str, bad: &'static str) -> Result<T, P3DError> {
+    let token = token.ok_or_else(|| off_error(line, missing))?;
+    token.parse().map_err(|_| off_error(line, bad))
+}
+
+// `ParseError` for lines missing at the end of the input
+fn truncated(reason: &'static str) -> P3DError {
+    P3DError::ParseError { format: Format::Off, offset: Offset::Unknown, reason }
+}
+
+// Positions and indices of an ASCII OFF file: the `OFF` keyword (optionally with the `ST`, `C`
+// and `N` prefixes of per-vertex texture coordinates, colors and normals), the vertex, face and
+// edge counts, one vertex per line (further columns ignored) and one polygon per line as its
+// vertex count followed by the indices (then an optional color). Polygons are fan triangulated,
+// `#` comments and blank lines skipped. The counts may follow the keyword on the same line, as
+// in the ModelNet files.
+pub(crate) fn parse(input: &[u8]) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    let text = core::str::from_utf8(input).map_err(|e| utf8_error(Format::Off, e))?;
+    let mut lines = text.lines().enumerate()
+        .map(|(n, raw)| (n + 1, raw.split('#').next().unwrap_or("").trim()))
+        .filter(|(_, content)| !content.is_empty());
+
+    let (n, header) = lines.next().ok_or_else(|| truncated("empty file"))?;
+    let keyword_end = header.find("OFF").map(|i| i + 3).ok_or_else(|| off_error(n, "missing 'OFF' keyword"))?;
+    let prefix = &header[..keyword_end - 3];
+    if !prefix.chars().all(|c| matches!(c, 'S' | 'T' | 'C' | 'N')) {
+        return Err(off_error(n, "unsupported variant"));
+    }
+    let counts = match header[keyword_end..].trim() {
+        "" => lines.next().ok_or_else(|| off_error(n, "missing counts"))?,
+        rest => (n, rest),
+    };
+    let mut tokens = counts.1.split_whitespace();
+    let vertex_count: usize = number(tokens.next(), counts.0, "missing vertex count", "bad vertex count")?;
+    let face_count: usize = number(tokens.next(), counts.0, "missing face count", "bad face count")?;
+
+    let mut positions: Vec<f64> = Vec::new();
+    for _ in 0..vertex_count {
+        let (n, line) = lines.next().ok_or_else(|| truncated("fewer vertices than the header counts"))?;
+        let mut tokens = line.split_whitespace();
+        for _ in 0..3 {
+            positions.push(number(tokens.next(), n, "missing coordinate", "bad coordinate")?);
+        }
+    }
+    let mut indices: Vec<u32> = Vec::new();
+    for _ in 0..face_count {
+        let (n, line) = lines.next().ok_or_else(|| truncated("fewer faces than the header counts"))?;
+        let mut tokens = line.split_whitespace();
+        let corners: usize = number(tokens.next(), n, "missing corner count", "bad corner count")?;
+        if corners < 3 {
+            return Err(off_error(n, "face needs at least 3 vertices"));
+        }
+        let polygon = (0..corners)
+            .map(|_| {
+                let i: u32 = number(tokens.next(), n, "missing vertex index", "bad vertex index")?;
+                if i as usize >= vertex_count {
+                    return Err(off_error(n, "vertex index out of range"));
+                }
+                Ok(i)
+            })
+            .collect::<Result<Vec<u32>, P3DError>>()?;
+        for k in 1..polygon.len() - 1 {
+            indices.extend_from_slice(&[polygon[0], polygon[k], polygon[k + 1]]);
+        }
+    }
+    Ok((positions, indices))
+}
@@ -0,0 +1,340 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::{check_limit, check_vertex_count, Format, Limits, Offset, P3DError, MAX_VERTICES};
+
+fn ply_error(offset: Offset, reason: &'static str) -> P3DError {
+    P3DError::ParseError { format: Format::Ply, offset, reason }
+}
+
+const END_OF_DATA: &str = "unexpected end of data";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Scalar> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // Value of the little endian bytes `b`
+    fn read_le(self, b: &[u8]) -> f64 {
+        match self {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]),
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    // Count type, item type
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Values of the properties of one element, a list as its items
+type Row = Vec<Vec<f64>>;
+
+// Reads the rows of every element in turn from an ASCII or binary body
+trait Body {
+    // The next row, or why it can't be read; `parse` places the error at the row
+    fn row(&mut self, element: &Element) -> Result<Row, &'static str>;
+    // Bytes of the body not read yet
+    fn remaining(&self) -> usize;
+    // Bytes a row of `element` takes at least
+    fn min_row_len(&self, element: &Element) -> usize;
+}
+
+// Items in a list of `count` items, at most `available` of them: a count that is not a whole
+// number, or claims more than the rest of the body can hold, is rejected before anything is
+// allocated for it
+fn list_len(count: f64, available: usize) -> Result<usize, &'static str> {
+    if !(count >= 0.0 && count.fract() == 0.0) {
+        return Err("bad list count");
+    }
+    if count > available as f64 {
+        return Err(END_OF_DATA);
+    }
+    Ok(count as usize)
+}
+
+struct Ascii<'a> {
+    // The body not read yet
+    text: &'a str,
+}
+
+impl<'a> Ascii<'a> {
+    fn number(&mut self) -> Result<f64, &'static str> {
+        let rest = self.text.trim_start();
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (token, rest) = rest.split_at(end);
+        self.text = rest;
+        if token.is_empty() {
+            return Err(END_OF_DATA);
+        }
+        token.parse().map_err(|_| "bad number")
+    }
+}
+
+impl<'a> Body for Ascii<'a> {
+    fn row(&mut self, element: &Element) -> Result<Row, &'static str> {
+        let mut row = Vec::with_capacity(element.properties.len());
+        for property in element.properties.iter() {
+            match property {
+                Property::Scalar(..) => row.push(vec![self.number()?]),
+                Property::List(..) => {
+                    // Every item takes at least a byte of the text
+                    let n = list_len(self.number()?, self.text.len())?;
+                    let mut items = Vec::new();
+                    for _ in 0..n {
+                        items.push(self.number()?);
+                    }
+                    row.push(items);
+                }
+            }
+        }
+        Ok(row)
+    }
+
+    // From the next number on, so a row is placed at its first one
+    fn remaining(&self) -> usize {
+        self.text.trim_start().len()
+    }
+
+    // A number takes at least a byte
+    fn min_row_len(&self, element: &Element) -> usize {
+        element.properties.len()
+    }
+}
+
+struct Binary<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Binary<'a> {
+    fn value(&mut self, scalar: Scalar) -> Result<f64, &'static str> {
+        let size = scalar.size();
+        if self.data.len() < size {
+            return Err(END_OF_DATA);
+        }
+        let mut bytes = [0u8; 8];
+        bytes[..size].copy_from_slice(&self.data[..size]);
+        if self.big_endian {
+            bytes[..size].reverse();
+        }
+        self.data = &self.data[size..];
+        Ok(scalar.read_le(&bytes))
+    }
+}
+
+impl<'a> Body for Binary<'a> {
+    fn row(&mut self, element: &Element) -> Result<Row, &'static str> {
+        let mut row = Vec::with_capacity(element.properties.len());
+        for property in element.properties.iter() {
+            match property {
+                Property::Scalar(_, scalar) => row.push(vec![self.value(*scalar)?]),
+                Property::List(_, count, item) => {
+                    let n = list_len(self.value(*count)?, self.data.len() / item.size())?;
+                    let mut items = Vec::with_capacity(n);
+                    for _ in 0..n {
+                        items.push(self.value(*item)?);
+                    }
+                    row.push(items);
+                }
+            }
+        }
+        Ok(row)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    // A list takes at least its count
+    fn min_row_len(&self, element: &Element) -> usize {
+        element.properties.iter().map(|p| match p {
+            Property::Scalar(_, scalar) | Property::List(_, scalar, _) => scalar.size(),
+        }).sum()
+    }
+}
+
+fn property_index(element: &Element, names: &[&str]) -> Option<usize> {
+    element.properties.iter().position(|p| match p {
+        Property::Scalar(name, _) | Property::List(name, _, _) => names.contains(&name.as_str()),
+    })
+}
+
+// Positions and indices of a PLY file (`format ascii`, `binary_little_endian` or
+// `binary_big_endian`, version 1.0): `x`, `y`, `z` of the `vertex` element and the
+// `vertex_indices` list of the `face` element, polygons fan triangulated. Other elements and
+// properties are read past and ignored. A file without a `face` element is a point cloud and
+// comes with no indices.
+pub(crate) fn parse(input: &[u8], limits: &Limits) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    let header_end = input.windows(10).position(|w| w == b"end_header")
+        .ok_or_else(|| ply_error(Offset::Unknown, "no end_header"))?;
+    let header = core::str::from_utf8(&input[..header_end])
+        .map_err(|e| ply_error(Offset::Byte(e.valid_up_to()), "header is not ASCII"))?;
+    // The body starts after the line break ending `end_header`
+    let mut body_start = header_end + 10;
+    while body_start < input.len() && (input[body_start] == b'\r' || input[body_start] == b' ') {
+        body_start += 1;
+    }
+    if body_start < input.len() && input[body_start] == b'\n' {
+        body_start += 1;
+    }
+
+    let mut lines = header.lines().map(|l| l.trim()).enumerate().map(|(n, l)| (n + 1, l));
+    if lines.next().map(|(_, l)| l) != Some("ply") {
+        return Err(ply_error(Offset::Line(1), "missing 'ply' magic"));
+    }
+    // Big endian or not for binary bodies, `None` for ASCII
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for (n, line) in lines {
+        let error = |reason| ply_error(Offset::Line(n), reason);
+        let scalar = |name| Scalar::parse(name).ok_or_else(|| error("unknown property type"));
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(None),
+            ["format", "binary_little_endian", _] => format = Some(Some(false)),
+            ["format", "binary_big_endian", _] => format = Some(Some(true)),
+            ["format", _, _] => return Err(error("unsupported format")),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error("bad element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements.last_mut()
+                .ok_or_else(|| error("property before any element"))?
+                .properties.push(Property::List(name.to_string(), scalar(count)?, scalar(item)?)),
+            ["property", kind, name] => elements.last_mut()
+                .ok_or_else(|| error("property before any element"))?
+                .properties.push(Property::Scalar(name.to_string(), scalar(kind)?)),
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(error("bad header line")),
+        }
+    }
+    let format = format.ok_or_else(|| ply_error(Offset::Unknown, "missing format line"))?;
+
+    let data = &input[body_start..];
+    let mut ascii;
+    let mut binary;
+    let body: &mut dyn Body = match format {
+        Some(big_endian) => {
+            binary = Binary { data, big_endian };
+            &mut binary
+        }
+        None => {
+            let text = core::str::from_utf8(data)
+                .map_err(|e| ply_error(Offset::Byte(body_start + e.valid_up_to()), "ASCII body is not valid UTF-8"))?;
+            ascii = Ascii { text };
+            &mut ascii
+        }
+    };
+
+    let mut positions: Vec<f64> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    let mut vertex_count = None;
+    for element in elements.iter() {
+        let (xyz, corners) = match element.name.as_str() {
+            "vertex" => {
+                let axis = |name: &str| match property_index(element, &[name]) {
+                    Some(i) if matches!(element.properties[i], Property::Scalar(..)) => Ok(i),
+                    Some(_) => Err(ply_error(Offset::Unknown, "vertex coordinate is a list")),
+                    None => Err(ply_error(Offset::Unknown, "vertex has no x, y or z property")),
+                };
+                (Some([axis("x")?, axis("y")?, axis("z")?]), None)
+            }
+            "face" => (None, Some(property_index(element, &["vertex_indices", "vertex_index"])
+                .ok_or_else(|| ply_error(Offset::Unknown, "face has no vertex_indices property"))?)),
+            _ => (None, None),
+        };
+        if xyz.is_some() {
+            check_vertex_count(element.count)?;
+            check_limit("vertices", element.count, limits.max_vertices)?;
+        }
+        // A count the rest of the body can't hold fails before its rows are looped over, and
+        // rows without properties hold nothing to read
+        let row_len = body.min_row_len(element);
+        // Rows are placed by their first byte
+        let at = |body: &dyn Body| Offset::Byte(input.len() - body.remaining());
+        if element.count.saturating_mul(row_len) > body.remaining() {
+            return Err(ply_error(at(body), END_OF_DATA));
+        }
+        let rows = if row_len == 0 { 0 } else { element.count };
+        for _ in 0..rows {
+            let offset = at(body);
+            let row = body.row(element).map_err(|reason| ply_error(offset, reason))?;
+            if let Some(xyz) = xyz {
+                positions.extend(xyz.iter().map(|&i| row[i][0]));
+            }
+            if let Some(corners) = corners {
+                let polygon = &row[corners];
+                if polygon.len() < 3 {
+                    return Err(ply_error(offset, "face needs at least 3 vertices"));
+                }
+                // Checked before narrowing: `as u32` would wrap a huge index and clamp a
+                // negative one to 0, which the range check below can't tell from a valid one
+                let id = |i: f64| if (0.0..MAX_VERTICES as f64).contains(&i) {
+                    Ok(i as u32)
+                } else {
+                    Err(ply_error(offset, "face index out of range"))
+                };
+                for k in 1..polygon.len() - 1 {
+                    for &i in [polygon[0], polygon[k], polygon[k + 1]].iter() {
+                        indices.push(id(i)?);
+                    }
+                }
+            }
+        }
+        if element.name == "vertex" {
+            vertex_count = Some(element.count);
+        }
+    }
+    let vertex_count = vertex_count.ok_or_else(|| ply_error(Offset::Unknown, "no vertex element"))?;
+    if indices.iter().any(|&i| i as usize >= vertex_count) {
+        return Err(ply_error(Offset::Unknown, "face index out of range"));
+    }
+    Ok((positions, indices))
+}
@@ -8,12 +8,17 @@ use cgmath::num_traits::Float;
 use cgmath::Point2;
 use sha2::{Digest, Sha256};
 
+use crate::cancel::Cancel;
 use crate::contour::{CellSet, Cntr, Rect};
+use crate::sum::CompensatedSum;
 
 pub(crate) const DISTANCE: i32 = 2;
 
 type Vec2 = Point2<f64>;
 
+// Best polylines of every contour: score, digest of the nodes and the nodes (grid cells)
+pub(crate) type Candidates = Vec<Vec<(f64, Vec<u8>, Vec<Point2<i32>>)>>;
+
 #[derive(Clone)]
 pub(crate) struct PolyLine {
     pub(crate) nodes: Vec<Point2<i32>>,
@@ -110,10 +115,28 @@ impl<'a> PolyLine {
 }
 
 
+// `f` of every contour, in order. Contours are ranked independently, so with the `parallel`
+// feature they are spread over the rayon pool; the results are the same either way.
+#[cfg(feature = "parallel")]
+fn map_contours<T, F>(cntrs: &[Vec<Vec2>], f: F) -> Vec<T>
+    where T: Send, F: Fn(&Vec<Vec2>) -> T + Sync + Send
+{
+    use rayon::prelude::*;
+    cntrs.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn map_contours<T, F>(cntrs: &[Vec<Vec2>], f: F) -> Vec<T>
+    where F: Fn(&Vec<Vec2>) -> T
+{
+    cntrs.iter().map(f).collect()
+}
+
 pub(crate) struct GenPolyLines {
     cells: CellSet,
     line_buf: PolyLine,
     lev: i32,
+    cancel: Option<Cancel>,
 }
 
 impl GenPolyLines {
@@ -122,27 +145,34 @@ impl GenPolyLines {
             cells: z,
             line_buf: PolyLine::new(Vec::with_capacity(100), grid_size),
             lev: 0,
+            cancel: None,
         }
     }
 
+    // Stops `complete_line` early once `cancel` is cancelled, the ranking is then incomplete
+    pub(crate) fn with_cancel(mut self, cancel: Option<&Cancel>) -> Self {
+        self.cancel = cancel.cloned();
+        self
+    }
+
     // Function to calculate the squared centroid distance between two sets of points
     fn sco2(v1: &Cntr, v2: &Cntr) -> f64 {
-        // Initialize squared sum as a floating point number
-        let mut s = 0f64;
+        // Initialize squared sum, compensated so the result doesn't depend on the target
+        let mut s = CompensatedSum::default();
 
         // Iterate through the corresponding pairs of points in both input vectors
         for (a1, a2) in v1.points.iter().zip(v2.points.iter()) {
             // Calculate the squared Euclidean distance between current pair of points
             // and add it to the cumulative squared sum
-            s += (a2.x - a1.x) * (a2.x - a1.x) + (a2.y - a1.y) * (a2.y - a1.y);
+            s.add((a2.x - a1.x) * (a2.x - a1.x) + (a2.y - a1.y) * (a2.y - a1.y));
         }
 
         // Return the mean squared distance by dividing the cumulative squared sum
         // by the number of points
-        s / (v1.points.len() as f64)
+        s.value() / (v1.points.len() as f64)
     }
 
-    pub(crate) fn select_top(counters: &Vec<Vec<Vec2>>, n: usize, grid_size: i16, rect: Rect) -> Vec<(f64, PolyLine)> {
+    pub(crate) fn select_top(counters: &Vec<Vec<Vec2>>, n: usize, grid_size: i16, rect: Rect, cancel: Option<&Cancel>) -> Vec<(f64, PolyLine)> {
         let mut top_heap: VecDeque<(f64, PolyLine)> = VecDeque::with_capacity(n);
         // TODO: select start point from self.cells
 
@@ -150,7 +180,7 @@ impl GenPolyLines {
             let cn = Cntr::new(Some(cntr.to_vec()), grid_size, &rect);
             let zone = cn.line_zone();
 
-            let mut gen_lines = GenPolyLines::new(zone, grid_size);
+            let mut gen_lines = GenPolyLines::new(zone, grid_size).with_cancel(cancel);
             let start_point = Point2 { x: 0, y: 0 };
             gen_lines.line_buf.nodes.push(start_point);
 
@@ -183,12 +213,9 @@ impl GenPolyLines {
 
     // This function selects the top n ranked PolyLines for each contour in a given grid.
     // The ranking is based on the score calculated by the `GenPolyLines::sco2` method.
-    pub(crate) fn select_top_all(counters: &Vec<Vec<Vec2>>, n: usize, grid_size: usize, rect: Rect) -> Vec<Vec<(f64, Vec<u8>)>> {
-        // Initialize a 2D Vector to store the top n PolyLines for each contour.
-        let mut top_heap: Vec<Vec<(f64, Vec<u8>)>> = Vec::with_capacity(grid_size as usize);
-
-        // Iterate through each contour.
-        for cntr in counters.iter() {
+    pub(crate) fn select_top_all(counters: &Vec<Vec<Vec2>>, n: usize, grid_size: usize, rect: Rect, cancel: Option<&Cancel>) -> Vec<Vec<(f64, Vec<u8>)>> {
+        // Rank the PolyLines of every contour, each independently of the others.
+        map_contours(counters, |cntr| {
             // Create a Deque to store the top n PolyLines within the current contour.
             let mut top_in_cntr: VecDeque<(f64, PolyLine)> = VecDeque::with_capacity(n);
 
@@ -197,7 +224,7 @@ impl GenPolyLines {
             let zone = cn.line_zone();
 
             // Initialize a new GenPolyLines object with the given zone.
-            let mut gen_lines = GenPolyLines::new(zone, grid_size as i16);
+            let mut gen_lines = GenPolyLines::new(zone, grid_size as i16).with_cancel(cancel);
             let start_point = Point2 { x: 0, y: 0 };
             gen_lines.line_buf.nodes.push(start_point);
 
@@ -230,21 +257,18 @@ impl GenPolyLines {
             // Generate and rank PolyLines for the current contour using the `ff` closure.
             gen_lines.complete_line(&mut ff);
 
-            // Add the ranked PolyLines for the current contour to the 2D Vector.
+            // Return the ranked PolyLines for the current contour.
             // Use calc_hash() to map the PolyLine to a Vec<u8> for storage.
-            top_heap.push(top_in_cntr.into_iter().map(|a| (a.0, a.1.calc_hash().to_vec())).collect());
-        }
-        top_heap
+            top_in_cntr.into_iter().map(|a| (a.0, a.1.calc_hash().to_vec())).collect()
+        })
     }
 
-    pub(crate) fn select_top_all_3(counters: &Vec<Vec<Vec2>>, depth: usize, grid_size: usize, rect: Rect) -> Vec<Vec<(f64, Vec<u8>)>> {
-        let mut top_heap: Vec<Vec<(f64, Vec<u8>)>> = Vec::with_capacity(grid_size as usize);
-
-        for cntr in counters.iter() {
+    pub(crate) fn select_top_all_3(counters: &Vec<Vec<Vec2>>, depth: usize, grid_size: usize, rect: Rect, cancel: Option<&Cancel>) -> Candidates {
+        map_contours(counters, |cntr| {
             let mut top_in_cntr: Vec<(f64, PolyLine)> = Vec::with_capacity(depth);
             let cn = Cntr::new(Some(cntr.to_vec()), grid_size as i16, &rect);
             let zone = cn.line_zone();
-            let mut gen_lines = GenPolyLines::new(zone, grid_size as i16);
+            let mut gen_lines = GenPolyLines::new(zone, grid_size as i16).with_cancel(cancel);
             let start_point = Point2 { x: 0, y: 0 };
 
             gen_lines.line_buf.nodes.push(start_point);
@@ -278,22 +302,19 @@ impl GenPolyLines {
                 }
             };
             gen_lines.complete_line(&mut ff);
-            top_heap.push(top_in_cntr.into_iter().map(|a| (a.0, a.1.calc_hash().to_vec())).collect());
-        }
-        top_heap
+            top_in_cntr.into_iter().map(|(d, pl)| (d, pl.calc_hash(), pl.nodes)).collect()
+        })
     }
 
     pub (crate) fn select_top_all_4(
-        cntrs: &Vec<Vec<Vec2>>, depth: usize, grid_size: usize, rect: Rect,
-    ) -> Vec<Vec<(f64, Vec<u8>)>> {
-
-        let mut top_heap: Vec<Vec<(f64, Vec<u8>)>> = Vec::with_capacity(grid_size as usize);
+        cntrs: &Vec<Vec<Vec2>>, depth: usize, grid_size: usize, rect: Rect, cancel: Option<&Cancel>,
+    ) -> Candidates {
 
-        for cntr in cntrs.iter() {
+        map_contours(cntrs, |cntr| {
             let mut top_in_cntr: Vec<(f64, PolyLine)> = Vec::with_capacity(depth);
             let cn = Cntr::new(Some(cntr.to_vec()), grid_size as i16, &rect);
             let zone = cn.line_zone();
-            let mut gen_lines = GenPolyLines::new(zone, grid_size as i16);
+            let mut gen_lines = GenPolyLines::new(zone, grid_size as i16).with_cancel(cancel);
             let start_point = Point2 { x: 0, y: 0 };
 
             gen_lines.line_buf.nodes.push(start_point);
@@ -329,14 +350,16 @@ impl GenPolyLines {
                 }
             };
             gen_lines.complete_line(&mut ff);
-            top_heap.push(top_in_cntr.into_iter().map(|a| (a.0, a.1.calc_hash().to_vec())).collect());
-        }
-        top_heap
+            top_in_cntr.into_iter().map(|(d, pl)| (d, pl.calc_hash(), pl.nodes)).collect()
+        })
     }
 
     // This function recursively explores and completes a polyline
     // using the provided closure or function `F` to process each completed polyline.
     fn complete_line<F>(&mut self, f: &mut F) where F: FnMut(&PolyLine) {
+        if matches!(&self.cancel, Some(cancel) if cancel.poll()) {
+            return;
+        }
         // Increment the recursion level counter
         self.lev += 1;
 
@@ -0,0 +1,44 @@
+use alloc::sync::Arc;
+use core::fmt;
+
+/// Stage of a `p3d_process_*` call reported to a `Progress`, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading the file (after decompression)
+    Parse,
+    /// Limits, validation, welding and repairs of the geometry
+    BuildMesh,
+    /// Alignment, normalization and the optional rotation
+    Transform,
+    /// Cutting the sections, reported after every one
+    Slice,
+    /// Ranking the sections into hashes
+    Hash,
+}
+
+/// Receives the progress of a `p3d_process_*` call, see `ProcessOptions::progress`: the stage
+/// and the percentage of it done. Every stage reports 0 when it starts and 100 when it ends,
+/// slicing also after every section; stages an algorithm skips (`CloudGrid` builds no mesh,
+/// `FixedGrid` and `CloudGrid` slice their own way) aren't reported. Called on the processing
+/// thread, or with the `parallel` feature on the threads slicing, so sections may be reported
+/// out of order.
+#[derive(Clone)]
+pub struct Progress(Arc<dyn Fn(Stage, u8) + Send + Sync>);
+
+impl Progress {
+    pub fn new<F: Fn(Stage, u8) + Send + Sync + 'static>(report: F) -> Self {
+        Progress(Arc::new(report))
+    }
+
+    // `done` of `total` steps of `stage` as a percentage
+    pub(crate) fn report(&self, stage: Stage, done: usize, total: usize) {
+        let percent = (done.min(total) * 100).checked_div(total).unwrap_or(100) as u8;
+        (self.0)(stage, percent)
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
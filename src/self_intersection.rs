@@ -0,0 +1,85 @@
+use alloc::vec::Vec;
+
+use tri_mesh::prelude::*;
+
+// Face pairs tested at most, beyond that the check gives up and reports no intersection
+const MAX_PAIRS: usize = 4_000_000;
+const EPS: f64 = 1e-9;
+
+// Whether the segment p-q crosses the interior of triangle t (Moller-Trumbore).
+// Touching at an edge or a vertex does not count.
+fn segment_crosses(p: Vector3<f64>, q: Vector3<f64>, t: &[Vector3<f64>; 3]) -> bool {
+    let dir = q - p;
+    let e1 = t[1] - t[0];
+    let e2 = t[2] - t[0];
+    let h = dir.cross(e2);
+    let a = e1.dot(h);
+    if a.abs() < EPS {
+        return false;
+    }
+    let f = 1.0 / a;
+    let s = p - t[0];
+    let u = f * s.dot(h);
+    if u <= EPS || u >= 1.0 - EPS {
+        return false;
+    }
+    let qv = s.cross(e1);
+    let v = f * dir.dot(qv);
+    if v <= EPS || u + v >= 1.0 - EPS {
+        return false;
+    }
+    let k = f * e2.dot(qv);
+    k > EPS && k < 1.0 - EPS
+}
+
+fn triangles_intersect(a: &[Vector3<f64>; 3], b: &[Vector3<f64>; 3]) -> bool {
+    (0..3).any(|i| segment_crosses(a[i], a[(i + 1) % 3], b))
+        || (0..3).any(|i| segment_crosses(b[i], b[(i + 1) % 3], a))
+}
+
+// First pair of non-adjacent faces (by `face_iter` position) that cut through each other.
+// Sweeps the faces along X and only tests pairs with overlapping bounding boxes; coplanar
+// overlaps are not detected.
+pub(crate) fn find_self_intersection(mesh: &Mesh) -> Option<(usize, usize)> {
+    let faces: Vec<([VertexID; 3], [Vector3<f64>; 3])> = mesh.face_iter()
+        .map(|fid| {
+            let (v0, v1, v2) = mesh.face_vertices(fid);
+            ([v0, v1, v2], [mesh.vertex_position(v0), mesh.vertex_position(v1), mesh.vertex_position(v2)])
+        })
+        .collect();
+    let bounds: Vec<(Vector3<f64>, Vector3<f64>)> = faces.iter()
+        .map(|(_, p)| {
+            let min = Vector3::new(p[0].x.min(p[1].x).min(p[2].x), p[0].y.min(p[1].y).min(p[2].y), p[0].z.min(p[1].z).min(p[2].z));
+            let max = Vector3::new(p[0].x.max(p[1].x).max(p[2].x), p[0].y.max(p[1].y).max(p[2].y), p[0].z.max(p[1].z).max(p[2].z));
+            (min, max)
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..faces.len()).collect();
+    order.sort_by(|&a, &b| bounds[a].0.x.partial_cmp(&bounds[b].0.x).unwrap_or(core::cmp::Ordering::Equal));
+
+    let mut pairs = 0;
+    for (k, &a) in order.iter().enumerate() {
+        for &b in order[k + 1..].iter() {
+            if bounds[b].0.x > bounds[a].1.x {
+                break;
+            }
+            let (amin, amax) = bounds[a];
+            let (bmin, bmax) = bounds[b];
+            if bmin.y > amax.y || bmax.y < amin.y || bmin.z > amax.z || bmax.z < amin.z {
+                continue;
+            }
+            if faces[a].0.iter().any(|v| faces[b].0.contains(v)) {
+                continue;
+            }
+            pairs += 1;
+            if pairs > MAX_PAIRS {
+                return None;
+            }
+            if triangles_intersect(&faces[a].1, &faces[b].1) {
+                return Some((a.min(b), a.max(b)));
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,134 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{p3d_process_n, AlgoType, InputFileType};
+
+const SELF_TEST_GLB: &[u8] = include_bytes!("../test-ht.glb");
+const GRID_SIZE: i16 = 8;
+const N_SECTIONS: i16 = 10;
+const DEPTH: usize = 10;
+
+// Expected output of `test-ht.glb` for every algorithm with the parameters above, in
+// `AlgoType` order. Regenerate with `cargo test print_self_test_golden -- --ignored --nocapture`
+// built against `Cargo.lock`: the pinned forks of cgmath, tri-mesh and gltf are what the
+// consensus hashes are computed with.
+pub(crate) const GOLDEN: &[(AlgoType, &[&str])] = &[
+    (AlgoType::Grid2d, &[
+        "43f6967db17e37bf92044b998b848b96739de65f0b01e2275733a2f67205bf57",
+        "97b896f5a124ef28874e6f29d978e43f8f767e6f9410601b8c0f9a9e00c0283c",
+        "89f8e5b88da14130edaad8e138bc1551a451c22def6b1c17ce6b7c062f5cb905",
+        "c096bf11ee86a81a9fb3adc433bd804436aa29e9a7d44a40e3ee2ad75b24af9a",
+        "db46fedab6bdb8eba31a186ae0dfb25650ebc48dba42170ce61c20810adcb62f",
+        "10a2413faf9ff82712d3d6f2e0d326ae8291f6eaad359ea521079b6da94846e1",
+        "8a5fdba423c9d0383dd8146cf7b9a93ec8b8c99231e47123957e01aa39183bcb",
+        "d7865848f7c222215c4f5b8a90e797fc1386e0a9eaf84e4ae459d613e4aaac6f",
+        "8278629f521fe0ed2d327bd2ed3e9c489bdc6fbefd9b5d233a7dc67700207349",
+        "5601eda0ed7cf96114dd634edb5ace540874330a9233c0ddde23b41498a546a1",
+    ]),
+    (AlgoType::Grid2dV2, &[
+        "2f895be0019e7ecdaaf6f94a7af99e39d9dfc05a55571bcdabd7480f55bcdf9b",
+    ]),
+    (AlgoType::Grid2dV3, &[
+        "4e2afdbbc99ce4c9b1132a9d568112cbbf26db858f6361223b5ab1082dfc972a",
+        "7ef91d767555110efaa478f5301b31234d8e7f6df70a0ed8d5891b4302b88e66",
+    ]),
+    (AlgoType::Grid2dV3a, &[
+        "c799db0e1907e97be2afb77a3c78cc1eb3b8f16f5e8630b73e6f4c619288c590",
+        "675742b3de6dc5718247ef0cceebc85073d337c1c5d2fd0cadd502de986b0734",
+    ]),
+    (AlgoType::Spectr, &[
+        "200cc05980cd311700e73d4132280ff5d7a3d4512c78e4db50bf4d86199f0e7d",
+        "26bc190975f6a16588a4b3cd9943ad04243f49cc3c96af015dc720e36971cc23",
+        "0440ebe392d08eb2a18e4dcdc8c88727690d99e5cd720f45ec6f228502367083",
+        "42d239cdad48a7c6b81c0c4688ed10caa50b2de6b59cb8491a53c7c6f6706081",
+    ]),
+    (AlgoType::Skeleton, &[
+        "4af31aea3ea3b96b2a75bb1d0b851167c91048d92d0cf4e99efa05a44b05e425",
+    ]),
+    (AlgoType::CloudGrid, &[
+        "36efe96110f09d886ef8130b29f20a6d3724a701acf9d203c23bdd2ace208344",
+        "1d74872cdd3868d3e6855b29d3a66153b3fbd35cceac11fba4dbe149fa296e81",
+        "732e844cd54448394895951bca52a1d7444507f8d5093c2895c61d2774157c97",
+        "7d2d56a1ed6b0be6dd1e7b15fb081713dac011505be12a4bc02ca9c834961f56",
+        "b330b2caefb29785dfe243bf3bd62c59688d07cce1626abb10690c1bb3d00a41",
+        "cbd27b01845377d8a27c76484dd5fd5585a6fa2115a5a553c16c5aa45a1fea35",
+        "f09e6ccb119e52150e383d8c17766b5fb96b5b3a3102eb1e19618e72cc38ef8b",
+        "5af474ea364cba02bd5583ec11ad3ed87ca334b748b131b18c430ccbbff61417",
+        "30428b7fd7958802b90e6b0d9be06b503818c24d6fdf0a8f56f293814b687316",
+        "795136f8a502800e98ff562206486d96ff085c029b6699af6f77b849f1ceaceb",
+    ]),
+    (AlgoType::Voxel, &[
+        "347aa0cbda1cd53e922901911a6b02d1c5525996c16377af28c3a24aae216749",
+        "d9265ecb9f4feacf38f69fdf6f117b5de0b6d465148f2f44293f4274e303cf28",
+        "cb45168dead438baf95f8fb1173f3d679e093faded8401ed7f555f0554682da6",
+        "16bc378ef2862fac15fd07a4f4111b664ee18a4a3a6036bd29fdaa67610a565f",
+        "2f2e7d5df28ccc59cc88f51b09b0cb9141efb19eef8f2da2f0ebac69f4c7ddbc",
+        "65c605f271cde5f7fd70b6b161526c255ae3bbe5064236edcb8f259e7f5a01e9",
+        "a8f3071a212e0df2a4add760daa66507569af03ecbbe0034f943833d4204f477",
+        "04a7bb4716667ed8589d9826db4779e2668fb8b63ba739ffcd4cd3bd4f98de91",
+        "99e1a6c26c1606a4865ade0f8c2a9f6f6b428a02fb573e19d962cd1c78a2d219",
+        "5fa38a49c526cc3c4d9d2f3a8da777f87ebd62c1951a10752e1b64204c08e3c4",
+    ]),
+    (AlgoType::FixedGrid, &[
+        "6bd1f0333d377aa6386ede84fbf6c0ac1fdb80c02b58fd57c0ecbe3a2b9f05e8",
+        "1bb1dc8d56b44ad80d333b78aa6aec78f8386445c46a8ba47fcc91eddbaac684",
+        "c7367bf8f187a1039adfcb836242eb255a49f023903a291e713f8842c2f0182a",
+        "2b40aa470eed65f5c6d8f7f182997d8a6a62885ae9991b54e3c92088b73a1460",
+        "9be87e3f5a76fa171e58b2075c604584c005d78370d9a6735f91162faab5a3be",
+        "d14256b9eaeffa45486918b9ac836b0cb605b5d352e43dd748e4c77c9bb57505",
+        "6b0aa247d17dfc53e7e6717f8ba29734853accc4d6e424eac8e31cb8c22c2c86",
+        "9e6f8fa5096409d122331b65aa5eaf9c302fb561973e6814012d6ac0d3bd626e",
+        "49a30c8e4e1fe7949ba39caacce53b47f362e29d9e916c246ade875b61577b61",
+        "f5ba6c68e60b777afef25c015b8b049a5f700fe77b58ab31e637947d34942712",
+    ]),
+    (AlgoType::CellStats, &[
+        "3bf43f01a3aa43ff21f7ec193fe32b86181daae7ae3a1289f2e18a005fd8bca3",
+        "1269fbfb09dfc2b3034f5b120876c58211f1a623cf97934ebee1188646521a38",
+        "e39dd170c7ff20c7a29e23574d5812d67a1907eba886c72d87341a3a3fb7b339",
+        "8ff691c5a6f1c770446b85b298f19cdec9b62d025792f0ddc0a5dbb0ae7238c5",
+        "c7df8958c7db07f1e2568fbb6a782cb3df81cf246dfe03cfdb5baf46a6ec985b",
+        "2bd341a0d10876555c5a8df1896a260cdd236c51c4df1e776c7a8a40f831d910",
+        "99a3d612a39e8be969b9deda0cee2e3bcae5743d18c5b406b1080d0ff0a72588",
+        "67b58dc51e16c686f2a18013e13f3eb6911e5a33ba112d924c1bf62076d928a3",
+        "2fbcab489c1cf63ac76574b5296d440e9a1edb620d255acd2a1ab68ce8b5f52a",
+    ]),
+    (AlgoType::GridNormals, &[
+        "5a1915144675b62cde7ac7c95ceb09c3c65ad6657bad266590ffe4de9f9eaa9d",
+        "ee19685b26ebc160a7f7653c4a90d13946dfdc4cfe2d6af132893a2928b7d59b",
+        "d4b70fd90d39ff7d17b88eb9bae425474966fa75a7cd1e0325dd8fe230812d7c",
+        "d4052afcd73c5babf44ef1b5837796f710ae6c9e96310aa4f718cfadb57395e6",
+        "0bf3495911bc172ca4f0b2be31d2dd1f869137364985b7d8ade4ad0be5123278",
+        "28f990c98d44caa7fa69aefc4896003640e599d2cd80720753f56e3e8d8d03d6",
+        "4dd123086b424e6ca57a9a0e504f351e330ea43093695efb9a47a62796745863",
+        "1d658f0e6945ec47678d507617acbb55c1cc86f4548dcb9cd8b31913e0774987",
+        "b70dcda1716ed8bd96ce00c022c7ac75950af15af3da4cdd0b950ddb9877941a",
+        "3e16d9b077e1ee795ae8f2ffb855d9152dcb9dd48c91f529870cc8c738196f4f",
+    ]),
+];
+
+pub(crate) fn self_test_with(golden: &[(AlgoType, &[&str])]) -> Result<(), String> {
+    let mut errors: Vec<String> = Vec::new();
+    for (algo, expected) in golden.iter() {
+        match p3d_process_n(SELF_TEST_GLB, InputFileType::Glb, *algo, DEPTH, GRID_SIZE, N_SECTIONS, None) {
+            Ok(hashes) => {
+                if hashes.len() != expected.len() || hashes.iter().zip(expected.iter()).any(|(a, b)| a != b) {
+                    errors.push(format!("{:?}: expected {:?}, got {:?}", algo, expected, hashes));
+                }
+            }
+            Err(e) => errors.push(format!("{:?}: processing failed: {:?}", algo, e)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// Runs the embedded `test-ht.glb` through every algorithm and compares the hashes with
+/// the outputs recorded for this release. A miner can call it at startup to make sure its
+/// build produces consensus-correct hashes; the error describes every mismatch.
+pub fn self_test() -> Result<(), String> {
+    self_test_with(GOLDEN)
+}
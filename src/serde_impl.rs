@@ -0,0 +1,135 @@
+// `serde(with = ...)` helpers so cgmath types are encoded as plain (column-major) arrays.
+
+pub(crate) mod matrix4 {
+    use cgmath::Matrix4;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(m: &Matrix4<f64>, s: S) -> Result<S::Ok, S::Error> {
+        let a: [[f64; 4]; 4] = (*m).into();
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Matrix4<f64>, D::Error> {
+        let a = <[[f64; 4]; 4]>::deserialize(d)?;
+        Ok(a.into())
+    }
+}
+
+pub(crate) mod vector3 {
+    use cgmath::Vector3;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &Vector3<f64>, s: S) -> Result<S::Ok, S::Error> {
+        let a: [f64; 3] = (*v).into();
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vector3<f64>, D::Error> {
+        let a = <[f64; 3]>::deserialize(d)?;
+        Ok(a.into())
+    }
+}
+
+pub(crate) mod contours {
+    use alloc::vec::Vec;
+    use cgmath::Point2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &Vec<Vec<Point2<f64>>>, s: S) -> Result<S::Ok, S::Error> {
+        let a: Vec<Vec<[f64; 2]>> = v.iter()
+            .map(|c| c.iter().map(|p| [p.x, p.y]).collect())
+            .collect();
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<Point2<f64>>>, D::Error> {
+        let a = Vec::<Vec<[f64; 2]>>::deserialize(d)?;
+        Ok(a.into_iter()
+            .map(|c| c.into_iter().map(|p| Point2::new(p[0], p[1])).collect())
+            .collect())
+    }
+}
+
+pub(crate) mod section_loops {
+    use alloc::vec::Vec;
+    use cgmath::Point2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &Vec<Vec<Vec<Point2<f64>>>>, s: S) -> Result<S::Ok, S::Error> {
+        let a: Vec<Vec<Vec<[f64; 2]>>> = v.iter()
+            .map(|l| l.iter().map(|c| c.iter().map(|p| [p.x, p.y]).collect()).collect())
+            .collect();
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Vec<Vec<Point2<f64>>>>, D::Error> {
+        let a = Vec::<Vec<Vec<[f64; 2]>>>::deserialize(d)?;
+        Ok(a.into_iter()
+            .map(|l| l.into_iter().map(|c| c.into_iter().map(|p| Point2::new(p[0], p[1])).collect()).collect())
+            .collect())
+    }
+}
+
+pub(crate) mod option_matrix4 {
+    use cgmath::Matrix4;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(m: &Option<Matrix4<f64>>, s: S) -> Result<S::Ok, S::Error> {
+        let a: Option<[[f64; 4]; 4]> = m.map(|m| m.into());
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Matrix4<f64>>, D::Error> {
+        let a = Option::<[[f64; 4]; 4]>::deserialize(d)?;
+        Ok(a.map(|a| a.into()))
+    }
+}
+
+pub(crate) mod matrix3 {
+    use cgmath::Matrix3;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(m: &Matrix3<f64>, s: S) -> Result<S::Ok, S::Error> {
+        let a: [[f64; 3]; 3] = (*m).into();
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Matrix3<f64>, D::Error> {
+        let a = <[[f64; 3]; 3]>::deserialize(d)?;
+        Ok(a.into())
+    }
+}
+
+// x, y, z, w
+pub(crate) mod quaternion {
+    use cgmath::Quaternion;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(q: &Quaternion<f64>, s: S) -> Result<S::Ok, S::Error> {
+        let a: [f64; 4] = (*q).into();
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Quaternion<f64>, D::Error> {
+        let a = <[f64; 4]>::deserialize(d)?;
+        Ok(a.into())
+    }
+}
+
+// Corners of a box, as two arrays
+pub(crate) mod region {
+    use cgmath::Vector3;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    type Region = Option<(Vector3<f64>, Vector3<f64>)>;
+
+    pub fn serialize<S: Serializer>(r: &Region, s: S) -> Result<S::Ok, S::Error> {
+        let a: Option<[[f64; 3]; 2]> = r.map(|(lo, hi)| [lo.into(), hi.into()]);
+        a.serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Region, D::Error> {
+        let a = Option::<[[f64; 3]; 2]>::deserialize(d)?;
+        Ok(a.map(|[lo, hi]| (lo.into(), hi.into())))
+    }
+}
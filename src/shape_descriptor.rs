@@ -0,0 +1,159 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use tri_mesh::mesh::Mesh;
+
+use crate::algo_grid::{digest, to_hex, HashAlgo};
+use crate::vec3::{cross, norm, sub, Point};
+use crate::P3DError;
+
+// Most shells and bands of a descriptor, the bands bound the table of Legendre polynomials
+const MAX_SHELLS: usize = 64;
+const MAX_BANDS: usize = 32;
+
+// Triangles are cut into about as many sub-triangles as squares of this fraction of the
+// surface area fit in them, and sampled at their centroids. Going by area, a sliver gets few
+// samples however long it is, and a mesh about `AREA_SAMPLES` plus a few per face in all. The
+// spacing depends on the area only, so the samples are the same in any pose.
+const AREA_SAMPLES: f64 = 4096.0;
+
+// Steps of a descriptor value `shape_descriptor_hash` rounds to
+const HASH_QUANTUM: f64 = 4096.0;
+
+// Centroids of the sub-triangles of a regular `k` x `k` split of the triangle, each standing
+// for `1 / k²` of its area
+fn split_centroids(t: [Point; 3], k: usize, mut sample: impl FnMut(Point)) {
+    let (u, v) = (sub(t[1], t[0]), sub(t[2], t[0]));
+    let at = |i: f64, j: f64| [0, 1, 2].map(|c| t[0][c] + (i * u[c] + j * v[c]) / k as f64);
+    for i in 0..k {
+        for j in 0..k - i {
+            let (i, j) = (i as f64, j as f64);
+            sample(at(i + 1.0 / 3.0, j + 1.0 / 3.0));
+            if i + j + 2.0 <= k as f64 {
+                sample(at(i + 2.0 / 3.0, j + 2.0 / 3.0));
+            }
+        }
+    }
+}
+
+// Points spread evenly over the surface, each with the area it stands for
+pub(crate) fn surface_samples(mesh: &Mesh) -> Vec<(Point, f64)> {
+    let triangles: Vec<([Point; 3], f64)> = mesh.face_iter()
+        .map(|f| {
+            let (a, b, c) = mesh.face_positions(f);
+            let t = [[a.x, a.y, a.z], [b.x, b.y, b.z], [c.x, c.y, c.z]];
+            let (u, v) = (sub(t[1], t[0]), sub(t[2], t[0]));
+            (t, norm(cross(u, v)) / 2.0)
+        })
+        .filter(|(_, area)| *area > 0.0)
+        .collect();
+    let spacing = libm::sqrt(triangles.iter().map(|(_, area)| area).sum::<f64>() / AREA_SAMPLES);
+    let mut samples = Vec::new();
+    for &(t, area) in triangles.iter() {
+        // `k²` sub-triangles for the share of the area, `sqrt(AREA_SAMPLES)` splits at most
+        let k = libm::ceil(libm::sqrt(area) / spacing).max(1.0) as usize;
+        let w = area / (k * k) as f64;
+        split_centroids(t, k, |p| samples.push((p, w)));
+    }
+    samples
+}
+
+// Orthonormal real spherical harmonics of bands `0..bands` in the direction (cos theta, phi),
+// band `l` at `l²..(l + 1)²`, m from -l to l
+fn spherical_harmonics(cos_theta: f64, phi: f64, bands: usize, out: &mut [f64]) {
+    let sin_theta = libm::sqrt((1.0 - cos_theta * cos_theta).max(0.0));
+    // Associated Legendre P_l^m of the column m, l >= m, by the usual recurrences
+    let mut p = [[0.0f64; MAX_BANDS]; MAX_BANDS];
+    let mut pmm = 1.0;
+    for m in 0..bands {
+        if m > 0 {
+            pmm *= -((2 * m - 1) as f64) * sin_theta;
+        }
+        p[m][m] = pmm;
+        if m + 1 < bands {
+            p[m + 1][m] = cos_theta * (2 * m + 1) as f64 * pmm;
+        }
+        for l in m + 2..bands {
+            p[l][m] = ((2 * l - 1) as f64 * cos_theta * p[l - 1][m] - (l + m - 1) as f64 * p[l - 2][m]) / (l - m) as f64;
+        }
+    }
+    for (l, row) in p.iter().enumerate().take(bands) {
+        for (m, &p_lm) in row.iter().enumerate().take(l + 1) {
+            // (l - m)! / (l + m)!
+            let ratio: f64 = (l - m + 1..=l + m).map(|f| 1.0 / f as f64).product();
+            let k = libm::sqrt((2 * l + 1) as f64 / (4.0 * core::f64::consts::PI) * ratio);
+            let base = l * l + l;
+            if m == 0 {
+                out[base] = k * p_lm;
+            } else {
+                let y = core::f64::consts::SQRT_2 * k * p_lm;
+                out[base + m] = y * libm::cos(m as f64 * phi);
+                out[base - m] = y * libm::sin(m as f64 * phi);
+            }
+        }
+    }
+}
+
+// Rotation-invariant descriptor of the surface of `mesh` (Kazhdan et al., "Rotation Invariant
+// Spherical Harmonic Representation of 3D Shape Descriptors"): the surface, sampled evenly,
+// is split into `shells` concentric shells around its area centroid, out to the farthest
+// sample, and the harmonics of every shell's points are summed band by band. The energy of a
+// band (the length of its coefficients) doesn't change under rotation, and weights are
+// shares of the area, so neither does the scale. Value `s * bands + l` is band `l` of shell `s`.
+pub(crate) fn shape_descriptor(mesh: &Mesh, shells: usize, bands: usize) -> Result<Vec<f64>, P3DError> {
+    if !(1..=MAX_SHELLS).contains(&shells) || !(1..=MAX_BANDS).contains(&bands) {
+        return Err(P3DError::InvalidParams { param: "shape descriptor", reason: "needs 1 to 64 shells and 1 to 32 bands" });
+    }
+    let samples = surface_samples(mesh);
+    let total: f64 = samples.iter().map(|(_, w)| w).sum();
+    // Samples only come from faces of some area
+    if total == 0.0 {
+        return Err(P3DError::EmptyMesh);
+    }
+    let mut center = [0.0; 3];
+    for (p, w) in samples.iter() {
+        for c in 0..3 {
+            center[c] += p[c] * w / total;
+        }
+    }
+    let radius = samples.iter().map(|(p, _)| norm(sub(*p, center))).fold(0.0, f64::max);
+
+    let mut coefficients = vec![0.0; shells * bands * bands];
+    let mut y = vec![0.0; bands * bands];
+    for (p, w) in samples.iter() {
+        let d = sub(*p, center);
+        let r = norm(d);
+        let shell = if radius > 0.0 { ((r / radius * shells as f64) as usize).min(shells - 1) } else { 0 };
+        // The center itself has no direction, only the constant band sees it
+        let (cos_theta, phi) = if r > 0.0 { (d[2] / r, libm::atan2(d[1], d[0])) } else { (1.0, 0.0) };
+        spherical_harmonics(cos_theta, phi, bands, &mut y);
+        if r == 0.0 {
+            y[1..].iter_mut().for_each(|v| *v = 0.0);
+        }
+        let shell = &mut coefficients[shell * bands * bands..(shell + 1) * bands * bands];
+        for (a, v) in shell.iter_mut().zip(y.iter()) {
+            *a += v * w / total;
+        }
+    }
+    Ok(coefficients.chunks(bands * bands)
+        .flat_map(|shell| (0..bands).map(move |l| libm::sqrt(shell[l * l..(l + 1) * (l + 1)].iter().map(|a| a * a).sum())))
+        .collect())
+}
+
+/// Euclidean distance between two shape descriptors of the same shells and bands, the metric
+/// to index them by for nearest-neighbor search; close to 0 for the same shape in any pose
+/// and size.
+pub fn shape_descriptor_distance(a: &[f64], b: &[f64]) -> f64 {
+    let sum: f64 = a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum();
+    libm::sqrt(sum)
+}
+
+/// Hash of a shape descriptor for exact lookups: the digest of every value rounded to steps
+/// of 1/4096, as little-endian `u16`. Nearly identical shapes can still round apart, so
+/// near duplicates are found by `shape_descriptor_distance`.
+pub fn shape_descriptor_hash(descriptor: &[f64], hash_algo: HashAlgo) -> String {
+    let bytes: Vec<u8> = descriptor.iter()
+        .flat_map(|&v| (libm::round(v * HASH_QUANTUM).clamp(0.0, u16::MAX as f64) as u16).to_le_bytes())
+        .collect();
+    to_hex(&digest(hash_algo, [&bytes[..]]))
+}
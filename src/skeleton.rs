@@ -0,0 +1,50 @@
+use alloc::vec::Vec;
+
+use cgmath::Point2;
+
+use crate::algo_grid::{digest, HashAlgo, RawHash};
+use crate::contour::Rect;
+
+type Vec2 = Point2<f64>;
+
+// Area centroid of a closed contour, the mean of its points when the area vanishes
+pub(crate) fn section_centroid(cntr: &[Vec2]) -> Vec2 {
+    let n = cntr.len();
+    let (mut a, mut cx, mut cy) = (0.0, 0.0, 0.0);
+    for i in 0..n {
+        let (p, q) = (cntr[i], cntr[(i + 1) % n]);
+        let cross = p.x * q.y - q.x * p.y;
+        a += cross;
+        cx += (p.x + q.x) * cross;
+        cy += (p.y + q.y) * cross;
+    }
+    if a.abs() > 1e-12 {
+        Point2::new(cx / (3.0 * a), cy / (3.0 * a))
+    } else {
+        let k = 1.0 / n.max(1) as f64;
+        Point2::new(cntr.iter().map(|p| p.x).sum::<f64>() * k, cntr.iter().map(|p| p.y).sum::<f64>() * k)
+    }
+}
+
+// Approximate medial axis: the centroid of every section, bottom to top
+pub(crate) fn skeleton(centers: &[Vec<Vec2>]) -> Vec<Vec2> {
+    centers.iter().map(|cntr| section_centroid(cntr)).collect()
+}
+
+// The skeleton as a chain of grid cells (repeats collapsed), hashed like the grid algorithms
+pub(crate) fn find_top_skeleton(centers: &[Vec<Vec2>], grid_size: i16, rect: &Rect, hash_algo: HashAlgo) -> Vec<RawHash> {
+    if centers.is_empty() || grid_size <= 0 {
+        return Vec::new();
+    }
+    let mut cells: Vec<(i32, i32)> = skeleton(centers).iter()
+        .map(|p| rect.cell(p, grid_size as i32))
+        .collect();
+    cells.dedup();
+
+    let data: Vec<u8> = cells.iter()
+        .flat_map(|&(i, j)| [i.to_be_bytes(), j.to_be_bytes()])
+        .flatten()
+        .collect();
+
+    vec![digest(hash_algo, [data.as_slice()])]
+}
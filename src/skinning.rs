@@ -0,0 +1,213 @@
+use alloc::vec::Vec;
+
+#[allow(unused_imports)]
+use cgmath::num_traits::Float;
+use gltf::animation::util::ReadOutputs;
+use gltf::animation::{Interpolation, Property};
+use gltf::{Buffer, Document};
+use tri_mesh::prelude::*;
+
+use crate::error::gltf_error;
+use crate::{AnimationPose, P3DError};
+
+// Translation, rotation (x, y, z, w) and scale of a node
+#[derive(Clone, Copy)]
+struct Trs {
+    t: [f32; 3],
+    r: [f32; 4],
+    s: [f32; 3],
+}
+
+impl Trs {
+    fn matrix(&self) -> Matrix4<f64> {
+        let t = Vector3::new(self.t[0] as f64, self.t[1] as f64, self.t[2] as f64);
+        let r = Quaternion::new(self.r[3] as f64, self.r[0] as f64, self.r[1] as f64, self.r[2] as f64);
+        Matrix4::from_translation(t)
+            * Matrix4::from(r.normalize())
+            * Matrix4::from_nonuniform_scale(self.s[0] as f64, self.s[1] as f64, self.s[2] as f64)
+    }
+}
+
+fn lerp(a: &[f32], b: &[f32], k: f32) -> Vec<f32> {
+    a.iter().zip(b.iter()).map(|(a, b)| a + (b - a) * k).collect()
+}
+
+fn slerp(a: &[f32], b: &[f32], k: f32) -> Vec<f32> {
+    let qa = Quaternion::new(a[3], a[0], a[1], a[2]);
+    let mut qb = Quaternion::new(b[3], b[0], b[1], b[2]);
+    // Take the short way round
+    if qa.dot(qb) < 0.0 {
+        qb = -qb;
+    }
+    let q = qa.slerp(qb, k);
+    vec![q.v.x, q.v.y, q.v.z, q.s]
+}
+
+// Value of a sampler at `time`, `values` holds one output per keyframe (three for cubic splines:
+// in-tangent, value, out-tangent). Times outside the keyframes are clamped.
+fn sample(times: &[f32], values: &[Vec<f32>], interpolation: Interpolation, rotation: bool, time: f32) -> Option<Vec<f32>> {
+    let cubic = interpolation == Interpolation::CubicSpline;
+    let value = |i: usize| if cubic { &values[i * 3 + 1] } else { &values[i] };
+    if times.is_empty() || values.len() < times.len() * if cubic { 3 } else { 1 } {
+        return None;
+    }
+    let last = times.len() - 1;
+    if time <= times[0] {
+        return Some(value(0).clone());
+    }
+    if time >= times[last] {
+        return Some(value(last).clone());
+    }
+    let i = times.windows(2).position(|w| time >= w[0] && time < w[1])?;
+    let dt = times[i + 1] - times[i];
+    let k = (time - times[i]) / dt;
+    let v = match interpolation {
+        Interpolation::Step => value(i).clone(),
+        Interpolation::Linear if rotation => slerp(value(i), value(i + 1), k),
+        Interpolation::Linear => lerp(value(i), value(i + 1), k),
+        Interpolation::CubicSpline => {
+            // Hermite spline through the two values with the out/in tangents scaled by dt
+            let (k2, k3) = (k * k, k * k * k);
+            let (p0, m0) = (&values[i * 3 + 1], &values[i * 3 + 2]);
+            let (p1, m1) = (&values[(i + 1) * 3 + 1], &values[(i + 1) * 3]);
+            let v: Vec<f32> = (0..p0.len()).map(|c| {
+                (2.0 * k3 - 3.0 * k2 + 1.0) * p0[c] + (k3 - 2.0 * k2 + k) * dt * m0[c]
+                    + (-2.0 * k3 + 3.0 * k2) * p1[c] + (k3 - k2) * dt * m1[c]
+            }).collect();
+            if rotation {
+                let n = v.iter().map(|c| c * c).sum::<f32>().sqrt();
+                v.iter().map(|c| c / n).collect()
+            } else {
+                v
+            }
+        }
+    };
+    Some(v)
+}
+
+// Global transform of every node (by index) with the animation sampled at `pose.time`
+pub(crate) fn posed_node_transforms<'s, F>(document: &Document, pose: &AnimationPose, get_buffer_data: F) -> Result<Vec<Matrix4<f64>>, P3DError>
+    where F: Clone + for<'a> Fn(Buffer<'a>) -> Option<&'s [u8]>
+{
+    let animation = document.animations().nth(pose.animation).ok_or_else(|| gltf_error("no animation with the pose index"))?;
+
+    let mut locals = rest_pose(document);
+
+    for channel in animation.channels() {
+        let reader = channel.reader(get_buffer_data.clone());
+        let times: Vec<f32> = match reader.read_inputs() {
+            Some(inputs) => inputs.collect(),
+            None => continue,
+        };
+        let (values, rotation): (Vec<Vec<f32>>, bool) = match reader.read_outputs() {
+            Some(ReadOutputs::Translations(it)) => (it.map(|v| v.to_vec()).collect(), false),
+            Some(ReadOutputs::Rotations(it)) => (it.into_f32().map(|v| v.to_vec()).collect(), true),
+            Some(ReadOutputs::Scales(it)) => (it.map(|v| v.to_vec()).collect(), false),
+            _ => continue,
+        };
+        let interpolation = channel.sampler().interpolation();
+        let v = match sample(&times, &values, interpolation, rotation, pose.time) {
+            Some(v) => v,
+            None => return Err(gltf_error("animation sampler output does not match its input")),
+        };
+        let trs = &mut locals[channel.target().node().index()];
+        match channel.target().property() {
+            Property::Translation => trs.t = [v[0], v[1], v[2]],
+            Property::Rotation => trs.r = [v[0], v[1], v[2], v[3]],
+            Property::Scale => trs.s = [v[0], v[1], v[2]],
+            Property::MorphTargetWeights => {}
+        }
+    }
+
+    Ok(global_transforms(document, &locals))
+}
+
+fn rest_pose(document: &Document) -> Vec<Trs> {
+    document.nodes()
+        .map(|node| {
+            let (t, r, s) = node.transform().decomposed();
+            Trs { t, r, s }
+        })
+        .collect()
+}
+
+// Global transform of every node (by index) as authored, without animation
+pub(crate) fn node_transforms(document: &Document) -> Vec<Matrix4<f64>> {
+    global_transforms(document, &rest_pose(document))
+}
+
+fn global_transforms(document: &Document, locals: &[Trs]) -> Vec<Matrix4<f64>> {
+    let mut parents: Vec<Option<usize>> = vec![None; locals.len()];
+    for node in document.nodes() {
+        for child in node.children() {
+            parents[child.index()] = Some(node.index());
+        }
+    }
+    let mut globals: Vec<Option<Matrix4<f64>>> = vec![None; locals.len()];
+    for i in 0..locals.len() {
+        // Walk up to the first node with a known transform, then back down
+        let mut chain = vec![i];
+        while let Some(p) = parents[*chain.last().unwrap()] {
+            if globals[p].is_some() || chain.contains(&p) {
+                chain.push(p);
+                break;
+            }
+            chain.push(p);
+        }
+        let mut m = Matrix4::identity();
+        for &n in chain.iter().rev() {
+            m = match globals[n] {
+                Some(g) => g,
+                None => {
+                    let g = m * locals[n].matrix();
+                    globals[n] = Some(g);
+                    g
+                }
+            };
+        }
+    }
+    globals.into_iter().map(|g| g.unwrap_or_else(Matrix4::identity)).collect()
+}
+
+// Deforms the positions of a primitive of `mesh` with the skin of the node instancing it.
+// Primitives without a skin or without JOINTS_0/WEIGHTS_0 are left as they are.
+pub(crate) fn skin_positions<'s, F>(
+    document: &Document,
+    mesh: &gltf::Mesh,
+    primitive: &gltf::Primitive,
+    globals: &[Matrix4<f64>],
+    positions: &mut [[f32; 3]],
+    get_buffer_data: F,
+) -> Result<(), P3DError>
+    where F: Clone + for<'a> Fn(Buffer<'a>) -> Option<&'s [u8]>
+{
+    let skin = match document.nodes().find(|n| n.mesh().map(|m| m.index()) == Some(mesh.index())).and_then(|n| n.skin()) {
+        Some(skin) => skin,
+        None => return Ok(()),
+    };
+    let inverse_bind: Vec<Matrix4<f64>> = match skin.reader(get_buffer_data.clone()).read_inverse_bind_matrices() {
+        Some(it) => it.map(|m| Matrix4::<f32>::from(m).cast::<f64>().unwrap()).collect(),
+        None => Vec::new(),
+    };
+    let joints: Vec<Matrix4<f64>> = skin.joints().enumerate()
+        .map(|(i, joint)| globals[joint.index()] * inverse_bind.get(i).cloned().unwrap_or_else(Matrix4::identity))
+        .collect();
+
+    let reader = primitive.reader(get_buffer_data);
+    let (joint_ids, weights) = match (reader.read_joints(0), reader.read_weights(0)) {
+        (Some(j), Some(w)) => (j.into_u16(), w.into_f32()),
+        _ => return Ok(()),
+    };
+    for ((p, j), w) in positions.iter_mut().zip(joint_ids).zip(weights) {
+        let v = Vector4::new(p[0] as f64, p[1] as f64, p[2] as f64, 1.0);
+        let mut skinned = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        for k in 0..4 {
+            if w[k] != 0.0 {
+                let m = joints.get(j[k] as usize).ok_or_else(|| gltf_error("joint out of range of the skin"))?;
+                skinned += (m * v) * w[k] as f64;
+            }
+        }
+        *p = [skinned.x as f32, skinned.y as f32, skinned.z as f32];
+    }
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use alloc::vec::Vec;
+use core::sync::atomic::AtomicUsize;
+
+use tri_mesh::prelude::{Matrix4, SquareMatrix, Vector3};
+
+use crate::algo_grid::{get_contour, intersect_edge, plane_range, NEAR_PLANE};
+use crate::contour::Rect;
+use crate::{
+    canonical_order_triangles, canonical_ranks, charge, check_flat, check_limit, check_params, collect_sections, finish_cut,
+    point_order, posed_result, rank_sections, report_progress, report_section, robustness, section_step, transform_matrix, triangle_array, triangle_bytes,
+    weighted_pit, AlgoType, Geometry, Normalization, P3DError, P3DResult, ProcessOptions, Ranked, Reflection,
+    SectionCut, SectionPlacement, Sections, SliceAxis, Stage, Transform, Vec2,
+};
+
+// Whether `Soup` gives the same result as the mesh would. The Grid2d family only needs the
+// vertices and edges crossing every plane, which the triangles give without building a
+// half-edge mesh; options that walk the mesh (loops, materials, self-intersections, the
+// measures behind orientation and normalization) still need it. A face with a repeated corner
+// gets edges of its own in the mesh, so such geometry goes through the mesh too.
+pub(crate) fn supported(algo: AlgoType, geometry: &Geometry, opts: &ProcessOptions) -> bool {
+    matches!(algo, AlgoType::Grid2d | AlgoType::Grid2dV2 | AlgoType::Grid2dV3 | AlgoType::Grid2dV3a)
+        && !opts.trace_loops
+        && !opts.tag_materials
+        && !opts.check_self_intersection
+        && opts.slice_axis == SliceAxis::Z
+        && opts.section_placement == SectionPlacement::Uniform
+        && opts.normalization == Normalization::None
+        && !opts.orient_axes
+        && opts.reflection == Reflection::Unspecified
+        && opts.inertia_region.is_none()
+        && geometry.indices.chunks(3).all(|t| t[0] != t[1] && t[1] != t[2] && t[2] != t[0])
+}
+
+// Triangles posed by `pose_mesh` without building the mesh. The slicing visits vertices and
+// edges in the order `Mesh` iterates them (vertices as given, edges as their faces first use
+// them), so sections and hashes are the same as through the mesh, bit for bit.
+pub(crate) struct Soup {
+    positions: Vec<Vector3<f64>>,
+    indices: Vec<u32>,
+    shift: Vector3<f64>,
+    transform: Matrix4<f64>,
+}
+
+impl Soup {
+    // `P3DMesh::load_opts` followed by the rotation of `process_transform`, on geometry that
+    // went through `checked_geometry`
+    pub(crate) fn pose(geometry: Geometry, rotation: Option<Transform>, opts: &ProcessOptions) -> Result<Self, P3DError> {
+        report_progress(opts, Stage::Transform, 0, 1);
+        if geometry.indices.is_empty() {
+            return Err(P3DError::EmptyMesh);
+        }
+        let mut soup = Soup {
+            positions: geometry.positions.chunks(3).map(|p| Vector3::new(p[0], p[1], p[2])).collect(),
+            indices: geometry.indices,
+            shift: Vector3::new(0.0, 0.0, 0.0),
+            transform: Matrix4::identity(),
+        };
+        match (opts.view_camera, opts.alignment) {
+            // A camera view fixes the slicing axis, the geometry was already rotated when loaded
+            (Some(_), _) => {}
+            (None, Some(alignment)) => {
+                soup.apply(alignment);
+                soup.transform = alignment;
+            }
+            (None, None) if opts.align => {
+                let _triangles = charge(opts, "triangle bytes", triangle_bytes(soup.indices.len() / 3))?;
+                let corners = soup.triangles();
+                let triangles = if opts.canonical_order { canonical_order_triangles(corners) } else { triangle_array(&corners) };
+                let (shift, tr) = weighted_pit(triangles, || soup.positions.clone(), opts.inertia)?;
+                soup.positions.iter_mut().for_each(|p| *p = shift + *p);
+                soup.apply(tr);
+                soup.shift = shift;
+                soup.transform = tr;
+            }
+            (None, None) => {}
+        }
+        let (v_min, v_max) = soup.extreme_coordinates();
+        check_flat(v_min, v_max)?;
+        if let Some(rot) = transform_matrix(rotation)? {
+            soup.apply(rot);
+            soup.transform = rot * soup.transform;
+        }
+        report_progress(opts, Stage::Transform, 1, 1);
+        Ok(soup)
+    }
+
+    fn apply(&mut self, transformation: Matrix4<f64>) {
+        for p in self.positions.iter_mut() {
+            *p = (transformation * p.extend(1.0)).truncate();
+        }
+    }
+
+    fn extreme_coordinates(&self) -> (Vector3<f64>, Vector3<f64>) {
+        let mut min = Vector3::new(f64::MAX, f64::MAX, f64::MAX);
+        let mut max = Vector3::new(f64::MIN, f64::MIN, f64::MIN);
+        for p in self.positions.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        (min, max)
+    }
+
+    // Corners of every triangle as `Mesh::face_vertices` gives them, from the second one
+    fn triangles(&self) -> Vec<[[f64; 3]; 3]> {
+        let corner = |i: u32| {
+            let p = self.positions[i as usize];
+            [p.x, p.y, p.z]
+        };
+        self.indices.chunks(3).map(|t| [corner(t[1]), corner(t[2]), corner(t[0])]).collect()
+    }
+
+    // Edges as `Mesh::edge_iter` gives them, ends as (lower, higher) vertex. The half-edges of a
+    // face (a, b, c) are a-b, c-a and b-c in that order; every edge is the first half-edge
+    // between its vertices, ending where the last one ends. That is the other end for a
+    // manifold edge (or a border), but the same vertex when the last half-edge runs the same
+    // way (an edge of more than two faces, or faces of opposite orientation), as in `Mesh::new`.
+    fn edges(&self) -> Vec<(u32, u32)> {
+        let mut halfedges: Vec<((u32, u32), usize, u32)> = self.indices.chunks(3)
+            .flat_map(|t| [(t[0], t[1]), (t[2], t[0]), (t[1], t[2])])
+            .enumerate()
+            .map(|(i, (from, to))| ((from.min(to), from.max(to)), i, to))
+            .collect();
+        halfedges.sort_unstable();
+        let mut edges: Vec<(usize, (u32, u32))> = Vec::new();
+        let mut start = 0;
+        while start < halfedges.len() {
+            let (pair, first, to) = halfedges[start];
+            let mut end = start + 1;
+            while end < halfedges.len() && halfedges[end].0 == pair {
+                end += 1;
+            }
+            let last = if end - start == 1 { if to == pair.1 { pair.0 } else { pair.1 } } else { halfedges[end - 1].2 };
+            edges.push((first, (to.min(last), to.max(last))));
+            start = end;
+        }
+        edges.sort_unstable_by_key(|&(first, _)| first);
+        edges.into_iter().map(|(_, edge)| edge).collect()
+    }
+
+    // `slice_mesh` with uniform placement, visiting for every plane the candidates
+    // `SectionIndex` would give it
+    pub(crate) fn slice(&self, algo: AlgoType, depth: usize, n_sections: i16, opts: &ProcessOptions) -> Result<Sections, P3DError> {
+        let (v_min, v_max) = self.extreme_coordinates();
+        let (n_sections, step) = section_step(v_max.z - v_min.z, n_sections, opts.min_step)?;
+        check_limit("sections", n_sections.max(0) as usize, opts.limits.max_sections)?;
+        let rect = Rect::new(v_min.x, v_max.x, v_min.y, v_max.y);
+        let n = n_sections.max(0) as usize;
+        let heights: Vec<f64> = (0..n_sections).map(|k| v_min.z + (k as f64 + 1.0f64) * step).collect();
+
+        // Edges for `Grid2dV3a`, vertices for the others
+        let edges = if algo == AlgoType::Grid2dV3a { self.edges() } else { Vec::new() };
+        let mut candidates: Vec<Vec<usize>> = vec![Vec::new(); n];
+        if algo == AlgoType::Grid2dV3a {
+            for (e, &(a, b)) in edges.iter().enumerate() {
+                let (z1, z2) = (self.positions[a as usize].z, self.positions[b as usize].z);
+                for k in plane_range(z1.min(z2), z1.max(z2), v_min.z, step, n) {
+                    candidates[k].push(e);
+                }
+            }
+        } else {
+            for (v, p) in self.positions.iter().enumerate() {
+                for k in plane_range(p.z - NEAR_PLANE, p.z + NEAR_PLANE, v_min.z, step, n) {
+                    candidates[k].push(v);
+                }
+            }
+        }
+
+        let cut = |k: usize| {
+            let z_sect = heights[k];
+            if matches!(&opts.cancel, Some(cancel) if cancel.is_cancelled()) {
+                return SectionCut { z: z_sect, cntr: Vec::new(), tags: Vec::new(), loops: Vec::new(), out_of_bounds: 0 };
+            }
+            let mut sect: Vec<Vec2> = if algo == AlgoType::Grid2dV3a {
+                let delta = step * opts.intersect_tolerance;
+                candidates[k].iter()
+                    .filter_map(|&e| {
+                        let (a, b) = edges[e];
+                        intersect_edge(self.positions[a as usize], self.positions[b as usize], z_sect, delta)
+                    })
+                    .collect()
+            } else {
+                candidates[k].iter()
+                    .map(|&v| self.positions[v])
+                    .filter(|p| (p.z - z_sect).abs() < NEAR_PLANE)
+                    .map(|p| Vec2 { x: p.x, y: p.y })
+                    .collect()
+            };
+            if opts.canonical_order {
+                sect.sort_by(point_order);
+            }
+            finish_cut(z_sect, get_contour(sect), Vec::new(), Vec::new(), &rect, opts)
+        };
+        let n_cut = AtomicUsize::new(0);
+        let cut = |k: usize| {
+            let section = cut(k);
+            report_section(opts, &n_cut, n);
+            section
+        };
+        report_progress(opts, Stage::Slice, 0, n);
+        #[cfg(feature = "parallel")]
+        let cuts: Vec<SectionCut> = {
+            use rayon::prelude::*;
+            (0..n).into_par_iter().map(cut).collect()
+        };
+        #[cfg(not(feature = "parallel"))]
+        let cuts: Vec<SectionCut> = (0..n).map(cut).collect();
+        collect_sections(cuts, rect, n_sections, depth, opts)
+    }
+
+    // `rank_posed` along z
+    pub(crate) fn rank(&self, algo: AlgoType, depth: usize, par1: i16, par2: i16, opts: &ProcessOptions) -> Result<Ranked, P3DError> {
+        check_params(algo, depth, par1, par2)?;
+        let sections = self.slice(algo, depth, par2, opts)?;
+        Ok(canonical_ranks(rank_sections(sections, algo, depth, par1, opts)?, opts))
+    }
+
+    // `process_posed` of a ranking
+    pub(crate) fn result(&self, ranked: Ranked, opts: &ProcessOptions) -> P3DResult {
+        let robustness = robustness(&triangle_array(&self.triangles()), &ranked.sections, ranked.grid_size);
+        let vertices = || self.positions.iter().map(|p| [p.x, p.y, p.z]).collect();
+        posed_result(ranked, (self.shift, self.transform), self.extreme_coordinates(), robustness, vertices, opts)
+    }
+}
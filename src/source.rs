@@ -0,0 +1,76 @@
+use alloc::vec::Vec;
+
+use crate::{check_limit, check_vertex_count, Geometry, P3DError, ProcessOptions};
+
+/// Triangle mesh supplied by the caller, see `p3d_process_source`. It is read once, vertex by
+/// vertex and triangle by triangle, into the buffers the processing moves and slices, so any
+/// vertex layout (interleaved, f32, 16-bit indices...) can be passed as it is stored.
+/// Implemented for `(&[f64], &[u32])` and `(&[f32], &[u32])`: the x, y, z of every vertex one
+/// after the other, and three vertex indices per triangle.
+pub trait MeshSource {
+    fn vertex_count(&self) -> usize;
+    /// Position of vertex `i`, for `i` below `vertex_count()`
+    fn position(&self, i: usize) -> [f64; 3];
+    fn triangle_count(&self) -> usize;
+    /// Vertex indices of triangle `i`, for `i` below `triangle_count()`
+    fn triangle(&self, i: usize) -> [u32; 3];
+}
+
+impl MeshSource for (&[f64], &[u32]) {
+    fn vertex_count(&self) -> usize {
+        self.0.len() / 3
+    }
+
+    fn position(&self, i: usize) -> [f64; 3] {
+        [self.0[3 * i], self.0[3 * i + 1], self.0[3 * i + 2]]
+    }
+
+    fn triangle_count(&self) -> usize {
+        self.1.len() / 3
+    }
+
+    fn triangle(&self, i: usize) -> [u32; 3] {
+        [self.1[3 * i], self.1[3 * i + 1], self.1[3 * i + 2]]
+    }
+}
+
+impl MeshSource for (&[f32], &[u32]) {
+    fn vertex_count(&self) -> usize {
+        self.0.len() / 3
+    }
+
+    fn position(&self, i: usize) -> [f64; 3] {
+        [self.0[3 * i] as f64, self.0[3 * i + 1] as f64, self.0[3 * i + 2] as f64]
+    }
+
+    fn triangle_count(&self) -> usize {
+        self.1.len() / 3
+    }
+
+    fn triangle(&self, i: usize) -> [u32; 3] {
+        [self.1[3 * i], self.1[3 * i + 1], self.1[3 * i + 2]]
+    }
+}
+
+// The geometry of a source, within the limits before anything is copied. Triangles are checked
+// against the vertices later, as those of any input.
+pub(crate) fn source_geometry<S: MeshSource + ?Sized>(source: &S, opts: &ProcessOptions) -> Result<Geometry, P3DError> {
+    if opts.view_camera.is_some() {
+        return Err(P3DError::UnsupportedFileType);
+    }
+    let (vertices, faces) = (source.vertex_count(), source.triangle_count());
+    check_limit("vertices", vertices, opts.limits.max_vertices)?;
+    check_vertex_count(vertices)?;
+    if faces > opts.max_faces {
+        return Err(P3DError::MeshTooLarge { faces, limit: opts.max_faces });
+    }
+    let mut positions = Vec::with_capacity(vertices * 3);
+    for i in 0..vertices {
+        positions.extend_from_slice(&source.position(i));
+    }
+    let mut indices = Vec::with_capacity(faces * 3);
+    for i in 0..faces {
+        indices.extend_from_slice(&source.triangle(i));
+    }
+    Ok(Geometry { positions, indices, materials: Vec::new() })
+}
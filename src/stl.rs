@@ -0,0 +1,91 @@
+use alloc::collections::btree_map::Entry;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{vertex_id, Format, Offset, P3DError};
+
+// Vertices closer than this along every axis are merged into one
+const WELD_EPSILON: f64 = 1e-6;
+
+// Binary STL: 80 byte header, triangle count, then 50 bytes per triangle (normal, 3 vertices,
+// attribute byte count). Some exporters pad the file, so bytes past the triangles are ignored.
+fn binary_triangles(input: &[u8]) -> Option<Vec<[f64; 3]>> {
+    if input.len() < 84 {
+        return None;
+    }
+    let count = u32::from_le_bytes([input[80], input[81], input[82], input[83]]) as usize;
+    let expected = count.checked_mul(50).and_then(|n| n.checked_add(84))?;
+    if input.len() < expected {
+        return None;
+    }
+    let f = |at: usize| f32::from_le_bytes([input[at], input[at + 1], input[at + 2], input[at + 3]]) as f64;
+    let mut corners = Vec::with_capacity(count * 3);
+    for t in 0..count {
+        let at = 84 + t * 50 + 12;
+        for v in 0..3 {
+            let at = at + v * 12;
+            corners.push([f(at), f(at + 4), f(at + 8)]);
+        }
+    }
+    Some(corners)
+}
+
+fn stl_error(offset: Offset, reason: &'static str) -> P3DError {
+    P3DError::ParseError { format: Format::Stl, offset, reason }
+}
+
+fn ascii_triangles(input: &[u8]) -> Result<Vec<[f64; 3]>, P3DError> {
+    let text = core::str::from_utf8(input).map_err(|e| stl_error(Offset::Byte(e.valid_up_to()), "neither binary nor ASCII STL"))?;
+    if !text.trim_start().starts_with("solid") {
+        return Err(stl_error(Offset::Byte(0), "neither binary nor ASCII STL"));
+    }
+    // Tokens are slices of `text`
+    let at = |token: &str| Offset::Byte(token.as_ptr() as usize - text.as_ptr() as usize);
+    let mut corners = Vec::new();
+    let mut tokens = text.split_whitespace();
+    while let Some(token) = tokens.next() {
+        if token != "vertex" {
+            continue;
+        }
+        let mut p = [0.0; 3];
+        for c in p.iter_mut() {
+            let token = tokens.next().ok_or_else(|| stl_error(Offset::Byte(text.len()), "vertex needs 3 coordinates"))?;
+            *c = token.parse().map_err(|_| stl_error(at(token), "bad coordinate"))?;
+        }
+        corners.push(p);
+    }
+    if corners.len() % 3 != 0 {
+        return Err(stl_error(Offset::Unknown, "vertices do not make whole triangles"));
+    }
+    Ok(corners)
+}
+
+// Positions and indices of an STL file (binary or ASCII). STL stores every triangle with its own
+// corners, coincident ones are welded so the mesh is connected.
+pub(crate) fn parse(input: &[u8]) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    let corners = match binary_triangles(input) {
+        Some(corners) => corners,
+        None => ascii_triangles(input)?,
+    };
+
+    let mut ids: BTreeMap<[i64; 3], u32> = BTreeMap::new();
+    let mut positions = Vec::new();
+    let mut indices = Vec::with_capacity(corners.len());
+    for p in corners.iter() {
+        let key = [
+            libm::round(p[0] / WELD_EPSILON) as i64,
+            libm::round(p[1] / WELD_EPSILON) as i64,
+            libm::round(p[2] / WELD_EPSILON) as i64,
+        ];
+        let next_id = ids.len();
+        let id = match ids.entry(key) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                positions.extend_from_slice(p);
+                *entry.insert(vertex_id(next_id)?)
+            }
+        };
+        indices.push(id);
+    }
+    Ok((positions, indices))
+}
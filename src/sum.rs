@@ -0,0 +1,33 @@
+// Compensated (Neumaier) float sum. Values are added in the order given and every step is a
+// plain IEEE add or subtract, so the result is the same on every target: nothing is left to a
+// library's unrolling or SIMD lanes, and no step can be fused into an FMA. The running error
+// term also makes it much less sensitive to the order than a naive sum.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CompensatedSum {
+    sum: f64,
+    compensation: f64,
+}
+
+impl CompensatedSum {
+    pub(crate) fn add(&mut self, x: f64) {
+        let t = self.sum + x;
+        if libm::fabs(self.sum) >= libm::fabs(x) {
+            self.compensation += (self.sum - t) + x;
+        } else {
+            self.compensation += (x - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    pub(crate) fn value(&self) -> f64 {
+        self.sum + self.compensation
+    }
+}
+
+pub(crate) fn compensated_sum<I: IntoIterator<Item = f64>>(values: I) -> f64 {
+    let mut s = CompensatedSum::default();
+    for x in values {
+        s.add(x);
+    }
+    s.value()
+}
@@ -0,0 +1,274 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::f64::consts::PI;
+use core::fmt::Write;
+
+use crate::{p3d_process_cfg, AlgoType, InputFileType, P3DError, P3DParams};
+
+// Segments around and rings from pole to pole (or around the tube) of the round shapes, and
+// quads along each side of a box face
+const SEGMENTS: usize = 32;
+const RINGS: usize = 16;
+const BOX_SPLITS: usize = 8;
+
+/// Shapes of the meshes `test_vectors` generates. None is symmetric enough to leave the
+/// principal axes undetermined, so the alignment is the same in any port.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum TestShape {
+    /// A UV sphere stretched by a different factor along each axis
+    Ellipsoid,
+    /// A box of three different sides with a smooth bump pushed in or out of every face
+    BumpyBox,
+    /// A torus stretched along one axis of its ring
+    Torus,
+}
+
+impl TestShape {
+    const ALL: [TestShape; 3] = [TestShape::Ellipsoid, TestShape::BumpyBox, TestShape::Torus];
+
+    fn name(self) -> &'static str {
+        match self {
+            TestShape::Ellipsoid => "ellipsoid",
+            TestShape::BumpyBox => "bumpy_box",
+            TestShape::Torus => "torus",
+        }
+    }
+}
+
+/// A conformance fixture: an input, the parameters it is processed with and the hashes this
+/// build gives for it. The input is OBJ text (`InputFileType::Obj`), processed with the
+/// default `ProcessOptions` and no rotation.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TestVector {
+    /// `<shape>/<algo>`, e.g. `torus/grid2d_v3a`
+    pub name: String,
+    pub seed: u64,
+    pub shape: TestShape,
+    pub input: String,
+    pub algo: AlgoType,
+    pub depth: usize,
+    pub grid_size: i16,
+    pub n_sections: i16,
+    pub hashes: Vec<String>,
+}
+
+impl TestVector {
+    /// The parameters the hashes were computed with
+    pub fn params(&self) -> P3DParams {
+        P3DParams::default().algo(self.algo).depth(self.depth).grid_size(self.grid_size).n_sections(self.n_sections)
+    }
+}
+
+// SplitMix64, fully specified by the seed so every port draws the same shapes
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [lo, hi), in steps of 2^-16 of the range so the parameters print short
+    fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (hi - lo) * (self.next() >> 48) as f64 / 65536.0
+    }
+}
+
+fn obj(points: &[[f64; 3]], faces: &[[u32; 3]]) -> String {
+    let mut obj = String::new();
+    for p in points.iter() {
+        let _ = writeln!(obj, "v {} {} {}", p[0], p[1], p[2]);
+    }
+    for f in faces.iter() {
+        let _ = writeln!(obj, "f {} {} {}", f[0] + 1, f[1] + 1, f[2] + 1);
+    }
+    obj
+}
+
+fn ellipsoid(rng: &mut Rng) -> String {
+    let a = rng.range(1.0, 1.5);
+    let b = a * rng.range(1.2, 1.5);
+    let c = b * rng.range(1.2, 1.5);
+    let mut points = vec![[0.0, 0.0, c]];
+    for r in 1..RINGS {
+        // Rings off their even spacing, so no ring lies in the plane of a section
+        let theta = PI * (r as f64 + rng.range(-0.3, 0.3)) / RINGS as f64;
+        for s in 0..SEGMENTS {
+            let phi = 2.0 * PI * s as f64 / SEGMENTS as f64;
+            points.push([a * libm::sin(theta) * libm::cos(phi), b * libm::sin(theta) * libm::sin(phi), c * libm::cos(theta)]);
+        }
+    }
+    points.push([0.0, 0.0, -c]);
+    let bottom = (points.len() - 1) as u32;
+    let ring = |r: usize, s: usize| (1 + (r - 1) * SEGMENTS + s % SEGMENTS) as u32;
+    let mut faces = Vec::new();
+    for s in 0..SEGMENTS {
+        faces.push([0, ring(1, s), ring(1, s + 1)]);
+        for r in 1..RINGS - 1 {
+            faces.push([ring(r, s), ring(r + 1, s), ring(r + 1, s + 1)]);
+            faces.push([ring(r, s), ring(r + 1, s + 1), ring(r, s + 1)]);
+        }
+        faces.push([bottom, ring(RINGS - 1, s + 1), ring(RINGS - 1, s)]);
+    }
+    obj(&points, &faces)
+}
+
+fn bumpy_box(rng: &mut Rng) -> String {
+    let x = rng.range(1.0, 1.2);
+    let sides = [x, x * rng.range(1.3, 1.6), x * rng.range(2.0, 2.5)];
+    let n = BOX_SPLITS;
+    // Lattice points on the surface, shared by the faces meeting at an edge
+    let mut ids: BTreeMap<[usize; 3], u32> = BTreeMap::new();
+    let mut points = Vec::new();
+    let mut faces = Vec::new();
+    for axis in 0..3 {
+        let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+        for side in 0..2 {
+            // Height and center of the bump, which vanishes at the edges of the face
+            let height = rng.range(-0.15, 0.15) * sides[axis];
+            let (cu, cv) = (rng.range(0.3, 0.7), rng.range(0.3, 0.7));
+            let direction = if side == 1 { 1.0 } else { -1.0 };
+            let mut vertex = |i: usize, j: usize| {
+                let mut key = [0; 3];
+                key[axis] = side * n;
+                key[b] = i;
+                key[c] = j;
+                *ids.entry(key).or_insert_with(|| {
+                    let (u, v) = (i as f64 / n as f64, j as f64 / n as f64);
+                    let window = libm::sin(PI * u) * libm::sin(PI * v);
+                    let bump = height * window * window * libm::exp(-8.0 * ((u - cu) * (u - cu) + (v - cv) * (v - cv)));
+                    let mut p = [0, 1, 2].map(|k| key[k] as f64 / n as f64 * sides[k]);
+                    p[axis] += direction * bump;
+                    points.push(p);
+                    (points.len() - 1) as u32
+                })
+            };
+            for i in 0..n {
+                for j in 0..n {
+                    let quad = [vertex(i, j), vertex(i + 1, j), vertex(i + 1, j + 1), vertex(i, j + 1)];
+                    // Counter-clockwise in (b, c) faces +axis
+                    if side == 1 {
+                        faces.push([quad[0], quad[1], quad[2]]);
+                        faces.push([quad[0], quad[2], quad[3]]);
+                    } else {
+                        faces.push([quad[0], quad[2], quad[1]]);
+                        faces.push([quad[0], quad[3], quad[2]]);
+                    }
+                }
+            }
+        }
+    }
+    obj(&points, &faces)
+}
+
+fn torus(rng: &mut Rng) -> String {
+    let major = rng.range(2.0, 3.0);
+    let minor = rng.range(0.5, 0.9);
+    let stretch = rng.range(1.2, 1.4);
+    // Tube rings off their even spacing, as the rings of the ellipsoid
+    let angles: Vec<f64> = (0..RINGS).map(|j| 2.0 * PI * (j as f64 + rng.range(-0.3, 0.3)) / RINGS as f64).collect();
+    let mut points = Vec::new();
+    for i in 0..SEGMENTS {
+        let u = 2.0 * PI * i as f64 / SEGMENTS as f64;
+        for &v in angles.iter() {
+            let r = major + minor * libm::cos(v);
+            points.push([stretch * r * libm::cos(u), r * libm::sin(u), minor * libm::sin(v)]);
+        }
+    }
+    let id = |i: usize, j: usize| ((i % SEGMENTS) * RINGS + j % RINGS) as u32;
+    let mut faces = Vec::new();
+    for i in 0..SEGMENTS {
+        for j in 0..RINGS {
+            faces.push([id(i, j), id(i + 1, j), id(i + 1, j + 1)]);
+            faces.push([id(i, j), id(i + 1, j + 1), id(i, j + 1)]);
+        }
+    }
+    obj(&points, &faces)
+}
+
+/// Conformance fixtures for ports of the hashing to other languages: for `seed`, a closed
+/// mesh of every `TestShape` with dimensions drawn from the seed, hashed by every
+/// `AlgoType` with the `P3DParams` defaults (depth 10, an 8 x 8 grid, 10 sections), shapes
+/// in `TestShape` order and algorithms in `AlgoType` order. Some algorithms give no hashes
+/// for some shapes (the older grids drop sections of the ellipsoid), which a port has to
+/// reproduce too. The same seed gives the same vectors on every build that hashes
+/// consistently; `test_vectors_json` writes them out.
+pub fn test_vectors(seed: u64) -> Result<Vec<TestVector>, P3DError> {
+    let mut rng = Rng(seed);
+    let mut vectors = Vec::new();
+    for shape in TestShape::ALL.iter().copied() {
+        let input = match shape {
+            TestShape::Ellipsoid => ellipsoid(&mut rng),
+            TestShape::BumpyBox => bumpy_box(&mut rng),
+            TestShape::Torus => torus(&mut rng),
+        };
+        for &(algo, algo_name) in AlgoType::NAMES.iter() {
+            let params = P3DParams::default().algo(algo);
+            let hashes = p3d_process_cfg(input.as_bytes(), InputFileType::Obj, &params)?;
+            vectors.push(TestVector {
+                name: format!("{}/{}", shape.name(), algo_name),
+                seed,
+                shape,
+                input: input.clone(),
+                algo,
+                depth: params.depth,
+                grid_size: params.grid_size,
+                n_sections: params.n_sections,
+                hashes,
+            });
+        }
+    }
+    Ok(vectors)
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// The vectors as a JSON array, one object per vector with the fields of `TestVector` and a
+/// `"file_type":"obj"`, names as serde writes them (`"algo":"grid2d_v3a"`,
+/// `"shape":"bumpy_box"`), for test suites in any language
+pub fn test_vectors_json(vectors: &[TestVector]) -> String {
+    let mut json = String::from("[");
+    for (k, v) in vectors.iter().enumerate() {
+        if k > 0 {
+            json.push(',');
+        }
+        json.push_str(r#"{"name":"#);
+        write_string(&mut json, &v.name);
+        let _ = write!(
+            json, r#","seed":{},"shape":"{}","file_type":"obj","algo":"{}","depth":{},"grid_size":{},"n_sections":{},"input":"#,
+            v.seed, v.shape.name(), v.algo, v.depth, v.grid_size, v.n_sections,
+        );
+        write_string(&mut json, &v.input);
+        json.push_str(r#","hashes":["#);
+        for (i, hash) in v.hashes.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write_string(&mut json, hash);
+        }
+        json.push_str("]}");
+    }
+    json.push(']');
+    json
+}
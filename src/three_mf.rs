@@ -0,0 +1,284 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use std::io::{Cursor, Read};
+
+use crate::{check_limit, check_vertex_count, Format, Limits, Offset, P3DError};
+
+// Part of the model when the package relationships don't name it
+const DEFAULT_MODEL: &str = "3D/3dmodel.model";
+
+// Relationship type of the model part, as it ends
+const MODEL_RELATIONSHIP: &str = "/3dmodel";
+
+// Components nested deeper than this are taken for a reference cycle
+const MAX_NESTING: usize = 32;
+
+// The model is an entry of the archive, no place of the input
+fn three_mf_error(reason: &'static str) -> P3DError {
+    P3DError::ParseError { format: Format::ThreeMf, offset: Offset::Unknown, reason }
+}
+
+// Reads an archive entry, at most `limit` bytes whatever size it declares
+pub(crate) fn read_entry(zip: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str, limit: usize) -> Result<Vec<u8>, P3DError> {
+    let file = zip.by_name(name).map_err(P3DError::Zip)?;
+    // The declared size can't be trusted, reading stops one byte past the limit
+    let mut data = Vec::new();
+    file.take(limit as u64 + 1).read_to_end(&mut data).map_err(P3DError::Io)?;
+    check_limit("input bytes", data.len(), limit)?;
+    Ok(data)
+}
+
+// Affine transform of a `transform` attribute: 3MF multiplies row vectors, so the 12 numbers
+// are the rows of the 3x3 part followed by the translation
+type Affine = [f64; 12];
+
+const IDENTITY: Affine = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+
+fn parse_transform(value: Option<&str>) -> Result<Affine, P3DError> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(IDENTITY),
+    };
+    let numbers: Vec<f64> = value.split_whitespace()
+        .map(|n| n.parse().map_err(|_| three_mf_error("bad transform")))
+        .collect::<Result<_, _>>()?;
+    if numbers.len() != 12 {
+        return Err(three_mf_error("transform needs 12 numbers"));
+    }
+    let mut m = IDENTITY;
+    m.copy_from_slice(&numbers);
+    Ok(m)
+}
+
+fn transform_point(m: &Affine, p: [f64; 3]) -> [f64; 3] {
+    let mut q = [m[9], m[10], m[11]];
+    for (c, q) in q.iter_mut().enumerate() {
+        *q += p[0] * m[c] + p[1] * m[3 + c] + p[2] * m[6 + c];
+    }
+    q
+}
+
+// `inner` followed by `outer`
+fn compose(inner: &Affine, outer: &Affine) -> Affine {
+    let mut m = [0.0; 12];
+    for row in 0..4 {
+        // The translation row is a point, the others directions
+        let mut v = transform_point(outer, [inner[3 * row], inner[3 * row + 1], inner[3 * row + 2]]);
+        if row < 3 {
+            for c in 0..3 {
+                v[c] -= outer[9 + c];
+            }
+        }
+        m[3 * row..3 * row + 3].copy_from_slice(&v);
+    }
+    m
+}
+
+fn determinant(m: &Affine) -> f64 {
+    m[0] * (m[4] * m[8] - m[5] * m[7]) - m[1] * (m[3] * m[8] - m[5] * m[6]) + m[2] * (m[3] * m[7] - m[4] * m[6])
+}
+
+// One element tag: its name, the text of its attributes and whether it opens (or is empty),
+// closes, or both
+struct Tag<'a> {
+    name: &'a str,
+    attributes: &'a str,
+    closing: bool,
+}
+
+impl<'a> Tag<'a> {
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        let mut rest = self.attributes;
+        loop {
+            let eq = rest.find('=')?;
+            let key = rest[..eq].trim();
+            let value = rest[eq + 1..].trim_start();
+            let quote = value.chars().next().filter(|&q| q == '"' || q == '\'')?;
+            let end = value[1..].find(quote)? + 1;
+            if key == name {
+                return Some(&value[1..end]);
+            }
+            rest = &value[end + 1..];
+        }
+    }
+
+    fn number<T: core::str::FromStr>(&self, name: &str) -> Result<T, P3DError> {
+        let value = self.attribute(name)
+            .ok_or_else(|| three_mf_error("element without a required attribute"))?;
+        value.trim().parse().map_err(|_| three_mf_error("element with a bad number attribute"))
+    }
+}
+
+// Element tags of an XML document in order, an empty element giving an opening and a closing
+// tag. Declarations, comments and text are skipped; the model has no use for them.
+fn tags(xml: &str) -> Result<Vec<Tag<'_>>, P3DError> {
+    let mut tags = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let end_marker = if rest.starts_with("!--") {
+            "-->"
+        } else if rest.starts_with("![CDATA[") {
+            "]]>"
+        } else if rest.starts_with('?') {
+            "?>"
+        } else {
+            ">"
+        };
+        let end = rest.find(end_marker).ok_or_else(|| three_mf_error("unterminated XML tag"))?;
+        let body = &rest[..end];
+        rest = &rest[end + end_marker.len()..];
+        if end_marker != ">" || body.starts_with('!') {
+            continue;
+        }
+        let (closing, body) = match body.strip_prefix('/') {
+            Some(body) => (true, body),
+            None => (false, body),
+        };
+        let (empty, body) = match body.strip_suffix('/') {
+            Some(body) => (true, body),
+            None => (false, body),
+        };
+        let name_end = body.find(|c: char| c.is_whitespace()).unwrap_or(body.len());
+        let tag = Tag { name: &body[..name_end], attributes: &body[name_end..], closing };
+        if empty {
+            tags.push(Tag { closing: false, ..tag });
+            tags.push(Tag { name: tag.name, attributes: "", closing: true });
+        } else {
+            tags.push(tag);
+        }
+    }
+    Ok(tags)
+}
+
+enum Object {
+    Mesh { positions: Vec<[f64; 3]>, triangles: Vec<[u32; 3]> },
+    // Other objects, by id, with their transform
+    Components(Vec<(usize, Affine)>),
+}
+
+struct Model {
+    objects: BTreeMap<usize, Object>,
+    // Build items: the object and its transform
+    items: Vec<(usize, Affine)>,
+}
+
+fn parse_model(xml: &str) -> Result<Model, P3DError> {
+    let mut objects = BTreeMap::new();
+    let mut items = Vec::new();
+    // The object being read, `None` for support objects and objects without geometry yet
+    let mut current: Option<(usize, Option<Object>)> = None;
+    let mut support = false;
+    for tag in tags(xml)? {
+        match (tag.name, tag.closing) {
+            ("object", false) => {
+                current = Some((tag.number("id")?, None));
+                // Supports are printed with the part but aren't part of its shape
+                support = tag.attribute("type") == Some("support");
+            }
+            ("object", true) => {
+                if let Some((id, Some(object))) = current.take() {
+                    if !support {
+                        objects.insert(id, object);
+                    }
+                }
+            }
+            ("mesh", false) => {
+                if let Some((_, object)) = current.as_mut() {
+                    *object = Some(Object::Mesh { positions: Vec::new(), triangles: Vec::new() });
+                }
+            }
+            ("components", false) => {
+                if let Some((_, object)) = current.as_mut() {
+                    *object = Some(Object::Components(Vec::new()));
+                }
+            }
+            ("vertex", false) => {
+                if let Some((_, Some(Object::Mesh { positions, .. }))) = current.as_mut() {
+                    positions.push([tag.number("x")?, tag.number("y")?, tag.number("z")?]);
+                }
+            }
+            ("triangle", false) => {
+                if let Some((_, Some(Object::Mesh { triangles, .. }))) = current.as_mut() {
+                    triangles.push([tag.number("v1")?, tag.number("v2")?, tag.number("v3")?]);
+                }
+            }
+            ("component", false) => {
+                if let Some((_, Some(Object::Components(components)))) = current.as_mut() {
+                    components.push((tag.number("objectid")?, parse_transform(tag.attribute("transform"))?));
+                }
+            }
+            ("item", false) => items.push((tag.number("objectid")?, parse_transform(tag.attribute("transform"))?)),
+            _ => {}
+        }
+    }
+    Ok(Model { objects, items })
+}
+
+// Appends the triangles of object `id` placed by `transform`
+fn instantiate(model: &Model, id: usize, transform: &Affine, nesting: usize, geometry: &mut (Vec<f64>, Vec<u32>), limits: &Limits) -> Result<(), P3DError> {
+    if nesting > MAX_NESTING {
+        return Err(three_mf_error("components nested too deep (reference cycle?)"));
+    }
+    let object = match model.objects.get(&id) {
+        Some(object) => object,
+        None => return Err(three_mf_error("reference to an object that doesn't exist")),
+    };
+    match object {
+        Object::Mesh { positions, triangles } => {
+            let (model_vertices, model_indices) = geometry;
+            let base = model_vertices.len() / 3;
+            // Instancing can multiply a small file, so the limits are checked as it grows
+            check_limit("vertices", base.saturating_add(positions.len()), limits.max_vertices)?;
+            check_vertex_count(base.saturating_add(positions.len()))?;
+            model_vertices.extend(positions.iter().flat_map(|&p| transform_point(transform, p)));
+            // A mirroring transform turns the faces inside out, unless their winding is too
+            let mirrored = determinant(transform) < 0.0;
+            for t in triangles.iter() {
+                if t.iter().any(|&v| v as usize >= positions.len()) {
+                    return Err(three_mf_error("triangle vertex out of range"));
+                }
+                let t = if mirrored { [t[0], t[2], t[1]] } else { *t };
+                model_indices.extend(t.iter().map(|&v| v + base as u32));
+            }
+        }
+        Object::Components(components) => {
+            for (child, child_transform) in components.iter() {
+                instantiate(model, *child, &compose(child_transform, transform), nesting + 1, geometry, limits)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Name of the model part, from the package relationships
+fn model_part(zip: &mut zip::ZipArchive<Cursor<&[u8]>>, limit: usize) -> Result<String, P3DError> {
+    let rels = match read_entry(zip, "_rels/.rels", limit) {
+        Ok(rels) => rels,
+        Err(P3DError::Zip(_)) => return Ok(DEFAULT_MODEL.to_string()),
+        Err(e) => return Err(e),
+    };
+    let rels = core::str::from_utf8(&rels).map_err(|_| three_mf_error("relationships are not UTF-8"))?;
+    let target = tags(rels)?.into_iter()
+        .filter(|tag| tag.name == "Relationship" && !tag.closing)
+        .find(|tag| matches!(tag.attribute("Type"), Some(t) if t.ends_with(MODEL_RELATIONSHIP)))
+        .and_then(|tag| tag.attribute("Target"));
+    Ok(target.map_or(DEFAULT_MODEL, |t| t.trim_start_matches('/')).to_string())
+}
+
+// Positions and indices of a 3MF package: every build item with its transform, components
+// resolved, in build order. Coordinates are kept in the unit of the model.
+pub(crate) fn parse(input: &[u8], limits: &Limits) -> Result<(Vec<f64>, Vec<u32>), P3DError> {
+    let mut zip = zip::ZipArchive::new(Cursor::new(input)).map_err(P3DError::Zip)?;
+    let part = model_part(&mut zip, limits.max_input_bytes)?;
+    let xml = read_entry(&mut zip, &part, limits.max_input_bytes)?;
+    let xml = core::str::from_utf8(&xml).map_err(|_| three_mf_error("model is not UTF-8"))?;
+    let model = parse_model(xml.trim_start_matches('\u{feff}'))?;
+
+    let mut geometry = (Vec::new(), Vec::new());
+    for (id, transform) in model.items.iter() {
+        instantiate(&model, *id, transform, 0, &mut geometry, limits)?;
+    }
+    Ok(geometry)
+}
@@ -0,0 +1,10 @@
+// Diagnostic events of the `trace` feature, through the `log` facade at debug level: the
+// target names the stage (`p3d::parse`, `p3d::align`, `p3d::sections`, `p3d::rank`) and the
+// message is `key=value` pairs. Without the feature an event expands to nothing, its
+// arguments aren't even evaluated, so they may compute what only the event needs.
+macro_rules! trace_event {
+    ($stage:literal, $($arg:tt)+) => {
+        #[cfg(feature = "trace")]
+        log::debug!(target: concat!("p3d::", $stage), $($arg)+);
+    };
+}
@@ -0,0 +1,183 @@
+use alloc::vec::Vec;
+
+use cgmath::Point2;
+
+type Vec2 = Point2<f64>;
+
+fn cross(o: Vec2, a: Vec2, b: Vec2) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+pub(crate) fn signed_area(polygon: &[Vec2]) -> f64 {
+    let n = polygon.len();
+    (0..n).map(|i| {
+        let (p, q) = (polygon[i], polygon[(i + 1) % n]);
+        p.x * q.y - q.x * p.y
+    }).sum::<f64>() / 2.0
+}
+
+// Whether `p` lies inside or on triangle a-b-c (counter-clockwise)
+fn in_triangle(p: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+// Triangulates a simple polygon by ear clipping. The loop may repeat its first point at the
+// end and run either way round; the triangles are counter-clockwise. Self-intersecting
+// loops are triangulated as far as possible.
+pub(crate) fn ear_clip(polygon: &[Vec2]) -> Vec<[Vec2; 3]> {
+    ear_clip_indices(polygon).iter().map(|t| [polygon[t[0]], polygon[t[1]], polygon[t[2]]]).collect()
+}
+
+// `ear_clip` as indices into `polygon`
+pub(crate) fn ear_clip_indices(polygon: &[Vec2]) -> Vec<[usize; 3]> {
+    let mut order: Vec<usize> = Vec::with_capacity(polygon.len());
+    for (k, p) in polygon.iter().enumerate() {
+        if order.last().map(|&last| polygon[last]) != Some(*p) {
+            order.push(k);
+        }
+    }
+    while order.len() > 1 && polygon[order[0]] == polygon[order[order.len() - 1]] {
+        order.pop();
+    }
+    if order.len() < 3 {
+        return Vec::new();
+    }
+    let pts: Vec<Vec2> = order.iter().map(|&k| polygon[k]).collect();
+    if signed_area(&pts) < 0.0 {
+        order.reverse();
+    }
+
+    let mut triangles = Vec::with_capacity(order.len() - 2);
+    let mut idx = order;
+    let mut misses = 0;
+    let mut i = 0;
+    while idx.len() > 3 && misses < idx.len() {
+        let n = idx.len();
+        let (ia, ib, ic) = (idx[(i + n - 1) % n], idx[i % n], idx[(i + 1) % n]);
+        let (a, b, c) = (polygon[ia], polygon[ib], polygon[ic]);
+        let convex = cross(a, b, c) > 0.0;
+        // Points on a corner don't block the ear: the ends of a hole bridge come twice
+        let is_ear = convex && idx.iter()
+            .map(|&k| polygon[k])
+            .filter(|&p| p != a && p != b && p != c)
+            .all(|p| !in_triangle(p, a, b, c));
+        if is_ear {
+            triangles.push([ia, ib, ic]);
+            idx.remove(i % n);
+            misses = 0;
+        } else if cross(a, b, c) == 0.0 {
+            // Collinear vertex, drops out without a triangle
+            idx.remove(i % n);
+            misses = 0;
+        } else {
+            i += 1;
+            misses += 1;
+        }
+        i %= idx.len();
+    }
+    if idx.len() == 3 && cross(polygon[idx[0]], polygon[idx[1]], polygon[idx[2]]) > 0.0 {
+        triangles.push([idx[0], idx[1], idx[2]]);
+    }
+    triangles
+}
+
+// Whether `p` lies inside the closed `polygon` (even-odd rule)
+fn encloses(polygon: &[Vec2], p: Vec2) -> bool {
+    let n = polygon.len();
+    let mut inside = false;
+    for i in 0..n {
+        let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Joins the clockwise `hole` into the counter-clockwise `outer` loop it lies in, through a
+// bridge from its rightmost point to a point of `outer` it can see (Eberly, "Triangulation by
+// Ear Clipping"). Both loops are open. The ends of the bridge come twice in the result.
+fn bridge_hole(outer: &[Vec2], hole: &[Vec2]) -> Option<Vec<Vec2>> {
+    let (m, &mp) = hole.iter().enumerate().max_by(|(_, a), (_, b)| a.x.partial_cmp(&b.x).unwrap_or(core::cmp::Ordering::Equal))?;
+    // Nearest edge of `outer` the ray from `mp` towards +x hits, and the end of it farther right
+    let n = outer.len();
+    let mut hit: Option<(f64, usize)> = None;
+    for i in 0..n {
+        let (a, b) = (outer[i], outer[(i + 1) % n]);
+        if (a.y > mp.y) == (b.y > mp.y) {
+            continue;
+        }
+        let x = a.x + (mp.y - a.y) / (b.y - a.y) * (b.x - a.x);
+        if x >= mp.x && x < hit.map_or(f64::INFINITY, |(best, _)| best) {
+            hit = Some((x, if a.x > b.x { i } else { (i + 1) % n }));
+        }
+    }
+    let (x, mut p) = hit?;
+    // A reflex point of `outer` inside the triangle the ray and that end span hides the end;
+    // the one at the smallest angle to the ray is visible
+    let (hit_point, end) = (Vec2::new(x, mp.y), outer[p]);
+    let spans = |q: Vec2| {
+        let d = [cross(mp, hit_point, q), cross(hit_point, end, q), cross(end, mp, q)];
+        !(d.iter().any(|&d| d < 0.0) && d.iter().any(|&d| d > 0.0))
+    };
+    let mut best = (f64::INFINITY, f64::INFINITY);
+    for k in 0..n {
+        let q = outer[k];
+        if q == end || q.x <= mp.x || cross(outer[(k + n - 1) % n], q, outer[(k + 1) % n]) >= 0.0 || !spans(q) {
+            continue;
+        }
+        let slope = (q.y - mp.y).abs() / (q.x - mp.x);
+        let distance = (q.x - mp.x) * (q.x - mp.x) + (q.y - mp.y) * (q.y - mp.y);
+        if (slope, distance) < best {
+            best = (slope, distance);
+            p = k;
+        }
+    }
+    let mut merged = Vec::with_capacity(n + hole.len() + 2);
+    merged.extend_from_slice(&outer[..=p]);
+    merged.extend_from_slice(&hole[m..]);
+    merged.extend_from_slice(&hole[..=m]);
+    merged.extend_from_slice(&outer[p..]);
+    Some(merged)
+}
+
+// Triangulates the area the loops of a section bound, oriented as `loops::section_loops`
+// traces them: counter-clockwise around material, clockwise around holes. Every hole is
+// joined into the smallest loop around it and cut out with it; open chains are triangulated
+// on their own as `ear_clip` does.
+pub(crate) fn fill_loops(loops: &[Vec<Vec2>]) -> Vec<[Vec2; 3]> {
+    let mut outers: Vec<(f64, Vec<Vec2>)> = Vec::new();
+    let mut holes: Vec<Vec<Vec2>> = Vec::new();
+    let mut triangles = Vec::new();
+    for l in loops.iter() {
+        if l.len() < 4 || l.first() != l.last() {
+            triangles.extend(ear_clip(l));
+            continue;
+        }
+        let mut open = l[..l.len() - 1].to_vec();
+        open.dedup();
+        let area = signed_area(&open);
+        if area > 0.0 {
+            outers.push((area, open));
+        } else if area < 0.0 {
+            holes.push(open);
+        }
+    }
+    // Rightmost holes first, so a later bridge can't cross the earlier ones
+    let right = |l: &[Vec2]| l.iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max);
+    holes.sort_by(|a, b| right(b).partial_cmp(&right(a)).unwrap_or(core::cmp::Ordering::Equal));
+    for hole in holes.iter() {
+        let around = outers.iter_mut()
+            .filter(|(_, outer)| encloses(outer, hole[0]))
+            .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+        if let Some((_, outer)) = around {
+            if let Some(merged) = bridge_hole(outer, hole) {
+                *outer = merged;
+            }
+        }
+    }
+    for (_, outer) in outers.iter() {
+        triangles.extend(ear_clip(outer));
+    }
+    triangles
+}
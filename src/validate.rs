@@ -0,0 +1,107 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{load_geometry, InputFileType, P3DError, ProcessOptions};
+
+/// Topology and extent of an object as loaded, see `p3d_validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshReport {
+    pub vertices: usize,
+    pub faces: usize,
+    /// Edges with a single face, zero for a closed surface
+    pub boundary_edges: usize,
+    /// Edges shared by more than two faces
+    pub non_manifold_edges: usize,
+    /// Faces with a repeated corner
+    pub degenerate_faces: usize,
+    /// Parts connected through shared vertices
+    pub components: usize,
+    /// Bounding box of the vertices faces use
+    pub min: [f64; 3],
+    pub max: [f64; 3],
+    /// Enclosed volume, negative when the faces are wound inwards. Only meaningful for a
+    /// closed surface.
+    pub volume: f64,
+}
+
+impl MeshReport {
+    /// Closed and manifold: every edge has exactly two faces, as the default volume inertia
+    /// transform needs
+    pub fn is_watertight(&self) -> bool {
+        self.boundary_edges == 0 && self.non_manifold_edges == 0
+    }
+}
+
+pub(crate) fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Loads an object the way `p3d_process` does and reports on its geometry without building
+/// the mesh, so broken inputs can be told apart before they are submitted or processed.
+/// Loading errors are returned as they would be by `p3d_process`; an object without faces
+/// fails with `P3DError::EmptyMesh`.
+pub fn p3d_validate(input: &[u8], file_type: InputFileType) -> Result<MeshReport, P3DError> {
+    let geometry = load_geometry(input, file_type, &ProcessOptions::default())?;
+    let (positions, indices) = (&geometry.positions, &geometry.indices);
+    let n_vertices = positions.len() / 3;
+    if indices.len() % 3 != 0 || indices.iter().any(|&i| i as usize >= n_vertices) {
+        return Err(P3DError::MalformedIndices { indices: indices.len(), vertices: n_vertices });
+    }
+    if indices.is_empty() {
+        return Err(P3DError::EmptyMesh);
+    }
+    let point = |i: u32| {
+        let k = i as usize * 3;
+        [positions[k], positions[k + 1], positions[k + 2]]
+    };
+
+    let mut edges: BTreeMap<(u32, u32), usize> = BTreeMap::new();
+    let mut parent: Vec<usize> = (0..n_vertices).collect();
+    let mut degenerate_faces = 0;
+    let mut volume = 0.0;
+    let (mut min, mut max) = (point(indices[0]), point(indices[0]));
+    for t in indices.chunks(3) {
+        if t[0] == t[1] || t[1] == t[2] || t[0] == t[2] {
+            degenerate_faces += 1;
+        }
+        for k in 0..3 {
+            let (a, b) = (t[k], t[(k + 1) % 3]);
+            if a != b {
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+            let (ra, rb) = (find(&mut parent, a as usize), find(&mut parent, b as usize));
+            parent[ra.max(rb)] = ra.min(rb);
+            let p = point(a);
+            for c in 0..3 {
+                min[c] = min[c].min(p[c]);
+                max[c] = max[c].max(p[c]);
+            }
+        }
+        // Signed volume of the tetrahedron with the origin
+        let (a, b, c) = (point(t[0]), point(t[1]), point(t[2]));
+        volume += (a[0] * (b[1] * c[2] - b[2] * c[1]) + a[1] * (b[2] * c[0] - b[0] * c[2]) + a[2] * (b[0] * c[1] - b[1] * c[0])) / 6.0;
+    }
+
+    let mut used = indices.clone();
+    used.sort_unstable();
+    used.dedup();
+    let mut roots: Vec<usize> = used.iter().map(|&i| find(&mut parent, i as usize)).collect();
+    roots.sort_unstable();
+    roots.dedup();
+
+    Ok(MeshReport {
+        vertices: n_vertices,
+        faces: indices.len() / 3,
+        boundary_edges: edges.values().filter(|&&n| n == 1).count(),
+        non_manifold_edges: edges.values().filter(|&&n| n > 2).count(),
+        degenerate_faces,
+        components: roots.len(),
+        min,
+        max,
+        volume,
+    })
+}
@@ -0,0 +1,20 @@
+// Arithmetic on plain `[f64; 3]` points, for the code working on raw positions rather than
+// the cgmath vectors of the mesh
+
+pub(crate) type Point = [f64; 3];
+
+pub(crate) fn sub(p: Point, q: Point) -> Point {
+    [p[0] - q[0], p[1] - q[1], p[2] - q[2]]
+}
+
+pub(crate) fn cross(u: Point, v: Point) -> Point {
+    [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]]
+}
+
+pub(crate) fn dot(u: Point, v: Point) -> f64 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+pub(crate) fn norm(v: Point) -> f64 {
+    libm::sqrt(dot(v, v))
+}
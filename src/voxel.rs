@@ -0,0 +1,134 @@
+use alloc::vec::Vec;
+
+use tri_mesh::mesh::Mesh;
+
+use crate::algo_grid::{digest, HashAlgo, RawHash};
+use crate::cancel::Cancel;
+
+// Candidate grids are shifted by `(a + 0.5) / OFFSETS` of a voxel along x and y
+const OFFSETS: usize = 4;
+
+// A voxel centre closer than this fraction of the layer height to the surface may flip
+const BORDERLINE: f64 = 0.1;
+
+// z where the vertical line through (x, y) crosses the triangle. Points on an edge count for
+// exactly one of the two triangles sharing it (top-left rule on the XY projection), so the
+// crossings of a closed surface come in pairs. Vertical triangles are never crossed.
+pub(crate) fn crossing(t: &[[f64; 3]; 3], x: f64, y: f64) -> Option<f64> {
+    let [mut p0, mut p1, p2] = *t;
+    let mut area = (p1[0] - p0[0]) * (p2[1] - p0[1]) - (p1[1] - p0[1]) * (p2[0] - p0[0]);
+    if area == 0.0 || !area.is_finite() {
+        return None;
+    }
+    if area < 0.0 {
+        core::mem::swap(&mut p0, &mut p1);
+        area = -area;
+    }
+    let edge = |p: [f64; 3], q: [f64; 3]| {
+        // Evaluated from the same end for both triangles, so they see the exact same value
+        let (dx, dy) = (q[0] - p[0], q[1] - p[1]);
+        let w = if (p[0], p[1]) < (q[0], q[1]) {
+            dx * (y - p[1]) - dy * (x - p[0])
+        } else {
+            -((-dx) * (y - q[1]) - (-dy) * (x - q[0]))
+        };
+        let owned = w > 0.0 || (w == 0.0 && (dy > 0.0 || (dy == 0.0 && dx < 0.0)));
+        (w, owned)
+    };
+    let (w0, in0) = edge(p1, p2);
+    let (w1, in1) = edge(p2, p0);
+    let (w2, in2) = edge(p0, p1);
+    if !(in0 && in1 && in2) {
+        return None;
+    }
+    Some((w0 * p0[2] + w1 * p1[2] + w2 * p2[2]) / area)
+}
+
+// Occupancy of the `n` x `n` x `layers` voxels of one candidate grid, layer by layer and rows
+// (y) first, and how many voxel centres are borderline. A centre is inside when an odd number
+// of crossings lies below it on its vertical line.
+fn voxelize(triangles: &[[[f64; 3]; 3]], min: [f64; 3], size: [f64; 3], n: usize, layers: usize, offset: (usize, usize)) -> (Vec<u8>, usize) {
+    let (dx, dy, dz) = (size[0] / n as f64, size[1] / n as f64, size[2] / layers as f64);
+    let fx = (offset.0 as f64 + 0.5) / OFFSETS as f64;
+    let fy = (offset.1 as f64 + 0.5) / OFFSETS as f64;
+    // Columns whose sample line lies within [lo, hi]
+    let columns = |lo: f64, hi: f64, origin: f64, step: f64, f: f64| {
+        let first = libm::ceil((lo - origin) / step - f).max(0.0);
+        let last = libm::floor((hi - origin) / step - f).min(n as f64 - 1.0);
+        if first > last {
+            return 0..0;
+        }
+        first as usize..last as usize + 1
+    };
+
+    let mut crossings: Vec<Vec<f64>> = vec![Vec::new(); n * n];
+    for t in triangles.iter() {
+        let (lo_x, hi_x) = (t[0][0].min(t[1][0]).min(t[2][0]), t[0][0].max(t[1][0]).max(t[2][0]));
+        let (lo_y, hi_y) = (t[0][1].min(t[1][1]).min(t[2][1]), t[0][1].max(t[1][1]).max(t[2][1]));
+        for j in columns(lo_y, hi_y, min[1], dy, fy) {
+            let y = min[1] + (j as f64 + fy) * dy;
+            for i in columns(lo_x, hi_x, min[0], dx, fx) {
+                if let Some(z) = crossing(t, min[0] + (i as f64 + fx) * dx, y) {
+                    crossings[j * n + i].push(z);
+                }
+            }
+        }
+    }
+
+    let mut voxels = vec![0u8; n * n * layers];
+    let mut borderline = 0;
+    for (c, column) in crossings.iter_mut().enumerate() {
+        column.sort_by(|a, b| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+        for k in 0..layers {
+            let z = min[2] + (k as f64 + 0.5) * dz;
+            let below = column.partition_point(|&c| c < z);
+            voxels[k * n * n + c] = (below % 2) as u8;
+            if column.iter().any(|&c| (c - z).abs() < BORDERLINE * dz) {
+                borderline += 1;
+            }
+        }
+    }
+    (voxels, borderline)
+}
+
+// `AlgoType::Voxel`: voxelizes the mesh into `grid_size` x `grid_size` columns of `layers`
+// voxels over its bounding box, once for every sub-voxel shift of the grid along x and y.
+// Every candidate hashes its occupancy layer by layer; candidates with fewer voxel centres
+// close to the surface, which small tessellation differences could flip, rank first.
+// Needs a closed mesh, like the volume inertia. Repeated hashes are dropped.
+pub(crate) fn find_top_voxel(mesh: &Mesh, depth: usize, grid_size: usize, layers: usize, hash_algo: HashAlgo, cancel: Option<&Cancel>) -> Vec<RawHash> {
+    let triangles: Vec<[[f64; 3]; 3]> = mesh.face_iter()
+        .map(|f| {
+            let (a, b, c) = mesh.face_positions(f);
+            [[a.x, a.y, a.z], [b.x, b.y, b.z], [c.x, c.y, c.z]]
+        })
+        .collect();
+    let (v_min, v_max) = mesh.extreme_coordinates();
+    let min = [v_min.x, v_min.y, v_min.z];
+    let size = [v_max.x - v_min.x, v_max.y - v_min.y, v_max.z - v_min.z];
+    if grid_size == 0 || layers == 0 || triangles.is_empty() {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<(usize, usize, RawHash)> = Vec::with_capacity(OFFSETS * OFFSETS);
+    for a in 0..OFFSETS {
+        for b in 0..OFFSETS {
+            if matches!(cancel, Some(cancel) if cancel.is_cancelled()) {
+                return Vec::new();
+            }
+            let (voxels, borderline) = voxelize(&triangles, min, size, grid_size, layers, (a, b));
+            candidates.push((borderline, candidates.len(), digest(hash_algo, [&voxels[..]])));
+        }
+    }
+    candidates.sort_by_key(|&(borderline, index, _)| (borderline, index));
+    let mut hashes: Vec<RawHash> = Vec::with_capacity(depth);
+    for (_, _, hash) in candidates {
+        if hashes.len() == depth {
+            break;
+        }
+        if !hashes.contains(&hash) {
+            hashes.push(hash);
+        }
+    }
+    hashes
+}
@@ -0,0 +1,110 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use js_sys::{Array, Reflect};
+use wasm_bindgen::prelude::*;
+
+use crate::{p3d_process_cfg, AlgoType, InputFileType, P3DError, P3DParams, TransEncoding};
+
+fn algo_type(name: &str) -> Option<AlgoType> {
+    match name.to_ascii_lowercase().as_str() {
+        "grid2d" => Some(AlgoType::Grid2d),
+        "grid2dv2" => Some(AlgoType::Grid2dV2),
+        "grid2dv3" => Some(AlgoType::Grid2dV3),
+        "grid2dv3a" => Some(AlgoType::Grid2dV3a),
+        "spectr" => Some(AlgoType::Spectr),
+        "skeleton" => Some(AlgoType::Skeleton),
+        "cloudgrid" => Some(AlgoType::CloudGrid),
+        "voxel" => Some(AlgoType::Voxel),
+        "fixedgrid" => Some(AlgoType::FixedGrid),
+        "cellstats" => Some(AlgoType::CellStats),
+        "gridnormals" => Some(AlgoType::GridNormals),
+        _ => None,
+    }
+}
+
+// JS `Error` with the message of `err` and its `P3DError::code` as `code`
+fn js_error(err: &P3DError) -> JsValue {
+    let error = js_sys::Error::new(&err.to_string());
+    let _ = Reflect::set(&error, &JsValue::from_str("code"), &JsValue::from(err.code()));
+    error.into()
+}
+
+fn invalid(param: &'static str, reason: &'static str) -> JsValue {
+    js_error(&P3DError::InvalidParams { param, reason })
+}
+
+// Field `name` of the options object, `None` when it or the object is missing
+fn field(options: &JsValue, name: &str) -> Option<JsValue> {
+    if options.is_undefined() || options.is_null() {
+        return None;
+    }
+    Reflect::get(options, &JsValue::from_str(name)).ok().filter(|v| !v.is_undefined() && !v.is_null())
+}
+
+fn string_field(options: &JsValue, name: &'static str) -> Result<Option<String>, JsValue> {
+    field(options, name)
+        .map(|v| v.as_string().ok_or_else(|| invalid(name, "must be a string")))
+        .transpose()
+}
+
+// Integer field within `min..=max`
+fn integer_field(options: &JsValue, name: &'static str, min: f64, max: f64) -> Result<Option<f64>, JsValue> {
+    field(options, name)
+        .map(|v| match v.as_f64() {
+            Some(x) if x == libm::trunc(x) && x >= min && x <= max => Ok(x),
+            _ => Err(invalid(name, "must be an integer in range")),
+        })
+        .transpose()
+}
+
+/// `p3d_process_cfg` for JavaScript (`wasm` feature), exported as `p3dProcess`. `input` is the
+/// file as a `Uint8Array`; `options` an object with:
+///
+/// - `fileType`: `"obj"`, `"gltf"`, `"glb"`, `"stl"`, `"ply"`, `"xyz"`, `"3mf"` or `"off"`;
+///   detected from the content (`InputFileType::Auto`) when missing
+/// - `algo`: `"grid2d"`, `"grid2dV2"`, `"grid2dV3"`, `"grid2dV3a"` (default), `"spectr"`,
+///   `"skeleton"`, `"cloudGrid"`, `"voxel"`, `"fixedGrid"`, `"cellStats"` or `"gridNormals"`, in any case
+/// - `depth`, `gridSize`, `nSections`: the `P3DParams` of the same names, 10, 8 and 10 by default
+/// - `trans`: 4 bytes, an array or a `Uint8Array` (the `trans` of `p3d_process`), or 8 in the
+///   finer `TransEncoding::V2`
+///
+/// Returns the hex hashes as an array of strings. Failures throw an `Error` whose `code` is the
+/// `P3DError::code`, so the hashes are those any other caller of the crate gets.
+#[wasm_bindgen(js_name = p3dProcess)]
+pub fn p3d_process_js(input: &[u8], options: JsValue) -> Result<Array, JsValue> {
+    let file_type = match string_field(&options, "fileType")? {
+        Some(name) => InputFileType::from_extension(&name).ok_or_else(|| js_error(&P3DError::UnsupportedFileType))?,
+        None => InputFileType::Auto,
+    };
+    let mut params = P3DParams::default();
+    if let Some(name) = string_field(&options, "algo")? {
+        params.algo = algo_type(&name).ok_or_else(|| invalid("algo", "unknown algorithm name"))?;
+    }
+    if let Some(depth) = integer_field(&options, "depth", 1.0, crate::MAX_DEPTH as f64)? {
+        params.depth = depth as usize;
+    }
+    if let Some(grid_size) = integer_field(&options, "gridSize", 1.0, i16::MAX as f64)? {
+        params.grid_size = grid_size as i16;
+    }
+    if let Some(n_sections) = integer_field(&options, "nSections", 1.0, i16::MAX as f64)? {
+        params.n_sections = n_sections as i16;
+    }
+    if let Some(trans) = field(&options, "trans") {
+        let trans = Array::from(&trans);
+        let bytes: Option<Vec<u8>> = trans.iter()
+            .map(|v| v.as_f64().filter(|&x| x == libm::trunc(x) && (0.0..=255.0).contains(&x)).map(|x| x as u8))
+            .collect();
+        let encoding = match bytes.as_ref().map(|b| b.len()) {
+            Some(8) => TransEncoding::V2,
+            _ => TransEncoding::V1,
+        };
+        match bytes.map(|b| encoding.decode(&b)) {
+            Some(Ok(transform)) => params = params.transform(transform),
+            _ => return Err(invalid("trans", "must be 4 or 8 integers between 0 and 255")),
+        }
+    }
+
+    let hashes = p3d_process_cfg(input, file_type, &params).map_err(|err| js_error(&err))?;
+    Ok(hashes.into_iter().map(JsValue::from).collect())
+}
@@ -0,0 +1,187 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+fn find(parent: &mut Vec<usize>, mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn point(positions: &[f64], i: usize) -> [f64; 3] {
+    [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]]
+}
+
+fn lex_cmp(a: &[f64; 3], b: &[f64; 3]) -> core::cmp::Ordering {
+    a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal)
+}
+
+// Smallest corner and diagonal of the bounding box of a non-empty vertex list
+fn bounds(positions: &[f64]) -> ([f64; 3], f64) {
+    let mut min = point(positions, 0);
+    let mut max = min;
+    for i in 1..positions.len() / 3 {
+        let p = point(positions, i);
+        for c in 0..3 {
+            min[c] = min[c].min(p[c]);
+            max[c] = max[c].max(p[c]);
+        }
+    }
+    let diagonal = ((max[0] - min[0]).powi(2) + (max[1] - min[1]).powi(2) + (max[2] - min[2]).powi(2)).sqrt();
+    (min, diagonal)
+}
+
+// Merges vertices closer than `epsilon` times the bounding box diagonal (transitively: chains of
+// close vertices become one). A merged vertex takes the smallest position of its group, so which
+// vertices merge and where they end up doesn't depend on the input vertex order. Groups keep the
+// place of their first vertex, a mesh without close vertices comes back unchanged.
+// Triangles left with a repeated corner are dropped; the returned mask tells which were kept.
+pub(crate) fn weld(positions: &[f64], indices: &[u32], epsilon: f64) -> (Vec<f64>, Vec<u32>, Vec<bool>) {
+    let n = positions.len() / 3;
+    let unchanged = || (positions.to_vec(), indices.to_vec(), vec![true; indices.len() / 3]);
+    if n == 0 || !(epsilon > 0.0) {
+        return unchanged();
+    }
+    let (min, diagonal) = bounds(positions);
+    let tolerance = epsilon * diagonal;
+    if !(tolerance > 0.0) {
+        return unchanged();
+    }
+
+    // Vertices by cell of a grid with `tolerance` spacing, close vertices are in neighbouring cells
+    let cell = |p: &[f64; 3]| [
+        libm::floor((p[0] - min[0]) / tolerance) as i64,
+        libm::floor((p[1] - min[1]) / tolerance) as i64,
+        libm::floor((p[2] - min[2]) / tolerance) as i64,
+    ];
+    let mut cells: BTreeMap<[i64; 3], Vec<usize>> = BTreeMap::new();
+    for i in 0..n {
+        cells.entry(cell(&point(positions, i))).or_insert_with(Vec::new).push(i);
+    }
+    let mut parent: Vec<usize> = (0..n).collect();
+    for i in 0..n {
+        let p = point(positions, i);
+        let c = cell(&p);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbours = match cells.get(&[c[0] + dx, c[1] + dy, c[2] + dz]) {
+                        Some(v) => v,
+                        None => continue,
+                    };
+                    for &j in neighbours.iter().filter(|&&j| j < i) {
+                        let q = point(positions, j);
+                        let d2 = (p[0] - q[0]).powi(2) + (p[1] - q[1]).powi(2) + (p[2] - q[2]).powi(2);
+                        if d2 <= tolerance * tolerance {
+                            let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                            parent[a.max(b)] = a.min(b);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Roots are the smallest vertex index of their group, so groups are numbered in that order
+    let mut group_id: BTreeMap<usize, u32> = BTreeMap::new();
+    let mut welded: Vec<f64> = Vec::with_capacity(positions.len());
+    for i in 0..n {
+        let root = find(&mut parent, i);
+        let p = point(positions, i);
+        match group_id.get(&root) {
+            Some(&k) => {
+                let m = &mut welded[k as usize * 3..k as usize * 3 + 3];
+                if lex_cmp(&p, &[m[0], m[1], m[2]]) == core::cmp::Ordering::Less {
+                    m.copy_from_slice(&p);
+                }
+            }
+            None => {
+                group_id.insert(root, group_id.len() as u32);
+                welded.extend_from_slice(&p);
+            }
+        }
+    }
+
+    let mut new_indices = Vec::with_capacity(indices.len());
+    let mut kept = Vec::with_capacity(indices.len() / 3);
+    for t in indices.chunks(3) {
+        let ids: Vec<u32> = t.iter().map(|&i| group_id[&find(&mut parent, i as usize)]).collect();
+        let keep = ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2];
+        if keep {
+            new_indices.extend(ids);
+        }
+        kept.push(keep);
+    }
+    (welded, new_indices, kept)
+}
+
+// Merges vertices at exactly the same position, keeping the first of each in place. Loaders
+// that emit a vertex per distinct (position, normal, texture) use it to join their seams; a
+// mesh without coincident vertices comes back unchanged.
+pub(crate) fn merge_coincident(positions: Vec<f64>, indices: Vec<u32>) -> (Vec<f64>, Vec<u32>) {
+    let mut ids: BTreeMap<[u64; 3], u32> = BTreeMap::new();
+    let mut merged: Vec<f64> = Vec::with_capacity(positions.len());
+    let remap: Vec<u32> = positions.chunks(3)
+        .map(|p| {
+            let next_id = ids.len() as u32;
+            *ids.entry([p[0].to_bits(), p[1].to_bits(), p[2].to_bits()]).or_insert_with(|| {
+                merged.extend_from_slice(p);
+                next_id
+            })
+        })
+        .collect();
+    if merged.len() == positions.len() {
+        return (positions, indices);
+    }
+    let indices = indices.iter().map(|&i| remap[i as usize]).collect();
+    (merged, indices)
+}
+
+// Triangles with less than this fraction of the squared bounding box diagonal as area count
+// as degenerate
+const DEGENERATE_AREA: f64 = 1e-12;
+
+// Drops zero-area triangles (repeated or collinear corners, or nearly so); the returned mask
+// tells which were kept.
+pub(crate) fn drop_degenerate(positions: &[f64], indices: &[u32]) -> (Vec<u32>, Vec<bool>) {
+    if positions.is_empty() {
+        return (indices.to_vec(), vec![true; indices.len() / 3]);
+    }
+    let (_, diagonal) = bounds(positions);
+    let limit = 2.0 * DEGENERATE_AREA * diagonal * diagonal;
+    let mut kept_indices = Vec::with_capacity(indices.len());
+    let mut kept = Vec::with_capacity(indices.len() / 3);
+    for t in indices.chunks(3) {
+        let (a, b, c) = (point(positions, t[0] as usize), point(positions, t[1] as usize), point(positions, t[2] as usize));
+        let (u, v) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+        let n = [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        // Twice the area; NaN corners count as degenerate too
+        let keep = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt() > limit;
+        if keep {
+            kept_indices.extend_from_slice(t);
+        }
+        kept.push(keep);
+    }
+    (kept_indices, kept)
+}
+
+// Removes vertices no triangle uses, keeping the others in order; a mesh without unused
+// vertices comes back unchanged.
+pub(crate) fn prune_unused(positions: Vec<f64>, indices: Vec<u32>) -> (Vec<f64>, Vec<u32>) {
+    let mut used = vec![false; positions.len() / 3];
+    for &i in indices.iter() {
+        used[i as usize] = true;
+    }
+    if used.iter().all(|&u| u) {
+        return (positions, indices);
+    }
+    let mut remap = vec![0u32; used.len()];
+    let mut pruned = Vec::with_capacity(positions.len());
+    for (i, p) in positions.chunks(3).enumerate().filter(|(i, _)| used[*i]) {
+        remap[i] = (pruned.len() / 3) as u32;
+        pruned.extend_from_slice(p);
+    }
+    let indices = indices.iter().map(|&i| remap[i as usize]).collect();
+    (pruned, indices)
+}
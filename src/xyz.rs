@@ -0,0 +1,49 @@
+use alloc::vec::Vec;
+
+use crate::error::{line_error, utf8_error};
+use crate::{Format, P3DError};
+
+fn xyz_error(line: usize, reason: &'static str) -> P3DError {
+    line_error(Format::Xyz, line, reason)
+}
+
+// Points of an ASCII XYZ file: `x y z` per line, separated by spaces, tabs or commas. Further
+// columns (color, intensity, normals) are ignored, as are blank lines and `#` comments.
+pub(crate) fn parse(input: &[u8]) -> Result<Vec<f64>, P3DError> {
+    let text = core::str::from_utf8(input).map_err(|e| utf8_error(Format::Xyz, e))?;
+    let mut positions = Vec::new();
+    for (n, raw) in text.lines().enumerate() {
+        let content = raw.split('#').next().unwrap_or("");
+        let tokens: Vec<&str> = content.split(|c: char| c.is_whitespace() || c == ',').filter(|t| !t.is_empty()).collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        if tokens.len() < 3 {
+            return Err(xyz_error(n + 1, "point needs 3 coordinates"));
+        }
+        for token in tokens[..3].iter() {
+            let c: f64 = token.parse().map_err(|_| xyz_error(n + 1, "bad coordinate"))?;
+            positions.push(c);
+        }
+    }
+    Ok(positions)
+}
+
+// Every `v` line of an OBJ file, whether a face uses it or not, so an OBJ without faces can
+// be read as a point cloud
+pub(crate) fn obj_points(input: &[u8]) -> Result<Vec<f64>, P3DError> {
+    let text = core::str::from_utf8(input).map_err(|e| utf8_error(Format::Obj, e))?;
+    let mut positions = Vec::new();
+    for (n, raw) in text.lines().enumerate() {
+        let mut tokens = raw.split('#').next().unwrap_or("").split_whitespace();
+        if tokens.next() != Some("v") {
+            continue;
+        }
+        for _ in 0..3 {
+            let token = tokens.next().ok_or_else(|| line_error(Format::Obj, n + 1, "vertex needs 3 coordinates"))?;
+            let c: f64 = token.parse().map_err(|_| line_error(Format::Obj, n + 1, "bad coordinate"))?;
+            positions.push(c);
+        }
+    }
+    Ok(positions)
+}
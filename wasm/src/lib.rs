@@ -0,0 +1,4 @@
+//! JavaScript binding of p3d, built for `wasm32-unknown-unknown` and run through wasm-bindgen.
+//! The binding is p3d's own (`wasm` feature); this crate only links it into the cdylib.
+
+pub use p3d::p3d_process_js;